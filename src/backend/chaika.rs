@@ -0,0 +1,393 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+// panda.chaika.moe, which keeps archives of galleries even after they're
+// expunged from e-hentai. everything is through its json api
+
+use std::io::Cursor;
+use std::sync::Arc;
+use futures::future::{self, BoxFuture, FutureExt};
+use futures::lock::Mutex;
+use hyper::body::Bytes;
+use serde_json::Value;
+
+use crate::ehentai::client::Client;
+use crate::ehentai::api::number;
+use crate::ehentai::page::percent_encode;
+use crate::ehentai::{EhError, ArticleKind, GalleryId, Tag, TagKind, TagMap};
+use super::{Backend, Search, Draft, Gallery, ErrorBox, zip_images, read_zip_file};
+
+const SITE: &str = "https://panda.chaika.moe";
+
+// an archive of a gallery kept by chaika
+#[derive(Debug, Clone)]
+pub struct ChaikaArchive {
+    pub id: u64,
+    pub path: String,
+    pub title: String,
+    pub title_jpn: String,
+    pub kind: Option<ArticleKind>,
+    pub uploader: String,
+    // unix timestamp
+    pub posted: u64,
+    pub length: usize,
+    pub file_size: u64,
+    pub expunged: bool,
+    pub rating: f64,
+    pub tags: TagMap,
+    // the gallery in e-hentai it was taken from, if we know
+    pub source: Option<GalleryId>,
+}
+
+impl ChaikaArchive {
+    fn from_value(id: u64, value: &Value) -> Self {
+        let text = |key: &str| value[key].as_str().unwrap_or("").to_owned();
+
+        // tags are like "female:big_breasts"
+        let tags = value["tags"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|tag| tag.as_str())
+            .map(|tag| match tag.replace('_', " ").parse::<Tag>() {
+                Ok(tag) => tag,
+                Err(_) => Tag::new(TagKind::Other, &tag.replace('_', " ")),
+            })
+            .collect();
+
+        let source = value["source"]
+            .as_str()
+            .and_then(|link| link.parse().ok())
+            .or_else(|| {
+                let gid = number::<u64>(&value["gid"])?;
+                Some(GalleryId::new(gid, value["token"].as_str()?))
+            });
+
+        Self {
+            id,
+            path: format!("{}/archive/{}/", SITE, id),
+            title: text("title"),
+            title_jpn: text("title_jpn"),
+            kind: text("category").parse().ok(),
+            uploader: text("uploader"),
+            posted: number(&value["posted"]).unwrap_or(0),
+            length: number(&value["filecount"]).unwrap_or(0),
+            file_size: number(&value["filesize"]).unwrap_or(0),
+            expunged: value["expunged"].as_bool().unwrap_or(false),
+            rating: number(&value["rating"]).unwrap_or(0.0),
+            tags,
+            source,
+        }
+    }
+
+    // the id is either given or in the link like "/archive/(id)/"
+    fn from_item(value: &Value) -> Option<Self> {
+        let id = number::<u64>(&value["id"]).or_else(|| {
+            value["download"]
+                .as_str()
+                .or_else(|| value["link"].as_str())
+                .and_then(archive_id)
+        })?;
+
+        Some(Self::from_value(id, value))
+    }
+
+    pub fn download_path(&self) -> String {
+        format!("{}/archive/{}/download/", SITE, self.id)
+    }
+}
+
+// get the id from a link like "https://panda.chaika.moe/archive/(id)/"
+fn archive_id(link: &str) -> Option<u64> {
+    let pos = link.find("/archive/")?;
+
+    link[(pos + "/archive/".len())..]
+        .split('/')
+        .nth(0)?
+        .parse()
+        .ok()
+}
+
+// search results are either a list or an object with a list in it
+pub(super) fn archive_list(value: &Value) -> Vec<ChaikaArchive> {
+    let list = value
+        .as_array()
+        .or_else(|| value["archives"].as_array())
+        .or_else(|| value["results"].as_array());
+
+    list.into_iter()
+        .flatten()
+        .filter_map(ChaikaArchive::from_item)
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct Chaika {
+    client: Arc<Client>,
+}
+
+impl Chaika {
+    pub fn new() -> Self {
+        Self {
+            client: Arc::new(Client::new()),
+        }
+    }
+
+    async fn get(&self, path: String) -> Result<Value, ErrorBox> {
+        self.client.get_json(path.parse()?).await
+    }
+
+    pub async fn archive(&self, id: u64) -> Result<ChaikaArchive, ErrorBox> {
+        let value = self.get(format!("{}/api?archive={}", SITE, id)).await?;
+
+        if value.get("title").is_none() {
+            return Err(EhError::NotFound(format!("archive {}", id)).into());
+        }
+
+        Ok(ChaikaArchive::from_value(id, &value))
+    }
+
+    // archives taken from a gallery of e-hentai, which may be expunged
+    pub async fn by_gallery(&self, id: &GalleryId)
+        -> Result<Vec<ChaikaArchive>, ErrorBox> {
+        let value = self.get(format!(
+            "{}/jsearch/?gid={}&key={}",
+            SITE, id.gid, id.token
+        )).await?;
+
+        Ok(archive_list(&value))
+    }
+
+    // archives which have an image of the sha-1 hash, e.g. from
+    // Article::load_image_hash()
+    pub async fn by_sha1(&self, hash: &str)
+        -> Result<Vec<ChaikaArchive>, ErrorBox> {
+        let value = self.get(format!("{}/jsearch/?sha1={}", SITE, hash)).await?;
+        Ok(archive_list(&value))
+    }
+
+    // the whole archive as a zip file
+    pub async fn download(&self, archive: &ChaikaArchive)
+        -> Result<Bytes, ErrorBox> {
+        self.client.get_bytes(archive.download_path().parse()?).await
+    }
+
+    pub fn open(&self, archive: ChaikaArchive) -> ChaikaArticle {
+        ChaikaArticle {
+            client: self.client.clone(),
+            meta: archive,
+            images: Mutex::new(None),
+        }
+    }
+}
+
+impl Default for Chaika {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct ChaikaPage {
+    client: Arc<Client>,
+    query: String,
+    page: usize,
+    done: bool,
+}
+
+impl ChaikaPage {
+    // the next page of results, or none once a page comes empty
+    pub async fn next(&mut self) -> Result<Option<Vec<ChaikaArchive>>, ErrorBox> {
+        if self.done {
+            return Ok(None);
+        }
+
+        self.page += 1;
+
+        let path = format!(
+            "{}/jsearch/?title={}&page={}",
+            SITE,
+            percent_encode(&self.query),
+            self.page
+        );
+
+        let list = archive_list(&self.client.get_json(path.parse()?).await?);
+
+        if list.is_empty() {
+            self.done = true;
+            return Ok(None);
+        }
+
+        Ok(Some(list))
+    }
+}
+
+pub struct ChaikaDraft {
+    client: Arc<Client>,
+    meta: ChaikaArchive,
+}
+
+// an archive, which is downloaded at once when an image is requested
+pub struct ChaikaArticle {
+    client: Arc<Client>,
+    meta: ChaikaArchive,
+    // the images in the archive, ordered by their names
+    images: Mutex<Option<Arc<Vec<Bytes>>>>,
+}
+
+// every image in a zip file, ordered by the names. it blocks; run it
+// off the runtime
+fn unzip_images(data: Bytes) -> Result<Vec<Bytes>, ErrorBox> {
+    let mut zip = zip::ZipArchive::new(Cursor::new(data))?;
+
+    zip_images(&zip)
+        .iter()
+        .map(|name| read_zip_file(&mut zip, name))
+        .collect()
+}
+
+impl ChaikaArticle {
+    pub fn meta(&self) -> &ChaikaArchive {
+        &self.meta
+    }
+
+    async fn images(&self) -> Result<Arc<Vec<Bytes>>, ErrorBox> {
+        let mut images = self.images.lock().await;
+
+        if let Some(images) = images.as_ref() {
+            return Ok(images.clone());
+        }
+
+        let data = self.client.get_bytes(self.meta.download_path().parse()?).await?;
+        let list = Arc::new(tokio::task::spawn_blocking(move || unzip_images(data)).await??);
+
+        *images = Some(list.clone());
+        Ok(list)
+    }
+
+    // chaika doesn't have thumbnails of its own; it's the first page
+    pub async fn load_thumb(&self) -> Result<Bytes, ErrorBox> {
+        self.load_image(0).await
+    }
+
+    pub async fn load_image(&self, index: usize) -> Result<Bytes, ErrorBox> {
+        let images = self.images().await?;
+
+        images.get(index).cloned().ok_or_else(|| {
+            EhError::IndexOutOfRange { index, len: images.len() }.into()
+        })
+    }
+}
+
+impl Backend for Chaika {
+    type Search = ChaikaPage;
+    type Draft = ChaikaDraft;
+    type Article = ChaikaArticle;
+
+    fn name(&self) -> &'static str {
+        "chaika"
+    }
+
+    fn search(&self, query: &str) -> Self::Search {
+        ChaikaPage {
+            client: self.client.clone(),
+            query: query.to_owned(),
+            page: 0,
+            done: false,
+        }
+    }
+
+    fn article<'a>(&'a self, path: &'a str)
+        -> BoxFuture<'a, Result<Self::Article, ErrorBox>> {
+        async move {
+            let id = archive_id(path)
+                .ok_or_else(|| EhError::NotFound(path.to_owned()))?;
+
+            Ok::<_, ErrorBox>(self.open(self.archive(id).await?))
+        }.boxed()
+    }
+}
+
+impl Search for ChaikaPage {
+    type Draft = ChaikaDraft;
+
+    fn next(&mut self) -> BoxFuture<'_, Result<Option<Vec<Self::Draft>>, ErrorBox>> {
+        async move {
+            let client = self.client.clone();
+
+            Ok::<_, ErrorBox>(ChaikaPage::next(self).await?.map(|list| {
+                list.into_iter()
+                    .map(|meta| ChaikaDraft { client: client.clone(), meta })
+                    .collect::<Vec<_>>()
+            }))
+        }.boxed()
+    }
+}
+
+impl Draft for ChaikaDraft {
+    type Meta = ChaikaArchive;
+    type Article = ChaikaArticle;
+
+    fn meta(&self) -> &Self::Meta {
+        &self.meta
+    }
+
+    fn title(&self) -> &str {
+        &self.meta.title
+    }
+
+    fn path(&self) -> &str {
+        &self.meta.path
+    }
+
+    fn tags(&self) -> &TagMap {
+        &self.meta.tags
+    }
+
+    fn length(&self) -> usize {
+        self.meta.length
+    }
+
+    // the search results had everything already
+    fn load(self) -> BoxFuture<'static, Result<Self::Article, ErrorBox>> {
+        let article = ChaikaArticle {
+            client: self.client,
+            meta: self.meta,
+            images: Mutex::new(None),
+        };
+
+        future::ready(Ok(article)).boxed()
+    }
+}
+
+impl Gallery for ChaikaArticle {
+    type Meta = ChaikaArchive;
+
+    fn meta(&self) -> &Self::Meta {
+        &self.meta
+    }
+
+    fn title(&self) -> &str {
+        &self.meta.title
+    }
+
+    fn path(&self) -> &str {
+        &self.meta.path
+    }
+
+    fn tags(&self) -> &TagMap {
+        &self.meta.tags
+    }
+
+    fn length(&self) -> usize {
+        self.meta.length
+    }
+
+    fn load_thumb(&self) -> BoxFuture<'_, Result<Bytes, ErrorBox>> {
+        ChaikaArticle::load_thumb(self).boxed()
+    }
+
+    fn load_image(&self, index: usize) -> BoxFuture<'_, Result<Bytes, ErrorBox>> {
+        ChaikaArticle::load_image(self, index).boxed()
+    }
+}
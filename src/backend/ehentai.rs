@@ -0,0 +1,97 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use futures::future::{BoxFuture, FutureExt};
+use hyper::body::Bytes;
+
+use crate::ehentai::{self as eh, TagMap, DraftMeta, ArticleMeta};
+use super::{Backend, Search, Draft, Gallery, ErrorBox};
+
+impl Backend for eh::Explorer {
+    type Search = eh::Page;
+    type Draft = eh::Draft;
+    type Article = eh::Article;
+
+    fn name(&self) -> &'static str {
+        "e-hentai"
+    }
+
+    fn search(&self, query: &str) -> Self::Search {
+        eh::Explorer::search(self, query)
+    }
+
+    fn article<'a>(&'a self, path: &'a str)
+        -> BoxFuture<'a, Result<Self::Article, ErrorBox>> {
+        self.article_from_path(path.to_owned()).boxed()
+    }
+}
+
+impl Search for eh::Page {
+    type Draft = eh::Draft;
+
+    fn next(&mut self) -> BoxFuture<'_, Result<Option<Vec<Self::Draft>>, ErrorBox>> {
+        eh::Page::next(self).boxed()
+    }
+}
+
+impl Draft for eh::Draft {
+    type Meta = DraftMeta;
+    type Article = eh::Article;
+
+    fn meta(&self) -> &Self::Meta {
+        eh::Draft::meta(self)
+    }
+
+    fn title(&self) -> &str {
+        &eh::Draft::meta(self).title
+    }
+
+    fn path(&self) -> &str {
+        &eh::Draft::meta(self).path
+    }
+
+    fn tags(&self) -> &TagMap {
+        &eh::Draft::meta(self).tags
+    }
+
+    fn length(&self) -> usize {
+        eh::Draft::meta(self).length
+    }
+
+    fn load(self) -> BoxFuture<'static, Result<Self::Article, ErrorBox>> {
+        async move { eh::Draft::load(&self).await }.boxed()
+    }
+}
+
+impl Gallery for eh::Article {
+    type Meta = ArticleMeta;
+
+    fn meta(&self) -> &Self::Meta {
+        eh::Article::meta(self)
+    }
+
+    fn title(&self) -> &str {
+        &eh::Article::meta(self).title
+    }
+
+    fn path(&self) -> &str {
+        &eh::Article::meta(self).path
+    }
+
+    fn tags(&self) -> &TagMap {
+        &eh::Article::meta(self).tags
+    }
+
+    fn length(&self) -> usize {
+        eh::Article::meta(self).length
+    }
+
+    fn load_thumb(&self) -> BoxFuture<'_, Result<Bytes, ErrorBox>> {
+        eh::Article::load_thumb(self).boxed()
+    }
+
+    fn load_image(&self, index: usize) -> BoxFuture<'_, Result<Bytes, ErrorBox>> {
+        eh::Article::load_image(self, index).boxed()
+    }
+}
@@ -0,0 +1,420 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+// hentaifox.com, which mirrors a lot of what e-hentai has with much less
+// to parse and without any limit. handy when e-hentai says we've had enough
+
+use std::sync::Arc;
+use futures::future::{BoxFuture, FutureExt};
+use hyper::body::Bytes;
+use select::document::Document;
+use select::predicate::{Predicate, Attr, Class, Name};
+
+use crate::ehentai::client::Client;
+use crate::ehentai::page::percent_encode;
+use crate::ehentai::{Tag, TagKind, TagMap};
+use super::{Backend, Search, Draft, Gallery, ErrorBox, Vocabulary};
+
+const SITE: &str = "https://hentaifox.com";
+
+#[derive(Clone)]
+pub struct HentaiFox {
+    client: Arc<Client>,
+}
+
+impl HentaiFox {
+    pub fn new() -> Self {
+        Self {
+            client: Arc::new(Client::new()),
+        }
+    }
+
+    pub async fn article(&self, path: &str) -> Result<FoxArticle, ErrorBox> {
+        FoxArticle::new(self.client.clone(), path.to_owned()).await
+    }
+}
+
+impl Default for HentaiFox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct FoxPage {
+    client: Arc<Client>,
+    query: String,
+    page: usize,
+    done: bool,
+}
+
+impl FoxPage {
+    // the next page of results, or none once a page comes empty
+    pub async fn next(&mut self) -> Result<Option<Vec<FoxDraft>>, ErrorBox> {
+        if self.done {
+            return Ok(None);
+        }
+
+        // the site counts pages from 1
+        self.page += 1;
+
+        let path = format!(
+            "{}/search/?q={}&page={}",
+            SITE,
+            percent_encode(&self.query),
+            self.page
+        );
+
+        let list = {
+            let doc = self.client.get_html(path.parse()?).await?;
+            parse_list(&doc)
+        };
+
+        if list.is_empty() {
+            self.done = true;
+            return Ok(None);
+        }
+
+        let list = list
+            .into_iter()
+            .map(|meta| FoxDraft {
+                client: self.client.clone(),
+                meta,
+            })
+            .collect();
+
+        Ok(Some(list))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FoxDraftMeta {
+    pub path: String,
+    pub title: String,
+    pub thumb: String,
+    // search results don't show tags
+    pub tags: TagMap,
+}
+
+pub struct FoxDraft {
+    client: Arc<Client>,
+    meta: FoxDraftMeta,
+}
+
+impl FoxDraft {
+    pub fn meta(&self) -> &FoxDraftMeta {
+        &self.meta
+    }
+
+    pub async fn load(self) -> Result<FoxArticle, ErrorBox> {
+        FoxArticle::new(self.client, self.meta.path).await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FoxMeta {
+    pub path: String,
+    pub title: String,
+    pub thumb: String,
+    pub tags: TagMap,
+    pub length: usize,
+
+    // where the images are, like "https://i.hentaifox.com/(dir)/(id)/"
+    dir: String,
+    id: String,
+    // extension of each image, because they don't share one
+    extensions: Vec<String>,
+}
+
+impl FoxMeta {
+    pub fn image_path(&self, index: usize) -> Option<String> {
+        if index >= self.length {
+            return None;
+        }
+
+        let ext = self.extensions
+            .get(index)
+            .map_or("jpg", |ext| ext.as_str());
+
+        // images start from 1
+        Some(format!(
+            "https://i.hentaifox.com/{}/{}/{}.{}",
+            self.dir, self.id, index + 1, ext
+        ))
+    }
+}
+
+pub struct FoxArticle {
+    client: Arc<Client>,
+    meta: FoxMeta,
+}
+
+impl FoxArticle {
+    async fn new(client: Arc<Client>, path: String) -> Result<Self, ErrorBox> {
+        let path = if path.starts_with('/') {
+            format!("{}{}", SITE, path)
+        } else {
+            path
+        };
+
+        let meta = {
+            let doc = client.get_html(path.parse()?).await?;
+            parse_article(&doc, path)?
+        };
+
+        Ok(Self {
+            client,
+            meta,
+        })
+    }
+
+    pub fn meta(&self) -> &FoxMeta {
+        &self.meta
+    }
+
+    pub fn image_path(&self, index: usize) -> Option<String> {
+        self.meta.image_path(index)
+    }
+
+    pub async fn load_thumb(&self) -> Result<Bytes, ErrorBox> {
+        self.client.get_image(self.meta.thumb.parse()?).await
+    }
+
+    pub async fn load_image(&self, index: usize) -> Result<Bytes, ErrorBox> {
+        let path = self.image_path(index).ok_or_else(|| {
+            crate::ehentai::EhError::IndexOutOfRange {
+                index,
+                len: self.meta.length,
+            }
+        })?;
+
+        self.client.get_image(path.parse()?).await
+    }
+}
+
+// the image source is lazily loaded, so it may be in either of them
+fn image_source(doc: &Document, scope: impl Predicate) -> String {
+    doc.find(scope.descendant(Name("img")))
+        .nth(0)
+        .and_then(|img| img.attr("data-src").or_else(|| img.attr("src")))
+        .unwrap_or("")
+        .to_owned()
+}
+
+// <div class="thumb"><div class="inner_thumb"><a href="/gallery/(id)/">
+// <img data-src="(thumb)"></a></div><div class="caption">
+// <h2 class="g_title"><a ...>(title)</a></h2>...</div></div>
+pub(super) fn parse_list(doc: &Document) -> Vec<FoxDraftMeta> {
+    doc.find(Class("thumb"))
+        .filter_map(|node| {
+            let link = node.find(Class("inner_thumb").descendant(Name("a"))).nth(0)?;
+            let path = link.attr("href")?;
+
+            let thumb = link
+                .find(Name("img"))
+                .nth(0)
+                .and_then(|img| img.attr("data-src").or_else(|| img.attr("src")))
+                .unwrap_or("")
+                .to_owned();
+
+            let title = node
+                .find(Class("g_title"))
+                .nth(0)
+                .map_or_else(String::new, |x| x.text().trim().to_owned());
+
+            Some(FoxDraftMeta {
+                path: format!("{}{}", SITE, path),
+                title,
+                thumb,
+                tags: TagMap::new(),
+            })
+        })
+        .collect()
+}
+
+pub(super) fn parse_article(doc: &Document, path: String) -> Result<FoxMeta, ErrorBox> {
+    let title = doc
+        .find(Class("info").descendant(Name("h1")))
+        .nth(0)
+        .map_or_else(String::new, |x| x.text().trim().to_owned());
+
+    let thumb = image_source(doc, Class("cover"));
+
+    // each namespace has a list like <ul class="artists">
+    let mut tags = TagMap::new();
+
+    let lists = [
+        ("parodies", TagKind::Parody),
+        ("characters", TagKind::Character),
+        ("tags", TagKind::Other),
+        ("artists", TagKind::Artist),
+        ("groups", TagKind::Group),
+        ("languages", TagKind::Language),
+    ];
+
+    for (class, kind) in lists.iter() {
+        let links = doc.find(Name("ul").and(Class(*class)).descendant(Name("a")));
+
+        for link in links {
+            // the name is followed by a badge with the count
+            let name = link
+                .children()
+                .filter_map(|x| x.as_text())
+                .collect::<String>();
+
+            let name = name.trim();
+
+            if !name.is_empty() {
+                tags.add(Tag::new(kind.clone(), name));
+            }
+        }
+    }
+
+    let value = |id: &str| {
+        doc.find(Attr("id", id))
+            .nth(0)
+            .and_then(|x| x.attr("value"))
+            .unwrap_or("")
+            .to_owned()
+    };
+
+    let dir = value("load_dir");
+    let id = value("load_id");
+    let length = value("load_pages").parse::<usize>()?;
+
+    // there is a script like "var g_th = $.parseJSON('{"1":"j,1280,1808",...}');"
+    // which tells the format of each image by the first letter
+    let extensions = doc
+        .find(Name("script"))
+        .map(|x| x.text())
+        .find(|text| text.contains("g_th"))
+        .and_then(|text| {
+            let begin = text.find("('")? + 2;
+            let end = text[begin..].find("')")? + begin;
+            serde_json::from_str::<serde_json::Value>(&text[begin..end]).ok()
+        })
+        .map(|value| {
+            (1..=length)
+                .map(|page| {
+                    let kind = value[page.to_string()].as_str().unwrap_or("j");
+
+                    match kind.split(',').nth(0).unwrap() {
+                        "p" => "png",
+                        "g" => "gif",
+                        "w" => "webp",
+                        _ => "jpg",
+                    }.to_owned()
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    Ok(FoxMeta {
+        path,
+        title,
+        thumb,
+        tags,
+        length,
+        dir,
+        id,
+        extensions,
+    })
+}
+
+impl Backend for HentaiFox {
+    type Search = FoxPage;
+    type Draft = FoxDraft;
+    type Article = FoxArticle;
+
+    fn name(&self) -> &'static str {
+        "hentaifox"
+    }
+
+    fn vocabulary(&self) -> Vocabulary {
+        Vocabulary::Flat
+    }
+
+    fn search(&self, query: &str) -> Self::Search {
+        FoxPage {
+            client: self.client.clone(),
+            query: query.to_owned(),
+            page: 0,
+            done: false,
+        }
+    }
+
+    fn article<'a>(&'a self, path: &'a str)
+        -> BoxFuture<'a, Result<Self::Article, ErrorBox>> {
+        HentaiFox::article(self, path).boxed()
+    }
+}
+
+impl Search for FoxPage {
+    type Draft = FoxDraft;
+
+    fn next(&mut self) -> BoxFuture<'_, Result<Option<Vec<Self::Draft>>, ErrorBox>> {
+        FoxPage::next(self).boxed()
+    }
+}
+
+impl Draft for FoxDraft {
+    type Meta = FoxDraftMeta;
+    type Article = FoxArticle;
+
+    fn meta(&self) -> &Self::Meta {
+        &self.meta
+    }
+
+    fn title(&self) -> &str {
+        &self.meta.title
+    }
+
+    fn path(&self) -> &str {
+        &self.meta.path
+    }
+
+    fn tags(&self) -> &TagMap {
+        &self.meta.tags
+    }
+
+    // search results don't tell; load the article to know
+    fn length(&self) -> usize {
+        0
+    }
+
+    fn load(self) -> BoxFuture<'static, Result<Self::Article, ErrorBox>> {
+        FoxDraft::load(self).boxed()
+    }
+}
+
+impl Gallery for FoxArticle {
+    type Meta = FoxMeta;
+
+    fn meta(&self) -> &Self::Meta {
+        &self.meta
+    }
+
+    fn title(&self) -> &str {
+        &self.meta.title
+    }
+
+    fn path(&self) -> &str {
+        &self.meta.path
+    }
+
+    fn tags(&self) -> &TagMap {
+        &self.meta.tags
+    }
+
+    fn length(&self) -> usize {
+        self.meta.length
+    }
+
+    fn load_thumb(&self) -> BoxFuture<'_, Result<Bytes, ErrorBox>> {
+        FoxArticle::load_thumb(self).boxed()
+    }
+
+    fn load_image(&self, index: usize) -> BoxFuture<'_, Result<Bytes, ErrorBox>> {
+        FoxArticle::load_image(self, index).boxed()
+    }
+}
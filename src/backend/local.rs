@@ -0,0 +1,434 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+// galleries already on the disk, as folders of images or cbz files along
+// with "info.json" or "ComicInfo.xml" like Article::download_to() and
+// Article::export_cbz() write them
+
+use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use futures::future::{self, BoxFuture, FutureExt};
+use serde_json::Value;
+use tokio::fs;
+use hyper::body::Bytes;
+
+use crate::ehentai::{EhError, GalleryId, Tag, TagKind, TagMap};
+use super::{Backend, Search, Draft, Gallery, ErrorBox};
+use super::{is_image, zip_images, read_zip_file};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalFormat {
+    Folder,
+    Cbz,
+}
+
+#[derive(Debug, Clone)]
+pub struct LocalMeta {
+    // the path of the folder or the file, also used as the link
+    pub path: String,
+    pub format: LocalFormat,
+    pub title: String,
+    pub tags: TagMap,
+    // the gallery of e-hentai it came from, if the sidecar says
+    pub source: Option<GalleryId>,
+    pub length: usize,
+}
+
+// take "info.json" in the format of eze
+pub(super) fn read_info_json(text: &str, meta: &mut LocalMeta) {
+    let value = match serde_json::from_str::<Value>(text) {
+        Ok(value) => value,
+        Err(_) => return,
+    };
+
+    let info = &value["gallery_info"];
+
+    if let Some(title) = info["title"].as_str() {
+        meta.title = title.to_owned();
+    }
+
+    for (kind, list) in info["tags"].as_object().into_iter().flatten() {
+        let kind = match kind.parse::<TagKind>() {
+            Ok(kind) => kind,
+            Err(_) => continue,
+        };
+
+        for tag in list.as_array().into_iter().flatten().filter_map(Value::as_str) {
+            meta.tags.add(Tag::new(kind.clone(), tag));
+        }
+    }
+
+    let source = &info["source"];
+
+    if let (Some(gid), Some(token)) = (source["gid"].as_u64(), source["token"].as_str()) {
+        meta.source = Some(GalleryId::new(gid, token));
+    }
+}
+
+// take "ComicInfo.xml"; only a few fields are needed, so no xml parser
+pub(super) fn read_comic_info(text: &str, meta: &mut LocalMeta) {
+    let field = |name: &str| {
+        let begin = text.find(&format!("<{}>", name))? + name.len() + 2;
+        let end = text[begin..].find(&format!("</{}>", name))? + begin;
+
+        Some(unescape(&text[begin..end]))
+    };
+
+    if let Some(title) = field("Title") {
+        meta.title = title;
+    }
+
+    // tags are like "artist:foo, female:glasses"
+    if let Some(tags) = field("Tags") {
+        for tag in tags.split(", ").filter_map(|x| x.parse::<Tag>().ok()) {
+            meta.tags.add(tag);
+        }
+    }
+
+    if let Some(web) = field("Web") {
+        meta.source = web.parse().ok();
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn read_sidecar(name: &str, text: &str, meta: &mut LocalMeta) {
+    match name {
+        "info.json" => read_info_json(text, meta),
+        // info.json has more, so don't let this overwrite it
+        "ComicInfo.xml" if meta.source.is_none() => read_comic_info(text, meta),
+        _ => (),
+    }
+}
+
+pub(super) fn empty_meta(path: &Path, format: LocalFormat) -> LocalMeta {
+    let title = path
+        .file_stem()
+        .map_or_else(String::new, |x| x.to_string_lossy().into_owned());
+
+    LocalMeta {
+        path: path.to_string_lossy().into_owned(),
+        format,
+        title,
+        tags: TagMap::new(),
+        source: None,
+        length: 0,
+    }
+}
+
+// names of the images in a folder, ordered like pages
+async fn folder_images(dir: &Path) -> Result<Vec<PathBuf>, ErrorBox> {
+    let mut images = Vec::new();
+    let mut entries = fs::read_dir(dir).await?;
+
+    while let Some(entry) = entries.next_entry().await? {
+        if is_image(&entry.file_name().to_string_lossy()) {
+            images.push(entry.path());
+        }
+    }
+
+    images.sort();
+    Ok(images)
+}
+
+async fn scan_folder(dir: &Path) -> Result<Option<LocalMeta>, ErrorBox> {
+    let images = folder_images(dir).await?;
+
+    if images.is_empty() {
+        return Ok(None);
+    }
+
+    let mut meta = empty_meta(dir, LocalFormat::Folder);
+    meta.length = images.len();
+
+    // the order matters; see read_sidecar()
+    for name in ["info.json", "ComicInfo.xml"].iter() {
+        if let Ok(text) = fs::read_to_string(dir.join(name)).await {
+            read_sidecar(name, &text, &mut meta);
+        }
+    }
+
+    Ok(Some(meta))
+}
+
+// only the directory of the archive and the sidecars are read, so it's
+// cheap even for large ones. it blocks; run it off the runtime
+fn scan_cbz(path: &Path) -> Result<LocalMeta, ErrorBox> {
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+    let mut meta = empty_meta(path, LocalFormat::Cbz);
+
+    meta.length = zip_images(&zip).len();
+
+    for name in ["info.json", "ComicInfo.xml"].iter() {
+        if let Ok(data) = read_zip_file(&mut zip, name) {
+            read_sidecar(name, &String::from_utf8_lossy(&data), &mut meta);
+        }
+    }
+
+    Ok(meta)
+}
+
+fn is_cbz(path: &Path) -> bool {
+    path.extension()
+        .map_or(false, |ext| ext.eq_ignore_ascii_case("cbz") || ext.eq_ignore_ascii_case("zip"))
+}
+
+// an index of galleries in a directory. it's built once when opened,
+// so call open() again to see what's changed since then
+#[derive(Clone)]
+pub struct Library {
+    root: PathBuf,
+    entries: Arc<Vec<LocalMeta>>,
+}
+
+impl Library {
+    // index every gallery under the directory, however deep it is
+    pub async fn open(root: impl AsRef<Path>) -> Result<Self, ErrorBox> {
+        let root = root.as_ref().to_owned();
+
+        // the root itself has to be there, unlike what's under it
+        fs::read_dir(&root).await?;
+
+        let mut entries = Vec::new();
+        let mut dirs = vec![root.clone()];
+
+        while let Some(dir) = dirs.pop() {
+            // a folder which can't be read shouldn't stop the others from
+            // indexed, as broken files don't
+            if let Ok(Some(meta)) = scan_folder(&dir).await {
+                entries.push(meta);
+            }
+
+            let mut children = match fs::read_dir(&dir).await {
+                Ok(children) => children,
+                Err(_) => continue,
+            };
+
+            while let Ok(Some(entry)) = children.next_entry().await {
+                let path = entry.path();
+                let is_dir = entry.file_type().await.map_or(false, |kind| kind.is_dir());
+
+                if is_dir {
+                    dirs.push(path);
+                } else if is_cbz(&path) {
+                    let scanned = tokio::task::spawn_blocking(move || scan_cbz(&path)).await?;
+
+                    if let Ok(meta) = scanned {
+                        entries.push(meta);
+                    }
+                }
+            }
+        }
+
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(Self {
+            root,
+            entries: Arc::new(entries),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn entries(&self) -> &[LocalMeta] {
+        &self.entries
+    }
+
+    // whether the gallery is downloaded already
+    pub fn contains(&self, id: &GalleryId) -> bool {
+        self.find(id).is_some()
+    }
+
+    pub fn find(&self, id: &GalleryId) -> Option<&LocalMeta> {
+        self.entries
+            .iter()
+            .find(|meta| meta.source.as_ref().map_or(false, |x| x.gid == id.gid))
+    }
+
+    // galleries of which title contains the query, or of which tags
+    // match the query in the search syntax of e-hentai
+    pub fn matches(&self, query: &str) -> Vec<LocalMeta> {
+        let query = query.trim();
+        let lower = query.to_lowercase();
+
+        self.entries
+            .iter()
+            .filter(|meta| {
+                query.is_empty()
+                    || meta.title.to_lowercase().contains(&lower)
+                    || meta.tags.matches(query)
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn article(&self, path: &str) -> Result<LocalArticle, ErrorBox> {
+        self.entries
+            .iter()
+            .find(|meta| meta.path == path)
+            .map(|meta| LocalArticle { meta: meta.clone() })
+            .ok_or_else(|| EhError::NotFound(path.to_owned()).into())
+    }
+}
+
+// everything is found at once, so there is only one page
+pub struct LocalPage {
+    list: Option<Vec<LocalMeta>>,
+}
+
+impl LocalPage {
+    // all of them the first time, and none after that
+    pub fn next(&mut self) -> Option<Vec<LocalDraft>> {
+        let list = self.list.take().filter(|list| !list.is_empty())?;
+        Some(list.into_iter().map(|meta| LocalDraft { meta }).collect())
+    }
+}
+
+pub struct LocalDraft {
+    meta: LocalMeta,
+}
+
+pub struct LocalArticle {
+    meta: LocalMeta,
+}
+
+impl LocalArticle {
+    pub fn meta(&self) -> &LocalMeta {
+        &self.meta
+    }
+
+    pub async fn load_image(&self, index: usize) -> Result<Bytes, ErrorBox> {
+        let out_of_range = EhError::IndexOutOfRange {
+            index,
+            len: self.meta.length,
+        };
+
+        match self.meta.format {
+            LocalFormat::Folder => {
+                let images = folder_images(Path::new(&self.meta.path)).await?;
+                let path = images.get(index).ok_or(out_of_range)?;
+
+                Ok(Bytes::from(fs::read(path).await?))
+            },
+            // only the directory and the one image are read
+            LocalFormat::Cbz => {
+                let path = self.meta.path.clone();
+
+                tokio::task::spawn_blocking(move || {
+                    let mut zip = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+
+                    let name = zip_images(&zip)
+                        .into_iter()
+                        .nth(index)
+                        .ok_or(out_of_range)?;
+
+                    read_zip_file(&mut zip, &name)
+                }).await?
+            },
+        }
+    }
+
+    // there is no thumbnail; it's the first page
+    pub async fn load_thumb(&self) -> Result<Bytes, ErrorBox> {
+        self.load_image(0).await
+    }
+}
+
+impl Backend for Library {
+    type Search = LocalPage;
+    type Draft = LocalDraft;
+    type Article = LocalArticle;
+
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn search(&self, query: &str) -> Self::Search {
+        LocalPage {
+            list: Some(self.matches(query)),
+        }
+    }
+
+    fn article<'a>(&'a self, path: &'a str)
+        -> BoxFuture<'a, Result<Self::Article, ErrorBox>> {
+        future::ready(Library::article(self, path)).boxed()
+    }
+}
+
+impl Search for LocalPage {
+    type Draft = LocalDraft;
+
+    fn next(&mut self) -> BoxFuture<'_, Result<Option<Vec<Self::Draft>>, ErrorBox>> {
+        future::ready(Ok(LocalPage::next(self))).boxed()
+    }
+}
+
+impl Draft for LocalDraft {
+    type Meta = LocalMeta;
+    type Article = LocalArticle;
+
+    fn meta(&self) -> &Self::Meta {
+        &self.meta
+    }
+
+    fn title(&self) -> &str {
+        &self.meta.title
+    }
+
+    fn path(&self) -> &str {
+        &self.meta.path
+    }
+
+    fn tags(&self) -> &TagMap {
+        &self.meta.tags
+    }
+
+    fn length(&self) -> usize {
+        self.meta.length
+    }
+
+    fn load(self) -> BoxFuture<'static, Result<Self::Article, ErrorBox>> {
+        future::ready(Ok(LocalArticle { meta: self.meta })).boxed()
+    }
+}
+
+impl Gallery for LocalArticle {
+    type Meta = LocalMeta;
+
+    fn meta(&self) -> &Self::Meta {
+        &self.meta
+    }
+
+    fn title(&self) -> &str {
+        &self.meta.title
+    }
+
+    fn path(&self) -> &str {
+        &self.meta.path
+    }
+
+    fn tags(&self) -> &TagMap {
+        &self.meta.tags
+    }
+
+    fn length(&self) -> usize {
+        self.meta.length
+    }
+
+    fn load_thumb(&self) -> BoxFuture<'_, Result<Bytes, ErrorBox>> {
+        LocalArticle::load_thumb(self).boxed()
+    }
+
+    fn load_image(&self, index: usize) -> BoxFuture<'_, Result<Bytes, ErrorBox>> {
+        LocalArticle::load_image(self, index).boxed()
+    }
+}
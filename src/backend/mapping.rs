@@ -0,0 +1,91 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+// sites don't agree on what a tag is. e-hentai tells "female:glasses" from
+// "male:glasses", while most of others (nhentai, hentaifox...) only have
+// "glasses". translate between them so tags from any site can be compared
+
+use std::collections::HashMap;
+
+use crate::ehentai::{Tag, TagKind, TagMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Vocabulary {
+    // with male, female, mixed and other namespaces like e-hentai
+    Namespaced,
+    // every tag about the content in one namespace, which is `other`
+    Flat,
+}
+
+// namespaces which are merged into `other` in flat vocabularies
+fn is_content(kind: &TagKind) -> bool {
+    match kind {
+        TagKind::Male | TagKind::Female | TagKind::Mixed | TagKind::Other => true,
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TagMapping {
+    // from namespaced tags to flat ones, and the reverse
+    to_flat: HashMap<Tag, Tag>,
+    to_namespaced: HashMap<Tag, Tag>,
+}
+
+impl TagMapping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // translate the namespaced tag into the flat one and vice versa,
+    // when they're named differently or the namespace can be told,
+    // e.g. "female:big breasts" and "other:big breasts"
+    pub fn rule(mut self, namespaced: Tag, flat: Tag) -> Self {
+        self.to_flat.insert(namespaced.clone(), flat.clone());
+        self.to_namespaced.insert(flat, namespaced);
+        self
+    }
+
+    pub fn to_flat(&self, tags: &TagMap) -> TagMap {
+        let mut res = TagMap::new();
+
+        for tag in tags.tags() {
+            let tag = match self.to_flat.get(&tag) {
+                Some(flat) => flat.clone(),
+                None if is_content(tag.kind()) => Tag::new(TagKind::Other, tag.name()),
+                None => tag,
+            };
+
+            if !res.has(&tag) {
+                res.add(tag);
+            }
+        }
+
+        res
+    }
+
+    // tags without a rule stay in `other` since it can't be told
+    // whether they were male or female
+    pub fn to_namespaced(&self, tags: &TagMap) -> TagMap {
+        let mut res = TagMap::new();
+
+        for tag in tags.tags() {
+            let tag = self.to_namespaced.get(&tag).cloned().unwrap_or(tag);
+
+            if !res.has(&tag) {
+                res.add(tag);
+            }
+        }
+
+        res
+    }
+
+    pub fn translate(&self, tags: &TagMap, from: Vocabulary, to: Vocabulary) -> TagMap {
+        match (from, to) {
+            (Vocabulary::Namespaced, Vocabulary::Flat) => self.to_flat(tags),
+            (Vocabulary::Flat, Vocabulary::Namespaced) => self.to_namespaced(tags),
+            _ => tags.clone(),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+// find the same gallery in another site, e.g. to download from whichever
+// is faster. titles differ a bit between sites, so it's all guesses
+
+use std::collections::HashSet;
+
+use super::{Backend, Search, Draft, Gallery, ErrorBox};
+
+// a gallery which is (probably) the same as the one we have
+pub struct Mirror<D> {
+    pub draft: D,
+    // from 0 to 1; 1 if the titles are the same after normalized and
+    // the number of pages agree
+    pub confidence: f64,
+}
+
+// lowercase words of the title without what's in brackets, like
+// "[Artist] Title (Comic Market 99) [English]" into "title"
+pub fn normalize_title(title: &str) -> String {
+    let mut res = String::new();
+    let mut depth = 0usize;
+
+    for c in title.chars() {
+        match c {
+            '[' | '(' | '{' | '【' => depth += 1,
+            ']' | ')' | '}' | '】' => depth = depth.saturating_sub(1),
+            c if depth == 0 && c.is_alphanumeric() => res.extend(c.to_lowercase()),
+            _ if depth == 0 => res.push(' '),
+            _ => (),
+        }
+    }
+
+    res.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// how many words the two titles share, from 0 to 1
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    let a = normalize_title(a);
+    let b = normalize_title(b);
+
+    let a = a.split(' ').filter(|x| !x.is_empty()).collect::<HashSet<_>>();
+    let b = b.split(' ').filter(|x| !x.is_empty()).collect::<HashSet<_>>();
+
+    let union = a.union(&b).count();
+
+    if union == 0 {
+        return 0.0;
+    }
+
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+fn confidence(title: &str, length: usize, draft: &impl Draft) -> f64 {
+    let similarity = title_similarity(title, draft.title());
+
+    // drafts of some sites don't know the length
+    match draft.length() {
+        0 => similarity * 0.9,
+        len if len == length => similarity,
+        _ => similarity * 0.5,
+    }
+}
+
+// search the backend for the gallery by its title, and give what looks
+// like the same one from the most likely. only the first page of
+// the search is looked at, and those below `threshold` are dropped
+pub async fn find_mirrors<G, B>(source: &G, backend: &B, threshold: f64)
+    -> Result<Vec<Mirror<B::Draft>>, ErrorBox>
+    where G: Gallery, B: Backend {
+    let title = normalize_title(source.title());
+
+    if title.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut search = backend.search(&title);
+    let list = search.next().await?.unwrap_or_default();
+
+    let mut mirrors = list
+        .into_iter()
+        .map(|draft| Mirror {
+            confidence: confidence(source.title(), source.length(), &draft),
+            draft,
+        })
+        .filter(|mirror| mirror.confidence >= threshold)
+        .collect::<Vec<_>>();
+
+    // confidences are never NaN
+    mirrors.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap());
+    Ok(mirrors)
+}
@@ -0,0 +1,117 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+// what every site looks like from far away: search for drafts, load an
+// article from a draft, and load images from the article. write against
+// these to support any site at once
+//
+// traits can't have async functions yet, so they give boxed futures
+
+mod ehentai;
+pub mod hentaifox;
+pub mod chaika;
+pub mod local;
+mod mapping;
+mod mirror;
+#[cfg(test)]
+mod tests;
+
+use std::error::Error;
+use std::io::{Read, Seek};
+use futures::future::BoxFuture;
+use hyper::body::Bytes;
+
+use crate::ehentai::TagMap;
+
+pub use mapping::{Vocabulary, TagMapping};
+pub use mirror::{Mirror, normalize_title, title_similarity, find_mirrors};
+
+pub type ErrorBox = Box<dyn Error + Send + Sync>;
+
+pub trait Backend: Send + Sync {
+    type Search: Search<Draft = Self::Draft>;
+    type Draft: Draft<Article = Self::Article>;
+    type Article: Gallery;
+
+    // short name of the site, like "e-hentai"
+    fn name(&self) -> &'static str;
+
+    // how the site names tags; see TagMapping
+    fn vocabulary(&self) -> Vocabulary {
+        Vocabulary::Namespaced
+    }
+
+    // the query is in whatever syntax the site understands
+    fn search(&self, query: &str) -> Self::Search;
+
+    // get an article from its link, without searching
+    fn article<'a>(&'a self, path: &'a str)
+        -> BoxFuture<'a, Result<Self::Article, ErrorBox>>;
+}
+
+// pages of search results, one by one
+pub trait Search: Send {
+    type Draft: Draft;
+
+    fn next(&mut self) -> BoxFuture<'_, Result<Option<Vec<Self::Draft>>, ErrorBox>>;
+}
+
+// an entry of search results, which has only a part of the metadata
+pub trait Draft: Send + Sync {
+    type Meta;
+    type Article: Gallery;
+
+    fn meta(&self) -> &Self::Meta;
+
+    fn title(&self) -> &str;
+    fn path(&self) -> &str;
+    fn tags(&self) -> &TagMap;
+    fn length(&self) -> usize;
+
+    fn load(self) -> BoxFuture<'static, Result<Self::Article, ErrorBox>>;
+}
+
+pub trait Gallery: Send + Sync {
+    type Meta;
+
+    fn meta(&self) -> &Self::Meta;
+
+    fn title(&self) -> &str;
+    fn path(&self) -> &str;
+    fn tags(&self) -> &TagMap;
+    // number of the images
+    fn length(&self) -> usize;
+
+    fn load_thumb(&self) -> BoxFuture<'_, Result<Bytes, ErrorBox>>;
+    fn load_image(&self, index: usize) -> BoxFuture<'_, Result<Bytes, ErrorBox>>;
+}
+
+// whether the file name looks like an image
+fn is_image(name: &str) -> bool {
+    const EXTENSIONS: [&str; 5] = [".jpg", ".jpeg", ".png", ".gif", ".webp"];
+
+    let name = name.to_lowercase();
+    EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+}
+
+// names of the images in a zip file, ordered by the names like pages are
+fn zip_images<R: Read + Seek>(zip: &zip::ZipArchive<R>) -> Vec<String> {
+    let mut names = zip
+        .file_names()
+        .filter(|name| is_image(name))
+        .map(|name| name.to_owned())
+        .collect::<Vec<_>>();
+
+    names.sort();
+    names
+}
+
+fn read_zip_file<R: Read + Seek>(zip: &mut zip::ZipArchive<R>, name: &str)
+    -> Result<Bytes, ErrorBox> {
+    let mut file = zip.by_name(name)?;
+    let mut data = Vec::with_capacity(file.size() as usize);
+
+    file.read_to_end(&mut data)?;
+    Ok(Bytes::from(data))
+}
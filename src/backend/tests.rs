@@ -0,0 +1,384 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use select::document::Document;
+
+use crate::ehentai::{Tag, TagKind};
+
+// pages as the sites gave them, trimmed down
+macro_rules! fixture {
+    ($name:literal) => {
+        include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/", $name))
+    };
+}
+
+#[test]
+fn fixture_fox_search() {
+    use super::hentaifox;
+
+    let list = hentaifox::parse_list(&Document::from(fixture!("hentaifox_search.html")));
+    assert_eq!(list.len(), 2);
+
+    assert_eq!(list[0].path, "https://hentaifox.com/gallery/12345/");
+    assert_eq!(list[0].title, "[Someone] Some Title");
+    // the lazy one, rather than the placeholder
+    assert_eq!(list[0].thumb, "https://i.hentaifox.com/003/1234567/thumb.jpg");
+    assert_eq!(list[0].tags.tags().count(), 0);
+
+    assert_eq!(list[1].title, "Another Title");
+    assert_eq!(list[1].thumb, "https://i.hentaifox.com/003/1234568/thumb.webp");
+}
+
+#[test]
+fn fixture_fox_gallery() {
+    use super::hentaifox;
+
+    let path = "https://hentaifox.com/gallery/12345/";
+    let doc = Document::from(fixture!("hentaifox_gallery.html"));
+    let meta = hentaifox::parse_article(&doc, path.to_owned()).unwrap();
+
+    assert_eq!(meta.path, path);
+    assert_eq!(meta.title, "[Someone] Some Title");
+    assert_eq!(meta.thumb, "https://i.hentaifox.com/003/1234567/cover.jpg");
+    assert_eq!(meta.length, 3);
+
+    // without the counts after the names
+    assert_eq!(meta.tags.first(TagKind::Artist), Some("someone"));
+    assert_eq!(meta.tags.first(TagKind::Parody), Some("original"));
+    assert_eq!(meta.tags.first(TagKind::Language), Some("english"));
+    assert!(meta.tags.has(&Tag::new(TagKind::Other, "schoolgirl uniform")));
+    assert_eq!(meta.tags.namespace(TagKind::Other).count(), 2);
+
+    // each image has its own format
+    assert_eq!(meta.image_path(0).unwrap(), "https://i.hentaifox.com/003/1234567/1.jpg");
+    assert_eq!(meta.image_path(1).unwrap(), "https://i.hentaifox.com/003/1234567/2.png");
+    assert_eq!(meta.image_path(2).unwrap(), "https://i.hentaifox.com/003/1234567/3.webp");
+    assert_eq!(meta.image_path(3), None);
+
+    assert!(hentaifox::parse_article(&Document::from("<p></p>"), path.to_owned()).is_err());
+}
+
+#[test]
+fn chaika_archives() {
+    use serde_json::Value;
+    use crate::ehentai::{ArticleKind, GalleryId};
+    use super::chaika;
+
+    // what "/jsearch/?gid=1234&key=abcdef1234" gives
+    let value = serde_json::from_str::<Value>(fixture!("chaika_gid.json")).unwrap();
+    let list = chaika::archive_list(&value);
+    assert_eq!(list.len(), 1);
+
+    let archive = &list[0];
+    assert_eq!(archive.id, 31234);
+    assert_eq!(archive.path, "https://panda.chaika.moe/archive/31234/");
+    assert_eq!(archive.download_path(), "https://panda.chaika.moe/archive/31234/download/");
+    assert_eq!(archive.title, "[Someone] Some Title [English]");
+    assert_eq!(archive.kind, Some(ArticleKind::Doujinshi));
+    // numbers come as strings or not, at random
+    assert_eq!(archive.posted, 1614861240);
+    assert_eq!(archive.length, 24);
+    assert_eq!(archive.file_size, 52380000);
+    assert_eq!(archive.rating, 4.52);
+    assert!(archive.expunged);
+    assert_eq!(archive.source, Some(GalleryId::new(1234, "abcdef1234")));
+
+    // underscores are spaces, and those without a namespace are others
+    assert!(archive.tags.has(&"female:big breasts".parse::<Tag>().unwrap()));
+    assert!(archive.tags.has(&Tag::new(TagKind::Other, "full color")));
+    assert_eq!(archive.tags.first(TagKind::Artist), Some("someone"));
+
+    // and "/jsearch/?sha1=(hash)", which has the list in an object
+    let value = serde_json::from_str::<Value>(fixture!("chaika_sha1.json")).unwrap();
+    let list = chaika::archive_list(&value);
+    assert_eq!(list.iter().map(|x| x.id).collect::<Vec<_>>(), vec![31234, 40000]);
+
+    assert_eq!(list[0].source, Some(GalleryId::new(1234, "abcdef1234")));
+    assert_eq!(list[0].length, 24);
+    assert_eq!(list[0].rating, 4.5);
+    assert_eq!(list[1].source, None);
+    assert_eq!(list[1].kind, None);
+    assert_eq!(list[1].posted, 0);
+
+    assert!(chaika::archive_list(&Value::Null).is_empty());
+}
+
+#[tokio::test]
+async fn local_sidecars() {
+    use std::io::Write;
+    use std::path::Path;
+    use zip::ZipWriter;
+    use zip::write::FileOptions;
+    use crate::ehentai::{ArticleMeta, GalleryId, TagMap};
+    use crate::ehentai::tests::article_meta;
+    use super::local::{self, Library, LocalFormat};
+
+    let meta = ArticleMeta {
+        title: "[Someone] Some <Title> & more".to_owned(),
+        length: 3,
+        tags: vec!["artist:someone", "female:glasses", "language:english"]
+            .into_iter()
+            .map(|x| x.parse::<Tag>().unwrap())
+            .collect::<TagMap>(),
+        ..article_meta()
+    };
+
+    let source = Some(GalleryId::new(1234, "abcdef1234"));
+
+    // what's written is read back the same
+    let mut read = local::empty_meta(Path::new("some/where"), LocalFormat::Folder);
+    local::read_info_json(&meta.info_json(), &mut read);
+    assert_eq!(read.title, meta.title);
+    assert_eq!(read.tags, meta.tags);
+    assert_eq!(read.source, source);
+
+    let mut read = local::empty_meta(Path::new("some/where.cbz"), LocalFormat::Cbz);
+    local::read_comic_info(&meta.comic_info(), &mut read);
+    assert_eq!(read.title, meta.title);
+    assert_eq!(read.tags, meta.tags);
+    assert_eq!(read.source, source);
+
+    // and the length is of the images along with them
+    let root = std::env::temp_dir().join(format!("lude-library-{}", std::process::id()));
+    let folder = root.join("folder");
+    tokio::fs::create_dir_all(&folder).await.unwrap();
+
+    for name in &["001.jpg", "002.jpg", "003.png"] {
+        tokio::fs::write(folder.join(name), b"not really an image").await.unwrap();
+    }
+
+    tokio::fs::write(folder.join("info.json"), meta.info_json()).await.unwrap();
+
+    let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+
+    for name in &["001.jpg", "002.jpg", "003.png", "ComicInfo.xml"] {
+        zip.start_file(*name, FileOptions::default()).unwrap();
+
+        match *name {
+            "ComicInfo.xml" => zip.write_all(meta.comic_info().as_bytes()).unwrap(),
+            _ => zip.write_all(b"not really an image").unwrap(),
+        }
+    }
+
+    let data = zip.finish().unwrap().into_inner();
+    tokio::fs::write(root.join("archive.cbz"), data).await.unwrap();
+
+    let library = Library::open(&root).await.unwrap();
+    let entries = library.entries();
+    assert_eq!(entries.len(), 2);
+
+    for entry in entries {
+        assert_eq!(entry.title, meta.title);
+        assert_eq!(entry.tags, meta.tags);
+        assert_eq!(entry.length, meta.length);
+    }
+
+    assert_eq!(entries[0].format, LocalFormat::Cbz);
+    assert_eq!(entries[1].format, LocalFormat::Folder);
+
+    let article = library.article(&entries[0].path).unwrap();
+    assert_eq!(&article.load_image(2).await.unwrap()[..], b"not really an image");
+    assert!(article.load_image(3).await.is_err());
+
+    tokio::fs::remove_dir_all(&root).await.unwrap();
+}
+
+#[test]
+fn tag_mapping() {
+    use crate::ehentai::TagMap;
+    use super::{TagMapping, Vocabulary};
+
+    let tag = |x: &str| x.parse::<Tag>().unwrap();
+    let tags = |list: &[&str]| list.iter().map(|x| tag(x)).collect::<TagMap>();
+
+    let mapping = TagMapping::new().rule(tag("female:big breasts"), tag("other:large breasts"));
+
+    let namespaced = tags(&["female:big breasts", "female:glasses", "male:glasses", "artist:someone"]);
+    let flat = mapping.to_flat(&namespaced);
+
+    // both of glasses are the same once flat
+    let mut other = flat.namespace(TagKind::Other).collect::<Vec<_>>();
+    other.sort();
+    assert_eq!(other, vec!["glasses", "large breasts"]);
+    assert_eq!(flat.namespace(TagKind::Female).count(), 0);
+    assert_eq!(flat.namespace(TagKind::Male).count(), 0);
+    assert_eq!(flat.first(TagKind::Artist), Some("someone"));
+
+    let back = mapping.to_namespaced(&flat);
+    assert!(back.has(&tag("female:big breasts")));
+    // it can't be told whose glasses they were
+    assert!(back.has(&tag("other:glasses")));
+    assert!(!back.has(&tag("other:large breasts")));
+    assert_eq!(back.first(TagKind::Artist), Some("someone"));
+
+    assert_eq!(mapping.translate(&namespaced, Vocabulary::Namespaced, Vocabulary::Flat), flat);
+    assert_eq!(mapping.translate(&namespaced, Vocabulary::Flat, Vocabulary::Flat), namespaced);
+}
+
+// a gallery of no site, for what works on any of them
+struct Fake {
+    title: String,
+    length: usize,
+    tags: crate::ehentai::TagMap,
+}
+
+fn fake(title: &str, length: usize) -> Fake {
+    Fake {
+        title: title.to_owned(),
+        length,
+        tags: crate::ehentai::TagMap::new(),
+    }
+}
+
+impl super::Draft for Fake {
+    type Meta = ();
+    type Article = Fake;
+
+    fn meta(&self) -> &() {
+        &()
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn path(&self) -> &str {
+        ""
+    }
+
+    fn tags(&self) -> &crate::ehentai::TagMap {
+        &self.tags
+    }
+
+    fn length(&self) -> usize {
+        self.length
+    }
+
+    fn load(self) -> futures::future::BoxFuture<'static, Result<Fake, super::ErrorBox>> {
+        Box::pin(futures::future::ready(Ok(self)))
+    }
+}
+
+impl super::Gallery for Fake {
+    type Meta = ();
+
+    fn meta(&self) -> &() {
+        &()
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn path(&self) -> &str {
+        ""
+    }
+
+    fn tags(&self) -> &crate::ehentai::TagMap {
+        &self.tags
+    }
+
+    fn length(&self) -> usize {
+        self.length
+    }
+
+    fn load_thumb(&self) -> futures::future::BoxFuture<'_, Result<hyper::body::Bytes, super::ErrorBox>> {
+        Box::pin(futures::future::ready(Err("no thumbnail".into())))
+    }
+
+    fn load_image(&self, _: usize) -> futures::future::BoxFuture<'_, Result<hyper::body::Bytes, super::ErrorBox>> {
+        Box::pin(futures::future::ready(Err("no image".into())))
+    }
+}
+
+// a site which has the same results for any query, and tells what's asked
+struct FakeSite {
+    results: Vec<(&'static str, usize)>,
+    queries: std::sync::Mutex<Vec<String>>,
+}
+
+struct FakePage(Option<Vec<Fake>>);
+
+impl super::Search for FakePage {
+    type Draft = Fake;
+
+    fn next(&mut self) -> futures::future::BoxFuture<'_, Result<Option<Vec<Fake>>, super::ErrorBox>> {
+        Box::pin(futures::future::ready(Ok(self.0.take())))
+    }
+}
+
+impl super::Backend for FakeSite {
+    type Search = FakePage;
+    type Draft = Fake;
+    type Article = Fake;
+
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn search(&self, query: &str) -> FakePage {
+        self.queries.lock().unwrap().push(query.to_owned());
+        FakePage(Some(self.results.iter().map(|&(title, length)| fake(title, length)).collect()))
+    }
+
+    fn article<'a>(&'a self, path: &'a str)
+        -> futures::future::BoxFuture<'a, Result<Fake, super::ErrorBox>> {
+        Box::pin(futures::future::ready(Ok(fake(path, 0))))
+    }
+}
+
+#[test]
+fn mirror_titles() {
+    use super::{normalize_title, title_similarity};
+
+    assert_eq!(normalize_title("[Artist] Title (Comic Market 99) [English]"), "title");
+    assert_eq!(normalize_title("(C97) [Circle (Artist)] Some Title: Part 1 (Some Parody)"), "some title part 1");
+    assert_eq!(normalize_title("【Event】 Full-Width Title【中文】"), "full width title");
+    assert_eq!(normalize_title("[Only Brackets]"), "");
+
+    assert_eq!(title_similarity("[A] Some Title", "(C99) some title [English]"), 1.0);
+    assert_eq!(title_similarity("Some Title", "Some Other Title"), 2.0 / 3.0);
+    assert_eq!(title_similarity("Some Title", "Nothing Alike"), 0.0);
+    assert_eq!(title_similarity("[A]", "(B)"), 0.0);
+}
+
+#[tokio::test]
+async fn mirror_confidence() {
+    use super::find_mirrors;
+
+    let site = FakeSite {
+        results: vec![
+            ("[Circle] Some Title [Chinese]", 20),
+            ("Some Title", 0),
+            ("(C99) Some Title", 24),
+            ("Some Other Title", 20),
+            ("Nothing Alike", 20),
+        ],
+        queries: std::sync::Mutex::new(Vec::new()),
+    };
+
+    let mirrors = find_mirrors(&fake("[Artist] Some Title [English]", 20), &site, 0.3)
+        .await
+        .unwrap();
+
+    let found = mirrors
+        .iter()
+        .map(|mirror| (mirror.draft.title.as_str(), mirror.confidence))
+        .collect::<Vec<_>>();
+
+    // the pages agree, then it's not known, then they disagree
+    assert_eq!(found, vec![
+        ("[Circle] Some Title [Chinese]", 1.0),
+        ("Some Title", 0.9),
+        ("Some Other Title", 2.0 / 3.0),
+        ("(C99) Some Title", 0.5),
+    ]);
+
+    assert_eq!(*site.queries.lock().unwrap(), vec!["some title"]);
+
+    // nothing to search by, so it's not even asked
+    let mirrors = find_mirrors(&fake("[Artist] (Event)", 20), &site, 0.0).await.unwrap();
+    assert!(mirrors.is_empty());
+    assert_eq!(site.queries.lock().unwrap().len(), 1);
+}
@@ -0,0 +1,226 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::error::Error;
+use std::str::FromStr;
+use serde_json::{json, Value};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+
+use super::client::Client;
+use super::article::DraftMeta;
+use super::error::EhError;
+use super::id::GalleryId;
+use super::image::ImageEntry;
+use super::tag::{ArticleKind, Tag, TagKind, TagMap};
+#[cfg(feature = "chrono")]
+use super::date;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+pub(super) const API: &str = "https://api.e-hentai.org/api.php";
+
+// the api doesn't allow more than this in a request
+const GALLERIES_PER_REQUEST: usize = 25;
+
+// metadata of a gallery from the api. it doesn't have as much as
+// the gallery page does, but it's much cheaper to get
+#[derive(Debug, Clone)]
+pub struct GalleryData {
+    pub id: GalleryId,
+    pub title: String,
+    pub title_jpn: String,
+    pub kind: ArticleKind,
+    pub thumb: String,
+    pub uploader: String,
+    // unix timestamp
+    pub posted: u64,
+    pub file_count: usize,
+    // size of the original files in bytes
+    pub file_size: u64,
+    pub expunged: bool,
+    pub rating: f64,
+    pub torrent_count: usize,
+    pub tags: TagMap,
+}
+
+// the api gives numbers as strings at random
+pub(crate) fn number<T: FromStr>(value: &Value) -> Option<T> {
+    match value {
+        Value::String(text) => text.parse().ok(),
+        Value::Number(num) => num.to_string().parse().ok(),
+        _ => None
+    }
+}
+
+fn api_error(message: &str) -> EhError {
+    EhError::Api(message.to_owned())
+}
+
+impl GalleryData {
+    #[cfg(feature = "chrono")]
+    pub fn posted_at(&self) -> Option<DateTime<Utc>> {
+        date::from_unix(self.posted)
+    }
+
+    fn from_value(value: &Value) -> Result<Self, ErrorBox> {
+        let gid = number::<u64>(&value["gid"])
+            .ok_or_else(|| api_error("gallery without gid"))?;
+
+        if let Some(err) = value["error"].as_str() {
+            return Err(EhError::Api(format!("{}: {}", gid, err)).into());
+        }
+
+        let text = |key: &str| value[key].as_str().unwrap_or("").to_owned();
+
+        let token = value["token"]
+            .as_str()
+            .ok_or_else(|| api_error("gallery without token"))?;
+
+        let mut tags = TagMap::new();
+
+        for tag in value["tags"].as_array().into_iter().flatten() {
+            let tag = tag.as_str().unwrap_or("");
+
+            // tags without namespace are others
+            match tag.parse::<Tag>() {
+                Ok(tag) => tags.add(tag),
+                Err(_) => tags.add(Tag::new(TagKind::Other, tag)),
+            }
+        }
+
+        Ok(Self {
+            id: GalleryId::new(gid, token),
+            title: text("title"),
+            title_jpn: text("title_jpn"),
+            kind: text("category").parse()?,
+            thumb: text("thumb"),
+            uploader: text("uploader"),
+            posted: number(&value["posted"]).unwrap_or(0),
+            file_count: number(&value["filecount"]).unwrap_or(0),
+            file_size: number(&value["filesize"]).unwrap_or(0),
+            expunged: value["expunged"].as_bool().unwrap_or(false),
+            rating: number(&value["rating"]).unwrap_or(0.0),
+            torrent_count: number(&value["torrentcount"]).unwrap_or(0),
+            tags,
+        })
+    }
+}
+
+pub(super) async fn call(client: &Client, body: Value)
+    -> Result<Value, ErrorBox> {
+    let res = client.post_json(API.parse()?, &body).await?;
+
+    match res["error"].as_str() {
+        Some(err) => Err(api_error(err).into()),
+        None => Ok(res)
+    }
+}
+
+// same as call(), for the methods which change something
+pub(super) async fn call_fresh(client: &Client, body: Value)
+    -> Result<Value, ErrorBox> {
+    let res = client.post_json_fresh(API.parse()?, &body).await?;
+
+    match res["error"].as_str() {
+        Some(err) => Err(api_error(err).into()),
+        None => Ok(res)
+    }
+}
+
+// metadata of galleries as the api gives, a request for each 25 of them
+async fn gmetadata(client: &Client, ids: &[GalleryId])
+    -> Result<Vec<Value>, ErrorBox> {
+    let mut items = Vec::with_capacity(ids.len());
+
+    for chunk in ids.chunks(GALLERIES_PER_REQUEST) {
+        let gidlist = chunk
+            .iter()
+            .map(|id| json!([id.gid, id.token]))
+            .collect::<Vec<_>>();
+
+        let mut res = call(client, json!({
+            "method": "gdata",
+            "gidlist": gidlist,
+            "namespace": 1,
+        })).await?;
+
+        match res["gmetadata"].take() {
+            Value::Array(list) => items.extend(list),
+            _ => return Err(api_error("response without gmetadata").into())
+        }
+    }
+
+    Ok(items)
+}
+
+// get metadata of galleries, in the same order as given
+pub(super) async fn gdata(client: &Client, ids: &[GalleryId])
+    -> Result<Vec<GalleryData>, ErrorBox> {
+    gmetadata(client, ids)
+        .await?
+        .iter()
+        .map(GalleryData::from_value)
+        .collect()
+}
+
+// same as gdata(), but those the api doesn't know, like galleries which
+// are gone, are left out rather than failing all of them
+pub(super) async fn gdata_known(client: &Client, ids: &[GalleryId])
+    -> Result<Vec<GalleryData>, ErrorBox> {
+    Ok(gmetadata(client, ids)
+        .await?
+        .iter()
+        .filter_map(|item| GalleryData::from_value(item).ok())
+        .collect())
+}
+
+// fill in drafts of a search by the api, which is a request for the
+// whole page rather than one for each gallery. those the api doesn't
+// know (or which aren't galleries of the site) are left as they are
+pub(super) async fn enrich(client: &Client, list: &mut [DraftMeta])
+    -> Result<(), ErrorBox> {
+    let ids = list.iter().filter_map(DraftMeta::id).collect::<Vec<_>>();
+
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    for item in gmetadata(client, &ids).await?.iter() {
+        let data = match GalleryData::from_value(item) {
+            Ok(data) => data,
+            Err(_) => continue,
+        };
+
+        if let Some(meta) = list.iter_mut().find(|meta| meta.id().as_ref() == Some(&data.id)) {
+            meta.enrich(data);
+        }
+    }
+
+    Ok(())
+}
+
+// find the gallery an image page belongs to
+pub(super) async fn gtoken(client: &Client, entry: &ImageEntry)
+    -> Result<GalleryId, ErrorBox> {
+    let res = call(client, json!({
+        "method": "gtoken",
+        "pagelist": [[entry.gid, entry.hash, entry.page]],
+    })).await?;
+
+    let item = &res["tokenlist"][0];
+
+    if let Some(err) = item["error"].as_str() {
+        return Err(api_error(err).into());
+    }
+
+    let gid = number::<u64>(&item["gid"])
+        .ok_or_else(|| api_error("token without gid"))?;
+
+    let token = item["token"]
+        .as_str()
+        .ok_or_else(|| api_error("token without token"))?;
+
+    Ok(GalleryId::new(gid, token))
+}
@@ -2,27 +2,132 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use std::fmt;
 use std::slice;
-use std::sync::Arc;
+use std::ops::Range;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::hash::{Hash, Hasher};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use hyper::body::Bytes;
+use select::document::Document;
 
 use super::tag::{ArticleKind, TagMap};
-use super::client::Client;
-use super::parser;
+use super::language::Language;
+use super::client::{self, Client};
+use super::image::{ImageEntry, Preview, ImageData, ImageFormat, Crop};
+use super::id::{ParseGalleryIdError, GalleryId};
+use super::error::EhError;
+use super::progress::Progress;
+use super::parser::{self, ParseWarning};
+use super::api;
+use super::limits;
+use super::verify;
+use super::rating::{self, Rating};
+use super::markup::CommentBody;
+#[cfg(feature = "chrono")]
+use super::date;
 
-type ErrorBox = Box<dyn std::error::Error>;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer, Deserialize};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+
+type ErrorBox = Box<dyn std::error::Error + Send + Sync>;
+
+// number of images shown in a page of an article, by default; accounts
+// may have it set to 20, 100 or 200 instead
+const IMAGES_PER_PAGE: usize = 40;
+
+// number of pages of an image list to fetch at once by default
+const LIST_CONCURRENCY: usize = 4;
+
+// number of images to download at once by default
+const IMAGE_CONCURRENCY: usize = 4;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DraftMeta {
     pub kind: ArticleKind,
     pub thumb: String,
+    // where the thumbnail is, if `thumb` is a sprite of several ones
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub thumb_crop: Option<Crop>,
     pub posted: String,
     pub path: String,
     pub title: String,
     pub tags: TagMap,
     pub uploader: String,
     pub length: usize,
+    // in stars as the list shows, rounded to halves
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rating: Option<f32>,
+    // whether there are torrents of the gallery
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub torrents: bool,
+    // the category of the favorites of the account it's in, if any
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub favorite: Option<usize>,
+    // size of the original files in bytes, and whether the gallery is
+    // expunged; lists don't show them, so they're only known if the
+    // page was enriched by the api. see Page::enrich()
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub file_size: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub expunged: bool,
+}
+
+impl DraftMeta {
+    pub fn id(&self) -> Option<GalleryId> {
+        self.path.parse().ok()
+    }
+
+    // lists don't show the language but by the tags
+    pub fn language(&self) -> Language {
+        Language::from_tags(&self.tags)
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn posted_at(&self) -> Option<DateTime<Utc>> {
+        date::to_utc(&self.posted)
+    }
+
+    // take what the api says of the gallery. its tags are all of them,
+    // while lists may show only some (or none, in minimal modes)
+    pub(super) fn enrich(&mut self, data: api::GalleryData) {
+        self.tags = data.tags;
+        self.file_size = Some(data.file_size);
+        self.expunged = data.expunged;
+        self.torrents = data.torrent_count > 0;
+    }
+}
+
+// what tells a gallery from the others; the id, or the path if it's not
+// a gallery of the site somehow
+fn identity(path: &str) -> Result<GalleryId, &str> {
+    path.parse().map_err(|_| path)
+}
+
+// metadata are the same if they're of the same gallery, even if one is
+// fetched later than the other
+impl PartialEq for DraftMeta {
+    fn eq(&self, other: &Self) -> bool {
+        identity(&self.path) == identity(&other.path)
+    }
+}
+
+impl Eq for DraftMeta {}
+
+impl Hash for DraftMeta {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        identity(&self.path).hash(state);
+    }
 }
 
+#[derive(Clone)]
 pub struct Draft {
     client: Arc<Client>,
     meta: DraftMeta,
@@ -40,17 +145,35 @@ impl Draft {
         &self.meta
     }
 
-    pub async fn load_thumb(&self) -> Result<Vec<u8>, ErrorBox> {
-        self.client.get_image(self.meta.thumb.parse()?).await
+    // the thumbnail cut out of its sprite with the image feature; the
+    // whole sprite without it, to be cut by `thumb_crop`
+    pub async fn load_thumb(&self) -> Result<Bytes, ErrorBox> {
+        let data = self.client.get_image(self.meta.thumb.parse()?).await?;
+
+        match &self.meta.thumb_crop {
+            #[cfg(feature = "image")]
+            Some(crop) => Ok(Bytes::from(crop.apply(&data)?)),
+            _ => Ok(data)
+        }
     }
 
-    pub async fn load(self) -> Result<Article, ErrorBox> {
-        Article::new(self.client, self.meta.path).await
+    // the draft stays, so it can be kept in a list while loading
+    pub async fn load(&self) -> Result<Article, ErrorBox> {
+        Article::new(self.client.clone(), self.meta.path.clone()).await
+    }
+}
+
+// only the metadata is serialized; get it back by Explorer::draft_from_meta()
+#[cfg(feature = "serde")]
+impl Serialize for Draft {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.meta.serialize(serializer)
     }
 }
 
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ArticleMeta {
     pub path: String,
 
@@ -63,7 +186,7 @@ pub struct ArticleMeta {
     pub posted: String,
     pub parent: Option<String>,
     pub visible: bool, // 'offensive for everyone' flag
-    pub language: String,
+    pub language: Language,
     pub translated: bool,
     pub file_size: String,
     pub length: usize,
@@ -74,14 +197,110 @@ pub struct ArticleMeta {
     pub tags: TagMap,
 }
 
-#[derive(Debug)]
+// parse a size of the gallery page like "24.15 MiB" into bytes. it's
+// rounded on the page, so it's a bit off from what the api says
+pub(super) fn parse_size(size: &str) -> Option<u64> {
+    let mut iter = size.split_whitespace();
+    let number = iter.next()?.parse::<f64>().ok()?;
+
+    let unit: u64 = match iter.next()? {
+        "B" => 1,
+        "KiB" | "KB" => 1 << 10,
+        "MiB" | "MB" => 1 << 20,
+        "GiB" | "GB" => 1 << 30,
+        "TiB" | "TB" => 1 << 40,
+        _ => return None
+    };
+
+    match number.is_finite() && number >= 0.0 {
+        true => Some((number * unit as f64).round() as u64),
+        false => None
+    }
+}
+
+impl ArticleMeta {
+    pub fn id(&self) -> Option<GalleryId> {
+        self.path.parse().ok()
+    }
+
+    // file_size in bytes
+    pub fn file_size_bytes(&self) -> Option<u64> {
+        parse_size(&self.file_size)
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn posted_at(&self) -> Option<DateTime<Utc>> {
+        date::to_utc(&self.posted)
+    }
+}
+
+// titles longer than this are cut in summaries
+const SUMMARY_TITLE: usize = 60;
+
+fn shorten(title: &str) -> String {
+    match title.char_indices().nth(SUMMARY_TITLE) {
+        Some((pos, _)) => format!("{}...", title[..pos].trim_end()),
+        None => title.to_owned()
+    }
+}
+
+// like "some title [Korean] (20 pages)"
+impl fmt::Display for DraftMeta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} [{}] ({} pages)", self.title, self.language(), self.length)
+    }
+}
+
+// like "some title [Korean] (20 pages, 4.52)"
+impl fmt::Display for ArticleMeta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f, "{} [{}] ({} pages, {:.2})",
+            self.title, self.language, self.length, self.rating
+        )
+    }
+}
+
+impl DraftMeta {
+    // the same as to_string(), but with a long title cut
+    pub fn summary(&self) -> String {
+        format!("{} [{}] ({} pages)", shorten(&self.title), self.language(), self.length)
+    }
+}
+
+impl ArticleMeta {
+    pub fn summary(&self) -> String {
+        format!(
+            "{} [{}] ({} pages, {:.2})",
+            shorten(&self.title), self.language, self.length, self.rating
+        )
+    }
+}
+
+impl PartialEq for ArticleMeta {
+    fn eq(&self, other: &Self) -> bool {
+        identity(&self.path) == identity(&other.path)
+    }
+}
+
+impl Eq for ArticleMeta {}
+
+impl Hash for ArticleMeta {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        identity(&self.path).hash(state);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub(super) struct Vote {
     pub(super) score: i64,
     pub(super) voters: Vec<(String, i64)>,
     pub(super) omitted: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Comment {
     pub(super) posted: String,
     pub(super) edited: Option<String>,
@@ -90,10 +309,45 @@ pub struct Comment {
     pub(super) vote: Option<Vote>,
 
     pub(super) writer: String,
+    // id of the writer in the forum, which is linked from the name
+    pub(super) writer_id: Option<u64>,
+    // the text without markup, and the markup
     pub(super) content: String,
+    pub(super) body: CommentBody,
 }
 
 impl Comment {
+    // like "04 March 2021, 12:34"
+    pub fn posted(&self) -> &str {
+        &self.posted
+    }
+
+    pub fn edited(&self) -> Option<&str> {
+        self.edited.as_deref()
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn posted_at(&self) -> Option<DateTime<Utc>> {
+        date::to_utc(&self.posted)
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn edited_at(&self) -> Option<DateTime<Utc>> {
+        self.edited.as_deref().and_then(date::to_utc)
+    }
+
+    pub fn writer(&self) -> &str {
+        &self.writer
+    }
+
+    pub fn writer_id(&self) -> Option<u64> {
+        self.writer_id
+    }
+
+    pub fn is_uploader(&self) -> bool {
+        self.vote.is_none()
+    }
+
     pub fn score(&self) -> Option<i64> {
         self.vote.as_ref().map(|v| v.score)
     }
@@ -105,80 +359,832 @@ impl Comment {
     pub fn omitted_voter(&self) -> Option<usize> {
         self.vote.as_ref().map(|v| v.omitted)
     }
+
+    // what it says, as plain text
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    // what it says, with links, spoilers and such
+    pub fn body(&self) -> &CommentBody {
+        &self.body
+    }
+}
+
+// what has changed in an article since it was fetched; see Article::refresh()
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ArticleChanges {
+    // either the rating or the number of votes
+    pub rating: bool,
+    pub favorited: bool,
+    pub tags: bool,
+    pub comments: bool,
+    // images are added or replaced; the image list has been dropped
+    pub images: bool,
+    // a newer version of it is posted
+    pub newer: bool,
+}
+
+impl ArticleChanges {
+    pub fn any(&self) -> bool {
+        self.rating || self.favorited || self.tags || self.comments || self.images || self.newer
+    }
+}
+
+// a newer version of a gallery, as the old one links to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NewerVersion {
+    pub path: String,
+    pub title: String,
+    // like "2021-03-04 12:34"
+    pub posted: String,
+}
+
+// the image list of an article, split into pages as the site does.
+// each page is fetched only when an image in it is requested
+#[derive(Debug)]
+struct ImageList {
+    per_page: usize,
+    // when the list is made without any page of the site, per_page is
+    // only the default until the first page is fetched
+    guessed: bool,
+    pages: Vec<Option<Vec<ImageEntry>>>,
+    // in the same layout as pages; the ones restored from ArticleData
+    // may lack these even if they have the entries
+    previews: Vec<Option<Vec<Preview>>>,
+}
+
+impl ImageList {
+    fn new(first: Vec<Preview>, len: usize) -> Self {
+        // the number of images per page depends on the account settings;
+        // trust the first page if it isn't the only one
+        let per_page = if first.len() < len {
+            first.len().max(1)
+        } else {
+            IMAGES_PER_PAGE
+        };
+
+        let count = 1 + len.saturating_sub(1) / per_page;
+
+        let mut list = Self {
+            per_page,
+            guessed: false,
+            pages: vec![None; count],
+            previews: vec![None; count],
+        };
+
+        list.insert(0, first);
+        list
+    }
+
+    // rebuild the list from entries of any pages. `per_page` is 0 if it
+    // isn't known yet
+    fn from_entries(entries: Vec<ImageEntry>, previews: Vec<Preview>,
+        per_page: usize, len: usize) -> Self {
+        let guessed = per_page == 0;
+        let per_page = if guessed { IMAGES_PER_PAGE } else { per_page };
+        let count = 1 + len.saturating_sub(1) / per_page;
+
+        let mut pages = vec![None; count];
+        let mut preview_pages = vec![None; count];
+
+        for entry in entries {
+            // entries know their page number starting from 1
+            let page = entry.page.saturating_sub(1) / per_page;
+
+            if let Some(slot) = pages.get_mut(page) {
+                slot.get_or_insert_with(Vec::new).push(entry);
+            }
+        }
+
+        for preview in previews {
+            let page = preview.entry.page.saturating_sub(1) / per_page;
+
+            if let Some(slot) = preview_pages.get_mut(page) {
+                slot.get_or_insert_with(Vec::new).push(preview);
+            }
+        }
+
+        Self {
+            per_page,
+            guessed,
+            pages,
+            previews: preview_pages,
+        }
+    }
+
+    fn page_of(&self, index: usize) -> usize {
+        index / self.per_page
+    }
+
+    fn get(&self, index: usize) -> Option<&ImageEntry> {
+        self.pages
+            .get(self.page_of(index))?
+            .as_ref()?
+            .get(index % self.per_page)
+    }
+
+    fn insert(&mut self, page: usize, previews: Vec<Preview>) {
+        if page >= self.pages.len() {
+            return;
+        }
+
+        let entries = previews
+            .iter()
+            .map(|preview| preview.entry.clone())
+            .collect();
+
+        self.pages[page] = Some(entries);
+        self.previews[page] = Some(previews);
+    }
+
+    // pages which are not fetched yet
+    fn missing(&self) -> Vec<usize> {
+        (0..self.pages.len())
+            .filter(|&page| self.pages[page].is_none())
+            .collect()
+    }
+
+    fn entries(&self) -> Vec<ImageEntry> {
+        self.pages
+            .iter()
+            .flatten()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    fn previews(&self) -> Vec<Preview> {
+        self.previews
+            .iter()
+            .flatten()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+}
+
+// everything an article has, without the connection to the site.
+// an article can be made back from this without fetching anything
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ArticleData {
+    pub meta: ArticleMeta,
+    // 0 if no page of the image list was fetched yet
+    pub images_per_page: usize,
+    // only the images fetched so far
+    pub images: Vec<ImageEntry>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub previews: Vec<Preview>,
+    pub comments: Vec<Comment>,
+}
+
+// the comments of a gallery page, without the broken ones if lenient
+fn comments(lenient: bool, doc: &Document, path: &str)
+    -> Result<(Vec<Comment>, Vec<ParseWarning>), ErrorBox> {
+    match lenient {
+        true => Ok(parser::comments_lenient(doc)),
+        false => match parser::comments(doc) {
+            Ok(comments) => Ok((comments, Vec::new())),
+            Err(err) => Err(parser::at(err, path))
+        }
+    }
+}
+
+// what a page of a gallery says, read out of it in one go so that
+// parsing can be done off the runtime
+struct GalleryPage {
+    meta: ArticleMeta,
+    previews: Vec<Preview>,
+    comments: Vec<Comment>,
+    warnings: Vec<ParseWarning>,
+    own_vote: Option<f64>,
+    newer: Vec<NewerVersion>,
+}
+
+impl GalleryPage {
+    async fn parse(client: &Client, raw: Bytes, path: String) -> Result<Self, ErrorBox> {
+        let lenient = client.is_lenient();
+
+        client::parse_with(raw, move |doc| {
+            let meta = parser::article(doc, path)?;
+            let (comments, warnings) = comments(lenient, doc, &meta.path)?;
+
+            Ok(Self {
+                previews: parser::previews(doc)?,
+                comments,
+                warnings,
+                own_vote: parser::rating(doc).and_then(|rating| rating.own_vote),
+                newer: parser::newer_versions(doc),
+                meta,
+            })
+        }).await
+    }
 }
 
 pub struct Article {
     client: Arc<Client>,
 
     meta: ArticleMeta,
-    links: Vec<String>,
+    images: Mutex<ImageList>,
     comments: Vec<Comment>,
+    // what the account voted, if known
+    own_vote: Option<f64>,
+    // comments skipped in lenient parsing
+    warnings: Vec<ParseWarning>,
+    newer: Vec<NewerVersion>,
+    // the page of the gallery fetched last, as it was
+    raw: Mutex<Option<Bytes>>,
 }
 
 impl Article {
     pub(super) async fn new(client: Arc<Client>, path: String)
         -> Result<Article, ErrorBox> {
-        let doc = client.get_html(path.parse()?).await?;
+        let raw = client.get_html_raw(path.parse()?).await?;
+        let page = GalleryPage::parse(&client, raw.clone(), path).await?;
+
         Ok(Self {
             client,
-            meta: parser::article(&doc, path)?,
-            links: parser::image_list(&doc)?,
-            comments: parser::comments(&doc)?,
+            images: Mutex::new(ImageList::new(page.previews, page.meta.length)),
+            meta: page.meta,
+            comments: page.comments,
+            own_vote: page.own_vote,
+            warnings: page.warnings,
+            newer: page.newer,
+            raw: Mutex::new(Some(raw)),
         })
     }
 
+    pub(super) fn from_data(client: Arc<Client>, data: ArticleData) -> Self {
+        let images = ImageList::from_entries(
+            data.images,
+            data.previews,
+            data.images_per_page,
+            data.meta.length
+        );
+
+        Self {
+            client,
+            meta: data.meta,
+            images: Mutex::new(images),
+            comments: data.comments,
+            own_vote: None,
+            warnings: Vec::new(),
+            newer: Vec::new(),
+            raw: Mutex::new(None),
+        }
+    }
+
+    // an article of which only the metadata is known, e.g. from a store.
+    // the image list is fetched (or read from the caches) when needed
+    pub(super) fn from_meta(client: Arc<Client>, meta: ArticleMeta) -> Self {
+        let images = ImageList::from_entries(
+            Vec::new(),
+            Vec::new(),
+            0,
+            meta.length
+        );
+
+        Self {
+            client,
+            meta,
+            images: Mutex::new(images),
+            comments: Vec::new(),
+            own_vote: None,
+            warnings: Vec::new(),
+            newer: Vec::new(),
+            raw: Mutex::new(None),
+        }
+    }
+
+    pub fn to_data(&self) -> ArticleData {
+        let images = self.images.lock().unwrap();
+
+        ArticleData {
+            meta: self.meta.clone(),
+            images_per_page: if images.guessed { 0 } else { images.per_page },
+            images: images.entries(),
+            previews: images.previews(),
+            comments: self.comments.clone(),
+        }
+    }
+
     pub fn meta(&self) -> &ArticleMeta {
         &self.meta
     }
 
+    pub fn rating(&self) -> Rating {
+        Rating {
+            average: self.meta.rating,
+            count: self.meta.rating_count,
+            own_vote: self.own_vote,
+        }
+    }
+
+    // vote on the gallery by the account, from 0.5 to 5 stars by halves.
+    // what the site answers replaces the rating of the metadata
+    pub async fn rate(&mut self, score: f64) -> Result<Rating, ErrorBox> {
+        let id = self.meta.id().ok_or(ParseGalleryIdError())?;
+
+        let raw = self.raw.get_mut().unwrap().clone();
+        let raw = match raw {
+            Some(raw) => raw,
+            None => self.client.get_html_raw(self.meta.path.parse()?).await?
+        };
+
+        let key = parser::api_key(&client::html(&raw)?)
+            .ok_or_else(|| EhError::NotFound("api key".to_owned()))?;
+
+        let rating = rating::rate(&self.client, &id, &key, score).await?;
+
+        self.meta.rating = rating.average;
+        self.meta.rating_count = rating.count;
+        self.own_vote = rating.own_vote;
+
+        Ok(rating)
+    }
+
     // it's O(1) to random access
     pub fn comments(&self) -> slice::Iter<'_, Comment> {
         self.comments.iter()
     }
 
-    pub async fn load_thumb(&self) -> Result<Vec<u8>, ErrorBox> {
+    // comments skipped in the page fetched last; always empty unless
+    // the explorer parses leniently
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
+    // newer versions of the gallery posted since, the newest last, as
+    // the page links to them. a gallery known only by its metadata has none
+    pub fn newer_versions(&self) -> &[NewerVersion] {
+        &self.newer
+    }
+
+    // forum id of the uploader, if there is the uploader comment
+    pub fn uploader_id(&self) -> Option<u64> {
+        self.comments
+            .iter()
+            .find(|comment| comment.is_uploader())
+            .and_then(|comment| comment.writer_id)
+    }
+
+    // only has the images fetched so far; call load_image_list() to get all
+    pub fn image_list(&self) -> Vec<ImageEntry> {
+        self.images.lock().unwrap().entries()
+    }
+
+    // thumbnails of the images fetched so far, which are enough to show
+    // a grid of pages without visiting every image page
+    pub fn previews(&self) -> Vec<Preview> {
+        self.images.lock().unwrap().previews()
+    }
+
+    pub async fn load_thumb(&self) -> Result<Bytes, ErrorBox> {
         self.client.get_image(self.meta.thumb.parse()?).await
     }
 
-    pub async fn load_image_list(&mut self) -> Result<(), ErrorBox> {
-        if self.links.len() == self.meta().length {
+    async fn fetch_image_page(&self, page: usize)
+        -> Result<Vec<Preview>, ErrorBox> {
+        let raw = self.client.get_html_raw(
+            format!("{}?p={}", self.meta.path, page).parse()?
+        ).await?;
+
+        let previews = parser::previews(&client::html(&raw)?)?;
+        *self.raw.lock().unwrap() = Some(raw);
+
+        Ok(previews)
+    }
+
+    // the page of the gallery fetched last, to find what this library
+    // doesn't parse by yourself. None if nothing's fetched yet
+    pub fn raw_html(&self) -> Option<String> {
+        self.raw
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|raw| String::from_utf8_lossy(raw).into_owned())
+    }
+
+    // fetch every page of the image list we don't have yet
+    pub async fn load_image_list(&self) -> Result<(), ErrorBox> {
+        self.load_image_list_with(LIST_CONCURRENCY).await
+    }
+
+    // same as load_image_list(), but with at most `concurrency` pages at once
+    pub async fn load_image_list_with(&self, concurrency: usize)
+        -> Result<(), ErrorBox> {
+        self.learn_layout().await?;
+        let missing = self.images.lock().unwrap().missing();
+
+        let mut pages = stream::iter(missing)
+            .map(|page| async move { (page, self.fetch_image_page(page).await) })
+            .buffer_unordered(concurrency.max(1));
+
+        while let Some((page, entries)) = pages.next().await {
+            let entries = entries?;
+            self.images.lock().unwrap().insert(page, entries);
+        }
+
+        Ok(())
+    }
+
+    // fetch the first page of the image list if it isn't known how many
+    // images a page has, which the other pages are asked by
+    async fn learn_layout(&self) -> Result<(), ErrorBox> {
+        if !self.images.lock().unwrap().guessed {
             return Ok(());
         }
 
-        const IMAGES_PER_PAGE: usize = 40;
-        let page_len = 1 + (self.meta.length - 1) / IMAGES_PER_PAGE;
+        let first = self.fetch_image_page(0).await?;
 
-        // start from 1 because we've already parsed page 0
-        for i in 1..page_len {
-            let doc = self.client.get_html(
-                format!("{}?p={}", self.meta.path, i).parse()?
-            ).await?;
+        let restored = {
+            let mut images = self.images.lock().unwrap();
+            std::mem::replace(&mut *images, ImageList::new(first, self.meta.length)).entries()
+        };
 
-            self.links.extend(parser::image_list(&doc)?);
+        // keep what was restored before, laid out again
+        self.restore_entries(restored);
+        Ok(())
+    }
+
+    // the entries of the image list known so far, in order
+    pub(super) fn image_entries(&self) -> Vec<ImageEntry> {
+        self.images.lock().unwrap().entries()
+    }
+
+    // fill in pages of the image list from entries known before, like
+    // those in a download manifest. pages which are loaded are kept
+    pub(super) fn restore_entries(&self, entries: Vec<ImageEntry>) {
+        let mut list = self.images.lock().unwrap();
+        let restored = ImageList::from_entries(entries, Vec::new(), list.per_page, self.meta.length);
+
+        for (slot, page) in list.pages.iter_mut().zip(restored.pages) {
+            if slot.is_none() {
+                *slot = page;
+            }
         }
+    }
 
-        Ok(())
+    // get an entry of the image list, fetching the page of the list
+    // which contains the entry if we don't have it yet
+    async fn image_entry(&self, index: usize) -> Result<ImageEntry, ErrorBox> {
+        let out_of_range = EhError::IndexOutOfRange {
+            index,
+            len: self.meta.length,
+        };
+
+        if index >= self.meta.length {
+            return Err(out_of_range.into());
+        }
+
+        self.learn_layout().await?;
+
+        let page = {
+            let images = self.images.lock().unwrap();
+
+            if let Some(entry) = images.get(index) {
+                return Ok(entry.clone());
+            }
+
+            images.page_of(index)
+        };
+
+        let entries = self.fetch_image_page(page).await?;
+
+        let mut images = self.images.lock().unwrap();
+        images.insert(page, entries);
+
+        // the site might have given less images than it said
+        images.get(index).cloned().ok_or_else(|| out_of_range.into())
+    }
+
+    async fn fetch_image(&self, entry: &ImageEntry, progress: &Progress)
+        -> Result<(Bytes, Option<String>), ErrorBox> {
+        // the hash tells what the image is, wherever it's from
+        let cache = self.client.disk_cache();
+        let key = format!("image-{}", entry.hash);
+
+        if let Some(cache) = &cache {
+            if let Some(data) = cache.get(&key).await {
+                progress.page_fetched();
+                progress.image_downloaded(data.len());
+
+                return Ok((Bytes::from(data), None));
+            }
+        }
+
+        limits::spend(&self.client).await?;
+
+        let path = self.image_path(entry, false).await?;
+        progress.page_fetched();
+
+        let (data, content_type) = self.client
+            .get_image_with_type(path.parse()?)
+            .await?;
+
+        progress.image_downloaded(data.len());
+
+        // it's kept by the hash, so it'd better be the image of it. the
+        // site resamples large images, which are of some other hash but
+        // still have to be whole
+        let is_image = verify::check(&data, None, Some(&entry.hash)).is_ok();
+
+        if let (Some(cache), true) = (cache, is_image) {
+            cache.put(&key, &data).await;
+        }
+
+        Ok((data, content_type))
     }
 
-    pub async fn load_image(&self, index: usize) -> Result<Vec<u8>, ErrorBox> {
-        // is this really the best?
-        if index >= self.links.len() {
-            panic!(":P"); // TODO
+    // the path to the image of an entry, from its image page. a reload
+    // asks the page for another server by the "reload broken image" link,
+    // since the one it gave before was bad
+    async fn image_path(&self, entry: &ImageEntry, reload: bool) -> Result<String, ErrorBox> {
+        let key = {
+            let doc = self.client.get_html(entry.path.parse()?).await?;
+
+            match reload {
+                false => return parser::image(&doc),
+                true => parser::image_reload(&doc)?,
+            }
+        };
+
+        let dest = format!("{}?nl={}", entry.path, key);
+        parser::image(&self.client.get_html(dest.parse()?).await?)
+    }
+
+    // same as fetch_image(), but into `writer` as the image arrives. with
+    // a disk cache the image is kept there too, which needs all of it.
+    // a reload goes around the cache, and throws away what's there.
+    // along with the format, it gives where the image was from, unless
+    // it was from the cache
+    async fn write_image<W: AsyncWrite + Unpin>(&self, entry: &ImageEntry,
+        mut writer: W, progress: &Progress, reload: bool)
+        -> Result<(Option<ImageFormat>, Option<String>), ErrorBox> {
+        if let (true, Some(cache)) = (reload, self.client.disk_cache()) {
+            let key = format!("image-{}", entry.hash);
+            cache.remove_where(|name, _| name == key).await;
+        }
+
+        if !reload && self.client.disk_cache().is_some() {
+            let (data, content_type) = self.fetch_image(entry, progress).await?;
+            writer.write_all(&data).await?;
+            writer.flush().await?;
+
+            let format = content_type
+                .as_deref()
+                .and_then(ImageFormat::from_mime)
+                .or_else(|| ImageFormat::sniff(&data));
+
+            return Ok((format, None));
+        }
+
+        limits::spend(&self.client).await?;
+
+        let path = self.image_path(entry, reload).await?;
+        progress.page_fetched();
+
+        let (written, content_type) = self.client
+            .download_to(path.parse()?, writer)
+            .await?;
+
+        progress.image_downloaded(written as usize);
+
+        Ok((content_type.as_deref().and_then(ImageFormat::from_mime), Some(path)))
+    }
+
+    // write the `index`-th image into `writer`, giving its format if the
+    // server told it (look at the beginning of the data otherwise) and
+    // the url it was at. see image_path() for `reload`
+    pub(super) async fn stream_image<W: AsyncWrite + Unpin>(&self, index: usize,
+        writer: W, progress: &Progress, reload: bool)
+        -> Result<(Option<ImageFormat>, Option<String>), ErrorBox> {
+        let res = async {
+            let entry = self.image_entry(index).await?;
+            self.write_image(&entry, writer, progress, reload).await
+        }.await;
+
+        if res.is_err() {
+            progress.failed();
+        }
+
+        res
+    }
+
+    pub async fn load_image(&self, index: usize) -> Result<Bytes, ErrorBox> {
+        let entry = self.image_entry(index).await?;
+        Ok(self.fetch_image(&entry, &Progress::new()).await?.0)
+    }
+
+    async fn try_load_image_data(&self, index: usize, progress: &Progress)
+        -> Result<ImageData, ErrorBox> {
+        let entry = self.image_entry(index).await?;
+        let (data, content_type) = self.fetch_image(&entry, progress).await?;
+
+        Ok(ImageData::new(index, entry.hash, data, content_type))
+    }
+
+    pub(super) async fn load_image_data(&self, index: usize, progress: Progress)
+        -> Result<ImageData, ErrorBox> {
+        let res = self.try_load_image_data(index, &progress).await;
+
+        if res.is_err() {
+            progress.failed();
+        }
+
+        res
+    }
+
+    // load images one by one in order; nothing is fetched until polled,
+    // and each image is given away as soon as it arrives
+    pub fn images(&self) -> impl Stream<Item = Result<ImageData, ErrorBox>> + '_ {
+        self.images_with_progress(Progress::new())
+    }
+
+    pub fn images_with_progress(&self, progress: Progress)
+        -> impl Stream<Item = Result<ImageData, ErrorBox>> + '_ {
+        progress.set_total(self.meta.length);
+
+        stream::iter(0..self.meta.length)
+            .then(move |index| self.load_image_data(index, progress.clone()))
+    }
+
+    // size of the original images in bytes, according to the api.
+    // the resampled images we download are usually smaller than that
+    pub async fn estimated_size(&self) -> Result<u64, ErrorBox> {
+        let id = self.meta.id().ok_or(ParseGalleryIdError())?;
+        let data = api::gdata(&self.client, &[id]).await?;
+
+        match data.first() {
+            Some(data) => Ok(data.file_size),
+            None => Err(EhError::Api("no such gallery".to_owned()).into())
+        }
+    }
+
+    // load images in the range, a few of them at once
+    pub async fn load_image_range(&self, range: Range<usize>)
+        -> Result<Vec<ImageData>, ErrorBox> {
+        if range.end > self.meta.length {
+            return Err(EhError::IndexOutOfRange {
+                index: range.end - 1,
+                len: self.meta.length,
+            }.into());
         }
 
-        let path = parser::image(
-            &self.client.get_html(self.links[index].parse()?).await?
+        stream::iter(range)
+            .map(|index| self.load_image_data(index, Progress::new()))
+            .buffered(IMAGE_CONCURRENCY)
+            .try_collect()
+            .await
+    }
+
+    // load every image of the article, at most `concurrency` of them at once.
+    // the images are given in order regardless of which one arrived first
+    pub async fn download_all(&self, concurrency: usize)
+        -> Result<Vec<ImageData>, ErrorBox> {
+        self.download_all_with_progress(concurrency, Progress::new()).await
+    }
+
+    pub async fn download_all_with_progress(
+        &self,
+        concurrency: usize,
+        progress: Progress
+    ) -> Result<Vec<ImageData>, ErrorBox> {
+        progress.set_total(self.meta.length);
+
+        // get the whole list first, or every request would race
+        // to fetch the same page of the list
+        self.load_image_list().await?;
+
+        stream::iter(0..self.meta.length)
+            .map(|index| self.load_image_data(index, progress.clone()))
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await
+    }
+
+    // download an image into a file, continuing from what the file already
+    // has if it's a part of the image. returns the length of the whole file
+    pub async fn resume_image(&self, index: usize, path: impl AsRef<Path>)
+        -> Result<u64, ErrorBox> {
+        let path = path.as_ref();
+        let entry = self.image_entry(index).await?;
+
+        limits::spend(&self.client).await?;
+
+        let dest = parser::image(
+            &self.client.get_html(entry.path.parse()?).await?
         )?;
 
-        let data = self.client.get_image(path.parse()?).await?;
-        Ok(data)
+        let offset = match fs::metadata(path).await {
+            Ok(meta) => meta.len(),
+            Err(err) if err.kind() == ErrorKind::NotFound => 0,
+            Err(err) => return Err(err.into()),
+        };
+
+        let part = self.client.get_image_from(dest.parse()?, offset).await?;
+
+        // the server might have ignored the range and given the whole file
+        let mut file = if part.offset == offset {
+            OpenOptions::new().create(true).append(true).open(path).await?
+        } else {
+            fs::File::create(path).await?
+        };
+
+        file.write_all(&part.data).await?;
+        file.flush().await?;
+
+        let actual = fs::metadata(path).await?.len();
+
+        match part.total {
+            Some(expected) if expected != actual => {
+                Err(EhError::LengthMismatch { expected, actual }.into())
+            },
+            _ => Ok(actual)
+        }
+    }
+
+    // the image list only gives the first 10 digits of the hash;
+    // this costs a request to the image page to get the whole one
+    pub async fn load_image_hash(&self, index: usize)
+        -> Result<Option<String>, ErrorBox> {
+        let entry = self.image_entry(index).await?;
+
+        parser::image_hash(
+            &self.client.get_html(entry.path.parse()?).await?
+        )
+    }
+
+    // fetch the article again and update what has changed, which are
+    // usually rating, favorites, tags and comments. all the comments
+    // are fetched regardless of whether load_all_comments() was called
+    pub async fn refresh(&mut self) -> Result<ArticleChanges, ErrorBox> {
+        let path = format!("{}?hc=1", self.meta.path).parse()?;
+        let raw = self.client.get_html_fresh_raw(path).await?;
+
+        let GalleryPage { meta, previews: first, comments, warnings, own_vote, newer } =
+            GalleryPage::parse(&self.client, raw.clone(), self.meta.path.clone()).await?;
+
+        let images = {
+            let list = self.images.get_mut().unwrap();
+
+            // the first page may be missing if restored from ArticleData
+            let same = list.pages[0].as_ref().map_or(false, |old| {
+                old.len() == first.len()
+                    && old.iter().zip(first.iter()).all(|(a, b)| a.same_image(&b.entry))
+            });
+
+            meta.length != self.meta.length || !same
+        };
+
+        let changes = ArticleChanges {
+            rating: meta.rating != self.meta.rating
+                || meta.rating_count != self.meta.rating_count,
+            favorited: meta.favorited != self.meta.favorited,
+            tags: meta.tags != self.meta.tags,
+            comments: comments != self.comments,
+            images,
+            newer: newer != self.newer,
+        };
+
+        if images {
+            *self.images.get_mut().unwrap() = ImageList::new(first, meta.length);
+        }
+
+        self.meta = meta;
+        self.comments = comments;
+        self.own_vote = own_vote;
+        self.warnings = warnings;
+        self.newer = newer;
+        *self.raw.get_mut().unwrap() = Some(raw);
+
+        Ok(changes)
     }
 
     pub async fn load_all_comments(&mut self) -> Result<(), ErrorBox> {
         let path = format!("{}?hc=1", self.meta.path).parse()?;
-        let doc = self.client.get_html(path).await?;
-        self.comments = parser::comments(&doc)?;
+        let raw = self.client.get_html_raw(path).await?;
+
+        let lenient = self.client.is_lenient();
+        let path = self.meta.path.clone();
+
+        let (comments, warnings) = client::parse_with(raw.clone(), move |doc| {
+            comments(lenient, doc, &path)
+        }).await?;
+        self.comments = comments;
+        self.warnings = warnings;
+        *self.raw.get_mut().unwrap() = Some(raw);
 
         Ok(())
     }
 }
+
+// serialized as ArticleData; get it back by Explorer::article_from_data()
+#[cfg(feature = "serde")]
+impl Serialize for Article {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_data().serialize(serializer)
+    }
+}
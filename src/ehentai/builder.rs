@@ -0,0 +1,162 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::mem;
+use std::error::Error;
+use std::time::Duration;
+use std::path::{Path, PathBuf};
+use hyper::Uri;
+use tokio::fs;
+
+use super::client::{Client, ClientOptions};
+use super::cookies::CookieJar;
+use super::explorer::Explorer;
+#[cfg(feature = "vcr")]
+use super::vcr::Tape;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+// which site to browse. exhentai needs cookies of an account which can
+// see it, "igneous" included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Site {
+    EHentai,
+    ExHentai,
+}
+
+impl Default for Site {
+    fn default() -> Self {
+        Site::EHentai
+    }
+}
+
+// how to make an explorer. nothing is read until build(), so
+// errors in files and such come out from there
+#[derive(Debug, Default)]
+pub struct ExplorerBuilder {
+    options: ClientOptions,
+    cookies: CookieJar,
+    cookie_files: Vec<PathBuf>,
+    cookie_headers: Vec<String>,
+}
+
+impl ExplorerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // connect through an http proxy, like "http://127.0.0.1:8080"
+    pub fn proxy(mut self, proxy: Uri) -> Self {
+        self.options.proxy = Some(proxy);
+        self
+    }
+
+    // log in by the cookies of the account
+    pub fn cookies(mut self, member_id: &str, pass_hash: &str) -> Self {
+        for domain in ["e-hentai.org", "exhentai.org"].iter() {
+            self.cookies.add(domain, true, "ipb_member_id", member_id);
+            self.cookies.add(domain, true, "ipb_pass_hash", pass_hash);
+        }
+
+        self
+    }
+
+    // use cookies of a browser, exported in the cookies.txt format
+    pub fn cookies_from_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.cookie_files.push(path.as_ref().to_owned());
+        self
+    }
+
+    // use cookies of a "Cookie" header a browser sent, like "a=1; b=2"
+    pub fn cookie_header(mut self, header: &str) -> Self {
+        self.cookie_headers.push(header.to_owned());
+        self
+    }
+
+    // send a request at most once in `interval`; the site bans those
+    // who send too many in a short time
+    pub fn rate_limit(mut self, interval: Duration) -> Self {
+        self.options.rate_limit = Some(interval);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.options.connect_timeout = Some(timeout);
+        self
+    }
+
+    // give up a request if the response doesn't come in `timeout`
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    pub fn site(mut self, site: Site) -> Self {
+        self.options.site = site;
+        self
+    }
+
+    // skip galleries of a search and comments which can't be parsed, rather
+    // than failing the whole page; see the warnings of Page and Article
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.options.lenient = lenient;
+        self
+    }
+
+    // connections kept open to a host after their requests are done, to
+    // be used again. 0 closes each of them once it's done
+    pub fn pool_max_idle(mut self, max: usize) -> Self {
+        self.options.pool_max_idle = Some(max);
+        self
+    }
+
+    // close a connection which has been idle for `timeout`
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.options.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    // write every response down in the directory as it comes, to be
+    // replayed later; cookies the site sets are left out
+    #[cfg(feature = "vcr")]
+    pub fn record(mut self, dir: impl AsRef<Path>) -> Self {
+        self.options.tape = Some(Tape::Record(dir.as_ref().to_owned()));
+        self
+    }
+
+    // answer every request from what's recorded in the directory, never
+    // going to the site. what's not recorded fails with EhError::NotFound
+    #[cfg(feature = "vcr")]
+    pub fn replay(mut self, dir: impl AsRef<Path>) -> Self {
+        self.options.tape = Some(Tape::Replay(dir.as_ref().to_owned()));
+        self
+    }
+
+    pub async fn build(mut self) -> Result<Explorer, ErrorBox> {
+        let mut jar = CookieJar::new();
+
+        // files, then headers, then cookies() override what's before
+        for path in self.cookie_files.iter() {
+            let text = fs::read_to_string(path).await?;
+            jar.extend(CookieJar::from_netscape(&text)?);
+        }
+
+        for header in self.cookie_headers.iter() {
+            jar.extend(CookieJar::from_header(header)?);
+        }
+
+        jar.extend(mem::take(&mut self.cookies));
+        self.cookies = jar;
+
+        Ok(self.finish())
+    }
+
+    // build it without reading anything, which can't fail
+    pub(super) fn finish(self) -> Explorer {
+        let mut client = Client::with_options(self.options);
+        client.add_cookies(self.cookies);
+
+        Explorer::from_client(client)
+    }
+}
@@ -0,0 +1,191 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use hyper::body::Bytes;
+
+// how much the caches are holding now
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    // pages and api responses in memory
+    pub entries: usize,
+    pub bytes: u64,
+    // files in the disk cache, if there is
+    pub disk_entries: usize,
+    pub disk_bytes: u64,
+}
+
+// what the server gave to tell whether the response has changed
+#[derive(Debug, Clone, Default)]
+pub(super) struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+struct Entry {
+    body: Bytes,
+    validators: Validators,
+    stored: Instant,
+    // larger if used more recently
+    used: u64,
+}
+
+struct Entries {
+    capacity: usize,
+    ttl: Duration,
+    map: HashMap<String, Entry>,
+    clock: u64,
+}
+
+// responses kept in memory for a while, so the same page isn't fetched
+// twice in a row. the least recently used one goes first when it's full.
+// expired ones are kept if they have validators, so they can be asked
+// whether they're still the same instead of fetched again
+pub(super) struct ResponseCache {
+    entries: Mutex<Entries>,
+}
+
+impl ResponseCache {
+    // it keeps nothing until configured
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(Entries {
+                capacity: 0,
+                ttl: Duration::from_secs(0),
+                map: HashMap::new(),
+                clock: 0,
+            }),
+        }
+    }
+
+    pub fn configure(&self, capacity: usize, ttl: Duration) {
+        let mut entries = self.entries.lock().unwrap();
+
+        entries.capacity = capacity;
+        entries.ttl = ttl;
+        entries.map.clear();
+    }
+
+    pub fn get(&self, key: &str) -> Option<Bytes> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+
+        let clock = entries.clock;
+        let ttl = entries.ttl;
+
+        let expired = match entries.map.get_mut(key) {
+            Some(entry) if entry.stored.elapsed() < ttl => {
+                entry.used = clock;
+                return Some(entry.body.clone());
+            },
+            Some(entry) => entry.validators.is_empty(),
+            None => false,
+        };
+
+        if expired {
+            entries.map.remove(key);
+        }
+
+        None
+    }
+
+    // the response we have, fresh or not
+    pub fn peek(&self, key: &str) -> Option<Bytes> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+
+        let clock = entries.clock;
+        let entry = entries.map.get_mut(key)?;
+        entry.used = clock;
+
+        Some(entry.body.clone())
+    }
+
+    // validators of the response we have, fresh or not
+    pub fn validators(&self, key: &str) -> Option<Validators> {
+        let entries = self.entries.lock().unwrap();
+
+        entries.map
+            .get(key)
+            .map(|entry| entry.validators.clone())
+            .filter(|validators| !validators.is_empty())
+    }
+
+    // the server said it's not modified; it's as good as new
+    pub fn revalidate(&self, key: &str) -> Option<Bytes> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+
+        let clock = entries.clock;
+        let entry = entries.map.get_mut(key)?;
+
+        entry.stored = Instant::now();
+        entry.used = clock;
+
+        Some(entry.body.clone())
+    }
+
+    pub fn insert(&self, key: String, body: Bytes, validators: Validators) {
+        let mut entries = self.entries.lock().unwrap();
+
+        if entries.capacity == 0 {
+            return;
+        }
+
+        entries.clock += 1;
+        let used = entries.clock;
+
+        // there are not so many of them to need a linked list
+        while entries.map.len() >= entries.capacity && !entries.map.contains_key(&key) {
+            let oldest = entries.map
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| key.clone())
+                .unwrap();
+
+            entries.map.remove(&oldest);
+        }
+
+        entries.map.insert(key, Entry {
+            body,
+            validators,
+            stored: Instant::now(),
+            used,
+        });
+    }
+
+    pub fn remove(&self, key: &str) {
+        self.entries.lock().unwrap().map.remove(key);
+    }
+
+    // remove every entry the predicate says yes to, given the key
+    // and how long ago it was stored
+    pub fn remove_where(&self, pred: impl Fn(&str, Duration) -> bool) {
+        self.entries
+            .lock()
+            .unwrap()
+            .map
+            .retain(|key, entry| !pred(key, entry.stored.elapsed()));
+    }
+
+    // the number of entries and the size of their bodies
+    pub fn size(&self) -> (usize, u64) {
+        let entries = self.entries.lock().unwrap();
+        let bytes = entries.map.values().map(|entry| entry.body.len() as u64).sum();
+
+        (entries.map.len(), bytes)
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().map.clear();
+    }
+}
@@ -5,72 +5,790 @@
 use std::str;
 use std::error::Error;
 
-use hyper::{Uri, Body, Request, Response};
+use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use hyper::{Uri, Body, Method, Request, Response, StatusCode};
+use hyper::body::{Bytes, HttpBody};
+use hyper::http::request;
+use hyper::http::uri::Authority;
 use hyper::client::connect::HttpConnector;
 use detour::HttpsConnector;
 use select::document::Document;
+use select::predicate::{Attr, Class};
+use serde_json::Value;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use super::cache::{CacheStats, ResponseCache, Validators};
+use super::disk_cache::{self, DiskCache};
+use super::error::EhError;
+use super::id::GalleryId;
+use super::image::ImageEntry;
+use super::page::percent_encode;
+use super::limits::Budget;
+use super::cookies::CookieJar;
+use super::proxy::ProxyConnector;
+use super::queue::{Priority, Queue};
+use super::builder::Site;
+#[cfg(feature = "vcr")]
+use super::vcr::{self, Tape};
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+type Connector = HttpsConnector<ProxyConnector>;
+
+// pages which are only on e-hentai, even for exhentai
+const EHENTAI_ONLY: [&str; 3] = ["/home.php", "/exchange.php", "/hentaiathome.php"];
 
-type ErrorBox = Box<dyn Error>;
-type Connector = HttpsConnector<HttpConnector>;
+// what's in a page which is not what we asked for. challenges are from
+// cloudflare and such, and downtimes are from the site itself
+const CHALLENGE_MARKERS: [&str; 4] = [
+    "<title>just a moment...</title>",
+    "cf-chl",
+    "challenge-platform",
+    "captcha",
+];
+const DOWNTIME_MARKERS: [&str; 3] = [
+    "down for maintenance",
+    "temporarily unavailable",
+    "server is too busy",
+];
+
+// error pages are short; longer ones are real pages which happen
+// to have the words, like in a comment
+const ERROR_PAGE_LEN: usize = 16 * 1024;
+
+// whether it's a page of the site after all, like a gallery with the
+// words in its title, rather than a page which only says it's down
+fn is_site_page(body: &[u8]) -> bool {
+    let doc = Document::from(String::from_utf8_lossy(body).as_ref());
+
+    doc.find(Attr("id", "gdd")).next().is_some()
+        || doc.find(Class("itg")).next().is_some()
+}
+
+// how the client connects, which can't be changed after it's made
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ClientOptions {
+    // an http proxy which can CONNECT
+    pub proxy: Option<Uri>,
+    // the least time between two requests
+    pub rate_limit: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    // how long to wait for a response after sending a request
+    pub timeout: Option<Duration>,
+    pub site: Site,
+    // skip what can't be parsed instead of failing the whole page
+    pub lenient: bool,
+    // connections kept open to a host for later requests, and for how
+    // long; hyper's defaults (no limit, 90 seconds) if not given
+    pub pool_max_idle: Option<usize>,
+    pub pool_idle_timeout: Option<Duration>,
+    // record the responses, or replay them instead of the site
+    #[cfg(feature = "vcr")]
+    pub tape: Option<Tape>,
+}
+
+// a part of an image, from `offset`-th byte to the end
+pub struct PartialImage {
+    pub offset: u64,
+    pub data: Bytes,
+    // length of the whole file, if the server told us
+    pub total: Option<u64>,
+}
 
 pub struct Client {
     inner: hyper::Client<Connector, Body>,
-    cookie: Option<String>,
+    cookies: CookieJar,
+    cache: ResponseCache,
+    disk_cache: Mutex<Option<Arc<DiskCache>>>,
+    offline: AtomicBool,
+    budget: Mutex<Option<Budget>>,
+
+    // requests waiting for their turn under the rate limit, if there is
+    queue: Option<Queue>,
+    timeout: Option<Duration>,
+    site: Site,
+    lenient: bool,
+    #[cfg(feature = "vcr")]
+    tape: Option<Tape>,
+}
+
+// the gallery the link is about, if it's a gallery or an image page
+fn gallery_of(key: &str) -> Option<u64> {
+    key.parse::<GalleryId>()
+        .map(|id| id.gid)
+        .or_else(|_| key.parse::<ImageEntry>().map(|entry| entry.gid))
+        .ok()
+}
+
+// name of a response in the disk cache, e.g. "page-0123456789abcdef".
+// pages of a gallery have its gid too, like "page-1234-0123456789abcdef",
+// so they can be found again without their links
+fn disk_key(kind: &str, key: &str) -> String {
+    let hash = disk_cache::fnv1a(key);
+
+    match gallery_of(key) {
+        Some(gid) => format!("{}-{}-{:016x}", kind, gid, hash),
+        None => format!("{}-{:016x}", kind, hash),
+    }
+}
+
+// tell pages of downtimes and challenges from what we asked for, so they
+// don't end up as parse errors somewhere in the parser. a successful
+// response only says the site is down in html, and a json of the api
+// saying "temporarily unavailable" in a title is fine
+pub(crate) fn inspect(status: StatusCode, challenged: bool, html: bool, body: &[u8])
+    -> Result<(), EhError> {
+    let text = || String::from_utf8_lossy(body).into_owned();
+    let short = body.len() <= ERROR_PAGE_LEN;
+
+    let has = |markers: &[&str]| short && {
+        let lower = String::from_utf8_lossy(body).to_lowercase();
+        markers.iter().any(|x| lower.contains(x))
+    };
+
+    // challenges come with 403 or 503, which would be taken as downtimes
+    let challenge = challenged || match status.as_u16() {
+        403 | 429 | 503 => has(&CHALLENGE_MARKERS[..]),
+        _ => false,
+    };
+
+    if challenge {
+        return Err(EhError::ChallengeRequired { body: text() });
+    }
+
+    let down = match status.as_u16() {
+        502 | 503 | 504 | 520..=524 => true,
+        _ if status.is_success() && !html => false,
+        _ => has(&DOWNTIME_MARKERS[..]) && !(status.is_success() && is_site_page(body)),
+    };
+
+    match down {
+        true => Err(EhError::SiteUnavailable { status: status.as_u16(), body: text() }),
+        false => Ok(())
+    }
+}
+
+fn is_html(res: &Response<Body>) -> bool {
+    res.headers()
+        .get("Content-Type")
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |text| text.starts_with("text/html"))
+}
+
+// cloudflare says so in a header, which is surer than the page
+fn challenged(res: &Response<Body>) -> bool {
+    res.headers()
+        .get("cf-mitigated")
+        .map_or(false, |value| value == "challenge")
+}
+
+// what's wrong with a response which isn't what was asked for; a
+// challenge or a downtime if the page says so
+async fn refused(res: Response<Body>) -> ErrorBox {
+    let (status, challenged, html) = (res.status(), challenged(&res), is_html(&res));
+    let body = hyper::body::to_bytes(res.into_body()).await.unwrap_or_default();
+
+    match inspect(status, challenged, html, &body) {
+        Err(err) => err.into(),
+        Ok(()) => EhError::UnexpectedStatus(status.as_u16()).into(),
+    }
+}
+
+// parse a page. don't keep it across an await; it's not Send
+pub(crate) fn html(bytes: &[u8]) -> Result<Document, ErrorBox> {
+    Ok(Document::from(str::from_utf8(bytes)?))
+}
+
+// pages larger than this are parsed on a blocking thread. a gallery or
+// a search is about 100KiB, and takes tens of milliseconds to parse;
+// that's long enough to hold up other tasks of the runtime
+const BLOCKING_PARSE_LEN: usize = 32 * 1024;
+
+// parse a page and read what's needed out of it by `f`. the document
+// can't leave the thread it's made in, so only what `f` gives comes back
+pub(crate) async fn parse_with<T, F>(bytes: Bytes, f: F) -> Result<T, ErrorBox>
+where
+    T: Send + 'static,
+    F: FnOnce(&Document) -> Result<T, ErrorBox> + Send + 'static,
+{
+    if bytes.len() < BLOCKING_PARSE_LEN {
+        return f(&html(&bytes)?);
+    }
+
+    tokio::task::spawn_blocking(move || f(&html(&bytes)?)).await?
 }
 
 impl Client {
     pub fn new() -> Self {
-        let https = HttpsConnector::new();
-        let inner = hyper::Client::builder()
-            .build::<_, Body>(https);
+        Self::with_options(ClientOptions::default())
+    }
+
+    pub(crate) fn with_options(options: ClientOptions) -> Self {
+        let mut http = HttpConnector::new();
+        http.set_connect_timeout(options.connect_timeout);
+
+        let proxy = ProxyConnector::new(http, options.proxy);
+        let https = HttpsConnector::new_with_connector(proxy);
+
+        let mut builder = hyper::Client::builder();
+
+        if let Some(max) = options.pool_max_idle {
+            builder.pool_max_idle_per_host(max);
+        }
+
+        if let Some(timeout) = options.pool_idle_timeout {
+            builder.pool_idle_timeout(timeout);
+        }
+
+        let inner = builder.build::<_, Body>(https);
 
         Self {
             inner,
-            cookie: None,
+            cookies: CookieJar::new(),
+            cache: ResponseCache::new(),
+            disk_cache: Mutex::new(None),
+            offline: AtomicBool::new(false),
+            budget: Mutex::new(None),
+            queue: options.rate_limit.map(Queue::new),
+            timeout: options.timeout,
+            site: options.site,
+            lenient: options.lenient,
+            #[cfg(feature = "vcr")]
+            tape: options.tape,
+        }
+    }
+
+    // keep up to `capacity` pages and api responses for `ttl`.
+    // a capacity of 0 turns the cache off, which is the default
+    pub fn set_cache(&self, capacity: usize, ttl: Duration) {
+        self.cache.configure(capacity, ttl);
+    }
+
+    pub fn clear_cache(&self) {
+        self.cache.clear();
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        let (entries, bytes) = self.cache.size();
+        let (disk_entries, disk_bytes) = self.disk_cache()
+            .map_or((0, 0), |cache| cache.size());
+
+        CacheStats {
+            entries,
+            bytes,
+            disk_entries,
+            disk_bytes,
+        }
+    }
+
+    // forget every page of the gallery, in memory or on disk.
+    // images are named after their hash, so they're never out of date
+    pub async fn purge_gallery(&self, gid: u64) {
+        self.cache.remove_where(|key, _| gallery_of(key) == Some(gid));
+
+        if let Some(cache) = self.disk_cache() {
+            let prefix = format!("page-{}-", gid);
+            cache.remove_where(|name, _| name.starts_with(&prefix)).await;
+        }
+    }
+
+    // forget everything stored more than `age` ago
+    pub async fn purge_older_than(&self, age: Duration) {
+        self.cache.remove_where(|_, stored| stored > age);
+
+        if let Some(cache) = self.disk_cache() {
+            cache.remove_where(|_, written| written > age).await;
+        }
+    }
+
+    pub(super) fn set_disk_cache(&self, cache: Option<DiskCache>) {
+        *self.disk_cache.lock().unwrap() = cache.map(Arc::new);
+    }
+
+    pub(super) fn disk_cache(&self) -> Option<Arc<DiskCache>> {
+        self.disk_cache.lock().unwrap().clone()
+    }
+
+    // answer only from the caches, never touching the network
+    pub fn set_offline(&self, offline: bool) {
+        self.offline.store(offline, Ordering::Relaxed);
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline.load(Ordering::Relaxed)
+    }
+
+    pub fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    // how many images we may download before the image limit
+    pub(super) fn budget(&self) -> &Mutex<Option<Budget>> {
+        &self.budget
+    }
+
+    fn check_online(&self, dest: &Uri) -> Result<(), ErrorBox> {
+        match self.is_offline() {
+            true => Err(EhError::Offline(dest.to_string()).into()),
+            false => Ok(())
+        }
+    }
+
+    // what we have of a response while offline; stale ones are
+    // better than nothing
+    async fn cached(&self, kind: &str, key: &str) -> Result<Bytes, ErrorBox> {
+        if let Some(bytes) = self.cache.peek(key) {
+            return Ok(bytes);
+        }
+
+        if let Some(cache) = self.disk_cache() {
+            if let Some(data) = cache.get(&disk_key(kind, key)).await {
+                return Ok(Bytes::from(data));
+            }
+        }
+
+        Err(EhError::Offline(key.to_owned()).into())
+    }
+
+    // keep a response in the disk cache too, to be read offline later
+    async fn persist(&self, kind: &str, key: &str, bytes: &[u8]) {
+        if let Some(cache) = self.disk_cache() {
+            cache.put(&disk_key(kind, key), bytes).await;
         }
     }
 
-    pub fn set_cookies(&mut self, member_id: &str, pass_hash: &str) {
-        self.cookie = Some(format!(
-            "ipb_member_id={}; ipb_pass_hash={}",
-            member_id, pass_hash
-        ));
+    pub(super) fn add_cookies(&mut self, jar: CookieJar) {
+        self.cookies.extend(jar);
+    }
+
+    // links of e-hentai go to exhentai if that's what we browse
+    fn locate(&self, dest: Uri) -> Uri {
+        if self.site != Site::ExHentai
+            || dest.host() != Some("e-hentai.org")
+            || EHENTAI_ONLY.contains(&dest.path()) {
+            return dest;
+        }
+
+        let mut parts = dest.into_parts();
+        parts.authority = Some(Authority::from_static("exhentai.org"));
+
+        // only the host is changed; the rest was fine
+        Uri::from_parts(parts).unwrap()
+    }
+
+    // what a page is kept in the cache by: its link on the site we browse,
+    // and the account it's seen by, like "1234@https://exhentai.org/g/..".
+    // a page differs by both, like a gallery only members are shown
+    fn cache_key(&self, dest: &Uri) -> String {
+        let dest = self.locate(dest.clone());
+        let member = dest.host().and_then(|host| self.cookies.value(host, "ipb_member_id"));
+
+        match member {
+            Some(member) => format!("{}@{}", member, dest),
+            None => dest.to_string(),
+        }
+    }
+
+    fn request(&self, method: Method, dest: Uri, mime: &str) -> request::Builder {
+        let dest = self.locate(dest);
+
+        // don't give our cookies to anyone else
+        let cookie = dest.host().and_then(|host| self.cookies.header(host));
+
+        let req = Request::builder()
+            .method(method)
+            .uri(dest)
+            .header("Content-Type", mime);
+
+        match cookie {
+            Some(cookie) => req.header("Cookie", cookie.as_str()),
+            None => req
+        }
+    }
+
+    // send a request, or answer it from the tape if we're replaying one
+    #[cfg(feature = "vcr")]
+    async fn send(&self, req: Request<Body>) -> Result<Response<Body>, ErrorBox> {
+        let tape = match &self.tape {
+            Some(tape) => tape,
+            None => return self.send_live(req).await,
+        };
+
+        let (req, key) = vcr::key(req).await?;
+
+        if let Some(res) = tape.replay(&key).await? {
+            return Ok(res);
+        }
+
+        let res = self.send_live(req).await?;
+        tape.record(key, res).await
+    }
+
+    #[cfg(not(feature = "vcr"))]
+    async fn send(&self, req: Request<Body>) -> Result<Response<Body>, ErrorBox> {
+        self.send_live(req).await
+    }
+
+    // send a request, keeping to the rate limit and the timeout. the
+    // more urgent go first under the rate limit; see Priority
+    async fn send_live(&self, req: Request<Body>) -> Result<Response<Body>, ErrorBox> {
+        if let Some(queue) = &self.queue {
+            queue.wait(Priority::of(&req)).await;
+        }
+
+        let sending = self.inner.request(req);
+
+        let res = match self.timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, sending).await {
+                Ok(res) => res?,
+                Err(_) => return Err(EhError::Timeout(timeout).into()),
+            },
+            None => sending.await?,
+        };
+
+        Ok(res)
     }
 
     async fn get(&self, dest: Uri, mime: &str)
         -> Result<Response<Body>, ErrorBox> {
-        let req = if dest.host() != Some("e-hentai.org") || self.cookie.is_none() {
-            Request::get(dest)
-                .header("Content-Type", mime)
-                .body(Body::empty())?
-        } else if let Some(cookie) = self.cookie.as_ref() {
-            Request::get(dest)
-                .header("Content-Type", mime)
-                .header("Cookie", cookie.as_str())
-                .body(Body::empty())?
-        } else {
-            // logically impossible
-            unreachable!()
-        };
+        self.check_online(&dest)?;
 
-        let res = self.inner.request(req).await?;
+        let req = self.request(Method::GET, dest, mime).body(Body::empty())?;
+        let res = self.send(req).await?;
         Ok(res)
     }
 
+    // thumbnails are kept in the disk cache if there is, by their links
     pub async fn get_image(&self, dest: Uri)
-        -> Result<Vec<u8>, ErrorBox> {
+        -> Result<Bytes, ErrorBox> {
+        let cache = self.disk_cache();
+        let key = disk_key("thumb", &dest.to_string());
+
+        if let Some(cache) = &cache {
+            if let Some(data) = cache.get(&key).await {
+                return Ok(Bytes::from(data));
+            }
+        }
+
+        let data = self.get_image_with_type(dest).await?.0;
+
+        if let Some(cache) = cache {
+            cache.put(&key, &data).await;
+        }
+
+        Ok(data)
+    }
+
+    // get an image along with its Content-Type header. an error page is
+    // an error, so it's never kept in place of the image
+    pub async fn get_image_with_type(&self, dest: Uri)
+        -> Result<(Bytes, Option<String>), ErrorBox> {
         let res = self.get(dest, "image/*").await?;
+
+        if !res.status().is_success() {
+            return Err(refused(res).await);
+        }
+
+        let content_type = res
+            .headers()
+            .get("Content-Type")
+            .and_then(|value| value.to_str().ok())
+            .map(|text| text.to_owned());
+
         let bytes = hyper::body::to_bytes(res.into_body()).await?;
-    
-        Ok(bytes.to_vec())
+
+        Ok((bytes, content_type))
+    }
+
+    // write an image into `writer` as it arrives, without having all of it
+    // in memory. gives the number of bytes and the Content-Type header.
+    // nothing is written if it's an error page
+    pub async fn download_to<W: AsyncWrite + Unpin>(&self, dest: Uri, mut writer: W)
+        -> Result<(u64, Option<String>), ErrorBox> {
+        let res = self.get(dest, "image/*").await?;
+
+        if !res.status().is_success() {
+            return Err(refused(res).await);
+        }
+
+        let content_type = res
+            .headers()
+            .get("Content-Type")
+            .and_then(|value| value.to_str().ok())
+            .map(|text| text.to_owned());
+
+        let mut body = res.into_body();
+        let mut written = 0;
+
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk?;
+
+            writer.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+        }
+
+        writer.flush().await?;
+        Ok((written, content_type))
+    }
+
+    // get an image from `offset`-th byte, for resuming a download
+    pub async fn get_image_from(&self, dest: Uri, offset: u64)
+        -> Result<PartialImage, ErrorBox> {
+        self.check_online(&dest)?;
+
+        let req = self.request(Method::GET, dest, "image/*")
+            .header("Range", format!("bytes={}-", offset))
+            .body(Body::empty())?;
+
+        let res = self.send(req).await?;
+
+        // the header would be like "bytes (begin)-(end)/(total)",
+        // or "bytes */(total)" if the range was not satisfiable
+        let total = res
+            .headers()
+            .get("Content-Range")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|text| text.rsplit('/').next())
+            .and_then(|text| text.parse::<u64>().ok());
+
+        let (offset, data) = match res.status() {
+            StatusCode::PARTIAL_CONTENT => {
+                (offset, hyper::body::to_bytes(res.into_body()).await?)
+            },
+            // we already have the whole file
+            StatusCode::RANGE_NOT_SATISFIABLE => (offset, Bytes::new()),
+            // the server doesn't support ranges and gave the whole file
+            StatusCode::OK => {
+                let bytes = hyper::body::to_bytes(res.into_body()).await?;
+                let total = bytes.len() as u64;
+
+                return Ok(PartialImage {
+                    offset: 0,
+                    data: bytes,
+                    total: Some(total),
+                });
+            },
+            // an error page, which mustn't take the place of what we have
+            _ => return Err(refused(res).await),
+        };
+
+        Ok(PartialImage {
+            offset,
+            data,
+            total,
+        })
+    }
+
+    // whatever the server gives, as it is; for what this library doesn't
+    // understand yet. it's cached like pages are
+    pub async fn get_raw(&self, dest: Uri) -> Result<Bytes, ErrorBox> {
+        match self.cache.get(&self.cache_key(&dest)) {
+            Some(bytes) => Ok(bytes),
+            None => self.fetch(dest, "*/*").await,
+        }
     }
 
     pub async fn get_html(&self, dest: Uri)
         -> Result<Document, ErrorBox> {
-        let res = self.get(dest, "text/html").await?;
+        html(&self.get_html_raw(dest).await?)
+    }
+
+    // same as get_html(), but read the page by `f` off the runtime if it's
+    // large; see parse_with()
+    pub async fn get_html_with<T, F>(&self, dest: Uri, f: F) -> Result<T, ErrorBox>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Document) -> Result<T, ErrorBox> + Send + 'static,
+    {
+        parse_with(self.get_html_raw(dest).await?, f).await
+    }
+
+    // get_html() before being parsed
+    pub async fn get_html_raw(&self, dest: Uri) -> Result<Bytes, ErrorBox> {
+        match self.cache.get(&self.cache_key(&dest)) {
+            Some(bytes) => Ok(bytes),
+            None => self.fetch_html(dest).await,
+        }
+    }
+
+    // same as get_html(), but never from the cache
+    pub async fn get_html_fresh(&self, dest: Uri)
+        -> Result<Document, ErrorBox> {
+        html(&self.fetch_html(dest).await?)
+    }
+
+    pub async fn get_html_fresh_raw(&self, dest: Uri) -> Result<Bytes, ErrorBox> {
+        self.fetch_html(dest).await
+    }
+
+    async fn fetch_html(&self, dest: Uri) -> Result<Bytes, ErrorBox> {
+        self.fetch(dest, "text/html").await
+    }
+
+    // get the body and keep it in the cache. if we have an old one,
+    // ask the server whether it has changed since then
+    async fn fetch(&self, dest: Uri, mime: &str) -> Result<Bytes, ErrorBox> {
+        let key = self.cache_key(&dest);
+
+        if self.is_offline() {
+            return self.cached("page", &key).await;
+        }
+
+        let mut req = self.request(Method::GET, dest.clone(), mime);
+
+        if let Some(validators) = self.cache.validators(&key) {
+            if let Some(etag) = validators.etag {
+                req = req.header("If-None-Match", etag);
+            }
+
+            if let Some(date) = validators.last_modified {
+                req = req.header("If-Modified-Since", date);
+            }
+        }
+
+        let res = self.send(req.body(Body::empty())?).await?;
+
+        let res = match res.status() == StatusCode::NOT_MODIFIED {
+            false => res,
+            true => match self.cache.revalidate(&key) {
+                Some(bytes) => return Ok(bytes),
+                // it's gone from the cache in the meantime, and a 304
+                // has nothing of it; ask for the whole page then
+                None => {
+                    let req = self.request(Method::GET, dest, mime).body(Body::empty())?;
+                    self.send(req).await?
+                }
+            },
+        };
+
+        let header = |name: &str| {
+            res.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|text| text.to_owned())
+        };
+
+        let validators = Validators {
+            etag: header("ETag"),
+            last_modified: header("Last-Modified"),
+        };
+
+        let (status, challenged, html) = (res.status(), challenged(&res), is_html(&res));
         let bytes = hyper::body::to_bytes(res.into_body()).await?;
-        let file = str::from_utf8(&bytes)?;
-    
-        Ok(Document::from(file))
+
+        // don't keep an error page in place of the real one
+        inspect(status, challenged, html, &bytes)?;
+
+        // the rest of them, like 404, are given as they are for the parser
+        // to tell what's wrong; but they're not what the page is
+        if !status.is_success() {
+            return Ok(bytes);
+        }
+
+        self.persist("page", &key, &bytes).await;
+        self.cache.insert(key, bytes.clone(), validators);
+        Ok(bytes)
+    }
+
+    // get whatever the server gives, unless it's an error
+    pub async fn get_bytes(&self, dest: Uri)
+        -> Result<Bytes, ErrorBox> {
+        let res = self.get(dest, "*/*").await?;
+
+        if !res.status().is_success() {
+            return Err(refused(res).await);
+        }
+
+        Ok(hyper::body::to_bytes(res.into_body()).await?)
+    }
+
+    pub async fn get_json(&self, dest: Uri)
+        -> Result<Value, ErrorBox> {
+        let bytes = match self.cache.get(&self.cache_key(&dest)) {
+            Some(bytes) => bytes,
+            None => self.fetch(dest, "application/json").await?,
+        };
+
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    // submit a form like a browser does. it changes something,
+    // so it's never cached
+    pub async fn post_form(&self, dest: Uri, fields: &[(String, String)])
+        -> Result<Document, ErrorBox> {
+        self.check_online(&dest)?;
+
+        let body = fields
+            .iter()
+            .map(|(key, value)| format!("{}={}", percent_encode(key), percent_encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let req = self.request(Method::POST, dest, "application/x-www-form-urlencoded")
+            .body(Body::from(body))?;
+
+        let res = self.send(req).await?;
+        let (status, challenged, html) = (res.status(), challenged(&res), is_html(&res));
+        let bytes = hyper::body::to_bytes(res.into_body()).await?;
+
+        inspect(status, challenged, html, &bytes)?;
+        Ok(Document::from(str::from_utf8(&bytes)?))
+    }
+
+    // post a json request and get a json response, which is how the api works
+    pub async fn post_json(&self, dest: Uri, body: &Value)
+        -> Result<Value, ErrorBox> {
+        let body = serde_json::to_vec(body)?;
+
+        // the api answers the same to the same request, of the same account
+        let key = format!("{} {}", self.cache_key(&dest), String::from_utf8_lossy(&body));
+
+        if let Some(bytes) = self.cache.get(&key) {
+            return Ok(serde_json::from_slice(&bytes)?);
+        }
+
+        if self.is_offline() {
+            let bytes = self.cached("api", &key).await?;
+            return Ok(serde_json::from_slice(&bytes)?);
+        }
+
+        let req = self.request(Method::POST, dest, "application/json")
+            .body(Body::from(body))?;
+
+        let res = self.send(req).await?;
+        let (status, challenged, html) = (res.status(), challenged(&res), is_html(&res));
+        let bytes = hyper::body::to_bytes(res.into_body()).await?;
+
+        inspect(status, challenged, html, &bytes)?;
+        let value = serde_json::from_slice::<Value>(&bytes)?;
+
+        // errors of the api, like being asked too much, don't last
+        if !status.is_success() || !value["error"].is_null() {
+            return Ok(value);
+        }
+
+        self.persist("api", &key, &bytes).await;
+        self.cache.insert(key, bytes, Validators::default());
+        Ok(value)
+    }
+
+    // same as post_json(), but for requests which change something;
+    // they're never answered from the caches nor kept in them
+    pub async fn post_json_fresh(&self, dest: Uri, body: &Value)
+        -> Result<Value, ErrorBox> {
+        self.check_online(&dest)?;
+
+        let req = self.request(Method::POST, dest, "application/json")
+            .body(Body::from(serde_json::to_vec(body)?))?;
+
+        let res = self.send(req).await?;
+        let (status, challenged, html) = (res.status(), challenged(&res), is_html(&res));
+        let bytes = hyper::body::to_bytes(res.into_body()).await?;
+
+        inspect(status, challenged, html, &bytes)?;
+
+        Ok(serde_json::from_slice(&bytes)?)
     }
 }
@@ -0,0 +1,88 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::fmt::Write;
+
+use super::article::ArticleMeta;
+use super::tag::{ArticleKind, TagKind};
+
+pub(super) fn escape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&apos;"),
+            c => res.push(c),
+        }
+    }
+
+    res
+}
+
+impl ArticleMeta {
+    // render the metadata in ComicInfo.xml format, which is what
+    // comic library servers (komga, kavita, comicrack...) understand
+    pub fn comic_info(&self) -> String {
+        let mut xml = String::new();
+
+        let mut field = |name: &str, value: &str| {
+            if !value.is_empty() {
+                // writing into a string never fails
+                writeln!(xml, "  <{0}>{1}</{0}>", name, escape(value)).unwrap();
+            }
+        };
+
+        let join = |kind: TagKind| self.tags[kind].join(", ");
+
+        field("Title", &self.title);
+        field("Series", &self.title);
+        field("Writer", &join(TagKind::Artist));
+        field("Penciller", &join(TagKind::Artist));
+        field("Publisher", &join(TagKind::Group));
+        field("Characters", &join(TagKind::Character));
+        field("Genre", &self.kind.to_string());
+
+        let tags = self.tags
+            .iter()
+            .flat_map(|(kind, tags)| tags.iter().map(move |tag| format!("{}:{}", kind, tag)))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        field("Tags", &tags);
+        field("Web", &self.path);
+        field("PageCount", &self.length.to_string());
+        field("LanguageISO", self.language.code().unwrap_or(""));
+
+        // the date would be like "2021-03-04 12:34"
+        let mut date = self.posted
+            .split(|c| c == '-' || c == ' ')
+            .map(|x| x.trim_start_matches('0'));
+
+        field("Year", date.next().unwrap_or(""));
+        field("Month", date.next().unwrap_or(""));
+        field("Day", date.next().unwrap_or(""));
+
+        match self.kind {
+            ArticleKind::NonH => (),
+            _ => field("AgeRating", "Adults Only 18+")
+        }
+
+        field("Manga", match self.kind {
+            ArticleKind::Doujinshi | ArticleKind::Manga => "YesAndRightToLeft",
+            _ => "Unknown"
+        });
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <ComicInfo xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" \
+             xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n\
+             {}</ComicInfo>\n",
+            xml
+        )
+    }
+}
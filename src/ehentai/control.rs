@@ -0,0 +1,120 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+use super::error::EhError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ControlState {
+    Running,
+    Paused,
+    // for good; it can't be resumed
+    Cancelled,
+}
+
+impl Default for ControlState {
+    fn default() -> Self {
+        ControlState::Running
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    state: Mutex<ControlState>,
+    changed: Notify,
+}
+
+impl Inner {
+    fn set(&self, state: ControlState) {
+        {
+            let mut current = self.state.lock().unwrap();
+
+            if *current == ControlState::Cancelled {
+                return;
+            }
+
+            *current = state;
+        }
+
+        self.changed.notify_waiters();
+    }
+
+    fn get(&self) -> ControlState {
+        *self.state.lock().unwrap()
+    }
+}
+
+// a handle to pause, resume or cancel a download from elsewhere, like
+// Progress. what's in flight is let finish, and nothing new starts
+// while it's paused. a child follows its parent as well as its own
+#[derive(Debug, Clone, Default)]
+pub struct Control {
+    inner: Arc<Inner>,
+    parent: Option<Arc<Inner>>,
+}
+
+impl Control {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // a handle which is paused or cancelled along with this one,
+    // but can be on its own as well
+    pub fn child(&self) -> Self {
+        Self {
+            inner: Arc::default(),
+            parent: Some(self.inner.clone()),
+        }
+    }
+
+    pub fn pause(&self) {
+        self.inner.set(ControlState::Paused);
+    }
+
+    pub fn resume(&self) {
+        self.inner.set(ControlState::Running);
+    }
+
+    pub fn cancel(&self) {
+        self.inner.set(ControlState::Cancelled);
+    }
+
+    // the more stopped of its own and the parent's
+    pub fn state(&self) -> ControlState {
+        let own = self.inner.get();
+
+        match &self.parent {
+            Some(parent) => own.max(parent.get()),
+            None => own,
+        }
+    }
+
+    // wait while paused, and fail with EhError::Cancelled if cancelled
+    pub(super) async fn proceed(&self) -> Result<(), EhError> {
+        loop {
+            // made before looking, not to miss a change in between
+            let own = self.inner.changed.notified();
+            let parent = self.parent.as_ref().map(|parent| parent.changed.notified());
+
+            match self.state() {
+                ControlState::Running => return Ok(()),
+                ControlState::Cancelled => return Err(EhError::Cancelled),
+                ControlState::Paused => match parent {
+                    Some(parent) => tokio::select! {
+                        _ = own => {},
+                        _ = parent => {},
+                    },
+                    None => own.await,
+                }
+            }
+        }
+    }
+}
+
+// whether the error is from cancelling by a Control
+pub(super) fn is_cancelled(err: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+    matches!(err.downcast_ref::<EhError>(), Some(EhError::Cancelled))
+}
@@ -0,0 +1,148 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::fmt;
+use std::error::Error;
+
+// the sites cookies of a raw header are given to
+const DOMAINS: [&str; 2] = ["e-hentai.org", "exhentai.org"];
+
+#[derive(Debug)]
+pub struct ParseCookiesError(usize);
+
+impl fmt::Display for ParseCookiesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cookies with wrong format were given at line {}", self.0)
+    }
+}
+
+impl Error for ParseCookiesError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cookie {
+    domain: String,
+    // whether it's given to subdomains too
+    subdomains: bool,
+    name: String,
+    value: String,
+}
+
+impl Cookie {
+    fn matches(&self, host: &str) -> bool {
+        host == self.domain || (self.subdomains && host
+            .strip_suffix(self.domain.as_str())
+            .map_or(false, |sub| sub.ends_with('.')))
+    }
+}
+
+// cookies to give to the sites, by their domains. they're only what we're
+// given; whatever the sites set is not kept
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct CookieJar {
+    cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // a cookie of the same name and domain is replaced
+    pub fn add(&mut self, domain: &str, subdomains: bool, name: &str, value: &str) {
+        let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+
+        self.cookies.retain(|x| x.domain != domain || x.name != name);
+        self.cookies.push(Cookie {
+            domain,
+            subdomains,
+            name: name.to_owned(),
+            value: value.to_owned(),
+        });
+    }
+
+    pub fn extend(&mut self, other: CookieJar) {
+        for x in other.cookies {
+            self.add(&x.domain, x.subdomains, &x.name, &x.value);
+        }
+    }
+
+    // read the cookies.txt format of netscape, which browser extensions
+    // export; a line is "domain, subdomains, path, secure, expiry, name,
+    // value" separated by tabs
+    pub fn from_netscape(text: &str) -> Result<Self, ParseCookiesError> {
+        let mut jar = Self::new();
+
+        for (num, line) in text.lines().enumerate() {
+            // curl marks http-only cookies like a comment
+            let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = line.split('\t').collect::<Vec<_>>();
+
+            if fields.len() < 7 {
+                return Err(ParseCookiesError(num + 1));
+            }
+
+            let subdomains = fields[1].eq_ignore_ascii_case("TRUE");
+            jar.add(fields[0], subdomains, fields[5], fields[6].trim_end());
+        }
+
+        Ok(jar)
+    }
+
+    // read a "Cookie" header a browser sends, like "a=1; b=2".
+    // it doesn't say where it's from, so it goes to every site of us
+    pub fn from_header(header: &str) -> Result<Self, ParseCookiesError> {
+        let header = header.trim();
+        let header = header.strip_prefix("Cookie:").unwrap_or(header);
+
+        let mut jar = Self::new();
+
+        for pair in header.split(';').filter(|x| !x.trim().is_empty()) {
+            let mut iter = pair.splitn(2, '=');
+
+            let name = iter.next().unwrap().trim();
+            let value = iter.next().ok_or(ParseCookiesError(1))?.trim();
+
+            if name.is_empty() {
+                return Err(ParseCookiesError(1));
+            }
+
+            for domain in DOMAINS.iter() {
+                jar.add(domain, true, name, value);
+            }
+        }
+
+        Ok(jar)
+    }
+
+    // the value of a cookie given to the host, like the member id
+    pub fn value(&self, host: &str, name: &str) -> Option<&str> {
+        let host = host.to_ascii_lowercase();
+
+        self.cookies
+            .iter()
+            .find(|x| x.name == name && x.matches(&host))
+            .map(|x| x.value.as_str())
+    }
+
+    // the "Cookie" header for the host, if it has any
+    pub fn header(&self, host: &str) -> Option<String> {
+        let host = host.to_ascii_lowercase();
+
+        let pairs = self.cookies
+            .iter()
+            .filter(|x| x.matches(&host))
+            .map(|x| format!("{}={}", x.name, x.value))
+            .collect::<Vec<_>>();
+
+        match pairs.is_empty() {
+            true => None,
+            false => Some(pairs.join("; "))
+        }
+    }
+}
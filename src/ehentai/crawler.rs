@@ -0,0 +1,315 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::io;
+use std::sync::Arc;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use serde_json::{json, Value};
+use tokio::fs;
+
+use super::client::Client;
+use super::tag::ArticleKind;
+use super::page::Page;
+use super::api::{self, GalleryData};
+use super::id::GalleryId;
+use super::control::Control;
+use super::queue::Priority;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+// what's crawled through
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CrawlSource {
+    // results of a search, like "language:english"
+    Search(String),
+    // every gallery of a category
+    Category(ArticleKind),
+}
+
+impl From<&str> for CrawlSource {
+    fn from(keyword: &str) -> Self {
+        CrawlSource::Search(keyword.to_owned())
+    }
+}
+
+impl From<String> for CrawlSource {
+    fn from(keyword: String) -> Self {
+        CrawlSource::Search(keyword)
+    }
+}
+
+impl From<ArticleKind> for CrawlSource {
+    fn from(kind: ArticleKind) -> Self {
+        CrawlSource::Category(kind)
+    }
+}
+
+impl CrawlSource {
+    fn to_value(&self) -> Value {
+        match self {
+            CrawlSource::Search(keyword) => json!({ "search": keyword }),
+            CrawlSource::Category(kind) => json!({ "category": kind.to_string() }),
+        }
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        if let Some(keyword) = value["search"].as_str() {
+            return Some(CrawlSource::Search(keyword.to_owned()));
+        }
+
+        value["category"].as_str()?.parse().ok().map(CrawlSource::Category)
+    }
+}
+
+// walks through all the results of a search or a category, a page at a
+// time, and gives the metadata of the galleries by the api. it's for
+// building a dataset of them:
+//
+// - requests go through the rate limit of the explorer like the others,
+//   and in the background, so what someone's waiting for goes first
+// - a gallery is given once, even if the results shift by galleries
+//   posted in between and it's seen again on the next page. results are
+//   sorted by gid, so it's enough to know the lowest one given
+// - with a checkpoint, where it's got to is written down after each page,
+//   and Explorer::resume_crawl() goes on from there after a restart
+//
+// it can be paused or stopped by its control as downloads are. with
+// since(), it's only the galleries posted after the one crawled last,
+// which keeps a mirror up to date in a few pages.
+pub struct Crawler {
+    client: Arc<Client>,
+    source: CrawlSource,
+    // where it's kept, if it is
+    checkpoint: Option<PathBuf>,
+    // the page to fetch next
+    page: usize,
+    done: bool,
+    // the lowest gid given so far; those above it were given already
+    lowest: Option<u64>,
+    // number of galleries given so far
+    count: usize,
+    // where the crawl stops, if it's from the front page to the one
+    // crawled last time
+    since: Option<u64>,
+    // the largest gid seen so far
+    newest: Option<u64>,
+    control: Control,
+    // the pages of the results, made when it's first asked for
+    pages: Option<Page>,
+    // a page fetched whose galleries the api hasn't told about yet, if
+    // it failed to; they're asked again before going on
+    pending: Option<Pending>,
+}
+
+struct Pending {
+    ids: Vec<GalleryId>,
+    // the page after it
+    page: usize,
+    // whether it's down to the watermark, and the crawl ends with it
+    last: bool,
+}
+
+impl Crawler {
+    pub(super) fn new(client: Arc<Client>, source: CrawlSource) -> Self {
+        Self {
+            client,
+            source,
+            checkpoint: None,
+            page: 0,
+            done: false,
+            lowest: None,
+            count: 0,
+            since: None,
+            newest: None,
+            control: Control::new(),
+            pages: None,
+            pending: None,
+        }
+    }
+
+    // a crawl written down by a checkpoint, from where it was
+    pub(super) async fn resume(client: Arc<Client>, path: impl AsRef<Path>)
+        -> Result<Self, ErrorBox> {
+        let path = path.as_ref();
+        let value = serde_json::from_slice::<Value>(&fs::read(path).await?)?;
+
+        let source = CrawlSource::from_value(&value["source"])
+            .ok_or("a checkpoint without what's crawled")?;
+
+        let mut crawler = Self::new(client, source);
+        crawler.checkpoint = Some(path.to_owned());
+        crawler.page = value["page"].as_u64().unwrap_or(0) as usize;
+        crawler.done = value["done"].as_bool().unwrap_or(false);
+        crawler.lowest = value["lowest"].as_u64();
+        crawler.count = value["count"].as_u64().unwrap_or(0) as usize;
+        crawler.since = value["since"].as_u64();
+        crawler.newest = value["newest"].as_u64();
+
+        Ok(crawler)
+    }
+
+    // write down where it's got to in the file, after each page
+    pub fn checkpoint(mut self, path: impl Into<PathBuf>) -> Self {
+        self.checkpoint = Some(path.into());
+        self
+    }
+
+    // only the galleries newer than `gid`, like the watermark() of the
+    // crawl before. results are sorted by the time they're posted and so
+    // by gid, so it stops at the first one which isn't
+    pub fn since(mut self, gid: u64) -> Self {
+        self.since = Some(gid);
+        self
+    }
+
+    // the newest gallery crawled, which is where the next crawl should
+    // start since(). it's the one it started since if there was none
+    pub fn watermark(&self) -> Option<u64> {
+        self.newest.max(self.since)
+    }
+
+    // pause or cancel the crawl by it; what's being fetched is let finish
+    pub fn control(mut self, control: Control) -> Self {
+        self.control = control;
+        self
+    }
+
+    pub fn source(&self) -> &CrawlSource {
+        &self.source
+    }
+
+    // index of the page next() would fetch
+    pub fn page(&self) -> usize {
+        self.page
+    }
+
+    // number of galleries given so far
+    pub fn seen(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    // the galleries of the next page which haven't been given yet. some
+    // pages may have none of those; it's not the end until it gives None
+    pub async fn next(&mut self) -> Result<Option<Vec<GalleryData>>, ErrorBox> {
+        // a crawl shouldn't get in the way of anything else
+        Priority::Background.or_current(self.crawl()).await
+    }
+
+    async fn crawl(&mut self) -> Result<Option<Vec<GalleryData>>, ErrorBox> {
+        if self.done {
+            return Ok(None);
+        }
+
+        self.control.proceed().await?;
+
+        if self.pages.is_none() {
+            let page = match &self.source {
+                CrawlSource::Search(keyword) => Page::new(self.client.clone(), self.page, keyword),
+                CrawlSource::Category(kind) => Page::new(self.client.clone(), self.page, "")
+                    .categories(&[*kind]),
+            };
+
+            // the results change as they're walked through, and the cache
+            // would only be in the way of that
+            self.pages = Some(page.fresh(true));
+        }
+
+        if self.pending.is_none() {
+            let pages = self.pages.as_mut().unwrap();
+
+            let drafts = match pages.next().await? {
+                Some(drafts) => drafts,
+                None => {
+                    self.done = true;
+                    self.save().await?;
+                    return Ok(None);
+                }
+            };
+
+            let lowest = self.lowest;
+
+            let mut ids = drafts
+                .iter()
+                .filter_map(|draft| draft.meta().id())
+                .filter(|id| lowest.map_or(true, |lowest| id.gid < lowest))
+                .collect::<Vec<_>>();
+
+            let mut last = false;
+
+            if let Some(since) = self.since {
+                // the rest is what was crawled before
+                if let Some(at) = ids.iter().position(|id| id.gid <= since) {
+                    ids.truncate(at);
+                    last = true;
+                }
+            }
+
+            self.pending = Some(Pending { ids, page: pages.page(), last });
+        }
+
+        // the page isn't gone past until the api has told about it
+        let ids = &self.pending.as_ref().unwrap().ids;
+
+        let list = match ids.is_empty() {
+            true => Vec::new(),
+            false => api::gdata_known(&self.client, ids).await?,
+        };
+
+        let pending = self.pending.take().unwrap();
+        self.page = pending.page;
+        self.done = pending.last;
+
+        // only what's given counts for the watermark, or a crawl after
+        // a failed one would miss the rest
+        self.newest = pending.ids.iter().map(|id| id.gid).chain(self.newest).max();
+
+        // those the api didn't know count as given too; they won't be
+        // known the next time either
+        self.lowest = pending.ids.iter().map(|id| id.gid).chain(self.lowest).min();
+        self.count += pending.ids.len();
+        self.save().await?;
+
+        Ok(Some(list))
+    }
+
+    // write down where it's got to, if there's a checkpoint; aside first
+    // and renamed, like manifests are
+    pub async fn save(&self) -> Result<(), ErrorBox> {
+        let path = match &self.checkpoint {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let value = json!({
+            "source": self.source.to_value(),
+            "page": self.page,
+            "done": self.done,
+            "lowest": self.lowest,
+            "count": self.count,
+            "since": self.since,
+            "newest": self.newest,
+        });
+
+        let mut part = path.clone().into_os_string();
+        part.push(".part");
+
+        fs::write(&part, serde_json::to_vec(&value)?).await?;
+        fs::rename(&part, path).await?;
+        Ok(())
+    }
+}
+
+// whether there's a checkpoint to resume from at the path
+pub(super) async fn has_checkpoint(path: &Path) -> io::Result<bool> {
+    match fs::metadata(path).await {
+        Ok(_) => Ok(true),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err),
+    }
+}
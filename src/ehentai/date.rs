@@ -0,0 +1,92 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July",
+    "August", "September", "October", "November", "December",
+];
+
+// a date of the site in utc, as (year, month, day, hour, minute).
+// it doesn't give seconds anywhere
+pub(super) type Parts = (i32, u32, u32, u32, u32);
+
+// galleries and favorites are like "2021-03-04 12:34", and
+// comments are like "04 March 2021, 12:34"
+pub(super) fn parse(date: &str) -> Option<Parts> {
+    let date = date.trim();
+    let (day, time) = match date.rfind(' ') {
+        Some(pos) => (date[..pos].trim_end_matches(','), &date[pos + 1..]),
+        None => return None
+    };
+
+    let mut time = time.splitn(2, ':').map(|x| x.parse::<u32>().ok());
+    let hour = time.next()??;
+    let minute = time.next()??;
+
+    let (year, month, day) = if day.contains('-') {
+        let mut iter = day.splitn(3, '-');
+        let year = iter.next()?.parse().ok()?;
+        let month = iter.next()?.parse().ok()?;
+        let day = iter.next()?.parse().ok()?;
+
+        (year, month, day)
+    } else {
+        let mut iter = day.split_whitespace();
+        let day = iter.next()?.parse().ok()?;
+        let month = iter.next()?;
+        let month = MONTHS.iter().position(|x| *x == month)? as u32 + 1;
+        let year = iter.next()?.parse().ok()?;
+
+        (year, month, day)
+    };
+
+    let valid = (1..=12).contains(&month) && (1..=31).contains(&day)
+        && hour < 24 && minute < 60;
+
+    match valid {
+        true => Some((year, month, day, hour, minute)),
+        false => None
+    }
+}
+
+// a unix timestamp as the site writes dates, like "2021-03-04 12:34".
+// it's the days since 1970 into a date of the proleptic gregorian
+// calendar, counted in eras of 400 years which are all the same length
+pub(super) fn format_unix(secs: u64) -> String {
+    let days = secs / 86400;
+    let time = secs % 86400;
+
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as u64;
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year, month, day, time / 3600, time % 3600 / 60
+    )
+}
+
+#[cfg(feature = "chrono")]
+pub(super) fn to_utc(date: &str) -> Option<DateTime<Utc>> {
+    let (year, month, day, hour, minute) = parse(date)?;
+    let naive = NaiveDate::from_ymd_opt(year, month, day)?
+        .and_hms_opt(hour, minute, 0)?;
+
+    Some(Utc.from_utc_datetime(&naive))
+}
+
+#[cfg(feature = "chrono")]
+pub(super) fn from_unix(secs: u64) -> Option<DateTime<Utc>> {
+    Utc.timestamp_opt(secs as i64, 0).single()
+}
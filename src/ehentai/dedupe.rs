@@ -0,0 +1,125 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::io;
+use std::error::Error;
+use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use serde_json::Value;
+use tokio::fs;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+// images saved so far, by the hash their image pages are named after.
+// the same image in another gallery, like a new revision or a re-upload
+// of it, is linked to the file there rather than downloaded again
+#[derive(Debug, Default)]
+pub struct ImageIndex {
+    // where it's kept, if it is
+    path: Option<PathBuf>,
+    files: Mutex<HashMap<String, PathBuf>>,
+    // one to write it at a time, for the downloads going on at once
+    saving: tokio::sync::Mutex<()>,
+}
+
+impl ImageIndex {
+    // an index only in memory, which is gone with it
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the index kept in the file, or a new one there if it isn't yet
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, ErrorBox> {
+        let path = path.as_ref().to_owned();
+
+        let files = match fs::read(&path).await {
+            Ok(text) => match serde_json::from_slice::<Value>(&text)? {
+                Value::Object(map) => map
+                    .into_iter()
+                    .filter_map(|(hash, file)| Some((hash, PathBuf::from(file.as_str()?))))
+                    .collect(),
+                _ => return Err("an image index which isn't an object".into())
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err.into())
+        };
+
+        Ok(Self {
+            path: Some(path),
+            files: Mutex::new(files),
+            saving: Default::default(),
+        })
+    }
+
+    // write it down if it's kept in a file; aside first and renamed,
+    // like manifests are
+    pub async fn save(&self) -> Result<(), ErrorBox> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let _saving = self.saving.lock().await;
+
+        let value = self.files
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(hash, file)| Some((hash.clone(), Value::from(file.to_str()?))))
+            .collect::<serde_json::Map<_, _>>();
+
+        let mut part = path.clone().into_os_string();
+        part.push(".part");
+
+        fs::write(&part, serde_json::to_vec(&Value::Object(value))?).await?;
+        fs::rename(&part, path).await?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, hash: &str) -> Option<PathBuf> {
+        self.files.lock().unwrap().get(hash).cloned()
+    }
+
+    pub fn insert(&self, hash: &str, file: PathBuf) {
+        self.files.lock().unwrap().insert(hash.to_owned(), file);
+    }
+
+    pub fn remove(&self, hash: &str) {
+        self.files.lock().unwrap().remove(hash);
+    }
+
+    // the file of the image, if it's still there. a file which has gone
+    // since is forgotten
+    pub(super) async fn find(&self, hash: &str) -> Option<PathBuf> {
+        let file = self.get(hash)?;
+
+        match fs::metadata(&file).await {
+            Ok(meta) if meta.len() > 0 => Some(file),
+            _ => {
+                self.remove(hash);
+                None
+            }
+        }
+    }
+}
+
+// put a file at `dest` too, by a hard link if the file system can or by
+// a copy otherwise
+pub(super) async fn link(src: &Path, dest: &Path) -> io::Result<()> {
+    fs::remove_file(dest).await.ok();
+
+    match fs::hard_link(src, dest).await {
+        Ok(()) => Ok(()),
+        Err(_) => fs::copy(src, dest).await.map(|_| ())
+    }
+}
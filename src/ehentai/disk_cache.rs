@@ -0,0 +1,241 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::error::Error;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tokio::fs;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+struct File {
+    size: u64,
+    // larger if used more recently
+    used: u64,
+    written: SystemTime,
+}
+
+struct Index {
+    files: HashMap<String, File>,
+    total: u64,
+    clock: u64,
+}
+
+// images kept in a directory, named after what they are so the same
+// image is never downloaded twice. when it's gone over the capacity,
+// the least recently used ones are removed
+pub(super) struct DiskCache {
+    dir: PathBuf,
+    capacity: u64,
+    index: Mutex<Index>,
+}
+
+// a hash which doesn't change between runs (or versions of rust),
+// since its values are names of the files
+pub(super) fn fnv1a(data: impl AsRef<[u8]>) -> u64 {
+    fnv1a_from(0xcbf29ce484222325, data.as_ref())
+}
+
+// same as fnv1a(), but going on from the hash of what came before
+pub(super) fn fnv1a_from(hash: u64, data: &[u8]) -> u64 {
+    data.iter().fold(hash, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl DiskCache {
+    // use the directory as a cache of `capacity` bytes, keeping
+    // what's already there from the last time
+    pub async fn open(dir: PathBuf, capacity: u64) -> Result<Self, ErrorBox> {
+        fs::create_dir_all(&dir).await?;
+
+        let mut found = Vec::new();
+        let mut entries = fs::read_dir(&dir).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let meta = entry.metadata().await?;
+
+            if !meta.is_file() {
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().into_owned();
+
+            // left over from a write which didn't finish
+            if name.ends_with(".part") {
+                fs::remove_file(entry.path()).await.ok();
+                continue;
+            }
+
+            let written = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            found.push((written, name, meta.len()));
+        }
+
+        // the file modified earlier is regarded as used earlier
+        found.sort();
+
+        let mut index = Index {
+            files: HashMap::new(),
+            total: 0,
+            clock: 0,
+        };
+
+        for (written, name, size) in found {
+            index.clock += 1;
+            index.total += size;
+            index.files.insert(name, File { size, used: index.clock, written });
+        }
+
+        let cache = Self {
+            dir,
+            capacity,
+            index: Mutex::new(index),
+        };
+
+        // the capacity may be smaller than the last time
+        cache.evict(0).await;
+        Ok(cache)
+    }
+
+    pub async fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let known = self.index.lock().unwrap().files.contains_key(key);
+
+        if !known {
+            return None;
+        }
+
+        match fs::read(self.dir.join(key)).await {
+            Ok(data) => {
+                let mut index = self.index.lock().unwrap();
+                index.clock += 1;
+
+                let clock = index.clock;
+
+                if let Some(file) = index.files.get_mut(key) {
+                    file.used = clock;
+                }
+
+                Some(data)
+            },
+            // someone removed it
+            Err(_) => {
+                let mut index = self.index.lock().unwrap();
+
+                if let Some(file) = index.files.remove(key) {
+                    index.total -= file.size;
+                }
+
+                None
+            }
+        }
+    }
+
+    // it's only a cache; failing to write is not a big deal
+    pub async fn put(&self, key: &str, data: &[u8]) {
+        let size = data.len() as u64;
+
+        if size > self.capacity {
+            return;
+        }
+
+        self.evict(size).await;
+
+        // written aside and moved in place, so that what's found by the
+        // key is always whole, even after a crash
+        let part = self.dir.join(format!("{}.part", key));
+
+        if fs::write(&part, data).await.is_err() {
+            return;
+        }
+
+        if fs::rename(&part, self.dir.join(key)).await.is_err() {
+            fs::remove_file(&part).await.ok();
+            return;
+        }
+
+        let mut index = self.index.lock().unwrap();
+        index.clock += 1;
+
+        let file = File {
+            size,
+            used: index.clock,
+            written: SystemTime::now(),
+        };
+
+        if let Some(old) = index.files.insert(key.to_owned(), file) {
+            index.total -= old.size;
+        }
+
+        index.total += size;
+    }
+
+    // the number of files and their total size
+    pub fn size(&self) -> (usize, u64) {
+        let index = self.index.lock().unwrap();
+        (index.files.len(), index.total)
+    }
+
+    // remove every file the predicate says yes to, given its name
+    // and how long ago it was written
+    pub async fn remove_where(&self, pred: impl Fn(&str, Duration) -> bool) {
+        let victims = {
+            let mut index = self.index.lock().unwrap();
+
+            let victims = index.files
+                .iter()
+                .filter(|(name, file)| {
+                    let age = file.written.elapsed().unwrap_or_default();
+                    pred(name, age)
+                })
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>();
+
+            for name in victims.iter() {
+                if let Some(file) = index.files.remove(name) {
+                    index.total -= file.size;
+                }
+            }
+
+            victims
+        };
+
+        for name in victims {
+            let _ = fs::remove_file(self.dir.join(name)).await;
+        }
+    }
+
+    // remove files until there is room for `size` more bytes
+    async fn evict(&self, size: u64) {
+        let victims = {
+            let mut index = self.index.lock().unwrap();
+
+            let mut files = index.files
+                .iter()
+                .map(|(name, file)| (file.used, file.size, name.clone()))
+                .collect::<Vec<_>>();
+
+            files.sort();
+
+            let mut victims = Vec::new();
+
+            for (_, file_size, name) in files {
+                if index.total + size <= self.capacity {
+                    break;
+                }
+
+                index.total -= file_size;
+                index.files.remove(&name);
+                victims.push(name);
+            }
+
+            victims
+        };
+
+        for name in victims {
+            let _ = fs::remove_file(self.dir.join(name)).await;
+        }
+    }
+}
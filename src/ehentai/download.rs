@@ -0,0 +1,592 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::io;
+use std::fmt::Write;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::error::Error;
+use std::collections::{HashSet, HashMap, BTreeMap};
+use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use futures::stream::{self, StreamExt};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWrite};
+
+use super::article::{Article, ArticleMeta};
+use super::image::{ImageEntry, ImageFormat};
+use super::manifest::{Manifest, PageRecord};
+use super::naming::NameTemplate;
+use super::verify::{self, PageCheck};
+use super::dedupe::{self, ImageIndex};
+use super::gallerydl;
+use super::disk_cache::{fnv1a, fnv1a_from};
+use super::progress::Progress;
+use super::control::{self, Control};
+use super::error::EhError;
+use super::queue::Priority;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    // number of images to download at once
+    pub concurrency: usize,
+    // don't download pages which already have a file in the directory
+    pub skip_existing: bool,
+    // read the files of those pages, and download them again if they're
+    // broken; see PageCheck. those downloaded are checked as well, and
+    // fail by EhError::BrokenImage if they came broken
+    pub verify: bool,
+    // download those which came broken once more, from another server
+    // as the "reload broken image" link of the site does
+    pub reload_broken: bool,
+    // write informations of the article into "info.txt"
+    pub metadata: bool,
+    // write "ComicInfo.xml" for comic library servers
+    pub comic_info: bool,
+    // write "info.json" in the format of eze and gallery-dl
+    pub info_json: bool,
+    // link images saved before, here or in another gallery, rather than
+    // downloading them again; those downloaded are put in it too
+    pub images: Option<Arc<ImageIndex>>,
+    // how images are named in the directory, like "{page:03}.{ext}";
+    // after their page number by page_name() if there's none
+    pub file_names: Option<NameTemplate>,
+    // name images as gallery-dl does, with its json of each beside them;
+    // see gallerydl.rs. file_names doesn't count then
+    pub gallery_dl: bool,
+    // keep a manifest of what's done in the directory, and go on from
+    // there the next time. pages in it are trusted over the files
+    pub manifest: bool,
+    pub progress: Progress,
+    // pause or cancel it between images
+    pub control: Control,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            skip_existing: true,
+            verify: true,
+            reload_broken: false,
+            metadata: true,
+            comic_info: false,
+            info_json: true,
+            images: None,
+            file_names: None,
+            gallery_dl: false,
+            manifest: false,
+            progress: Progress::new(),
+            control: Control::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DownloadSummary {
+    pub dir: PathBuf,
+    pub downloaded: usize,
+    pub skipped: usize,
+    // pages which had a file before, by index, and how it was found.
+    // those which were broken are downloaded again, not skipped
+    pub existing: Vec<(usize, PageCheck)>,
+    // pages downloaded and found broken; they're failed too, unless they
+    // came whole when reloaded
+    pub broken: Vec<usize>,
+    // pages linked to the same image saved before, by the index of the
+    // options, rather than downloaded
+    pub linked: usize,
+    // index of the image and what went wrong
+    pub failed: Vec<(usize, String)>,
+    pub bytes: u64,
+}
+
+impl DownloadSummary {
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+// name of the file of an image without extension, like "007".
+// pages start from 1 unlike indices
+pub(super) fn page_name(index: usize, len: usize) -> String {
+    let width = len.to_string().len();
+    format!("{:0width$}", index + 1, width = width)
+}
+
+// the reverse of page_name(); get the index from a path like "dir/007.jpg"
+fn page_index(path: &Path, len: usize) -> Option<usize> {
+    let width = len.to_string().len();
+
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|stem| stem.len() == width)
+        .and_then(|stem| stem.parse::<usize>().ok())
+        .filter(|&page| page >= 1 && page <= len)
+        .map(|page| page - 1)
+}
+
+pub(super) fn info_text(meta: &ArticleMeta) -> String {
+    let mut text = String::new();
+
+    // writing into a string never fails
+    writeln!(text, "title: {}", meta.title).unwrap();
+    writeln!(text, "original title: {}", meta.original_title).unwrap();
+    writeln!(text, "url: {}", meta.path).unwrap();
+    writeln!(text, "category: {}", meta.kind).unwrap();
+    writeln!(text, "uploader: {}", meta.uploader).unwrap();
+    writeln!(text, "posted: {}", meta.posted).unwrap();
+    writeln!(text, "language: {}", meta.language).unwrap();
+    writeln!(text, "length: {} pages", meta.length).unwrap();
+    writeln!(text, "rating: {} ({} votes)", meta.rating, meta.rating_count).unwrap();
+    writeln!(text).unwrap();
+    writeln!(text, "tags:").unwrap();
+
+    for (kind, tags) in meta.tags.iter() {
+        writeln!(text, "> {}: {}", kind, tags.join(", ")).unwrap();
+    }
+
+    text
+}
+
+impl Article {
+    // download the whole article into a directory, creating it if
+    // there isn't. images are named after their page number like "007.jpg",
+    // or as the template of the options says
+    pub async fn download_to(&self, dir: impl AsRef<Path>,
+        options: &DownloadOptions) -> Result<DownloadSummary, ErrorBox> {
+        Priority::Download.or_current(self.save_all(dir.as_ref(), options)).await
+    }
+
+    async fn save_all(&self, dir: &Path, options: &DownloadOptions)
+        -> Result<DownloadSummary, ErrorBox> {
+        let len = self.meta().length;
+
+        fs::create_dir_all(dir).await?;
+
+        // what's done as the manifest says, if it's of this gallery
+        let mut manifest = match options.manifest {
+            true => Some(match Manifest::load(dir).await {
+                Some(old) if old.path == self.meta().path && old.length == len => old,
+                _ => Manifest::new(self.meta().path.clone(), len),
+            }),
+            false => None,
+        };
+
+        // files of pages which are already there, whatever the extension is
+        let mut found = BTreeMap::new();
+
+        if let Some(manifest) = manifest.as_mut() {
+            // the file may have gone since
+            let mut gone = Vec::new();
+
+            for (&index, page) in manifest.pages.iter() {
+                let path = dir.join(&page.file);
+
+                match fs::metadata(&path).await {
+                    Ok(meta) if meta.len() == page.size => { found.insert(index, path); },
+                    _ => gone.push(index),
+                }
+            }
+
+            for index in gone {
+                manifest.pages.remove(&index);
+            }
+
+            let entries = manifest.images
+                .iter()
+                .filter_map(|path| path.parse::<ImageEntry>().ok())
+                .collect();
+
+            self.restore_entries(entries);
+        } else if let (true, true, Some(id)) = (options.skip_existing, options.gallery_dl, self.meta().id()) {
+            let mut entries = fs::read_dir(dir).await?;
+
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                let ext = path.extension().and_then(|x| x.to_str()).unwrap_or("");
+
+                if entry.metadata().await?.len() == 0 || ext == "part" || ext == "json" {
+                    continue;
+                }
+
+                let index = path
+                    .file_name()
+                    .and_then(|x| x.to_str())
+                    .and_then(|x| gallerydl::image_index(x, id.gid, len));
+
+                if let Some(index) = index {
+                    found.insert(index, path);
+                }
+            }
+        } else if let (true, Some(template)) = (options.skip_existing, &options.file_names) {
+            // the extension isn't known until it's downloaded
+            for index in 0..len {
+                for format in ImageFormat::ALL.iter() {
+                    let path = dir.join(template.render(self.meta(), Some((index, format.extension()))));
+
+                    if fs::metadata(&path).await.map_or(false, |x| x.len() > 0) {
+                        found.insert(index, path);
+                    }
+                }
+            }
+        } else if options.skip_existing {
+            let mut entries = fs::read_dir(dir).await?;
+
+            while let Some(entry) = entries.next_entry().await? {
+                // an empty file must be from a failed download, and
+                // a part is from one which was cut off
+                if entry.metadata().await?.len() == 0
+                    || entry.path().extension().map_or(false, |ext| ext == "part") {
+                    continue;
+                }
+
+                if let Some(index) = page_index(&entry.path(), len) {
+                    found.insert(index, entry.path());
+                }
+            }
+        }
+
+        if options.metadata {
+            fs::write(dir.join("info.txt"), info_text(self.meta())).await?;
+        }
+
+        if options.comic_info {
+            fs::write(dir.join("ComicInfo.xml"), self.meta().comic_info()).await?;
+        }
+
+        if options.info_json {
+            fs::write(dir.join("info.json"), self.meta().info_json()).await?;
+        }
+
+        self.load_image_list().await?;
+
+        if let Some(manifest) = manifest.as_mut() {
+            manifest.images = self.image_entries()
+                .into_iter()
+                .map(|entry| entry.path)
+                .collect();
+
+            manifest.save(dir).await?;
+        }
+
+        // the list is loaded by now, so every hash is known
+        let hashes = self.image_entries()
+            .into_iter()
+            .map(|entry| (entry.page - 1, entry.hash))
+            .collect::<HashMap<_, _>>();
+
+        let mut present = HashSet::new();
+        let mut existing = Vec::new();
+
+        for (index, path) in found {
+            let check = match options.verify {
+                true => {
+                    let checksum = manifest
+                        .as_ref()
+                        .and_then(|x| x.pages.get(&index))
+                        .map(|page| page.checksum);
+
+                    let hash = hashes.get(&index).cloned();
+
+                    // a file it can't read is as good as broken
+                    match fs::read(&path).await {
+                        Ok(data) => tokio::task::spawn_blocking(move || {
+                            verify::check(&data, checksum, hash.as_deref())
+                        }).await?,
+                        Err(_) => PageCheck::Broken,
+                    }
+                },
+                false => PageCheck::Unverified,
+            };
+
+            if check.is_ok() {
+                if let (Some(images), Some(hash)) = (&options.images, hashes.get(&index)) {
+                    images.insert(hash, path);
+                }
+
+                present.insert(index);
+            } else {
+                // the new one may not have the same extension
+                fs::remove_file(&path).await.ok();
+
+                if let Some(manifest) = manifest.as_mut() {
+                    manifest.pages.remove(&index);
+                }
+            }
+
+            existing.push((index, check));
+        }
+
+        let progress = &options.progress;
+        progress.set_total(len - present.len());
+
+        let control = &options.control;
+
+        // images in flight are let finish when it's paused or cancelled;
+        // the others wait, or fail as cancelled
+        let mut images = stream::iter((0..len).filter(|i| !present.contains(i)))
+            .map(|index| {
+                let progress = progress.clone();
+                let hash = hashes.get(&index).map(|x| x.as_str());
+
+                async move {
+                    let res = match control.proceed().await {
+                        Ok(()) => match self.link_image(dir, index, options, hash).await {
+                            Some(page) => {
+                                progress.image_downloaded(0);
+                                return (index, Ok((page, true)), false);
+                            },
+                            None => self.save_image(dir, index, options, &progress, hash, false).await,
+                        },
+                        Err(err) => Err(err.into()),
+                    };
+
+                    let broken = matches!(&res, Err(err) if verify::is_broken(&**err));
+
+                    // another server may do better
+                    let res = match res {
+                        Err(_) if broken && options.reload_broken => match control.proceed().await {
+                            Ok(()) => self.save_image(dir, index, options, &progress, hash, true).await,
+                            Err(err) => Err(err.into()),
+                        },
+                        res => res,
+                    };
+
+                    (index, res.map(|page| (page, false)), broken)
+                }
+            })
+            .buffer_unordered(options.concurrency.max(1));
+
+        let mut cancelled = false;
+
+        let mut summary = DownloadSummary {
+            dir: dir.to_owned(),
+            downloaded: 0,
+            skipped: present.len(),
+            existing,
+            broken: Vec::new(),
+            linked: 0,
+            failed: Vec::new(),
+            bytes: 0,
+        };
+
+        while let Some((index, res, broken)) = images.next().await {
+            if broken {
+                summary.broken.push(index);
+            }
+
+            match res {
+                Ok((page, linked)) => {
+                    match linked {
+                        true => summary.linked += 1,
+                        false => {
+                            summary.downloaded += 1;
+                            summary.bytes += page.size;
+                        }
+                    }
+
+                    if let (Some(images), Some(hash)) = (&options.images, hashes.get(&index)) {
+                        images.insert(hash, dir.join(&page.file));
+                    }
+
+                    if let Some(manifest) = manifest.as_mut() {
+                        manifest.pages.insert(index, page);
+                        manifest.save(dir).await?;
+                    }
+                },
+                Err(err) if control::is_cancelled(&*err) => cancelled = true,
+                Err(err) => summary.failed.push((index, err.to_string())),
+            }
+        }
+
+        // what's done is kept, with the manifest, to go on from later
+        if cancelled {
+            return Err(EhError::Cancelled.into());
+        }
+
+        Ok(summary)
+    }
+
+    // the name of an image in the directory, of the template if any
+    fn image_name(&self, index: usize, options: &DownloadOptions, ext: &str) -> String {
+        match &options.file_names {
+            Some(template) => template.render(self.meta(), Some((index, ext))),
+            None => format!("{}.{}", page_name(index, self.meta().length), ext),
+        }
+    }
+
+    // put the same image saved before, here or in another gallery, as
+    // the page. nothing is, if the index of the options doesn't know it
+    async fn link_image(&self, dir: &Path, index: usize, options: &DownloadOptions,
+        hash: Option<&str>) -> Option<PageRecord> {
+        let src = options.images.as_ref()?.find(hash?).await?;
+
+        let ext = src.extension().and_then(|x| x.to_str()).unwrap_or("jpg");
+
+        // gallery-dl names it after what it was uploaded as, which is in
+        // the name of the other if that's of gallery-dl too
+        let original = src
+            .file_name()
+            .and_then(|x| x.to_str())
+            .and_then(gallerydl::original_name)
+            .map_or_else(|| format!("{}.{}", page_name(index, self.meta().length), ext), str::to_owned);
+
+        let name = match options.gallery_dl {
+            true => gallerydl::image_name(self.meta(), index, hash?, &original),
+            false => self.image_name(index, options, ext),
+        };
+
+        let path = dir.join(&name);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await.ok()?;
+        }
+
+        // it may be the very file, when it's downloaded again
+        let same = match (fs::canonicalize(&src).await, fs::canonicalize(&path).await) {
+            (Ok(src), Ok(path)) => src == path,
+            _ => false,
+        };
+
+        if !same {
+            dedupe::link(&src, &path).await.ok()?;
+        }
+
+        if options.gallery_dl {
+            let json = gallerydl::metadata(self.meta(), index, hash?, &original);
+            fs::write(dir.join(format!("{}.json", name)), json).await.ok()?;
+        }
+
+        let data = fs::read(&path).await.ok()?;
+
+        Some(PageRecord {
+            file: name,
+            size: data.len() as u64,
+            checksum: fnv1a(&data),
+        })
+    }
+
+    // write an image into "007.part" as it arrives, and name it after its
+    // format once it's all there. it's never in memory as a whole. `hash`
+    // is of its image page, to verify it by; see image_path() for `reload`
+    async fn save_image(&self, dir: &Path, index: usize, options: &DownloadOptions,
+        progress: &Progress, hash: Option<&str>, reload: bool) -> Result<PageRecord, ErrorBox> {
+        let part = dir.join(format!("{}.part", page_name(index, self.meta().length)));
+
+        let res = async {
+            let mut file = Checksummed::new(fs::File::create(&part).await?);
+            let (format, url) = self.stream_image(index, &mut file, progress, reload).await?;
+
+            // the server didn't say, so look at the beginning
+            let format = match format {
+                Some(format) => Some(format),
+                None => {
+                    let mut head = [0; 16];
+                    let read = fs::File::open(&part).await?.read(&mut head).await?;
+                    ImageFormat::sniff(&head[..read])
+                }
+            };
+
+            // jpg is the most common anyway
+            let ext = format.as_ref().map_or("jpg", ImageFormat::extension);
+
+            // gallery-dl goes by the name at the url; one from the cache
+            // doesn't tell it, so it's after the page then
+            let original = url
+                .as_deref()
+                .map(gallerydl::url_name)
+                .filter(|x| !x.is_empty())
+                .unwrap_or_else(|| format!("{}.{}", page_name(index, self.meta().length), ext));
+
+            let name = match options.gallery_dl {
+                true => gallerydl::image_name(self.meta(), index, hash.unwrap_or(""), &original),
+                false => self.image_name(index, options, ext),
+            };
+
+            let path = dir.join(&name);
+
+            // the template may put it in a directory of its own
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+
+            // the decoder wants all of it, so it's read back for that alone
+            if options.verify {
+                let data = fs::read(&part).await?;
+                let hash = hash.map(|x| x.to_owned());
+
+                let check = tokio::task::spawn_blocking(move || {
+                    verify::check(&data, None, hash.as_deref())
+                }).await?;
+
+                if !check.is_ok() {
+                    return Err(EhError::BrokenImage { index }.into());
+                }
+            }
+
+            fs::rename(&part, &path).await?;
+
+            if options.gallery_dl {
+                let json = gallerydl::metadata(self.meta(), index, hash.unwrap_or(""), &original);
+                fs::write(dir.join(format!("{}.json", name)), json).await?;
+            }
+
+            Ok::<_, ErrorBox>(PageRecord {
+                file: name,
+                size: file.len,
+                checksum: file.checksum,
+            })
+        }.await;
+
+        if res.is_err() {
+            fs::remove_file(&part).await.ok();
+        }
+
+        res
+    }
+}
+
+// passes what's written on to the file, keeping the checksum and the
+// length of it, so the file isn't read back for them
+struct Checksummed<W> {
+    inner: W,
+    checksum: u64,
+    len: u64,
+}
+
+impl<W> Checksummed<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            checksum: fnv1a(b""),
+            len: 0,
+        }
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for Checksummed<W> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>,
+        buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let res = Pin::new(&mut this.inner).poll_write(cx, buf);
+
+        if let Poll::Ready(Ok(written)) = &res {
+            this.checksum = fnv1a_from(this.checksum, &buf[..*written]);
+            this.len += *written as u64;
+        }
+
+        res
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
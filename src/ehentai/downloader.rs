@@ -0,0 +1,461 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::fmt;
+use std::error::Error;
+use std::time::Duration;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use tokio::fs;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinHandle;
+
+use super::client::Client;
+use super::id::GalleryId;
+use super::article::{Article, Draft};
+use super::download::DownloadOptions;
+use super::export::ExportOptions;
+use super::control::{self, Control};
+use super::progress::{Progress, ProgressState};
+use super::naming::{file_name, NameTemplate};
+use super::gallerydl;
+use super::dedupe::ImageIndex;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+// what a gallery is saved as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadFormat {
+    // a directory of images, as Article::download_to() does
+    Folder,
+    // a cbz archive, as Article::export_cbz() does
+    Cbz,
+    // a directory as gallery-dl makes, "exhentai/{gid} {title}", with its
+    // names and json files; the naming of the options doesn't count
+    GalleryDl,
+}
+
+#[derive(Debug, Clone)]
+pub struct DownloaderOptions {
+    // where galleries go, each in a directory or a file of its own
+    pub dest: PathBuf,
+    pub format: DownloadFormat,
+    // where each gallery goes in the destination, like "{artist}/{title}";
+    // "{gid} {title}" of the site if there's none. if it goes down to the
+    // images, like "{artist}/{title}/{page:03}.{ext}", they're named by
+    // what's after the gallery
+    pub naming: Option<NameTemplate>,
+    // link images which are saved already, in any gallery the index
+    // knows, rather than downloading them again. it's saved as each
+    // gallery is done. only folders are linked; archives can't be
+    pub images: Option<Arc<ImageIndex>>,
+    // number of galleries to download at once
+    pub jobs: usize,
+    // number of images to download at once, in each gallery
+    pub concurrency: usize,
+    // how many times to try a gallery again after it failed. a folder
+    // keeps the images it got, so only the rest is downloaded again
+    pub retries: usize,
+    pub retry_delay: Duration,
+}
+
+impl Default for DownloaderOptions {
+    fn default() -> Self {
+        Self {
+            dest: PathBuf::from("."),
+            format: DownloadFormat::Folder,
+            naming: None,
+            images: None,
+            jobs: 2,
+            concurrency: 4,
+            retries: 2,
+            retry_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+// a gallery in the queue, numbered in the order it was put in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JobId(pub u64);
+
+impl fmt::Display for JobId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+// how a gallery went in the end
+#[derive(Debug, Clone)]
+pub struct JobReport {
+    pub job: JobId,
+    pub path: String,
+    pub title: String,
+    // the directory or the file it's saved in
+    pub dest: PathBuf,
+    pub attempts: usize,
+    pub bytes: u64,
+    // what went wrong the last time, if it never went right
+    pub error: Option<String>,
+    // it was stopped by Control::cancel(); what's done is kept
+    pub cancelled: bool,
+}
+
+impl JobReport {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum DownloadEvent {
+    Queued(JobId),
+    // the gallery page is fetched and its images are about to be
+    Started { job: JobId, title: String },
+    Retrying { job: JobId, attempt: usize, error: String },
+    Done(JobReport),
+}
+
+// what's kept of a job to look at it from outside
+#[derive(Clone)]
+struct Handle {
+    control: Control,
+    // of the attempt going on, since each starts over
+    progress: Arc<Mutex<Progress>>,
+}
+
+struct Shared {
+    client: Arc<Client>,
+    options: DownloaderOptions,
+    // galleries downloaded at once
+    slots: Semaphore,
+    subscribers: Mutex<Vec<mpsc::UnboundedSender<DownloadEvent>>>,
+}
+
+impl Shared {
+    fn emit(&self, event: DownloadEvent) {
+        // those who dropped the receiver aren't listening anymore
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
+
+// downloads galleries put in the queue, a few at once. each is tried
+// again a few times if it fails, and how it goes is told by events
+//
+// NOTE: jobs run on the tokio runtime it's made in, and go on without
+// anyone awaiting them; dropping the downloader doesn't stop them.
+pub struct Downloader {
+    shared: Arc<Shared>,
+    next_job: Mutex<u64>,
+    running: Mutex<Vec<JoinHandle<JobReport>>>,
+    // every job follows this as well as its own
+    control: Control,
+    jobs: Mutex<HashMap<JobId, Handle>>,
+}
+
+impl Downloader {
+    pub(super) fn new(client: Arc<Client>, options: DownloaderOptions) -> Self {
+        let slots = Semaphore::new(options.jobs.max(1));
+
+        Self {
+            shared: Arc::new(Shared {
+                client,
+                options,
+                slots,
+                subscribers: Mutex::new(Vec::new()),
+            }),
+            next_job: Mutex::new(0),
+            running: Mutex::new(Vec::new()),
+            control: Control::new(),
+            jobs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // pause every job. those downloading let their images in flight
+    // finish, and don't start any more until resumed
+    pub fn pause(&self) {
+        self.control.pause();
+    }
+
+    pub fn resume(&self) {
+        self.control.resume();
+    }
+
+    // cancel every job, including those put in later. what's done is
+    // kept, and a download of the same gallery goes on from there
+    pub fn cancel(&self) {
+        self.control.cancel();
+    }
+
+    // the handle of a job, to pause, resume or cancel it alone
+    pub fn job(&self, job: JobId) -> Option<Control> {
+        self.jobs.lock().unwrap().get(&job).map(|x| x.control.clone())
+    }
+
+    // how the job is going: the images, the speed and the time left of
+    // the attempt going on
+    pub fn progress(&self, job: JobId) -> Option<ProgressState> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .get(&job)
+            .map(|x| x.progress.lock().unwrap().state())
+    }
+
+    // bytes per second of every job downloading now, in total
+    pub fn speed(&self) -> f64 {
+        self.jobs
+            .lock()
+            .unwrap()
+            .values()
+            .map(|x| x.progress.lock().unwrap().state())
+            .filter(|state| state.remaining() > 0)
+            .map(|state| state.speed)
+            .sum()
+    }
+
+    pub fn options(&self) -> &DownloaderOptions {
+        &self.shared.options
+    }
+
+    // events of every job from now on
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<DownloadEvent> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.shared.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    pub fn enqueue(&self, id: &GalleryId) -> JobId {
+        self.enqueue_path(id.path())
+    }
+
+    pub fn enqueue_draft(&self, draft: &Draft) -> JobId {
+        self.enqueue_path(draft.meta().path.clone())
+    }
+
+    fn enqueue_path(&self, path: String) -> JobId {
+        let job = {
+            let mut next = self.next_job.lock().unwrap();
+            *next += 1;
+            JobId(*next)
+        };
+
+        let handle = Handle {
+            control: self.control.child(),
+            progress: Arc::new(Mutex::new(Progress::new())),
+        };
+
+        self.jobs.lock().unwrap().insert(job, handle.clone());
+
+        // told before it can start
+        self.shared.emit(DownloadEvent::Queued(job));
+
+        let shared = self.shared.clone();
+        let task = tokio::spawn(async move {
+            // a job paused before its turn doesn't take a slot. if it's
+            // cancelled, run() finds it out
+            handle.control.proceed().await.ok();
+
+            // the semaphore is never closed
+            let _slot = shared.slots.acquire().await.unwrap();
+            run(&shared, job, path, &handle).await
+        });
+
+        self.running.lock().unwrap().push(task);
+        job
+    }
+
+    // wait until every job in the queue is done, including those put in
+    // while waiting, and give how they went in the order they were put in
+    pub async fn join(&self) -> Vec<JobReport> {
+        let mut reports = Vec::new();
+
+        loop {
+            let running = std::mem::take(&mut *self.running.lock().unwrap());
+
+            if running.is_empty() {
+                break;
+            }
+
+            for handle in running {
+                match handle.await {
+                    Ok(report) => reports.push(report),
+                    // it can only panic, since nothing aborts it
+                    Err(err) => std::panic::resume_unwind(err.into_panic()),
+                }
+            }
+        }
+
+        reports.sort_by_key(|report| report.job);
+        reports
+    }
+}
+
+// download a gallery, trying again as many times as told
+async fn run(shared: &Shared, job: JobId, path: String, handle: &Handle) -> JobReport {
+    let options = &shared.options;
+    let control = &handle.control;
+
+    let mut report = JobReport {
+        job,
+        path,
+        title: String::new(),
+        dest: options.dest.clone(),
+        attempts: 0,
+        bytes: 0,
+        error: None,
+        cancelled: false,
+    };
+
+    let mut article = None;
+
+    loop {
+        let res = match control.proceed().await {
+            Ok(()) => {
+                report.attempts += 1;
+                let progress = Progress::new();
+                *handle.progress.lock().unwrap() = progress.clone();
+
+                attempt(shared, control, progress, &mut article, &mut report).await
+            },
+            Err(err) => Err(err.into()),
+        };
+
+        match res {
+            Ok(()) => {
+                report.error = None;
+                break;
+            },
+            Err(err) if control::is_cancelled(&*err) => {
+                report.error = Some(err.to_string());
+                report.cancelled = true;
+                break;
+            },
+            Err(err) if report.attempts <= options.retries => {
+                shared.emit(DownloadEvent::Retrying {
+                    job,
+                    attempt: report.attempts,
+                    error: err.to_string(),
+                });
+
+                tokio::time::sleep(options.retry_delay).await;
+            },
+            Err(err) => {
+                report.error = Some(err.to_string());
+                break;
+            }
+        }
+    }
+
+    // it's only to save space; a download isn't failed for it
+    if let Some(images) = &options.images {
+        images.save().await.ok();
+    }
+
+    shared.emit(DownloadEvent::Done(report.clone()));
+    report
+}
+
+async fn attempt(shared: &Shared, control: &Control, progress: Progress,
+    article: &mut Option<Article>, report: &mut JobReport) -> Result<(), ErrorBox> {
+    let options = &shared.options;
+
+    // the gallery is fetched once, even if its images are tried again
+    if article.is_none() {
+        let loaded = Article::new(shared.client.clone(), report.path.clone()).await?;
+
+        report.title = loaded.meta().title.clone();
+        shared.emit(DownloadEvent::Started {
+            job: report.job,
+            title: report.title.clone(),
+        });
+
+        *article = Some(loaded);
+    }
+
+    let article = article.as_ref().unwrap();
+    let meta = article.meta();
+
+    let (gallery, file_names) = match &options.naming {
+        Some(template) => template.split_images(),
+        None => (None, None),
+    };
+
+    // a path under the destination, of directories if there are slashes
+    let name = match (gallery, meta.id()) {
+        (Some(template), _) => template.render(meta, None),
+        (None, Some(id)) => file_name(&format!("{} {}", id.gid, meta.title)),
+        (None, None) => file_name(&meta.title),
+    };
+
+    match options.format {
+        DownloadFormat::Folder | DownloadFormat::GalleryDl => {
+            let gallery_dl = options.format == DownloadFormat::GalleryDl;
+
+            let download = DownloadOptions {
+                concurrency: options.concurrency,
+                skip_existing: true,
+                // gallery-dl writes none of them
+                metadata: !gallery_dl,
+                info_json: !gallery_dl,
+                manifest: true,
+                images: options.images.clone(),
+                file_names,
+                gallery_dl,
+                progress,
+                control: control.clone(),
+                ..DownloadOptions::default()
+            };
+
+            report.dest = match gallery_dl {
+                true => options.dest.join(gallerydl::directory(meta)),
+                false => options.dest.join(&name),
+            };
+
+            let summary = article.download_to(&report.dest, &download).await?;
+            report.bytes += summary.bytes;
+
+            match summary.failed.first() {
+                None => Ok(()),
+                Some((index, err)) => Err(format!(
+                    "{} of the images failed, the first ({}) by: {}",
+                    summary.failed.len(), index + 1, err
+                ).into())
+            }
+        },
+        DownloadFormat::Cbz => {
+            let export = ExportOptions {
+                concurrency: options.concurrency,
+                file_names,
+                progress,
+                control: control.clone(),
+                ..ExportOptions::default()
+            };
+
+            // written aside first, so that a cut off archive is never
+            // taken for a whole one
+            report.dest = options.dest.join(format!("{}.cbz", name));
+            let part = options.dest.join(format!("{}.cbz.part", name));
+
+            if let Some(parent) = part.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+
+            let file = std::fs::File::create(&part)?;
+            let res = article.export_cbz(file, &export).await;
+
+            if let Err(err) = res {
+                fs::remove_file(&part).await.ok();
+                return Err(err);
+            }
+
+            fs::rename(&part, &report.dest).await?;
+            report.bytes = fs::metadata(&report.dest).await?.len();
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,332 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use futures::stream::{Stream, StreamExt};
+use serde_json::{Map, Value};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use super::article::{Draft, DraftMeta, Article, ArticleMeta};
+use super::api::GalleryData;
+use super::language::Language;
+use super::tag::TagMap;
+use super::date;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    // a json object in a line for each gallery
+    Ndjson,
+    // a header of the fields and then a row for each gallery
+    Csv,
+}
+
+// what a row can have of a gallery. those which what's dumped doesn't
+// know, like file sizes of drafts which aren't enriched, are null in
+// json and empty in csv
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpField {
+    Gid,
+    Token,
+    Path,
+    Title,
+    OriginalTitle,
+    Category,
+    Language,
+    Uploader,
+    // like "2021-03-04 12:34"
+    Posted,
+    Pages,
+    Rating,
+    // like ["artist:foo", "female:glasses"]; in csv, joined by commas
+    Tags,
+    Thumb,
+    // in bytes
+    FileSize,
+    Expunged,
+}
+
+impl DumpField {
+    pub const ALL: [DumpField; 15] = [
+        DumpField::Gid,
+        DumpField::Token,
+        DumpField::Path,
+        DumpField::Title,
+        DumpField::OriginalTitle,
+        DumpField::Category,
+        DumpField::Language,
+        DumpField::Uploader,
+        DumpField::Posted,
+        DumpField::Pages,
+        DumpField::Rating,
+        DumpField::Tags,
+        DumpField::Thumb,
+        DumpField::FileSize,
+        DumpField::Expunged,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DumpField::Gid => "gid",
+            DumpField::Token => "token",
+            DumpField::Path => "path",
+            DumpField::Title => "title",
+            DumpField::OriginalTitle => "original_title",
+            DumpField::Category => "category",
+            DumpField::Language => "language",
+            DumpField::Uploader => "uploader",
+            DumpField::Posted => "posted",
+            DumpField::Pages => "pages",
+            DumpField::Rating => "rating",
+            DumpField::Tags => "tags",
+            DumpField::Thumb => "thumb",
+            DumpField::FileSize => "file_size",
+            DumpField::Expunged => "expunged",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseDumpFieldError(String);
+
+impl fmt::Display for ParseDumpFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unknown field to dump: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseDumpFieldError {}
+
+impl FromStr for DumpField {
+    type Err = ParseDumpFieldError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DumpField::ALL
+            .iter()
+            .copied()
+            .find(|field| field.name() == s.trim())
+            .ok_or_else(|| ParseDumpFieldError(s.to_owned()))
+    }
+}
+
+fn tags(tags: &TagMap) -> Value {
+    tags.tags().map(|tag| Value::from(tag.to_string())).collect()
+}
+
+fn or_null<T: Into<Value>>(value: Option<T>) -> Value {
+    value.map_or(Value::Null, Into::into)
+}
+
+// what can be dumped as a row: metadata of drafts, articles and of the
+// api, like what searches, downloads and crawls give
+pub trait DumpRow {
+    fn field(&self, field: DumpField) -> Value;
+}
+
+impl DumpRow for DraftMeta {
+    fn field(&self, field: DumpField) -> Value {
+        let id = self.id();
+
+        match field {
+            DumpField::Gid => or_null(id.map(|id| id.gid)),
+            DumpField::Token => or_null(id.map(|id| id.token)),
+            DumpField::Path => self.path.clone().into(),
+            DumpField::Title => self.title.clone().into(),
+            // lists don't show it
+            DumpField::OriginalTitle => Value::Null,
+            DumpField::Category => self.kind.to_string().into(),
+            DumpField::Language => self.language().to_string().into(),
+            DumpField::Uploader => self.uploader.clone().into(),
+            DumpField::Posted => self.posted.clone().into(),
+            DumpField::Pages => self.length.into(),
+            DumpField::Rating => or_null(self.rating),
+            DumpField::Tags => tags(&self.tags),
+            DumpField::Thumb => self.thumb.clone().into(),
+            DumpField::FileSize => or_null(self.file_size),
+            // it's only known along with the file size
+            DumpField::Expunged => or_null(self.file_size.map(|_| self.expunged)),
+        }
+    }
+}
+
+impl DumpRow for ArticleMeta {
+    fn field(&self, field: DumpField) -> Value {
+        let id = self.id();
+
+        match field {
+            DumpField::Gid => or_null(id.map(|id| id.gid)),
+            DumpField::Token => or_null(id.map(|id| id.token)),
+            DumpField::Path => self.path.clone().into(),
+            DumpField::Title => self.title.clone().into(),
+            DumpField::OriginalTitle => self.original_title.clone().into(),
+            DumpField::Category => self.kind.to_string().into(),
+            DumpField::Language => self.language.to_string().into(),
+            DumpField::Uploader => self.uploader.clone().into(),
+            DumpField::Posted => self.posted.clone().into(),
+            DumpField::Pages => self.length.into(),
+            DumpField::Rating => self.rating.into(),
+            DumpField::Tags => tags(&self.tags),
+            DumpField::Thumb => self.thumb.clone().into(),
+            DumpField::FileSize => or_null(self.file_size_bytes()),
+            // the gallery page doesn't tell
+            DumpField::Expunged => Value::Null,
+        }
+    }
+}
+
+impl DumpRow for GalleryData {
+    fn field(&self, field: DumpField) -> Value {
+        match field {
+            DumpField::Gid => self.id.gid.into(),
+            DumpField::Token => self.id.token.clone().into(),
+            DumpField::Path => self.id.path().into(),
+            DumpField::Title => self.title.clone().into(),
+            DumpField::OriginalTitle => self.title_jpn.clone().into(),
+            DumpField::Category => self.kind.to_string().into(),
+            DumpField::Language => Language::from_tags(&self.tags).to_string().into(),
+            DumpField::Uploader => self.uploader.clone().into(),
+            DumpField::Posted => date::format_unix(self.posted).into(),
+            DumpField::Pages => self.file_count.into(),
+            DumpField::Rating => self.rating.into(),
+            DumpField::Tags => tags(&self.tags),
+            DumpField::Thumb => self.thumb.clone().into(),
+            DumpField::FileSize => self.file_size.into(),
+            DumpField::Expunged => self.expunged.into(),
+        }
+    }
+}
+
+impl DumpRow for Draft {
+    fn field(&self, field: DumpField) -> Value {
+        self.meta().field(field)
+    }
+}
+
+impl DumpRow for Article {
+    fn field(&self, field: DumpField) -> Value {
+        self.meta().field(field)
+    }
+}
+
+impl<T: DumpRow + ?Sized> DumpRow for &T {
+    fn field(&self, field: DumpField) -> Value {
+        (**self).field(field)
+    }
+}
+
+// a value of csv, quoted if it has to be
+fn csv_value(value: &Value) -> String {
+    let text = match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(list) => list
+            .iter()
+            .map(|x| x.as_str().map_or_else(|| x.to_string(), str::to_owned))
+            .collect::<Vec<_>>()
+            .join(", "),
+        value => value.to_string(),
+    };
+
+    match text.contains(|c: char| matches!(c, ',' | '"' | '\n' | '\r')) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text,
+    }
+}
+
+// writes metadata as rows as they come, so that a dump of millions of
+// galleries is never all in memory. each row is written on its own, so
+// give it a buffered writer
+pub struct Dumper<W> {
+    writer: W,
+    format: DumpFormat,
+    fields: Vec<DumpField>,
+    // rows written so far
+    rows: usize,
+}
+
+impl<W: AsyncWrite + Unpin> Dumper<W> {
+    // with all the fields, in the order of DumpField::ALL
+    pub fn new(writer: W, format: DumpFormat) -> Self {
+        Self {
+            writer,
+            format,
+            fields: DumpField::ALL.to_vec(),
+            rows: 0,
+        }
+    }
+
+    // only these fields, in the order given in csv; json objects are
+    // sorted by the names anyway
+    pub fn fields(mut self, fields: &[DumpField]) -> Self {
+        self.fields = fields.to_vec();
+        self
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    fn line(&self, row: &dyn DumpRow) -> String {
+        let mut line = match self.format {
+            DumpFormat::Ndjson => {
+                let object = self.fields
+                    .iter()
+                    .map(|field| (field.name().to_owned(), row.field(*field)))
+                    .collect::<Map<_, _>>();
+
+                // serializing a json value never fails
+                serde_json::to_string(&Value::Object(object)).unwrap()
+            },
+            DumpFormat::Csv => self.fields
+                .iter()
+                .map(|field| csv_value(&row.field(*field)))
+                .collect::<Vec<_>>()
+                .join(","),
+        };
+
+        line.push('\n');
+        line
+    }
+
+    // write a row, after the header if it's the first one in csv
+    pub async fn write(&mut self, row: &(impl DumpRow + ?Sized)) -> io::Result<()> {
+        if self.rows == 0 && self.format == DumpFormat::Csv {
+            let header = self.fields
+                .iter()
+                .map(|field| field.name())
+                .collect::<Vec<_>>()
+                .join(",");
+
+            self.writer.write_all(header.as_bytes()).await?;
+            self.writer.write_all(b"\n").await?;
+        }
+
+        let line = self.line(&row);
+        self.writer.write_all(line.as_bytes()).await?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    // write all the rows of a stream, like Explorer::watch() gives, and
+    // tell how many there were
+    pub async fn write_stream<R: DumpRow>(&mut self, rows: impl Stream<Item = R>)
+        -> io::Result<usize> {
+        futures::pin_mut!(rows);
+        let mut count = 0;
+
+        while let Some(row) = rows.next().await {
+            self.write(&row).await?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    // flush what's left, and give the writer back
+    pub async fn finish(mut self) -> io::Result<W> {
+        self.writer.flush().await?;
+        Ok(self.writer)
+    }
+}
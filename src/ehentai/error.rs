@@ -0,0 +1,102 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::fmt;
+use std::error::Error;
+use std::time::Duration;
+
+// errors which aren't from networking nor parsing, but from how the site
+// (or this library) works. functions still return a boxed error, so
+// downcast it to check for these.
+#[derive(Debug)]
+pub enum EhError {
+    // requested an image the article doesn't have
+    IndexOutOfRange { index: usize, len: usize },
+    // a downloaded file doesn't have the length the server told us
+    LengthMismatch { expected: u64, actual: u64 },
+    // the api said something went wrong
+    Api(String),
+    // whatever was looked up by the name doesn't exist
+    NotFound(String),
+    // the explorer is offline and doesn't have it in the caches
+    Offline(String),
+    // the site is down or too busy, with the status and the page it gave
+    SiteUnavailable { status: u16, body: String },
+    // something in front of the site wants a browser to prove itself,
+    // like a captcha; the page is given as is
+    ChallengeRequired { body: String },
+    // a download was cancelled by its Control
+    Cancelled,
+    // an image was downloaded, but it's cut off or not an image at all
+    BrokenImage { index: usize },
+    // the server answered with an error page instead, like 509 when the
+    // limit of images is exceeded
+    UnexpectedStatus(u16),
+    // the server didn't answer within the timeout of the explorer
+    Timeout(Duration),
+}
+
+impl fmt::Display for EhError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EhError::IndexOutOfRange { index, len } => write!(
+                f, "Index {} is out of range for an article of {} images",
+                index, len
+            ),
+            EhError::LengthMismatch { expected, actual } => write!(
+                f, "Expected a file of {} bytes, but got {} bytes",
+                expected, actual
+            ),
+            EhError::Api(message) => write!(f, "API error: {}", message),
+            EhError::NotFound(name) => write!(f, "Couldn't find {}", name),
+            EhError::Offline(what) => write!(f, "{} is not available offline", what),
+            EhError::SiteUnavailable { status, .. } => write!(
+                f, "The site is unavailable now ({})", status
+            ),
+            EhError::ChallengeRequired { .. } => write!(
+                f, "The site asked for a challenge to be solved in a browser"
+            ),
+            EhError::Cancelled => write!(f, "The download was cancelled"),
+            EhError::BrokenImage { index } => write!(
+                f, "The image of page {} came broken", index + 1
+            ),
+            EhError::UnexpectedStatus(status) => write!(
+                f, "The server answered {} instead of the file", status
+            ),
+            EhError::Timeout(timeout) => write!(
+                f, "The server didn't answer in {:?}", timeout
+            ),
+        }
+    }
+}
+
+impl Error for EhError {}
+
+// the markup of a page isn't what the parser expects, mostly because the
+// site has changed its layout. there's enough in it for a bug report
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    // what was being parsed, like "rating" or "uploader"
+    pub field: String,
+    // where it was looked for, like "#gdd tr > td.gdt2"
+    pub selector: String,
+    // the page it was in, if known
+    pub url: Option<String>,
+    // the beginning of the html around where it should have been
+    pub snippet: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Couldn't parse {} at `{}`", self.field, self.selector)?;
+
+        if let Some(url) = &self.url {
+            write!(f, " in {}", url)?;
+        }
+
+        write!(f, ", around: {}", self.snippet)
+    }
+}
+
+impl Error for ParseError {}
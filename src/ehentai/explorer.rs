@@ -5,12 +5,39 @@
 use std::str;
 use std::sync::Arc;
 use std::error::Error;
+use std::time::Duration;
+use std::path::Path;
+use std::collections::HashSet;
+use hyper::Uri;
+use hyper::body::Bytes;
+use futures::stream::Stream;
 
 use super::client::Client;
-use super::article::Article;
-use super::page::Page;
+use super::builder::ExplorerBuilder;
+use super::disk_cache::DiskCache;
+use super::cache::CacheStats;
+use super::id::GalleryId;
+use super::article::{DraftMeta, ArticleMeta, Draft, ArticleData, Article};
+use super::page::{self, Page};
+use super::downloader::{DownloaderOptions, Downloader};
+use super::watch::{self, WatchSource, GalleryEvent};
+use super::crawler::{self, CrawlSource, Crawler};
+use super::profile::Profile;
+use super::settings::{self, Settings};
+use super::favorites::{self, FavoriteSort, Favorites, FavoritesDiff};
+use super::mytags::{self, WatchedTag};
+use super::hath::{self, HathResolution, HathClient};
+use super::limits::{self, Budget, ImageLimit, Funds};
+use super::wiki::{self, TagWiki};
+use super::tag::Tag;
+use super::error::EhError;
+use super::parser;
+use super::image::ImageEntry;
+use super::api;
+#[cfg(feature = "store")]
+use super::store::Store;
 
-type ErrorBox = Box<dyn Error>;
+type ErrorBox = Box<dyn Error + Send + Sync>;
 
 #[derive(Clone)]
 pub struct Explorer {
@@ -19,28 +46,357 @@ pub struct Explorer {
 
 impl Explorer {
     pub fn new() -> Self {
-        let client = Client::new();
+        Self::builder().finish()
+    }
+
+    pub fn builder() -> ExplorerBuilder {
+        ExplorerBuilder::new()
+    }
 
+    pub(super) fn from_client(client: Client) -> Self {
         Self {
             client: Arc::new(client),
         }
     }
 
     pub fn with_cookies(member_id: &str, pass_hash: &str) -> Self {
-        let mut client = Client::new();
-        client.set_cookies(member_id, pass_hash);
+        Self::builder().cookies(member_id, pass_hash).finish()
+    }
 
-        Self {
-            client: Arc::new(client),
-        }
+    // keep up to `capacity` fetched pages and api responses for `ttl`,
+    // so opening the same gallery again doesn't fetch it again.
+    // it's off by default; a capacity of 0 turns it off again
+    pub fn set_cache(&self, capacity: usize, ttl: Duration) {
+        self.client.set_cache(capacity, ttl);
+    }
+
+    pub fn clear_cache(&self) {
+        self.client.clear_cache();
+    }
+
+    // how much is kept in memory and on disk
+    pub fn cache_stats(&self) -> CacheStats {
+        self.client.cache_stats()
+    }
+
+    // forget the pages of a gallery, so it's fetched again next time
+    pub async fn purge_gallery(&self, id: &GalleryId) {
+        self.client.purge_gallery(id.gid).await;
+    }
+
+    // forget whatever is older than `age`, in memory or on disk
+    pub async fn purge_older_than(&self, age: Duration) {
+        self.client.purge_older_than(age).await;
+    }
+
+    // keep thumbnails and images in the directory up to `capacity` bytes,
+    // so they're not downloaded again even in another run
+    pub async fn set_disk_cache(&self, dir: impl AsRef<Path>, capacity: u64)
+        -> Result<(), ErrorBox> {
+        let cache = DiskCache::open(dir.as_ref().to_owned(), capacity).await?;
+        self.client.set_disk_cache(Some(cache));
+
+        Ok(())
+    }
+
+    pub fn remove_disk_cache(&self) {
+        self.client.set_disk_cache(None);
+    }
+
+    // answer only from the caches and never touch the network, failing with
+    // EhError::Offline if something isn't there. pages and api responses
+    // are kept in the disk cache as well, so set one up while online
+    pub fn set_offline(&self, offline: bool) {
+        self.client.set_offline(offline);
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.client.is_offline()
     }
 
     pub fn search(&self, keyword: &str) -> Page {
         Page::new(self.client.clone(), 0, keyword)
     }
 
+    // galleries of the account's watched tags, as the watched page has
+    pub fn watched(&self, keyword: &str) -> Page {
+        Page::watched(self.client.clone(), keyword)
+    }
+
+    // new galleries of a search (or of the watched page) as they're posted,
+    // the oldest first, by looking at it every `interval`. those already
+    // there don't count; see watch_since(). it never ends, and a look
+    // which fails is tried again the next time
+    pub fn watch(&self, source: impl Into<WatchSource>, interval: Duration)
+        -> impl Stream<Item = Draft> + Send + 'static {
+        watch::watch(self.client.clone(), source.into(), interval, None)
+    }
+
+    // same as watch(), but from the gallery after `gid`, like the last one
+    // given before a restart
+    pub fn watch_since(&self, source: impl Into<WatchSource>, interval: Duration, gid: u64)
+        -> impl Stream<Item = Draft> + Send + 'static {
+        watch::watch(self.client.clone(), source.into(), interval, Some(gid))
+    }
+
+    // what changes in the galleries, looking at them every `interval`:
+    // new comments, the rating, tags voted on or off and newer versions
+    // posted. the first look is only to know how they are. it never ends
+    pub fn watch_galleries(&self, ids: impl IntoIterator<Item = GalleryId>, interval: Duration)
+        -> impl Stream<Item = GalleryEvent> + Send + 'static {
+        watch::watch_galleries(self.client.clone(), ids.into_iter().collect(), interval)
+    }
+
+    // walk through every gallery of a search or a category; see Crawler
+    pub fn crawl(&self, source: impl Into<CrawlSource>) -> Crawler {
+        Crawler::new(self.client.clone(), source.into())
+    }
+
+    // the galleries posted after `gid`, from the front page; see
+    // Crawler::since() and Crawler::watermark()
+    pub fn crawl_since(&self, source: impl Into<CrawlSource>, gid: u64) -> Crawler {
+        self.crawl(source).since(gid)
+    }
+
+    // same as crawl() with a checkpoint at `path`, but from where the
+    // checkpoint says if it's there already
+    pub async fn resume_crawl(&self, source: impl Into<CrawlSource>, path: impl AsRef<Path>)
+        -> Result<Crawler, ErrorBox> {
+        let path = path.as_ref();
+        let source = source.into();
+
+        if !crawler::has_checkpoint(path).await? {
+            return Ok(self.crawl(source).checkpoint(path));
+        }
+
+        let crawler = Crawler::resume(self.client.clone(), path).await?;
+
+        match crawler.source() == &source {
+            true => Ok(crawler),
+            false => Err("the checkpoint is of another crawl".into())
+        }
+    }
+
     pub async fn article_from_path(&self, path: String)
         -> Result<Article, ErrorBox> {
         Article::new(self.client.clone(), path).await
     }
+
+    // only the metadata of a gallery, for when the comments and the images
+    // don't matter. it's still a page to fetch, but nothing more than that
+    pub async fn article_meta(&self, id: &GalleryId)
+        -> Result<ArticleMeta, ErrorBox> {
+        let path = id.path();
+
+        self.client
+            .get_html_with(path.parse()?, move |doc| parser::article(doc, path))
+            .await
+    }
+
+    // get the article which has the image page, whose link would be like
+    // "https://e-hentai.org/s/(hash)/(gid)-(page)"
+    pub async fn gallery_from_page_url(&self, url: &str)
+        -> Result<Article, ErrorBox> {
+        let entry = url.parse::<ImageEntry>()?;
+        let id = api::gtoken(&self.client, &entry).await?;
+
+        Article::new(self.client.clone(), id.path()).await
+    }
+
+    // get a member profile from the forum
+    pub async fn profile(&self, id: u64) -> Result<Profile, ErrorBox> {
+        let path = format!("https://forums.e-hentai.org/index.php?showuser={}", id);
+        let doc = self.client.get_html(path.parse()?).await?;
+
+        parser::profile(&doc, id)
+    }
+
+    // get the profile of an uploader by name, looking up the member list
+    pub async fn uploader_profile(&self, name: &str)
+        -> Result<Profile, ErrorBox> {
+        let path = format!(
+            "https://forums.e-hentai.org/index.php?act=members&name_box=all&name={}",
+            page::percent_encode(name)
+        );
+
+        // the document can't be held across another request
+        let id = {
+            let doc = self.client.get_html(path.parse()?).await?;
+            parser::member_id(&doc, name)?
+        };
+
+        match id {
+            Some(id) => self.profile(id).await,
+            None => Err(EhError::NotFound(name.to_owned()).into())
+        }
+    }
+
+    // get the settings of the account. they change how pages look,
+    // so they're never from the caches
+    pub async fn settings(&self) -> Result<Settings, ErrorBox> {
+        let doc = self.client.get_html_fresh(settings::UCONFIG.parse()?).await?;
+        Ok(Settings::from_form(parser::form_fields(&doc)))
+    }
+
+    // submit the settings, changing the account for every client of it
+    pub async fn set_settings(&self, settings: &Settings)
+        -> Result<(), ErrorBox> {
+        let form = settings.to_form();
+
+        self.client.post_form(settings::UCONFIG.parse()?, &form).await?;
+
+        // pages cached before may look different now
+        self.client.clear_cache();
+        Ok(())
+    }
+
+    // the favorites of the account, of a category from 0 to 9 or all of them
+    pub fn favorites(&self, category: Option<usize>, sort: FavoriteSort) -> Favorites {
+        Favorites::new(self.client.clone(), category, sort)
+    }
+
+    // add a gallery to a favorite category from 0 to 9, or move it there
+    // if it's already in another one
+    pub async fn add_favorite(&self, id: &GalleryId, category: usize, note: &str)
+        -> Result<(), ErrorBox> {
+        favorites::add(&self.client, id, category, note).await
+    }
+
+    pub async fn remove_favorite(&self, id: &GalleryId)
+        -> Result<(), ErrorBox> {
+        favorites::remove(&self.client, id).await
+    }
+
+    // compare the favorites (of a category, or all of them) with
+    // a local collection of galleries
+    pub async fn diff_favorites(&self, local: &HashSet<GalleryId>,
+        category: Option<usize>) -> Result<FavoritesDiff, ErrorBox> {
+        let remote = self
+            .favorites(category, FavoriteSort::Favorited)
+            .collect()
+            .await?
+            .into_iter()
+            .filter_map(|favorite| favorite.meta.id());
+
+        Ok(FavoritesDiff::new(remote, local))
+    }
+
+    // make the favorites the same as the local collection by adding and
+    // removing galleries. new ones go to `category`, or the first one
+    pub async fn push_favorites(&self, diff: &FavoritesDiff,
+        category: Option<usize>) -> Result<(), ErrorBox> {
+        for id in diff.only_local.iter() {
+            self.add_favorite(id, category.unwrap_or(0), "").await?;
+        }
+
+        for id in diff.only_remote.iter() {
+            self.remove_favorite(id).await?;
+        }
+
+        Ok(())
+    }
+
+    // the tags in "My Tags" of the account
+    pub async fn my_tags(&self) -> Result<Vec<WatchedTag>, ErrorBox> {
+        Ok(mytags::list(&self.client).await?.0)
+    }
+
+    // the id of the tag is ignored; get it by my_tags() after this
+    pub async fn add_my_tag(&self, tag: &WatchedTag) -> Result<(), ErrorBox> {
+        mytags::add(&self.client, tag).await
+    }
+
+    pub async fn remove_my_tag(&self, id: u64) -> Result<(), ErrorBox> {
+        mytags::remove(&self.client, id).await
+    }
+
+    // change the flags, color and weight of a tag to what's given
+    pub async fn update_my_tag(&self, tag: &WatchedTag) -> Result<(), ErrorBox> {
+        mytags::update(&self.client, tag).await
+    }
+
+    // have a Hentai@Home client of the account download the gallery,
+    // instead of downloading it here. gives what the site said
+    pub async fn hath_download(&self, id: &GalleryId, resolution: HathResolution)
+        -> Result<String, ErrorBox> {
+        hath::dispatch(&self.client, id, resolution).await
+    }
+
+    // the Hentai@Home clients of the account and their status
+    pub async fn hath_clients(&self) -> Result<Vec<HathClient>, ErrorBox> {
+        hath::clients(&self.client).await
+    }
+
+    // how many images the account has viewed, out of how many it can
+    pub async fn image_limit(&self) -> Result<ImageLimit, ErrorBox> {
+        limits::image_limit(&self.client).await
+    }
+
+    // how much GP and credits the account has, e.g. to check whether
+    // it can afford an archive before asking for one
+    pub async fn funds(&self) -> Result<Funds, ErrorBox> {
+        limits::funds(&self.client).await
+    }
+
+    // make downloads wait while the account is within `margin` of its image
+    // limit, until it comes back; instead of going over and getting 509s.
+    // None turns it off, which is the default
+    pub fn set_image_budget(&self, margin: Option<u64>) {
+        *self.client.budget().lock().unwrap() = margin.map(Budget::new);
+    }
+
+    // get the page of ehwiki which explains the tag
+    pub async fn tag_wiki(&self, tag: &Tag) -> Result<TagWiki, ErrorBox> {
+        let path = wiki::wiki_path(tag);
+        let doc = self.client.get_html(path.parse()?).await?;
+
+        parser::tag_wiki(&doc, tag, path)?
+            .ok_or_else(|| EhError::NotFound(tag.to_string()).into())
+    }
+
+    // same as article_from_path(), but never from the caches. what was
+    // cached of the gallery is thrown away, and the fresh one is kept instead
+    pub async fn force_refresh(&self, path: String)
+        -> Result<Article, ErrorBox> {
+        if self.client.is_offline() {
+            return Err(EhError::Offline(path).into());
+        }
+
+        let id = path.parse::<GalleryId>()?;
+        self.client.purge_gallery(id.gid).await;
+
+        Article::new(self.client.clone(), path).await
+    }
+
+    // get whatever is at the link as it is, so you can parse what this library
+    // doesn't by yourself. it goes through the caches and the rate limit
+    pub async fn get_raw(&self, dest: Uri) -> Result<Bytes, ErrorBox> {
+        self.client.get_raw(dest).await
+    }
+
+    // a queue to download galleries into, through this explorer
+    pub fn downloader(&self, options: DownloaderOptions) -> Downloader {
+        Downloader::new(self.client.clone(), options)
+    }
+
+    // make a draft back from saved metadata, e.g. a cached search result
+    pub fn draft_from_meta(&self, meta: DraftMeta) -> Draft {
+        Draft::new(self.client.clone(), meta)
+    }
+
+    // make an article back from Article::to_data() without fetching anything
+    pub fn article_from_data(&self, data: ArticleData) -> Article {
+        Article::from_data(self.client.clone(), data)
+    }
+
+    // make an article from the metadata saved in the store, which works
+    // offline too. comments are not saved, so it doesn't have any
+    #[cfg(feature = "store")]
+    pub fn article_from_store(&self, store: &Store, gid: u64)
+        -> Result<Article, ErrorBox> {
+        match store.load(gid)? {
+            Some(meta) => Ok(Article::from_meta(self.client.clone(), meta)),
+            None => Err(EhError::NotFound(format!("gallery {}", gid)).into())
+        }
+    }
 }
@@ -0,0 +1,194 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::fs::File;
+use std::error::Error;
+use std::io::{self, Read, Write, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use futures::stream::{self, StreamExt};
+use tokio::fs;
+use zip::ZipWriter;
+use zip::write::FileOptions;
+use zip::CompressionMethod;
+
+use super::article::Article;
+use super::download::{page_name, info_text};
+use super::naming::NameTemplate;
+use super::image::ImageFormat;
+use super::progress::Progress;
+use super::control::Control;
+use super::queue::Priority;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    // number of images to download at once
+    pub concurrency: usize,
+    // put informations of the article into the file
+    // ("info.txt" in cbz archives, and the document info in pdf files)
+    pub metadata: bool,
+    // put "ComicInfo.xml" in cbz archives
+    pub comic_info: bool,
+    // how images are named in cbz archives, like "{page:03}.{ext}"; after
+    // their page number if there's none. slashes put them in folders
+    pub file_names: Option<NameTemplate>,
+    pub progress: Progress,
+    // pause or cancel it between images
+    pub control: Control,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            metadata: true,
+            comic_info: true,
+            file_names: None,
+            progress: Progress::new(),
+            control: Control::new(),
+        }
+    }
+}
+
+// an image waiting in a temporary file for its turn into the archive;
+// the file goes away with it
+struct Spooled {
+    path: PathBuf,
+    format: Option<ImageFormat>,
+}
+
+impl Drop for Spooled {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+// exports made so far by this process, to tell their directories apart
+static EXPORTS: AtomicUsize = AtomicUsize::new(0);
+
+// where an export spools its images, of its own so that two exports of
+// the same gallery don't write over each other's files. it's removed
+// along with whatever's left in it
+struct SpoolDir {
+    path: PathBuf,
+}
+
+impl SpoolDir {
+    async fn new(gid: u64) -> io::Result<Self> {
+        let count = EXPORTS.fetch_add(1, Ordering::Relaxed);
+        let name = format!("lude-{}-{}-{}", gid, std::process::id(), count);
+
+        let path = std::env::temp_dir().join(name);
+        fs::create_dir_all(&path).await?;
+
+        Ok(Self { path })
+    }
+}
+
+impl Drop for SpoolDir {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.path).ok();
+    }
+}
+
+impl Article {
+    async fn spool_image(&self, dir: &Path, index: usize, progress: &Progress)
+        -> Result<Spooled, ErrorBox> {
+        // it's made right away so that the file is removed on failures too
+        let mut spooled = Spooled {
+            path: dir.join(format!("{}.part", index)),
+            format: None,
+        };
+
+        let mut file = fs::File::create(&spooled.path).await?;
+        spooled.format = self.stream_image(index, &mut file, progress, false).await?.0;
+
+        Ok(spooled)
+    }
+
+    // download the whole article into a cbz (which is just a zip) archive.
+    // images go into the archive as soon as they arrive, in order of pages.
+    // those which arrive before their turn wait in temporary files
+    //
+    // NOTE: writing into `writer` blocks, so give it something fast
+    // (a file or a buffer) rather than a socket.
+    pub async fn export_cbz<W: Write + Seek>(&self, writer: W,
+        options: &ExportOptions) -> Result<W, ErrorBox> {
+        Priority::Download.or_current(self.write_cbz(writer, options)).await
+    }
+
+    async fn write_cbz<W: Write + Seek>(&self, writer: W,
+        options: &ExportOptions) -> Result<W, ErrorBox> {
+        let len = self.meta().length;
+        let mut zip = ZipWriter::new(writer);
+
+        // images are compressed enough already
+        let stored = FileOptions::default()
+            .compression_method(CompressionMethod::Stored);
+
+        if options.metadata {
+            zip.start_file("info.txt", stored)?;
+            zip.write_all(info_text(self.meta()).as_bytes())?;
+        }
+
+        if options.comic_info {
+            zip.start_file("ComicInfo.xml", stored)?;
+            zip.write_all(self.meta().comic_info().as_bytes())?;
+        }
+
+        self.load_image_list().await?;
+
+        let progress = &options.progress;
+        progress.set_total(len);
+
+        let spool = SpoolDir::new(self.meta().id().map_or(0, |id| id.gid)).await?;
+        let dir = spool.path.as_path();
+
+        let control = &options.control;
+
+        let mut images = stream::iter(0..len)
+            .map(|index| async move {
+                let res = match control.proceed().await {
+                    Ok(()) => self.spool_image(dir, index, progress).await,
+                    Err(err) => Err(err.into()),
+                };
+
+                (index, res)
+            })
+            .buffered(options.concurrency.max(1));
+
+        // readers sort pages by name, so zero-padded names keep the order
+        while let Some((index, image)) = images.next().await {
+            let image = image?;
+            let mut file = File::open(&image.path)?;
+
+            // the server didn't say, so look at the beginning
+            let format = match image.format {
+                Some(format) => Some(format),
+                None => {
+                    let mut head = [0; 16];
+                    let read = file.read(&mut head)?;
+                    file.seek(SeekFrom::Start(0))?;
+
+                    ImageFormat::sniff(&head[..read])
+                }
+            };
+
+            // jpg is the most common anyway
+            let ext = format.as_ref().map_or("jpg", ImageFormat::extension);
+
+            let name = match &options.file_names {
+                Some(template) => template.render(self.meta(), Some((index, ext))),
+                None => format!("{}.{}", page_name(index, len), ext),
+            };
+
+            zip.start_file(name, stored)?;
+            io::copy(&mut file, &mut zip)?;
+        }
+
+        Ok(zip.finish()?)
+    }
+}
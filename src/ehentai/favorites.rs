@@ -0,0 +1,201 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::sync::Arc;
+use std::error::Error;
+use std::collections::HashSet;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+
+use super::client::Client;
+use super::article::DraftMeta;
+use super::id::GalleryId;
+use super::parser;
+#[cfg(feature = "chrono")]
+use super::date;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+// the site shows favorite categories by these colors, from 0 to 9
+pub(super) const COLORS: [&str; 10] = [
+    "#000", "#f00", "#fa0", "#dd0", "#080", "#9f4", "#4bf", "#00f", "#508", "#e8e",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FavoriteSort {
+    // when the gallery was posted
+    Posted,
+    // when it was added to the favorites
+    Favorited,
+}
+
+// a gallery in the favorites of the account
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Favorite {
+    // make a draft out of it by Explorer::draft_from_meta()
+    pub meta: DraftMeta,
+    // from 0 to 9
+    pub category: usize,
+    // what the account named the category, like "Favorites 0"
+    pub category_name: String,
+    pub note: Option<String>,
+    // like "2021-03-04 12:34"
+    pub favorited: String,
+}
+
+// the popup of a gallery which adds it to the favorites
+fn popup_path(id: &GalleryId) -> String {
+    format!(
+        "https://e-hentai.org/gallerypopups.php?gid={}&t={}&act=addfav",
+        id.gid, id.token
+    )
+}
+
+pub(super) async fn add(client: &Client, id: &GalleryId, category: usize,
+    note: &str) -> Result<(), ErrorBox> {
+    let form = [
+        ("favcat".to_owned(), category.min(9).to_string()),
+        ("favnote".to_owned(), note.to_owned()),
+        ("apply".to_owned(), "Add to Favorites".to_owned()),
+        ("update".to_owned(), "1".to_owned()),
+    ];
+
+    client.post_form(popup_path(id).parse()?, &form).await?;
+    Ok(())
+}
+
+pub(super) async fn remove(client: &Client, id: &GalleryId)
+    -> Result<(), ErrorBox> {
+    let form = [
+        ("favcat".to_owned(), "favdel".to_owned()),
+        ("favnote".to_owned(), String::new()),
+        ("update".to_owned(), "1".to_owned()),
+    ];
+
+    client.post_form(popup_path(id).parse()?, &form).await?;
+    Ok(())
+}
+
+// how the favorites of the site and a local collection differ
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FavoritesDiff {
+    // in the collection but not in the favorites
+    pub only_local: Vec<GalleryId>,
+    // in the favorites but not in the collection
+    pub only_remote: Vec<GalleryId>,
+}
+
+impl Favorite {
+    #[cfg(feature = "chrono")]
+    pub fn favorited_at(&self) -> Option<DateTime<Utc>> {
+        date::to_utc(&self.favorited)
+    }
+}
+
+impl FavoritesDiff {
+    pub fn new(remote: impl IntoIterator<Item = GalleryId>,
+        local: &HashSet<GalleryId>) -> Self {
+        let remote = remote.into_iter().collect::<HashSet<_>>();
+
+        let mut only_local = local.difference(&remote).cloned().collect::<Vec<_>>();
+        let mut only_remote = remote.difference(local).cloned().collect::<Vec<_>>();
+
+        // sets don't keep the order; give the same diff for the same sets
+        only_local.sort();
+        only_remote.sort();
+
+        Self {
+            only_local,
+            only_remote,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.only_local.is_empty() && self.only_remote.is_empty()
+    }
+
+    // make the collection the same as the favorites
+    pub fn apply_local(&self, local: &mut HashSet<GalleryId>) {
+        for id in self.only_local.iter() {
+            local.remove(id);
+        }
+
+        local.extend(self.only_remote.iter().cloned());
+    }
+}
+
+pub struct Favorites {
+    client: Arc<Client>,
+    page: usize,
+    category: Option<usize>,
+    sort: FavoriteSort,
+    done: bool,
+}
+
+impl Favorites {
+    pub(super) fn new(client: Arc<Client>, category: Option<usize>,
+        sort: FavoriteSort) -> Self {
+        Self {
+            client,
+            page: 0,
+            category,
+            sort,
+            done: false,
+        }
+    }
+
+    fn path(&self) -> String {
+        let category = self.category.map_or("all".to_owned(), |x| x.to_string());
+
+        // the sort order is a setting of the account rather than of a page
+        let sort = match self.sort {
+            FavoriteSort::Posted => "fs_p",
+            FavoriteSort::Favorited => "fs_f",
+        };
+
+        format!(
+            "https://e-hentai.org/favorites.php?page={}&favcat={}&inline_set={}",
+            self.page, category, sort
+        )
+    }
+
+    pub fn page(&self) -> usize {
+        self.page
+    }
+
+    // the next page of favorites, or none after the last one
+    pub async fn next(&mut self) -> Result<Option<Vec<Favorite>>, ErrorBox> {
+        if self.done {
+            return Ok(None);
+        }
+
+        // the list changes whenever a gallery is added, so don't cache it
+        let doc = self.client.get_html_fresh(self.path().parse()?).await?;
+        self.page += 1;
+        self.done = !parser::has_next_page(&doc);
+
+        match parser::favorites(&doc)? {
+            Some(list) if !list.is_empty() => Ok(Some(list)),
+            _ => {
+                self.done = true;
+                Ok(None)
+            }
+        }
+    }
+
+    // everything left in the list at once
+    pub async fn collect(mut self) -> Result<Vec<Favorite>, ErrorBox> {
+        let mut all = Vec::new();
+
+        while let Some(list) = self.next().await? {
+            all.extend(list);
+        }
+
+        Ok(all)
+    }
+}
@@ -0,0 +1,227 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::fmt::Write;
+use futures::stream::{Stream, StreamExt};
+
+use super::article::{Draft, DraftMeta};
+use super::image::ImageFormat;
+use super::comicinfo::escape;
+use super::date;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun",
+    "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+}
+
+impl Default for FeedFormat {
+    fn default() -> Self {
+        FeedFormat::Rss
+    }
+}
+
+// a feed of galleries, like the results of a search, for feed readers.
+// serving what render() gives over http is all it takes for a reader to
+// follow a search
+#[derive(Debug, Clone, Default)]
+pub struct Feed {
+    pub format: FeedFormat,
+    pub title: String,
+    // where the feed is of, like the url of the search
+    pub link: String,
+    pub description: String,
+}
+
+// "2021-03-04 12:34" as rfc 822 wants it in rss, "04 Mar 2021 12:34:00 +0000".
+// the day of the week is optional there, which saves working it out
+fn rfc822(posted: &str) -> Option<String> {
+    let (year, month, day, hour, minute) = date::parse(posted)?;
+
+    Some(format!(
+        "{:02} {} {} {:02}:{:02}:00 +0000",
+        day, MONTHS[month as usize - 1], year, hour, minute
+    ))
+}
+
+// and as atom wants it, "2021-03-04T12:34:00Z"
+fn rfc3339(posted: &str) -> Option<String> {
+    let (year, month, day, hour, minute) = date::parse(posted)?;
+
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:00Z",
+        year, month, day, hour, minute
+    ))
+}
+
+// thumbnails are named with their extensions, like "..._250.jpg"
+fn thumb_mime(thumb: &str) -> &'static str {
+    let ext = thumb.rsplit('.').next().unwrap_or("");
+
+    ImageFormat::ALL
+        .iter()
+        .find(|x| x.extension().eq_ignore_ascii_case(ext))
+        .map_or("image/jpeg", |x| x.mime())
+}
+
+// what readers show of an item, in html: the thumbnail and a line of
+// what the list tells
+fn summary(meta: &DraftMeta) -> String {
+    let mut html = String::new();
+
+    if !meta.thumb.is_empty() {
+        write!(html, "<img src=\"{}\"><br>", escape(&meta.thumb)).unwrap();
+    }
+
+    write!(html, "{}, {} pages", meta.kind, meta.length).unwrap();
+
+    if !meta.uploader.is_empty() {
+        write!(html, ", by {}", escape(&meta.uploader)).unwrap();
+    }
+
+    html
+}
+
+impl Feed {
+    pub fn new(format: FeedFormat, title: &str, link: &str) -> Self {
+        Self {
+            format,
+            title: title.to_owned(),
+            link: link.to_owned(),
+            description: String::new(),
+        }
+    }
+
+    // the document of the feed with the galleries in the order given,
+    // which should be the newest first as lists are
+    pub fn render<'a>(&self, drafts: impl IntoIterator<Item = &'a DraftMeta>) -> String {
+        match self.format {
+            FeedFormat::Rss => self.rss(drafts),
+            FeedFormat::Atom => self.atom(drafts),
+        }
+    }
+
+    // same as render(), but of the first `limit` galleries of a stream,
+    // like Explorer::watch() gives. a feed of a stream which never ends
+    // needs one, or it's never rendered
+    pub async fn render_stream(&self, drafts: impl Stream<Item = Draft>, limit: usize) -> String {
+        let drafts = drafts.take(limit).collect::<Vec<_>>().await;
+        self.render(drafts.iter().map(Draft::meta))
+    }
+
+    fn rss<'a>(&self, drafts: impl IntoIterator<Item = &'a DraftMeta>) -> String {
+        let mut xml = String::new();
+
+        for meta in drafts {
+            xml.push_str("    <item>\n");
+            writeln!(xml, "      <title>{}</title>", escape(&meta.title)).unwrap();
+            writeln!(xml, "      <link>{}</link>", escape(&meta.path)).unwrap();
+            writeln!(xml, "      <guid isPermaLink=\"true\">{}</guid>", escape(&meta.path)).unwrap();
+
+            if let Some(date) = rfc822(&meta.posted) {
+                writeln!(xml, "      <pubDate>{}</pubDate>", date).unwrap();
+            }
+
+            for tag in meta.tags.tags() {
+                writeln!(xml, "      <category>{}</category>", escape(&tag.to_string())).unwrap();
+            }
+
+            // the size isn't known without fetching it, and readers are
+            // fine with a zero
+            if !meta.thumb.is_empty() {
+                writeln!(
+                    xml, "      <enclosure url=\"{}\" type=\"{}\" length=\"0\"/>",
+                    escape(&meta.thumb), thumb_mime(&meta.thumb)
+                ).unwrap();
+            }
+
+            writeln!(xml, "      <description>{}</description>", escape(&summary(meta))).unwrap();
+            xml.push_str("    </item>\n");
+        }
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <rss version=\"2.0\">\n  \
+             <channel>\n    \
+             <title>{}</title>\n    \
+             <link>{}</link>\n    \
+             <description>{}</description>\n\
+             {}  </channel>\n\
+             </rss>\n",
+            escape(&self.title), escape(&self.link), escape(&self.description), xml
+        )
+    }
+
+    fn atom<'a>(&self, drafts: impl IntoIterator<Item = &'a DraftMeta>) -> String {
+        let mut xml = String::new();
+        // atom wants to know when the feed changed, which is when the
+        // newest of them was posted
+        let mut updated: Option<String> = None;
+
+        for meta in drafts {
+            let date = rfc3339(&meta.posted);
+
+            // the dates compare as text as long as they're of the same form
+            if let Some(date) = &date {
+                if updated.as_ref().map_or(true, |x| x < date) {
+                    updated = Some(date.clone());
+                }
+            }
+
+            xml.push_str("  <entry>\n");
+            writeln!(xml, "    <title>{}</title>", escape(&meta.title)).unwrap();
+            writeln!(xml, "    <link rel=\"alternate\" href=\"{}\"/>", escape(&meta.path)).unwrap();
+            writeln!(xml, "    <id>{}</id>", escape(&meta.path)).unwrap();
+
+            if let Some(date) = &date {
+                writeln!(xml, "    <published>{}</published>", date).unwrap();
+                writeln!(xml, "    <updated>{}</updated>", date).unwrap();
+            }
+
+            if !meta.uploader.is_empty() {
+                writeln!(xml, "    <author><name>{}</name></author>", escape(&meta.uploader)).unwrap();
+            }
+
+            for tag in meta.tags.tags() {
+                writeln!(xml, "    <category term=\"{}\"/>", escape(&tag.to_string())).unwrap();
+            }
+
+            if !meta.thumb.is_empty() {
+                writeln!(
+                    xml, "    <link rel=\"enclosure\" href=\"{}\" type=\"{}\"/>",
+                    escape(&meta.thumb), thumb_mime(&meta.thumb)
+                ).unwrap();
+            }
+
+            writeln!(xml, "    <summary type=\"html\">{}</summary>", escape(&summary(meta))).unwrap();
+            xml.push_str("  </entry>\n");
+        }
+
+        let mut head = String::new();
+        writeln!(head, "  <title>{}</title>", escape(&self.title)).unwrap();
+        writeln!(head, "  <link rel=\"alternate\" href=\"{}\"/>", escape(&self.link)).unwrap();
+        writeln!(head, "  <id>{}</id>", escape(&self.link)).unwrap();
+
+        if !self.description.is_empty() {
+            writeln!(head, "  <subtitle>{}</subtitle>", escape(&self.description)).unwrap();
+        }
+
+        writeln!(
+            head, "  <updated>{}</updated>",
+            updated.as_deref().unwrap_or("1970-01-01T00:00:00Z")
+        ).unwrap();
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <feed xmlns=\"http://www.w3.org/2005/Atom\">\n\
+             {}{}</feed>\n",
+            head, xml
+        )
+    }
+}
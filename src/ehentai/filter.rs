@@ -0,0 +1,88 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::article::DraftMeta;
+use super::language::Language;
+use super::tag::{ArticleKind, TagMap};
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+
+type Check = Box<dyn Fn(&DraftMeta) -> bool + Send + Sync>;
+
+// what's done to the drafts of a search on our side, for what the site
+// can't search by. checks go in the order they're added, and a draft is
+// dropped at the first one it fails
+#[derive(Default)]
+pub(super) struct DraftFilter {
+    checks: Vec<Check>,
+    // the search ends at the first draft failing this
+    until: Option<Check>,
+}
+
+impl DraftFilter {
+    pub fn add(&mut self, check: impl Fn(&DraftMeta) -> bool + Send + Sync + 'static) {
+        self.checks.push(Box::new(check));
+    }
+
+    pub fn set_until(&mut self, check: impl Fn(&DraftMeta) -> bool + Send + Sync + 'static) {
+        self.until = Some(Box::new(check));
+    }
+
+    pub fn matches(&self, meta: &DraftMeta) -> bool {
+        self.checks.iter().all(|check| check(meta))
+    }
+
+    // the drafts which pass, and whether the search should end here
+    pub fn apply(&self, list: Vec<DraftMeta>) -> (Vec<DraftMeta>, bool) {
+        let mut res = Vec::with_capacity(list.len());
+
+        for meta in list {
+            if let Some(until) = &self.until {
+                if !until(&meta) {
+                    return (res, true);
+                }
+            }
+
+            if self.matches(&meta) {
+                res.push(meta);
+            }
+        }
+
+        (res, false)
+    }
+}
+
+pub(super) fn tags(include: TagMap, exclude: TagMap) -> impl Fn(&DraftMeta) -> bool + Send + Sync {
+    move |meta| meta.tags.contains_all(&include)
+        && !exclude.tags().any(|tag| meta.tags.has(&tag))
+}
+
+// drafts whose rating isn't shown don't pass
+pub(super) fn min_rating(min: f32) -> impl Fn(&DraftMeta) -> bool + Send + Sync {
+    move |meta| meta.rating.map_or(false, |rating| rating >= min)
+}
+
+pub(super) fn min_pages(min: usize) -> impl Fn(&DraftMeta) -> bool + Send + Sync {
+    move |meta| meta.length >= min
+}
+
+pub(super) fn language(language: Language) -> impl Fn(&DraftMeta) -> bool + Send + Sync {
+    move |meta| meta.language() == language
+}
+
+pub(super) fn kind(kind: ArticleKind) -> impl Fn(&DraftMeta) -> bool + Send + Sync {
+    move |meta| meta.kind == kind
+}
+
+// drafts whose date can't be read don't pass either of these
+#[cfg(feature = "chrono")]
+pub(super) fn posted_since(since: DateTime<Utc>) -> impl Fn(&DraftMeta) -> bool + Send + Sync {
+    move |meta| meta.posted_at().map_or(false, |date| date >= since)
+}
+
+#[cfg(feature = "chrono")]
+pub(super) fn posted_before(before: DateTime<Utc>) -> impl Fn(&DraftMeta) -> bool + Send + Sync {
+    move |meta| meta.posted_at().map_or(false, |date| date < before)
+}
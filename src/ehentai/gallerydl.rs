@@ -0,0 +1,175 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::path::{Path, PathBuf};
+use serde_json::json;
+
+use super::article::ArticleMeta;
+
+// how gallery-dl lays out what its e-hentai extractor downloads, so those
+// moving from it (or to it) find their library where it was:
+//
+//   exhentai/{gid} {title[:247]}/{gid}_{num:>04}_{image_token}_{filename}.{extension}
+//
+// with "{file}.json" beside each image, as its metadata post processor
+// writes by default.
+
+// what gallery-dl calls both sites
+const CATEGORY: &str = "exhentai";
+
+// what gallery-dl leaves out of titles by default ("path-remove"), and
+// what it replaces with underscores ("path-restrict": "auto"), which is
+// the slash alone on anything but windows
+fn clean(name: &str) -> String {
+    let restricted = |c: char| match cfg!(windows) {
+        true => matches!(c, '\\' | '/' | '<' | '>' | ':' | '"' | '|' | '?' | '*'),
+        false => c == '/',
+    };
+
+    let name = name
+        .chars()
+        .filter(|&c| !matches!(c, '\x00'..='\x1f' | '\x7f'))
+        .map(|c| if restricted(c) { '_' } else { c })
+        .collect::<String>();
+
+    // "path-strip": "auto", which is only on windows as well
+    match cfg!(windows) {
+        true => name.trim_end_matches(|c| c == '.' || c == ' ').to_owned(),
+        false => name,
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+            .and_then(|x| std::str::from_utf8(x).ok())
+            .and_then(|x| u8::from_str_radix(x, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                res.push(byte);
+                i += 3;
+            },
+            (byte, _) => {
+                res.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&res).into_owned()
+}
+
+// the name of the file at the url, like "001.jpg" of ".../keystamp=.../001.jpg";
+// it's the name it was uploaded with, which gallery-dl names images by
+pub(super) fn url_name(url: &str) -> String {
+    let path = url.split(|c| c == '?' || c == '#').next().unwrap_or("");
+    percent_decode(path.rsplit('/').next().unwrap_or(""))
+}
+
+// the name split as gallery-dl does, with the extension in lowercase
+fn name_ext(name: &str) -> (&str, String) {
+    match name.rfind('.') {
+        Some(pos) if pos > 0 => (&name[..pos], name[pos + 1..].to_lowercase()),
+        _ => (name, String::new()),
+    }
+}
+
+// the directory of the gallery, under where they're downloaded
+pub(super) fn directory(meta: &ArticleMeta) -> PathBuf {
+    let gid = meta.id().map_or(String::new(), |id| id.gid.to_string());
+    let title = meta.title.chars().take(247).collect::<String>();
+
+    Path::new(CATEGORY).join(clean(&format!("{} {}", gid, title)))
+}
+
+// the file of an image; `original` is the name it was uploaded with
+pub(super) fn image_name(meta: &ArticleMeta, index: usize, hash: &str, original: &str) -> String {
+    let gid = meta.id().map_or(String::new(), |id| id.gid.to_string());
+    let (filename, extension) = name_ext(original);
+
+    clean(&format!("{}_{:04}_{}_{}.{}", gid, index + 1, hash, filename, extension))
+}
+
+// the name the image was uploaded with, back from one of image_name()
+pub(super) fn original_name(file: &str) -> Option<&str> {
+    file.splitn(4, '_').nth(3).filter(|x| !x.is_empty())
+}
+
+// the index of the image a file is of, if it's one of image_name() and
+// of the gallery
+pub(super) fn image_index(file: &str, gid: u64, len: usize) -> Option<usize> {
+    let mut parts = file.splitn(3, '_');
+
+    if parts.next()? != gid.to_string() {
+        return None;
+    }
+
+    parts.next()?
+        .parse::<usize>()
+        .ok()
+        .filter(|&num| num >= 1 && num <= len)
+        .map(|num| num - 1)
+}
+
+// what gallery-dl knows of a gallery and an image of it; the json it
+// writes beside the image. the count of torrents isn't known here, so
+// it's left out
+pub(super) fn metadata(meta: &ArticleMeta, index: usize, hash: &str, original: &str) -> String {
+    let id = meta.id();
+    let (filename, extension) = name_ext(original);
+
+    // the time only to minutes, as the site shows
+    let date = match meta.posted.trim().len() {
+        16 => format!("{}:00", meta.posted.trim()),
+        _ => meta.posted.clone(),
+    };
+
+    let language = meta.language.to_string();
+
+    let value = json!({
+        "category": CATEGORY,
+        "subcategory": "gallery",
+        "gid": id.as_ref().map(|id| id.gid),
+        "token": id.as_ref().map(|id| id.token.clone()),
+        "thumb": meta.thumb,
+        "title": meta.title,
+        "title_jpn": meta.original_title,
+        "eh_category": meta.kind.to_string(),
+        "uploader": meta.uploader,
+        "date": date,
+        "parent": meta.parent.clone().unwrap_or_default(),
+        "expunged": !meta.visible,
+        // the first word only, like "English" of "English TR"
+        "language": language.split_whitespace().next().unwrap_or(""),
+        "lang": meta.language.code(),
+        "filesize": meta.file_size_bytes(),
+        // these are as they're on the page
+        "filecount": meta.length.to_string(),
+        "count": meta.length,
+        "favorites": meta.favorited.to_string(),
+        "rating": meta.rating.to_string(),
+        "tags": meta.tags.tags().map(|tag| tag.to_string()).collect::<Vec<_>>(),
+        "num": index + 1,
+        "image_token": hash,
+        "filename": filename,
+        "extension": extension,
+    });
+
+    // serializing a json value never fails
+    let text = serde_json::to_string_pretty(&value).unwrap();
+
+    // gallery-dl indents by 4, not 2 as serde_json does. newlines in
+    // strings are escaped, so every line starts with indents alone
+    text.lines()
+        .map(|line| {
+            let body = line.trim_start_matches(' ');
+            format!("{}{}\n", " ".repeat((line.len() - body.len()) * 2), body)
+        })
+        .collect()
+}
@@ -0,0 +1,92 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::fmt;
+use std::error::Error;
+
+use super::client::Client;
+use super::id::GalleryId;
+use super::error::EhError;
+use super::parser;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+const HATH: &str = "https://e-hentai.org/hentaiathome.php";
+
+// what a Hentai@Home client downloads the images in. smaller ones
+// resample the originals, and cost less
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HathResolution {
+    Original,
+    X780,
+    X980,
+    X1280,
+    X1600,
+    X2400,
+}
+
+// how the archiver calls them
+impl fmt::Display for HathResolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HathResolution::Original => write!(f, "org"),
+            HathResolution::X780 => write!(f, "780"),
+            HathResolution::X980 => write!(f, "980"),
+            HathResolution::X1280 => write!(f, "1280"),
+            HathResolution::X1600 => write!(f, "1600"),
+            HathResolution::X2400 => write!(f, "2400"),
+        }
+    }
+}
+
+// a Hentai@Home client of the account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HathClient {
+    pub id: u64,
+    pub name: String,
+    // like "Online" or "Offline"
+    pub status: String,
+}
+
+// the archiver wants a key which is only in the gallery page
+async fn archiver_path(client: &Client, id: &GalleryId) -> Result<String, ErrorBox> {
+    let doc = client.get_html(id.path().parse()?).await?;
+
+    Ok(parser::archiver_path(&doc).unwrap_or_else(|| format!(
+        "https://e-hentai.org/archiver.php?gid={}&token={}",
+        id.gid, id.token
+    )))
+}
+
+// queue the gallery to the client of the account, returning what
+// the site said about it.
+//
+// NOTE: there's no asking about it afterwards. the site hands queued
+// galleries to the client when it asks for them, over the protocol of
+// the clients (which needs the key of the client, not the account), and
+// no page of the site lists what's waiting or done. what the site says
+// here and clients() below are all there is to know
+pub(super) async fn dispatch(client: &Client, id: &GalleryId,
+    resolution: HathResolution) -> Result<String, ErrorBox> {
+    let path = archiver_path(client, id).await?;
+    let form = [("hathdl_xres".to_owned(), resolution.to_string())];
+
+    let message = {
+        let doc = client.post_form(path.parse()?, &form).await?;
+        parser::archiver_message(&doc)
+    };
+
+    // e.g. when there's no client, or it's offline
+    match message.contains("queued") {
+        true => Ok(message),
+        false => Err(EhError::Api(message).into())
+    }
+}
+
+// the clients of the account and whether they're up, to see whether
+// what's dispatched would be downloaded soon
+pub(super) async fn clients(client: &Client) -> Result<Vec<HathClient>, ErrorBox> {
+    let doc = client.get_html_fresh(HATH.parse()?).await?;
+    Ok(parser::hath_clients(&doc))
+}
@@ -0,0 +1,71 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::fmt;
+use std::str::FromStr;
+use std::error::Error;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug)]
+pub struct ParseGalleryIdError();
+
+impl fmt::Display for ParseGalleryIdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Gallery link with wrong format was given")
+    }
+}
+
+impl Error for ParseGalleryIdError {}
+
+// what the site uses to identify a gallery; the number alone is not
+// enough to access it, the token must come along
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GalleryId {
+    pub gid: u64,
+    pub token: String,
+}
+
+impl GalleryId {
+    pub fn new(gid: u64, token: &str) -> Self {
+        Self {
+            gid,
+            token: token.to_owned(),
+        }
+    }
+
+    pub fn path(&self) -> String {
+        format!("https://e-hentai.org/g/{}/{}/", self.gid, self.token)
+    }
+}
+
+impl FromStr for GalleryId {
+    type Err = ParseGalleryIdError;
+
+    // the link would be like "https://e-hentai.org/g/(gid)/(token)/"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pos = s.find("/g/").ok_or(ParseGalleryIdError())?;
+        let mut iter = s[(pos + "/g/".len())..].split('/');
+
+        let gid = iter
+            .next()
+            .and_then(|x| x.parse::<u64>().ok())
+            .ok_or(ParseGalleryIdError())?;
+
+        let token = iter
+            .next()
+            .filter(|x| !x.is_empty() && x.bytes().all(|c| c.is_ascii_hexdigit()))
+            .ok_or(ParseGalleryIdError())?;
+
+        Ok(Self::new(gid, token))
+    }
+}
+
+impl fmt::Display for GalleryId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.gid, self.token)
+    }
+}
@@ -0,0 +1,263 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::fmt;
+use std::str::FromStr;
+use std::error::Error;
+
+use hyper::body::Bytes;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+#[derive(Debug)]
+pub struct ParseImageEntryError();
+
+impl fmt::Display for ParseImageEntryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Image page link with wrong format was given")
+    }
+}
+
+impl Error for ParseImageEntryError {}
+
+// an entry of the image list of an article, which points to an image page
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ImageEntry {
+    pub path: String,
+
+    // the first 10 hex digits of sha-1 of the image file. the site names
+    // image pages after it, so identical images share the same hash even
+    // across different galleries (e.g. re-uploads)
+    pub hash: String,
+
+    // id of the article; the token is not in the link
+    pub gid: u64,
+    // page number in the article, starting from 1
+    pub page: usize,
+}
+
+impl ImageEntry {
+    // whether the two entries (most likely) point to the same image file
+    pub fn same_image(&self, other: &ImageEntry) -> bool {
+        self.hash == other.hash
+    }
+}
+
+impl FromStr for ImageEntry {
+    type Err = ParseImageEntryError;
+
+    // the link would be like "https://e-hentai.org/s/(hash)/(gid)-(page)"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pos = s.find("/s/").ok_or(ParseImageEntryError())?;
+        let mut iter = s[(pos + "/s/".len())..].split('/');
+
+        let hash = iter.next().ok_or(ParseImageEntryError())?;
+
+        if hash.len() != 10 || !hash.bytes().all(|x| x.is_ascii_hexdigit()) {
+            return Err(ParseImageEntryError());
+        }
+
+        let mut iter = iter
+            .next()
+            .ok_or(ParseImageEntryError())?
+            .split('-');
+
+        let gid = iter
+            .next()
+            .and_then(|x| x.parse::<u64>().ok())
+            .ok_or(ParseImageEntryError())?;
+
+        let page = iter
+            .next()
+            .and_then(|x| x.parse::<usize>().ok())
+            .ok_or(ParseImageEntryError())?;
+
+        Ok(Self {
+            path: s.to_owned(),
+            hash: hash.to_owned(),
+            gid,
+            page,
+        })
+    }
+}
+
+// a thumbnail of an image shown in the gallery page
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Preview {
+    pub entry: ImageEntry,
+    pub url: String,
+
+    // the site shows either normal or large thumbnails by the account
+    // settings. normal ones are cut out from a sprite of several images
+    pub large: bool,
+    // where it is in the sprite, for normal ones; `url` is of the sprite
+    pub crop: Option<Crop>,
+}
+
+// where a thumbnail is in a sprite of several ones, in pixels from the
+// top left corner. some layouts give them so, rather than one by one
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Crop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Crop {
+    // read a style like "width:100px; height:144px; background:transparent
+    // url(...) -100px 0 no-repeat", which is how the site draws sprites.
+    // the link of the sprite comes along
+    pub(super) fn from_style(style: &str) -> Option<(String, Self)> {
+        let px = |name: &str| {
+            let begin = style.find(name)? + name.len();
+            style[begin..]
+                .trim_start()
+                .split(|c: char| !c.is_ascii_digit())
+                .nth(0)?
+                .parse::<u32>()
+                .ok()
+        };
+
+        let begin = style.find("url(")? + "url(".len();
+        let end = begin + style[begin..].find(')')?;
+        let url = style[begin..end].trim_matches(|c| c == '"' || c == '\'').to_owned();
+
+        // offsets are negative, to move the sprite to the left
+        let mut offsets = style[(end + 1)..]
+            .split_whitespace()
+            .map(|x| x.trim_end_matches(';').trim_end_matches("px").parse::<i64>().ok());
+
+        let x = offsets.next().flatten().unwrap_or(0);
+        let y = offsets.next().flatten().unwrap_or(0);
+
+        let crop = Self {
+            x: (-x).max(0) as u32,
+            y: (-y).max(0) as u32,
+            width: px("width:")?,
+            height: px("height:")?,
+        };
+
+        Some((url, crop))
+    }
+
+    // cut the thumbnail out of the sprite, as a png
+    #[cfg(feature = "image")]
+    pub fn apply(&self, sprite: &[u8]) -> Result<Vec<u8>, ErrorBox> {
+        use std::io::Cursor;
+
+        let image = ::image::load_from_memory(sprite)?
+            .crop_imm(self.x, self.y, self.width, self.height);
+
+        let mut data = Cursor::new(Vec::new());
+        image.write_to(&mut data, ::image::ImageOutputFormat::Png)?;
+
+        Ok(data.into_inner())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+}
+
+impl ImageFormat {
+    // every format there is, to look for a file of any of them
+    pub(super) const ALL: [ImageFormat; 4] = [
+        ImageFormat::Jpeg,
+        ImageFormat::Png,
+        ImageFormat::Gif,
+        ImageFormat::Webp,
+    ];
+
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        // ignore parameters like "image/jpeg; charset=..."
+        match mime.split(';').nth(0).unwrap().trim() {
+            "image/jpeg" | "image/jpg" => Some(ImageFormat::Jpeg),
+            "image/png" => Some(ImageFormat::Png),
+            "image/gif" => Some(ImageFormat::Gif),
+            "image/webp" => Some(ImageFormat::Webp),
+            _ => None
+        }
+    }
+
+    // guess the format from magic bytes at the beginning of the file
+    pub fn sniff(data: &[u8]) -> Option<Self> {
+        if data.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(ImageFormat::Jpeg)
+        } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageFormat::Png)
+        } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+            Some(ImageFormat::Gif)
+        } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+            Some(ImageFormat::Webp)
+        } else {
+            None
+        }
+    }
+
+    pub fn mime(&self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Png => "image/png",
+            ImageFormat::Gif => "image/gif",
+            ImageFormat::Webp => "image/webp",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Png => "png",
+            ImageFormat::Gif => "gif",
+            ImageFormat::Webp => "webp",
+        }
+    }
+}
+
+// an image loaded from an article
+#[derive(Debug, Clone)]
+pub struct ImageData {
+    // index of the image in the article, starting from 0
+    pub index: usize,
+    pub hash: String,
+    pub data: Bytes,
+
+    // what the server said in the response header
+    pub content_type: Option<String>,
+    // from the content type, or sniffed from the data if it was useless
+    pub format: Option<ImageFormat>,
+}
+
+impl ImageData {
+    pub(super) fn new(index: usize, hash: String,
+        data: Bytes, content_type: Option<String>) -> Self {
+        let format = content_type
+            .as_deref()
+            .and_then(ImageFormat::from_mime)
+            .or_else(|| ImageFormat::sniff(&data));
+
+        Self {
+            index,
+            hash,
+            data,
+            content_type,
+            format,
+        }
+    }
+
+    // an extension for the file name, like "jpg"
+    pub fn extension(&self) -> Option<&'static str> {
+        self.format.as_ref().map(ImageFormat::extension)
+    }
+}
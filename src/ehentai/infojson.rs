@@ -0,0 +1,55 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use serde_json::{json, Map, Value};
+
+use super::article::ArticleMeta;
+use super::date;
+
+// a date like "2021-03-04 12:34" into [2021, 3, 4, 12, 34, 0]
+fn date_parts(date: &str) -> Option<Vec<i64>> {
+    let (year, month, day, hour, minute) = date::parse(date)?;
+
+    // the site doesn't give seconds
+    Some(vec![year as i64, month as i64, day as i64, hour as i64, minute as i64, 0])
+}
+
+impl ArticleMeta {
+    // render the metadata in "info.json" format of eze, which is also
+    // understood by gallery-dl and lanraragi
+    pub fn info_json(&self) -> String {
+        let tags = self.tags
+            .iter()
+            .map(|(kind, tags)| {
+                let tags = tags.iter().map(|tag| &tag[..]).collect::<Vec<_>>();
+                (kind.to_string(), json!(tags))
+            })
+            .collect::<Map<String, Value>>();
+
+        let id = self.id();
+
+        let info = json!({
+            "gallery_info": {
+                "title": self.title,
+                "title_original": self.original_title,
+                "category": self.kind.to_string().to_lowercase(),
+                "tags": tags,
+                "language": self.language.to_string().to_lowercase(),
+                "translated": self.translated,
+                "favorite_category": Value::Null,
+                "upload_date": date_parts(&self.posted),
+                "source": {
+                    "site": "e-hentai",
+                    "gid": id.as_ref().map(|id| id.gid),
+                    "token": id.as_ref().map(|id| id.token.clone()),
+                    "parent_gallery": self.parent,
+                    "newer_versions": [],
+                },
+            }
+        });
+
+        // serializing a json value never fails
+        serde_json::to_string_pretty(&info).unwrap()
+    }
+}
@@ -0,0 +1,43 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::sync::{Arc, Mutex};
+use std::collections::HashSet;
+
+// the set isn't swept until it has this many
+const SWEEP_MIN: usize = 1024;
+
+// names of tags, shared by every TagMap. thousands of galleries have only
+// a few thousand tags between them, so a crawl keeps each of them once
+// rather than once per gallery
+struct Interner {
+    names: HashSet<Arc<str>>,
+    // how many were left after the last sweep
+    swept: usize,
+}
+
+static INTERNER: Mutex<Option<Interner>> = Mutex::new(None);
+
+pub(super) fn intern(name: &str) -> Arc<str> {
+    let mut interner = INTERNER.lock().unwrap();
+    let interner = interner.get_or_insert_with(|| Interner {
+        names: HashSet::new(),
+        swept: 0,
+    });
+
+    if let Some(name) = interner.names.get(name) {
+        return name.clone();
+    }
+
+    // forget names no one has anymore, each time the set doubles.
+    // it's never much larger than what's in use then
+    if interner.names.len() >= SWEEP_MIN.max(interner.swept * 2) {
+        interner.names.retain(|name| Arc::strong_count(name) > 1);
+        interner.swept = interner.names.len();
+    }
+
+    let name = Arc::<str>::from(name);
+    interner.names.insert(name.clone());
+    name
+}
@@ -0,0 +1,135 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+use super::tag::{Tag, TagKind, TagMap};
+
+// language tags which don't tell the language
+pub(super) const NOT_LANGUAGES: [&str; 2] = ["translated", "rewrite"];
+
+// (language, its name on the site, its ISO 639-1 code)
+static LANGUAGES: [(Language, &str, &str); 20] = [
+    (Language::Japanese, "Japanese", "ja"),
+    (Language::English, "English", "en"),
+    (Language::Chinese, "Chinese", "zh"),
+    (Language::Korean, "Korean", "ko"),
+    (Language::Spanish, "Spanish", "es"),
+    (Language::French, "French", "fr"),
+    (Language::German, "German", "de"),
+    (Language::Italian, "Italian", "it"),
+    (Language::Portuguese, "Portuguese", "pt"),
+    (Language::Russian, "Russian", "ru"),
+    (Language::Thai, "Thai", "th"),
+    (Language::Vietnamese, "Vietnamese", "vi"),
+    (Language::Indonesian, "Indonesian", "id"),
+    (Language::Polish, "Polish", "pl"),
+    (Language::Dutch, "Dutch", "nl"),
+    (Language::Hungarian, "Hungarian", "hu"),
+    (Language::Czech, "Czech", "cs"),
+    (Language::Turkish, "Turkish", "tr"),
+    (Language::Arabic, "Arabic", "ar"),
+    (Language::Ukrainian, "Ukrainian", "uk"),
+];
+
+// the language of a gallery. whatever isn't listed here is kept as
+// the site calls it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Language {
+    Japanese,
+    English,
+    Chinese,
+    Korean,
+    Spanish,
+    French,
+    German,
+    Italian,
+    Portuguese,
+    Russian,
+    Thai,
+    Vietnamese,
+    Indonesian,
+    Polish,
+    Dutch,
+    Hungarian,
+    Czech,
+    Turkish,
+    Arabic,
+    Ukrainian,
+    Other(String),
+}
+
+impl Language {
+    fn entry(&self) -> Option<&'static (Language, &'static str, &'static str)> {
+        LANGUAGES.iter().find(|x| x.0 == *self)
+    }
+
+    // ISO 639-1 code, which ComicInfo and such want
+    pub fn code(&self) -> Option<&'static str> {
+        self.entry().map(|x| x.2)
+    }
+
+    // what's searched by, like "language:korean"
+    pub fn tag(&self) -> Tag {
+        Tag::new(TagKind::Language, &self.to_string().to_lowercase())
+    }
+
+    // the language a tag tells, if it's a language tag at all
+    pub fn from_tag(tag: &Tag) -> Option<Self> {
+        match tag.kind() {
+            TagKind::Language if !NOT_LANGUAGES.contains(&tag.name()) =>
+                Some(Self::from(tag.name())),
+            _ => None
+        }
+    }
+
+    // the language of a gallery by its tags. the site shows japanese
+    // galleries without a language tag, so it's the default
+    pub fn from_tags(tags: &TagMap) -> Self {
+        tags.namespace(TagKind::Language)
+            .find(|name| !NOT_LANGUAGES.contains(name))
+            .map_or(Language::Japanese, Self::from)
+    }
+}
+
+// by the name on the site or the code; it's never an error since
+// unknown ones fall back to Other
+impl From<&str> for Language {
+    fn from(name: &str) -> Self {
+        let name = name.trim();
+
+        LANGUAGES.iter()
+            .find(|x| x.1.eq_ignore_ascii_case(name) || x.2.eq_ignore_ascii_case(name))
+            .map_or_else(|| Language::Other(name.to_owned()), |x| x.0.clone())
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self, self.entry()) {
+            (Language::Other(name), _) => write!(f, "{}", name),
+            (_, Some(entry)) => write!(f, "{}", entry.1),
+            // every language but Other is in the table
+            (_, None) => unreachable!()
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Language {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Language {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Ok(Self::from(text.as_str()))
+    }
+}
@@ -0,0 +1,233 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::io;
+use std::error::Error;
+use std::path::Path;
+use futures::stream::{self, StreamExt};
+use hyper::{Body, Method, Request, Response, Uri};
+use hyper::body::Bytes;
+use hyper::client::connect::HttpConnector;
+use detour::HttpsConnector;
+use serde_json::Value;
+use sha1::{Sha1, Digest};
+use tokio::fs;
+use tokio::io::AsyncReadExt;
+
+use super::article::ArticleMeta;
+use super::page::percent_encode;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+// how much of the archive is read at a time, to hash and to send
+const CHUNK_LEN: usize = 64 * 1024;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// lanraragi wants the api key in base64 as the bearer token
+pub(super) fn base64(data: &[u8]) -> String {
+    let mut text = String::with_capacity((data.len() + 2) / 3 * 4);
+
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => text.push(BASE64[(n >> (18 - i * 6)) as usize & 0x3f] as char),
+                false => text.push('='),
+            }
+        }
+    }
+
+    text
+}
+
+// the tags of a gallery as lanraragi keeps them, "namespace:tag" joined
+// by commas. where it's from and its category come along, as the
+// e-hentai plugin of lanraragi does
+pub(super) fn tags(meta: &ArticleMeta) -> String {
+    let mut tags = meta.tags
+        .tags()
+        .map(|tag| tag.to_string())
+        .collect::<Vec<_>>();
+
+    tags.push(format!("category:{}", meta.kind.to_string().to_lowercase()));
+
+    if let Some(id) = meta.id() {
+        tags.push(format!("source:e-hentai.org/g/{}/{}", id.gid, id.token));
+    }
+
+    tags.join(", ")
+}
+
+// the archive in chunks as it's read, so it's never in memory as a whole
+fn chunks(file: fs::File) -> impl futures::Stream<Item = io::Result<Bytes>> {
+    stream::try_unfold(file, |mut file| async move {
+        let mut buf = vec![0; CHUNK_LEN];
+
+        match file.read(&mut buf).await? {
+            0 => Ok(None),
+            read => {
+                buf.truncate(read);
+                Ok(Some((Bytes::from(buf), file)))
+            }
+        }
+    })
+}
+
+// the sha-1 of the archive, which lanraragi checks what it got against
+async fn checksum(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha1::new();
+    let mut chunks = Box::pin(chunks(fs::File::open(path).await?));
+
+    while let Some(chunk) = chunks.next().await {
+        hasher.update(&chunk?);
+    }
+
+    Ok(hasher.finalize().iter().map(|x| format!("{:02x}", x)).collect())
+}
+
+// servers on the lan are mostly of plain http, which doesn't need the
+// detour of the site
+enum Inner {
+    Http(hyper::Client<HttpConnector, Body>),
+    Https(hyper::Client<HttpsConnector<HttpConnector>, Body>),
+}
+
+// a LANraragi server, to put downloaded archives in. it's its own
+// client, with none of the cookies or the rate limit of the explorer
+pub struct Lanraragi {
+    // like "http://localhost:3000", without the slash at the end
+    base: String,
+    // lanraragi lets anyone upload without one, if it's set up so
+    key: Option<String>,
+    inner: Inner,
+}
+
+impl Lanraragi {
+    pub fn new(base: &str, key: Option<&str>) -> Self {
+        let inner = match base.starts_with("https://") {
+            true => Inner::Https(hyper::Client::builder()
+                .build(HttpsConnector::new_with_connector(HttpConnector::new()))),
+            false => Inner::Http(hyper::Client::new()),
+        };
+
+        Self {
+            base: base.trim_end_matches('/').to_owned(),
+            key: key.map(str::to_owned),
+            inner,
+        }
+    }
+
+    fn request(&self, method: Method, path: &str) -> Result<hyper::http::request::Builder, ErrorBox> {
+        let uri = format!("{}{}", self.base, path).parse::<Uri>()?;
+        let req = Request::builder().method(method).uri(uri);
+
+        Ok(match &self.key {
+            Some(key) => req.header("Authorization", format!("Bearer {}", base64(key.as_bytes()))),
+            None => req,
+        })
+    }
+
+    // what lanraragi answers, which says if it failed in the body
+    async fn send(&self, req: Request<Body>) -> Result<Value, ErrorBox> {
+        let res: Response<Body> = match &self.inner {
+            Inner::Http(client) => client.request(req).await?,
+            Inner::Https(client) => client.request(req).await?,
+        };
+        let status = res.status();
+        let body = hyper::body::to_bytes(res.into_body()).await?;
+        let value = serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null);
+
+        let failed = !status.is_success() || value["success"].as_i64() == Some(0);
+
+        match failed {
+            true => {
+                let message = value["error"].as_str().unwrap_or("no reason given");
+                Err(format!("LANraragi said {}: {}", status, message).into())
+            },
+            false => Ok(value),
+        }
+    }
+
+    // upload an archive (a cbz, or a zip of the images) of the gallery
+    // with its title and tags, and tell the id lanraragi gave it. the
+    // archive is read twice, to hash and to send, but a chunk at a time
+    pub async fn upload(&self, archive: impl AsRef<Path>, meta: &ArticleMeta, category: Option<&str>)
+        -> Result<String, ErrorBox> {
+        let archive = archive.as_ref();
+        let checksum = checksum(archive).await?;
+
+        let name = archive
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("archive.cbz")
+            .replace('"', "'");
+
+        // the checksum is unlikely to be in the archive as it is
+        let boundary = format!("lude-{}", checksum);
+        let mut head = Vec::new();
+
+        let mut field = |name: &str, value: &str| {
+            head.extend(format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                boundary, name, value
+            ).bytes());
+        };
+
+        field("title", &meta.title);
+        field("tags", &tags(meta));
+        field("file_checksum", &checksum);
+
+        if let Some(category) = category {
+            field("category_id", category);
+        }
+
+        head.extend(format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n\
+             Content-Type: application/zip\r\n\r\n",
+            boundary, name
+        ).bytes());
+
+        let tail = format!("\r\n--{}--\r\n", boundary).into_bytes();
+
+        // told beforehand, as some servers don't take chunked uploads
+        let file = fs::File::open(archive).await?;
+        let len = head.len() as u64 + file.metadata().await?.len() + tail.len() as u64;
+
+        let body = stream::once(async move { Ok::<_, io::Error>(Bytes::from(head)) })
+            .chain(chunks(file))
+            .chain(stream::once(async move { Ok(Bytes::from(tail)) }));
+
+        let req = self.request(Method::PUT, "/api/archives/upload")?
+            .header("Content-Type", format!("multipart/form-data; boundary={}", boundary))
+            .header("Content-Length", len)
+            .body(Body::wrap_stream(body))?;
+
+        let value = self.send(req).await?;
+
+        value["id"]
+            .as_str()
+            .map(str::to_owned)
+            .ok_or_else(|| "LANraragi didn't tell the id of the archive".into())
+    }
+
+    // set the title and tags of an archive there again, like after the
+    // gallery is refreshed
+    pub async fn update_metadata(&self, id: &str, meta: &ArticleMeta)
+        -> Result<(), ErrorBox> {
+        let body = format!(
+            "title={}&tags={}",
+            percent_encode(&meta.title), percent_encode(&tags(meta))
+        );
+
+        let req = self.request(Method::PUT, &format!("/api/archives/{}/metadata", percent_encode(id)))?
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(Body::from(body))?;
+
+        self.send(req).await?;
+        Ok(())
+    }
+}
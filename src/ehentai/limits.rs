@@ -0,0 +1,122 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::error::Error;
+use std::time::Duration;
+
+use super::client::Client;
+use super::parser;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+pub(super) const HOME: &str = "https://e-hentai.org/home.php";
+pub(super) const EXCHANGE: &str = "https://e-hentai.org/exchange.php?t=gp";
+
+// how long to wait before asking again when we're out of the budget
+const POLL: Duration = Duration::from_secs(60);
+
+// images counted by ourselves before asking the site again. originals and
+// large images cost more than one, so the count drifts away
+const RECHECK: u64 = 50;
+
+// how many images the account has viewed, out of how many it can.
+// the site says "509" with a picture instead once it's over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageLimit {
+    pub used: u64,
+    pub limit: u64,
+    // how much the limit comes back per minute
+    pub regen_per_minute: Option<u64>,
+    // GP it costs to reset the count to zero
+    pub reset_cost: Option<u64>,
+}
+
+impl ImageLimit {
+    pub fn remaining(&self) -> u64 {
+        self.limit.saturating_sub(self.used)
+    }
+}
+
+// what the account has to pay for archives, resets and such
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Funds {
+    pub gp: u64,
+    pub credits: u64,
+}
+
+impl Funds {
+    // whether it can pay `gp` GP, e.g. the cost of an archive
+    pub fn can_afford(&self, gp: u64) -> bool {
+        self.gp >= gp
+    }
+}
+
+// downloads wait while the account is within `margin` of the limit
+#[derive(Debug)]
+pub(super) struct Budget {
+    margin: u64,
+    known: Option<ImageLimit>,
+    // images since `known` was told
+    spent: u64,
+}
+
+impl Budget {
+    pub fn new(margin: u64) -> Self {
+        Self {
+            margin,
+            known: None,
+            spent: 0,
+        }
+    }
+}
+
+pub(super) async fn image_limit(client: &Client) -> Result<ImageLimit, ErrorBox> {
+    let doc = client.get_html_fresh(HOME.parse()?).await?;
+    parser::image_limit(&doc)
+}
+
+pub(super) async fn funds(client: &Client) -> Result<Funds, ErrorBox> {
+    let doc = client.get_html_fresh(EXCHANGE.parse()?).await?;
+    parser::funds(&doc)
+}
+
+enum Decision {
+    Go,
+    Check,
+    Wait,
+}
+
+// make sure there is room for another image before downloading it,
+// waiting if there isn't. it does nothing without a budget
+pub(super) async fn spend(client: &Client) -> Result<(), ErrorBox> {
+    loop {
+        let decision = match client.budget().lock().unwrap().as_mut() {
+            None => Decision::Go,
+            Some(budget) => match budget.known {
+                None => Decision::Check,
+                Some(_) if budget.spent >= RECHECK => Decision::Check,
+                Some(limit) if limit.used + budget.spent + budget.margin >= limit.limit => {
+                    Decision::Wait
+                },
+                Some(_) => {
+                    budget.spent += 1;
+                    Decision::Go
+                }
+            }
+        };
+
+        match decision {
+            Decision::Go => return Ok(()),
+            Decision::Wait => tokio::time::sleep(POLL).await,
+            Decision::Check => (),
+        }
+
+        let limit = image_limit(client).await?;
+
+        if let Some(budget) = client.budget().lock().unwrap().as_mut() {
+            budget.known = Some(limit);
+            budget.spent = 0;
+        }
+    }
+}
@@ -0,0 +1,108 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::error::Error;
+use std::path::Path;
+use std::collections::BTreeMap;
+use serde_json::{json, Value};
+use tokio::fs;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+// the file in a download directory which says how far it went
+pub(super) const MANIFEST: &str = ".manifest.json";
+
+// a page which is done
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRecord {
+    pub file: String,
+    pub size: u64,
+    // fnv-1a of the file, to tell if it's changed since
+    pub checksum: u64,
+}
+
+// what a download has done so far, written as it goes so that another
+// one can go on from there after a crash or a restart: the image list
+// isn't fetched again, and neither are the pages which are done
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    // the gallery it's of
+    pub path: String,
+    pub length: usize,
+    // links to the image pages, as many as known
+    pub images: Vec<String>,
+    // pages done, by index
+    pub pages: BTreeMap<usize, PageRecord>,
+}
+
+impl Manifest {
+    pub fn new(path: String, length: usize) -> Self {
+        Self {
+            path,
+            length,
+            ..Self::default()
+        }
+    }
+
+    // the manifest of a download directory. a broken one is as good as
+    // none, since the download can only start over then
+    pub async fn load(dir: impl AsRef<Path>) -> Option<Self> {
+        let text = fs::read(dir.as_ref().join(MANIFEST)).await.ok()?;
+        Self::from_value(&serde_json::from_slice(&text).ok()?)
+    }
+
+    // written aside and renamed, so that a crash never leaves half of it
+    pub async fn save(&self, dir: impl AsRef<Path>) -> Result<(), ErrorBox> {
+        let dir = dir.as_ref();
+        let part = dir.join(format!("{}.part", MANIFEST));
+
+        fs::write(&part, serde_json::to_vec_pretty(&self.to_value())?).await?;
+        fs::rename(&part, dir.join(MANIFEST)).await?;
+        Ok(())
+    }
+
+    fn to_value(&self) -> Value {
+        // checksums are in hex; javascript can't read 64-bit numbers
+        let pages = self.pages
+            .iter()
+            .map(|(index, page)| (index.to_string(), json!({
+                "file": page.file,
+                "size": page.size,
+                "checksum": format!("{:016x}", page.checksum),
+            })))
+            .collect::<serde_json::Map<_, _>>();
+
+        json!({
+            "path": self.path,
+            "length": self.length,
+            "images": self.images,
+            "pages": pages,
+        })
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        let images = value["images"]
+            .as_array()?
+            .iter()
+            .map(|x| x.as_str().map(|x| x.to_owned()))
+            .collect::<Option<Vec<_>>>()?;
+
+        let pages = value["pages"]
+            .as_object()?
+            .iter()
+            .map(|(index, page)| Some((index.parse().ok()?, PageRecord {
+                file: page["file"].as_str()?.to_owned(),
+                size: page["size"].as_u64()?,
+                checksum: u64::from_str_radix(page["checksum"].as_str()?, 16).ok()?,
+            })))
+            .collect::<Option<BTreeMap<_, _>>>()?;
+
+        Some(Self {
+            path: value["path"].as_str()?.to_owned(),
+            length: value["length"].as_u64()? as usize,
+            images,
+            pages,
+        })
+    }
+}
@@ -0,0 +1,126 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+// a piece of a comment, as the bbcode of the writer is rendered.
+// only what's here is kept; other markup is dropped for its text
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Span {
+    Text(String),
+    Bold(Vec<Span>),
+    Italic(Vec<Span>),
+    Underline(Vec<Span>),
+    Strike(Vec<Span>),
+    // hidden until it's hovered over, or selected
+    Spoiler(Vec<Span>),
+    Link { url: String, content: Vec<Span> },
+    Image(String),
+    // a line break in a paragraph
+    Break,
+}
+
+// what a comment says; paragraphs are what blank lines divide
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CommentBody {
+    pub paragraphs: Vec<Vec<Span>>,
+}
+
+// the text of spans without any markup. images are left out, since
+// they're not meant to be read
+fn plain(spans: &[Span], out: &mut String) {
+    for span in spans {
+        match span {
+            Span::Text(text) => out.push_str(text),
+            Span::Bold(inner)
+            | Span::Italic(inner)
+            | Span::Underline(inner)
+            | Span::Strike(inner)
+            | Span::Spoiler(inner)
+            | Span::Link { content: inner, .. } => plain(inner, out),
+            Span::Image(_) => {},
+            Span::Break => out.push('\n'),
+        }
+    }
+}
+
+impl CommentBody {
+    // break spans into paragraphs where two breaks (or more) are in a row
+    pub(super) fn from_spans(spans: Vec<Span>) -> Self {
+        let mut paragraphs = Vec::new();
+        let mut current = Vec::new();
+        let mut breaks = 0;
+
+        for span in spans {
+            if span == Span::Break {
+                breaks += 1;
+                continue;
+            }
+
+            match breaks {
+                0 => {},
+                1 => current.push(Span::Break),
+                _ => paragraphs.push(std::mem::take(&mut current)),
+            }
+
+            breaks = 0;
+            current.push(span);
+        }
+
+        paragraphs.push(current);
+
+        // spaces around the breaks are left over from the source
+        paragraphs.retain(|x: &Vec<Span>| x.iter().any(|span| match span {
+            Span::Text(text) => !text.trim().is_empty(),
+            _ => true,
+        }));
+
+        Self { paragraphs }
+    }
+
+    // every link in the comment, images not included
+    pub fn links(&self) -> Vec<&str> {
+        fn collect<'a>(spans: &'a [Span], out: &mut Vec<&'a str>) {
+            for span in spans {
+                match span {
+                    Span::Link { url, content } => {
+                        out.push(url);
+                        collect(content, out);
+                    },
+                    Span::Bold(inner)
+                    | Span::Italic(inner)
+                    | Span::Underline(inner)
+                    | Span::Strike(inner)
+                    | Span::Spoiler(inner) => collect(inner, out),
+                    _ => {},
+                }
+            }
+        }
+
+        let mut links = Vec::new();
+
+        for paragraph in self.paragraphs.iter() {
+            collect(paragraph, &mut links);
+        }
+
+        links
+    }
+
+    // the text of the comment, with a blank line between paragraphs
+    pub fn plain_text(&self) -> String {
+        self.paragraphs
+            .iter()
+            .map(|paragraph| {
+                let mut text = String::new();
+                plain(paragraph, &mut text);
+
+                text.lines().map(|line| line.trim()).collect::<Vec<_>>().join("\n")
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
@@ -2,16 +2,108 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+mod error;
+mod id;
+mod intern;
 mod tag;
-mod page;
+mod language;
+mod title;
+mod markup;
+pub(crate) mod page;
+mod image;
+mod progress;
+mod control;
+mod profile;
+mod date;
+mod settings;
+mod limits;
+mod favorites;
+mod mytags;
+mod rating;
+mod hath;
+mod cookies;
+mod builder;
+mod proxy;
+mod wiki;
+mod stats;
+mod filter;
 mod article;
+mod comicinfo;
+mod infojson;
+mod reader;
+mod naming;
+mod download;
+mod manifest;
+mod verify;
+mod dedupe;
+mod gallerydl;
+mod export;
+mod dump;
+mod downloader;
+#[cfg(feature = "pdf")]
+mod pdf;
+#[cfg(feature = "store")]
+mod store;
+#[cfg(feature = "feed")]
+mod feed;
+#[cfg(feature = "lanraragi")]
+mod lanraragi;
 mod parser;
-mod client;
+pub(crate) mod client;
+mod queue;
+mod cache;
+mod disk_cache;
+#[cfg(feature = "vcr")]
+mod vcr;
+pub(crate) mod api;
+mod watch;
+mod crawler;
 mod explorer;
 
+pub use error::{EhError, ParseError};
+pub use id::{ParseGalleryIdError, GalleryId};
 pub use tag::{ParseTagError, TagKind, Tag, TagMap, ArticleKind};
-pub use article::{Draft, Comment, Article};
+pub use language::Language;
+pub use title::ParsedTitle;
+pub use markup::{Span, CommentBody};
+pub use parser::ParseWarning;
+pub use article::{DraftMeta, ArticleMeta, Draft, Comment, ArticleData, ArticleChanges, NewerVersion, Article};
+pub use image::{ParseImageEntryError, ImageEntry, Preview, Crop, ImageFormat, ImageData};
+pub use progress::{Progress, ProgressState};
+pub use control::{ControlState, Control};
+pub use queue::Priority;
+pub use reader::Reader;
+pub use naming::{ParseTemplateError, NameTemplate};
+pub use download::{DownloadOptions, DownloadSummary};
+pub use manifest::{Manifest, PageRecord};
+pub use verify::PageCheck;
+pub use dedupe::ImageIndex;
+pub use export::ExportOptions;
+pub use dump::{DumpFormat, DumpField, ParseDumpFieldError, DumpRow, Dumper};
+pub use downloader::{DownloadFormat, DownloaderOptions, JobId, JobReport, DownloadEvent, Downloader};
+pub use api::GalleryData;
+pub use cache::CacheStats;
+pub use profile::Profile;
+pub use favorites::{FavoriteSort, Favorite, Favorites, FavoritesDiff};
+pub use mytags::WatchedTag;
+pub use rating::Rating;
+pub use hath::{HathResolution, HathClient};
+pub use limits::{ImageLimit, Funds};
+pub use settings::{ParseDisplayModeError, DisplayMode, Settings};
+pub use wiki::TagWiki;
+pub use stats::SearchStats;
+pub use page::{PageInfo, Page};
+pub use watch::{WatchSource, GalleryEvent};
+pub use crawler::{CrawlSource, Crawler};
+#[cfg(feature = "feed")]
+pub use feed::{FeedFormat, Feed};
+#[cfg(feature = "lanraragi")]
+pub use lanraragi::Lanraragi;
+#[cfg(feature = "store")]
+pub use store::{Store, DownloadState};
+pub use cookies::ParseCookiesError;
+pub use builder::{Site, ExplorerBuilder};
 pub use explorer::{Explorer};
 
 #[cfg(test)]
-mod tests;
+pub(crate) mod tests;
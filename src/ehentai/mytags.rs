@@ -0,0 +1,108 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::error::Error;
+use serde_json::json;
+
+use super::client::Client;
+use super::tag::Tag;
+use super::error::EhError;
+use super::parser;
+use super::api;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+const MYTAGS: &str = "https://e-hentai.org/mytags.php";
+
+// a tag in "My Tags" of the account, which searches and lists look at
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchedTag {
+    // given by the site when added; it's 0 for a new one
+    pub id: u64,
+    pub tag: Tag,
+    // show galleries with the tag in the watched list
+    pub watch: bool,
+    // hide galleries with the tag from everywhere
+    pub hide: bool,
+    // like "#ff0000", to highlight the tag
+    pub color: Option<String>,
+    // galleries whose tags weigh less than the threshold are hidden
+    pub weight: i32,
+}
+
+impl WatchedTag {
+    pub fn new(tag: Tag) -> Self {
+        Self {
+            id: 0,
+            tag,
+            watch: true,
+            hide: false,
+            color: None,
+            weight: 10,
+        }
+    }
+}
+
+// what the page gives to use the api on behalf of the account
+pub(super) struct ApiKey {
+    pub uid: u64,
+    pub key: String,
+}
+
+pub(super) async fn list(client: &Client)
+    -> Result<(Vec<WatchedTag>, Option<ApiKey>), ErrorBox> {
+    let doc = client.get_html_fresh(MYTAGS.parse()?).await?;
+    Ok((parser::my_tags(&doc)?, parser::api_key(&doc)))
+}
+
+pub(super) async fn add(client: &Client, tag: &WatchedTag)
+    -> Result<(), ErrorBox> {
+    let flag = |on: bool| if on { "on" } else { "" }.to_owned();
+
+    let form = [
+        ("usertag_action".to_owned(), "add".to_owned()),
+        ("tagname_new".to_owned(), tag.tag.to_string()),
+        ("tagwatch_new".to_owned(), flag(tag.watch)),
+        ("taghide_new".to_owned(), flag(tag.hide)),
+        ("tagcolor_new".to_owned(), tag.color.clone().unwrap_or_default()),
+        ("tagweight_new".to_owned(), tag.weight.to_string()),
+        ("usertag_target".to_owned(), "0".to_owned()),
+    ];
+
+    client.post_form(MYTAGS.parse()?, &form).await?;
+    Ok(())
+}
+
+pub(super) async fn remove(client: &Client, id: u64) -> Result<(), ErrorBox> {
+    let form = [
+        ("usertag_action".to_owned(), "mass".to_owned()),
+        ("usertag_target".to_owned(), "0".to_owned()),
+        ("modify_usertags[]".to_owned(), id.to_string()),
+    ];
+
+    client.post_form(MYTAGS.parse()?, &form).await?;
+    Ok(())
+}
+
+// change the flags, color and weight of a tag already there
+pub(super) async fn update(client: &Client, tag: &WatchedTag)
+    -> Result<(), ErrorBox> {
+    let key = match list(client).await?.1 {
+        Some(key) => key,
+        None => return Err(EhError::NotFound("api key".to_owned()).into())
+    };
+
+    api::call_fresh(client, json!({
+        "method": "setusertag",
+        "apiuid": key.uid,
+        "apikey": key.key,
+        "tagid": tag.id,
+        "tagwatch": tag.watch as u8,
+        "taghide": tag.hide as u8,
+        "tagcolor": tag.color.clone().unwrap_or_default(),
+        "tagweight": tag.weight,
+    })).await?;
+
+    Ok(())
+}
@@ -0,0 +1,365 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::fmt;
+use std::error::Error;
+use std::str::FromStr;
+
+use super::article::ArticleMeta;
+use super::tag::TagKind;
+
+// names windows keeps for devices, whatever the extension is
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// the name of a file or a directory out of a title, without what
+// file systems don't allow. it's cut short, for those with a limit
+pub(super) fn file_name(text: &str) -> String {
+    const MAX_CHARS: usize = 150;
+
+    let name = text
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_CHARS)
+        .collect::<String>();
+
+    // windows doesn't like them at the end
+    let name = name.trim().trim_end_matches('.').trim_end();
+
+    let stem = name.split('.').next().unwrap().trim_end();
+
+    if name.is_empty() {
+        "_".to_owned()
+    } else if RESERVED_NAMES.iter().any(|x| x.eq_ignore_ascii_case(stem)) {
+        format!("_{}", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseTemplateError(String);
+
+impl fmt::Display for ParseTemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid naming template: {}", self.0)
+    }
+}
+
+impl Error for ParseTemplateError {}
+
+// what a template can fill in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Gid,
+    Token,
+    // the title without the brackets around it, as ParsedTitle gives
+    Title,
+    // the title as the site shows it
+    FullTitle,
+    OriginalTitle,
+    // the first of them, from the tags or else from the title
+    Artist,
+    // all of them, separated by commas
+    Artists,
+    Circle,
+    Parody,
+    Event,
+    Language,
+    Category,
+    Uploader,
+    // like "2021-03-04"
+    Date,
+    // number of images in the gallery
+    Pages,
+    // of an image; pages start from 1 unlike indices
+    Page,
+    Index,
+    Ext,
+}
+
+impl Key {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "gid" => Some(Key::Gid),
+            "token" => Some(Key::Token),
+            "title" => Some(Key::Title),
+            "full_title" => Some(Key::FullTitle),
+            "original_title" => Some(Key::OriginalTitle),
+            "artist" => Some(Key::Artist),
+            "artists" => Some(Key::Artists),
+            "circle" | "group" => Some(Key::Circle),
+            "parody" => Some(Key::Parody),
+            "event" => Some(Key::Event),
+            "language" => Some(Key::Language),
+            "category" => Some(Key::Category),
+            "uploader" => Some(Key::Uploader),
+            "date" => Some(Key::Date),
+            "pages" => Some(Key::Pages),
+            "page" => Some(Key::Page),
+            "index" => Some(Key::Index),
+            "ext" => Some(Key::Ext),
+            _ => None
+        }
+    }
+
+    fn of_image(&self) -> bool {
+        matches!(self, Key::Page | Key::Index | Key::Ext)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    // numbers are zero-padded to the width, and text is cut to it
+    Key { key: Key, width: Option<usize> },
+}
+
+// what's between two slashes, with the text it's parsed from
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Component {
+    source: String,
+    parts: Vec<Part>,
+}
+
+// how to name what's downloaded, like
+// "{artist}/{title} ({language})/{page:03}.{ext}". keys in braces are
+// filled in from the metadata and the parsed title, and braces
+// themselves are written twice. each part between slashes is a directory
+// or a file, and is made a valid name on its own; a key which has nothing,
+// like an artist of a gallery without one, is left empty (and a part
+// with nothing at all is "_").
+//
+// parts which name images (by {page}, {index} or {ext}) are after those
+// which name the gallery, and split from them by split_images().
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameTemplate {
+    components: Vec<Component>,
+}
+
+impl FromStr for NameTemplate {
+    type Err = ParseTemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut components = Vec::new();
+
+        for component in s.split(|c| c == '/' || c == '\\') {
+            // "a//b" is as good as "a/b"
+            if component.is_empty() {
+                continue;
+            }
+
+            let mut parts = Vec::new();
+            let mut text = String::new();
+            let mut chars = component.chars();
+
+            while let Some(c) = chars.next() {
+                match c {
+                    '{' if chars.as_str().starts_with('{') => {
+                        chars.next();
+                        text.push('{');
+                    },
+                    '}' if chars.as_str().starts_with('}') => {
+                        chars.next();
+                        text.push('}');
+                    },
+                    '{' => {
+                        let rest = chars.as_str();
+                        let end = rest
+                            .find('}')
+                            .ok_or_else(|| ParseTemplateError(format!("unclosed brace in {:?}", s)))?;
+
+                        let (name, width) = match rest[..end].split_once(':') {
+                            Some((name, width)) => (name, Some(width)),
+                            None => (&rest[..end], None),
+                        };
+
+                        let key = Key::from_name(name.trim())
+                            .ok_or_else(|| ParseTemplateError(format!("unknown key {:?}", name)))?;
+
+                        let width = match width {
+                            Some(width) => Some(width.parse::<usize>()
+                                .map_err(|_| ParseTemplateError(format!("bad width {:?}", width)))?),
+                            None => None,
+                        };
+
+                        if !text.is_empty() {
+                            parts.push(Part::Text(std::mem::take(&mut text)));
+                        }
+
+                        parts.push(Part::Key { key, width });
+                        chars = rest[end + 1..].chars();
+                    },
+                    '}' => return Err(ParseTemplateError(format!("stray brace in {:?}", s))),
+                    c => text.push(c),
+                }
+            }
+
+            if !text.is_empty() {
+                parts.push(Part::Text(text));
+            }
+
+            components.push(Component {
+                source: component.to_owned(),
+                parts,
+            });
+        }
+
+        if components.is_empty() {
+            return Err(ParseTemplateError("it's empty".to_owned()));
+        }
+
+        // every image would be written over the one before
+        let mut images = components.iter().skip_while(|x| !x.names_image()).peekable();
+
+        if images.peek().is_some() && !images.any(Component::numbers_image) {
+            return Err(ParseTemplateError(format!(
+                "images of {:?} are all named the same; give them {{page}} or {{index}}", s
+            )));
+        }
+
+        Ok(Self { components })
+    }
+}
+
+impl fmt::Display for NameTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sources = self.components
+            .iter()
+            .map(|x| x.source.as_str())
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", sources.join("/"))
+    }
+}
+
+impl Component {
+    fn names_image(&self) -> bool {
+        self.parts.iter().any(|part| match part {
+            Part::Key { key, .. } => key.of_image(),
+            Part::Text(_) => false,
+        })
+    }
+
+    // whether it tells images apart, which {ext} alone doesn't
+    fn numbers_image(&self) -> bool {
+        self.parts.iter().any(|part| matches!(
+            part,
+            Part::Key { key: Key::Page, .. } | Part::Key { key: Key::Index, .. }
+        ))
+    }
+}
+
+impl NameTemplate {
+    // whether any part of it names images, not only the gallery
+    pub fn names_images(&self) -> bool {
+        self.components.iter().any(Component::names_image)
+    }
+
+    // split it into the parts naming the gallery and those naming its
+    // images, like "{artist}/{title}" and "{page:03}.{ext}". either can
+    // be missing, when all of it is of the other
+    pub fn split_images(&self) -> (Option<Self>, Option<Self>) {
+        let at = self.components
+            .iter()
+            .position(Component::names_image)
+            .unwrap_or(self.components.len());
+
+        let (gallery, images) = self.components.split_at(at);
+
+        let part = |components: &[Component]| match components.is_empty() {
+            true => None,
+            false => Some(Self { components: components.to_vec() }),
+        };
+
+        (part(gallery), part(images))
+    }
+
+    // the path made of it, relative to where it's saved, with slashes
+    // between directories. image keys are empty without an image
+    pub fn render(&self, meta: &ArticleMeta, image: Option<(usize, &str)>) -> String {
+        let parsed = meta.parsed_title();
+        let id = meta.id();
+
+        let tag_or = |kind: TagKind, fallback: Option<&str>| meta.tags
+            .first(kind)
+            .or(fallback)
+            .unwrap_or("")
+            .to_owned();
+
+        let text = |key: Key| match key {
+            Key::Gid => id.as_ref().map_or(String::new(), |id| id.gid.to_string()),
+            Key::Token => id.as_ref().map_or(String::new(), |id| id.token.clone()),
+            Key::Title => parsed.title.clone(),
+            Key::FullTitle => meta.title.clone(),
+            Key::OriginalTitle => meta.original_title.clone(),
+            Key::Artist => tag_or(TagKind::Artist, parsed.artists.first().map(|x| x.as_str())),
+            Key::Artists => match meta.tags.namespace(TagKind::Artist).next() {
+                Some(_) => meta.tags.namespace(TagKind::Artist).collect::<Vec<_>>().join(", "),
+                None => parsed.artists.join(", "),
+            },
+            Key::Circle => tag_or(TagKind::Group, parsed.circle.as_deref()),
+            Key::Parody => tag_or(TagKind::Parody, parsed.parody.as_deref()),
+            Key::Event => parsed.event.clone().unwrap_or_default(),
+            Key::Language => meta.language.to_string(),
+            Key::Category => meta.kind.to_string(),
+            Key::Uploader => meta.uploader.clone(),
+            Key::Date => meta.posted.split_whitespace().next().unwrap_or("").to_owned(),
+            Key::Ext => image.map_or("", |(_, ext)| ext).to_owned(),
+            // numbers are done below
+            Key::Pages | Key::Page | Key::Index => String::new(),
+        };
+
+        let number = |key: Key| match (key, image) {
+            (Key::Pages, _) => Some(meta.length),
+            (Key::Page, Some((index, _))) => Some(index + 1),
+            (Key::Index, Some((index, _))) => Some(index),
+            _ => None,
+        };
+
+        // pages are as wide as the last one by default, like page_name()
+        let default_width = meta.length.to_string().len();
+
+        self.components
+            .iter()
+            .map(|component| {
+                let mut name = String::new();
+
+                for part in component.parts.iter() {
+                    match part {
+                        Part::Text(text) => name.push_str(text),
+                        Part::Key { key, width } => match number(*key) {
+                            Some(number) => {
+                                let width = match key {
+                                    Key::Pages => width.unwrap_or(0),
+                                    _ => width.unwrap_or(default_width),
+                                };
+
+                                name.push_str(&format!("{:0width$}", number, width = width));
+                            },
+                            None => {
+                                let value = text(*key);
+
+                                match width {
+                                    Some(width) => name.extend(value.chars().take(*width)),
+                                    None => name.push_str(&value),
+                                }
+                            }
+                        }
+                    }
+                }
+
+                file_name(&name)
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
@@ -5,14 +5,25 @@
 use std::sync::Arc;
 use std::error::Error;
 use hyper::Uri;
+use hyper::body::Bytes;
+use tokio::task::JoinHandle;
 
-use super::client::Client;
-use super::article::Draft;
-use super::parser;
+use super::client::{self, Client};
+use super::article::{Draft, DraftMeta};
+use super::tag::{ArticleKind, TagMap};
+use super::language::Language;
+use super::settings::DisplayMode;
+use super::filter::{self, DraftFilter};
+use super::parser::{self, ParseWarning};
+use super::api;
+use super::queue::Priority;
 
-type ErrorBox = Box<dyn Error>;
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
 
-fn percent_encode(from: &str) -> String {
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+pub(crate) fn percent_encode(from: &str) -> String {
     let mut res = String::new();
 
     for byte in from.as_bytes() {
@@ -31,12 +42,85 @@ fn percent_encode(from: &str) -> String {
     res
 }
 
+// the categories to leave out, which is what the site asks for. private
+// ones aren't searched anyway
+fn category_mask(kinds: &[ArticleKind]) -> u32 {
+    const ALL: u32 = 0x3ff;
+
+    let bit = |kind: &ArticleKind| match kind {
+        ArticleKind::Misc => 1,
+        ArticleKind::Doujinshi => 2,
+        ArticleKind::Manga => 4,
+        ArticleKind::ArtistCG => 8,
+        ArticleKind::GameCG => 16,
+        ArticleKind::ImageSet => 32,
+        ArticleKind::Cosplay => 64,
+        ArticleKind::AsianPorn => 128,
+        ArticleKind::NonH => 256,
+        ArticleKind::Western => 512,
+        ArticleKind::Private => 0,
+    };
+
+    ALL & !kinds.iter().map(bit).fold(0, |acc, x| acc | x)
+}
+
+// where a page is in the results of a search, as the site tells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageInfo {
+    // which of the results are in the page, counting from 1
+    pub first_shown: Option<usize>,
+    pub last_shown: Option<usize>,
+    pub total: usize,
+    // the site only guesses the count of a large result, "about" it
+    pub approximate: bool,
+    // number of pages the pager shows, if it does
+    pub pages: Option<usize>,
+}
+
+impl PageInfo {
+    // nothing was found at all
+    fn empty() -> Self {
+        Self {
+            first_shown: None,
+            last_shown: None,
+            total: 0,
+            approximate: false,
+            pages: Some(0),
+        }
+    }
+}
+
 pub struct Page {
     client: Arc<Client>,
+    // the list it searches, like "/" or "/watched"
+    base: &'static str,
     page: usize,
-    results: Option<usize>,
+    info: Option<PageInfo>,
+    // the last page had no link to the next
+    done: bool,
     limit: Option<usize>,
     query: String,
+    // the page fetched last and what was in it
+    current: Option<(usize, Vec<DraftMeta>)>,
+    filter: DraftFilter,
+    // the layout to ask the site for, instead of the one of the account
+    mode: Option<DisplayMode>,
+    // what was skipped in the page fetched last, in lenient parsing
+    warnings: Vec<ParseWarning>,
+    // fetch the next page while the last one is looked at
+    prefetch: bool,
+    prefetched: Option<(usize, JoinHandle<Result<Bytes, ErrorBox>>)>,
+    // fill in the drafts by the api
+    enrich: bool,
+    // never take a page from the cache, for results which keep coming
+    fresh: bool,
+}
+
+async fn get_page(client: &Client, uri: Uri, fresh: bool) -> Result<Bytes, ErrorBox> {
+    match fresh {
+        true => client.get_html_fresh_raw(uri).await,
+        false => client.get_html_raw(uri).await,
+    }
 }
 
 impl Page {
@@ -45,43 +129,86 @@ impl Page {
 
         Self {
             client,
+            base: "/",
             page,
-            results: None,
+            info: None,
+            done: false,
             limit: None,
             query,
+            current: None,
+            filter: DraftFilter::default(),
+            mode: None,
+            warnings: Vec::new(),
+            prefetch: false,
+            prefetched: None,
+            enrich: false,
+            fresh: false,
         }
     }
 
-    fn uri(&self) -> Result<Uri, impl Error> {
+    // galleries with tags which are watched in "my tags" of the account
+    pub(super) fn watched(client: Arc<Client>, keyword: &str) -> Self {
+        let mut page = Self::new(client, 0, keyword);
+        page.base = "/watched";
+        page
+    }
+
+    fn uri(&self, page: usize) -> Result<Uri, impl Error> {
+        let mode = self.mode
+            .map_or_else(String::new, |mode| format!("&inline_set=dm_{}", mode));
+
         Uri::builder()
             .scheme("https")
             .authority("e-hentai.org")
-            .path_and_query(format!("?page={}&{}", self.page, self.query))
+            .path_and_query(format!("{}?page={}&{}{}", self.base, page, self.query, mode))
             .build()
     }
 
-    // number of found search results
-    pub fn results(&self) -> Option<usize> {
-        self.results
+    // what the page fetched last said about the results
+    pub fn info(&self) -> Option<&PageInfo> {
+        self.info.as_ref()
     }
 
+    // number of pages, if known. a guessed count of results doesn't tell it,
+    // but the end is still found by the pager
     pub fn len(&self) -> Option<usize> {
         const ARTICLES_PER_PAGE: usize = 25;
 
-        if let Some(lim) = self.limit {
-            Some(lim)
-        } else if let Some(n) = self.results {
-            // self.results must not be 0
-            Some((n - 1) / ARTICLES_PER_PAGE + 1)
-        } else {
-            None
+        let info = match (self.limit, &self.info) {
+            (Some(lim), _) => return Some(lim),
+            (None, Some(info)) => info,
+            (None, None) => return None,
+        };
+
+        match info.pages {
+            Some(pages) => Some(pages),
+            None if !info.approximate =>
+                Some((info.total + ARTICLES_PER_PAGE - 1) / ARTICLES_PER_PAGE),
+            None => None
         }
     }
 
+    // index of the page next() would give
     pub fn page(&self) -> usize {
         self.page
     }
 
+    // index of the page fetched last
+    pub fn current_page(&self) -> Option<usize> {
+        self.current.as_ref().map(|(page, _)| *page)
+    }
+
+    // galleries skipped in the page fetched last; always empty unless
+    // the explorer parses leniently
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
+    // the page fetched last again, without fetching it again
+    pub fn current(&self) -> Option<Vec<Draft>> {
+        self.current.as_ref().map(|(_, list)| self.drafts(list.clone()))
+    }
+
     // both Iterator and Stream suck, so i have to mimic them by myself...
     pub fn skip(mut self, n: usize) -> Self {
         self.page += n;
@@ -93,24 +220,215 @@ impl Page {
         self
     }
 
+    // every mode is parsed, but some show more than the others; minimal
+    // modes don't have tags, for one. note that the site remembers it
+    // as the setting of the account
+    pub fn display_mode(mut self, mode: DisplayMode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    // start fetching the next page as soon as one is given, so that it's
+    // there by the time it's asked for. it's only one page ahead, and
+    // still keeps to the rate limit of the explorer
+    pub fn prefetch(mut self, prefetch: bool) -> Self {
+        self.prefetch = prefetch;
+        self
+    }
+
+    // ask the api of the galleries in each page, for all of their tags
+    // (lists show only some), file sizes and whether they're expunged.
+    // it's one more request for a page, instead of one for each gallery
+    // with Draft::load(); filters see the drafts after it's done
+    pub fn enrich(mut self, enrich: bool) -> Self {
+        self.enrich = enrich;
+        self
+    }
+
+    // ask the site each time, even for pages fetched before
+    pub fn fresh(mut self, fresh: bool) -> Self {
+        self.fresh = fresh;
+        self
+    }
+
+    // these drop drafts on our side, so a page may come out empty;
+    // it's not the end until next() gives None
+    pub fn filter(mut self, f: impl Fn(&DraftMeta) -> bool + Send + Sync + 'static) -> Self {
+        self.filter.add(f);
+        self
+    }
+
+    // drafts with all of `include`, and none of `exclude`
+    pub fn filter_tags(self, include: TagMap, exclude: TagMap) -> Self {
+        self.filter(filter::tags(include, exclude))
+    }
+
+    pub fn min_rating(self, min: f32) -> Self {
+        self.filter(filter::min_rating(min))
+    }
+
+    pub fn min_pages(self, min: usize) -> Self {
+        self.filter(filter::min_pages(min))
+    }
+
+    pub fn language(self, language: Language) -> Self {
+        self.filter(filter::language(language))
+    }
+
+    pub fn kind(self, kind: ArticleKind) -> Self {
+        self.filter(filter::kind(kind))
+    }
+
+    // search only in `kinds`. unlike kind(), it's the site which leaves
+    // the others out, so pages aren't emptied by it
+    pub fn categories(mut self, kinds: &[ArticleKind]) -> Self {
+        self.query.push_str(&format!("&f_cats={}", category_mask(kinds)));
+        self
+    }
+
+    // by the date of posting in the list, which doesn't need the galleries
+    #[cfg(feature = "chrono")]
+    pub fn posted_since(self, since: DateTime<Utc>) -> Self {
+        self.filter(filter::posted_since(since))
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn posted_before(self, before: DateTime<Utc>) -> Self {
+        self.filter(filter::posted_before(before))
+    }
+
+    // searches are sorted by the date by default, so nothing after the
+    // first one older than `since` would pass
+    #[cfg(feature = "chrono")]
+    pub fn until_posted(self, since: DateTime<Utc>) -> Self {
+        self.take_while(filter::posted_since(since))
+    }
+
+    // end the search at the first draft which fails `f`, e.g. one posted
+    // before some day when the results are sorted by date
+    pub fn take_while(mut self, f: impl Fn(&DraftMeta) -> bool + Send + Sync + 'static) -> Self {
+        self.filter.set_until(f);
+        self
+    }
+
     pub async fn next(&mut self) -> Result<Option<Vec<Draft>>, ErrorBox> {
-        if self.len().filter(|len| len <= &self.page).is_some() {
+        if self.done || self.len().filter(|len| len <= &self.page).is_some() {
             return Ok(None);
         }
 
-        let doc = self.client.get_html(self.uri()?).await?;
-        self.page += 1;
-        self.results = Some(parser::search_results(&doc)?);
-
-        if let Some(list) = parser::article_list(&doc)? {
-            let list = list
-                .into_iter()
-                .map(|meta| Draft::new(self.client.clone(), meta))
-                .collect();
-            
-            Ok(Some(list))
-        } else {
-            Ok(None)
+        self.load(self.page).await
+    }
+
+    // jump to the `page`-th page; next() goes on from there
+    pub async fn goto(&mut self, page: usize)
+        -> Result<Option<Vec<Draft>>, ErrorBox> {
+        if self.len().filter(|len| len <= &page).is_some() {
+            return Ok(None);
+        }
+
+        self.load(page).await
+    }
+
+    // the page before the one fetched last, if there is
+    pub async fn prev(&mut self) -> Result<Option<Vec<Draft>>, ErrorBox> {
+        match self.current_page() {
+            Some(page) if page > 0 => self.load(page - 1).await,
+            _ => Ok(None)
+        }
+    }
+
+    fn drafts(&self, list: Vec<DraftMeta>) -> Vec<Draft> {
+        list.into_iter()
+            .map(|meta| Draft::new(self.client.clone(), meta))
+            .collect()
+    }
+
+    // the page, from what's prefetched if it's the one
+    async fn fetch(&mut self, uri: Uri, page: usize) -> Result<Bytes, ErrorBox> {
+        match self.prefetched.take() {
+            Some((at, handle)) if at == page => handle.await?,
+            // jumped elsewhere
+            Some((_, handle)) => {
+                handle.abort();
+                get_page(&self.client, uri, self.fresh).await
+            },
+            None => get_page(&self.client, uri, self.fresh).await,
+        }
+    }
+
+    fn start_prefetch(&mut self, page: usize) -> Result<(), ErrorBox> {
+        if self.done || self.len().filter(|len| len <= &page).is_some() {
+            return Ok(());
+        }
+
+        let client = self.client.clone();
+        let uri = self.uri(page)?;
+        let fresh = self.fresh;
+
+        let handle = tokio::spawn(Priority::Background.scope(async move {
+            get_page(&client, uri, fresh).await
+        }));
+        self.prefetched = Some((page, handle));
+        Ok(())
+    }
+
+    async fn load(&mut self, page: usize)
+        -> Result<Option<Vec<Draft>>, ErrorBox> {
+        let uri = self.uri(page)?;
+        let bytes = self.fetch(uri.clone(), page).await?;
+
+        let lenient = self.client.is_lenient();
+        let at = uri.to_string();
+
+        let (info, done, mut list, warnings) = client::parse_with(bytes, move |doc| {
+            // nothing found at all, if it doesn't say
+            let info = parser::page_info(doc)?.unwrap_or_else(PageInfo::empty);
+            let done = !parser::has_next_page(doc);
+
+            let (list, warnings) = match lenient {
+                true => match parser::article_list_lenient(doc) {
+                    Some((list, warnings)) => (Some(list), warnings),
+                    None => (None, Vec::new()),
+                },
+                false => {
+                    let list = parser::article_list(doc).map_err(|err| parser::at(err, &at))?;
+                    (list, Vec::new())
+                }
+            };
+
+            Ok((info, done, list, warnings))
+        }).await?;
+
+        self.info = Some(info);
+        self.done = done;
+        self.warnings = warnings;
+
+        if let (true, Some(list)) = (self.enrich, list.as_mut()) {
+            api::enrich(&self.client, list).await?;
+        }
+
+        let list = list.map(|list| {
+            let (list, stop) = self.filter.apply(list);
+            self.done |= stop;
+            list
+        });
+
+        self.page = page + 1;
+        self.current = list.clone().map(|list| (page, list));
+
+        if self.prefetch && list.is_some() {
+            self.start_prefetch(page + 1)?;
+        }
+
+        Ok(list.map(|list| self.drafts(list)))
+    }
+}
+
+// the page being prefetched isn't wanted anymore
+impl Drop for Page {
+    fn drop(&mut self) {
+        if let Some((_, handle)) = self.prefetched.take() {
+            handle.abort();
         }
     }
 }
@@ -7,139 +7,462 @@ use std::num::ParseIntError;
 use select::document::Document;
 use select::node::Node;
 use select::predicate::{Predicate, Attr, Class, Name};
-use super::article::{DraftMeta, ArticleMeta, Vote, Comment};
+use super::article::{DraftMeta, ArticleMeta, Vote, Comment, NewerVersion};
+use super::markup::{Span, CommentBody};
 use super::tag::{ParseTagError, TagKind, Tag, TagMap, ArticleKind};
+use super::language::Language;
+use super::image::{ImageEntry, Preview, Crop};
+use super::profile::Profile;
+use super::wiki::TagWiki;
+use super::id::GalleryId;
+use super::favorites::{self, Favorite};
+use super::mytags::{WatchedTag, ApiKey};
+use super::hath::HathClient;
+use super::limits::{ImageLimit, Funds};
+use super::rating::Rating;
+use super::settings::DisplayMode;
+use super::page::PageInfo;
+use super::error::{EhError, ParseError};
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+// take a document for an article list, return where the page is in the
+// results. it would be like "Showing 1 - 25 of 1,632 results" or "Found
+// about 10,000 results"; or there's none when nothing's found
+pub fn page_info(doc: &Document) -> Result<Option<PageInfo>, ErrorBox> {
+    let text = match doc.find(Class("ip")).nth(0) {
+        Some(node) => node.text(),
+        None => return Ok(None),
+    };
+
+    // rust's parse() doesn't understand thousands separators
+    let numbers = text
+        .split(|c: char| !c.is_ascii_digit() && c != ',')
+        .map(|word| word.replace(',', ""))
+        .filter_map(|word| word.parse::<usize>().ok())
+        .collect::<Vec<_>>();
+
+    let (first_shown, last_shown, total) = match numbers[..] {
+        [first, last, total] => (Some(first), Some(last), total),
+        [.., total] => (None, None, total),
+        [] => return Ok(None),
+    };
 
-// take a document for an article list,
-// return total count of results of the list
-pub fn search_results(doc: &Document) -> Result<usize, Box<dyn Error>> {
-    Ok(doc.find(Class("ip"))
-        .nth(0).unwrap()
-        .first_child().unwrap()
-        .as_text().unwrap() // this would be like "Showing 608,394 results"
-        .strip_prefix("Showing ").unwrap()
-        .strip_suffix(" results").unwrap()
-        .replace(',', "") // rust's parse() doesn't understand thousands separators
-        .parse::<usize>()?)
+    // the pager shows the number of the last page, unless it's the
+    // newer one which only has links to the previous and the next
+    let pages = doc
+        .find(Name("table").and(Class("ptt")))
+        .nth(0)
+        .and_then(|pager| pager
+            .find(Name("td"))
+            .filter_map(|cell| cell.text().trim().parse::<usize>().ok())
+            .max());
+
+    Ok(Some(PageInfo {
+        first_shown,
+        last_shown,
+        total,
+        approximate: text.contains("about"),
+        pages,
+    }))
 }
 
-// take a document for a list page (e.g. search result),
-// return the list of the articles in the document
-pub fn article_list(doc: &Document)
-    -> Result<Option<Vec<DraftMeta>>, Box<dyn Error>> {
-    let table = doc
-        .find(Name("table").and(Class("gltc")))
-        .nth(0);
+// the stars of a row are a sprite, shifted 16px left for each star less
+// to 5, and to the lower line (-21px) for an extra half star less. it's
+// rounded, but better than nothing
+fn star_rating(node: &Node) -> Option<f32> {
+    let style = node
+        .find(Class("ir"))
+        .nth(0)?
+        .attr("style")?;
+
+    let pos = &style[style.find("background-position:")? + "background-position:".len()..];
+    let mut iter = pos
+        .split(';').nth(0)?
+        .split_whitespace()
+        .map(|x| x.trim_end_matches("px").parse::<i32>().ok());
+
+    let x = iter.next()??;
+    let y = iter.next()??;
+
+    let half = match y {
+        -21 => 0.5,
+        _ => 0.0,
+    };
 
-    // no hits found
-    if table.is_none() {
-        return Ok(None);
+    Some(5.0 - (-x) as f32 / 16.0 - half)
+}
+
+// whether a row lists torrents of the gallery, and which category of
+// the favorites it's in. the date of posting is bordered with the color
+// of the category, if it's in the favorites
+fn row_markers(row: &Node) -> (bool, Option<usize>) {
+    let torrents = row
+        .find(Name("a"))
+        .filter_map(|node| node.attr("href"))
+        .any(|href| href.contains("gallerytorrents.php"));
+
+    let favorite = row
+        .find(Name("div"))
+        .find(|node| node.attr("id").map_or(false, |id| id.starts_with("posted_")))
+        .and_then(|node| node.attr("style"))
+        .and_then(|style| favorites::COLORS.iter().position(|color| {
+            style.contains(&format!("border-color:{}", color))
+        }));
+
+    (torrents, favorite)
+}
+
+// the thumbnail in a node, which is either a part of a sprite drawn as
+// the background of a div, or an image by itself. the sprite goes first,
+// since it's drawn under a blank image
+fn thumbnail(node: &Node) -> Option<(String, Option<Crop>)> {
+    let sprite = node
+        .attr("style")
+        .and_then(Crop::from_style)
+        .or_else(|| node
+            .find(Name("div"))
+            .filter_map(|div| div.attr("style"))
+            .find_map(Crop::from_style));
+
+    if let Some((url, crop)) = sprite {
+        return Some((url, Some(crop)));
     }
 
-    let table = table.unwrap().first_child().unwrap();
+    // images further down are loaded lazily, from data-src
+    node.find(Name("img"))
+        .nth(0)
+        .and_then(|img| img.attr("data-src").or_else(|| img.attr("src")))
+        .map(|src| (src.to_owned(), None))
+}
+
+// how much of the html to put in a parse error
+const SNIPPET_LEN: usize = 200;
 
-    // requested invalid page
-    if table.children().nth(1).unwrap().first_child().unwrap().as_text().is_some() {
-        return Ok(None);
+// an element the markup should have but doesn't; `node` is the closest
+// one which is there
+fn missing(field: &str, selector: &str, node: &Node) -> ErrorBox {
+    let html = node.html();
+    let snippet = match html.char_indices().nth(SNIPPET_LEN) {
+        Some((pos, _)) => format!("{}...", &html[..pos]),
+        None => html
+    };
+
+    ParseError {
+        field: field.to_owned(),
+        selector: selector.to_owned(),
+        url: None,
+        snippet,
+    }.into()
+}
+
+// the element of the id, which is where some field of a page is
+fn by_id<'a>(doc: &'a Document, field: &str, id: &str) -> Result<Node<'a>, ErrorBox> {
+    doc.find(Attr("id", id)).nth(0).ok_or_else(|| ParseError {
+        field: field.to_owned(),
+        selector: format!("#{}", id),
+        url: None,
+        snippet: String::new(),
+    }.into())
+}
+
+// tell the page a parse error was in, which the parser doesn't know
+pub(super) fn at(mut err: ErrorBox, url: &str) -> ErrorBox {
+    if let Some(err) = err.downcast_mut::<ParseError>() {
+        err.url.get_or_insert_with(|| url.to_owned());
     }
 
-    // we can't '?' in a closure, so can't we map().collect()
-    let mut list = Vec::new();
+    err
+}
 
-    // the first element is header row; skip
-    for node in table.children().skip(1) {
-        // advert!
-        if node.first_child().unwrap().attr("class") == Some("itd") {
-            continue;
+// something which couldn't be parsed and was skipped in lenient parsing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+    // what was skipped, like "gallery" or "comment"
+    pub what: &'static str,
+    // its position in the page, counting the skipped ones too
+    pub index: usize,
+    pub message: String,
+}
+
+// keep what's parsed, and warn for the rest
+fn lenient<T>(what: &'static str, results: Vec<Result<T, ErrorBox>>)
+    -> (Vec<T>, Vec<ParseWarning>) {
+    let mut list = Vec::new();
+    let mut warnings = Vec::new();
+
+    for (index, res) in results.into_iter().enumerate() {
+        match res {
+            Ok(item) => list.push(item),
+            Err(err) => warnings.push(ParseWarning {
+                what,
+                index,
+                message: err.to_string(),
+            }),
         }
+    }
+
+    (list, warnings)
+}
+
+fn list_row(node: &Node) -> Result<DraftMeta, ErrorBox> {
+    let mut iter = node.children();
+
+    // 4 columns of the row in total
+    let first = iter.next().ok_or_else(|| missing("category column", "table.itg > tr > td", node))?;
+    let second = iter.next().ok_or_else(|| missing("thumbnail column", "table.itg > tr > td", node))?;
+    let third = iter.next().ok_or_else(|| missing("title column", "table.itg > tr > td", node))?;
+    let fourth = iter.next().ok_or_else(|| missing("uploader column", "table.itg > tr > td", node))?;
+
+    // the first column contains category of the article
+    let kind = {
+        let text = first.first_child().ok_or_else(|| missing("category", "td.gl1c > div", &first))?.text();
+        text.parse::<ArticleKind>()?
+    };
+
+    // the second contains thumbnail, uploaded time,
+    // rate, and download link (costing GP)
+    let rating = star_rating(&second);
+    let ((thumb, thumb_crop), posted) = {
+        let mut iter = second.children().skip(1);
+
+        let thumb = iter
+            .next()
+            .and_then(|node| thumbnail(&node))
+            .ok_or_else(|| missing("thumbnail", "td.gl2c img", &second))?;
+
+        let posted = iter
+            .next()
+            .and_then(|node| node.first_child())
+            .ok_or_else(|| missing("date of posting", "td.gl2c div[id^=posted_]", &second))?
+            .text();
+
+        (thumb, posted)
+    };
+
+    // the third contains link, title, and tags
+    let (path, title, tags) = {
+        let node = third.first_child().ok_or_else(|| missing("link", "td.gl3c > a", &third))?;
+
+        let path = node
+            .attr("href")
+            .ok_or_else(|| missing("link", "td.gl3c > a", &third))?
+            .to_string();
 
         let mut iter = node.children();
+        let title = iter.next().ok_or_else(|| missing("title", "td.gl3c .glink", &third))?.text();
 
-        // 4 columns of the row in total
-        let first = iter.next().unwrap();
-        let second = iter.next().unwrap();
-        let third = iter.next().unwrap();
-        let fourth = iter.next().unwrap();
+        // although only some of the tags are visible in a browser,
+        // there are all the tags in html; the rest are just hidden
+        let tags = iter
+            .next()
+            .ok_or_else(|| missing("tags", "td.gl3c .gt", &third))?
+            .children()
+            .map(|x| x.attr("title").ok_or(ParseTagError())?.parse::<Tag>())
+            .collect::<Result<TagMap, _>>()?;
 
-        // the first column contains category of the article
-        let kind = {
-            let text = first.first_child().unwrap().text();
-            text.parse::<ArticleKind>()?
-        };
+        (path, title, tags)
+    };
 
-        // the second contains thumbnail, uploaded time,
-        // rate, and download link (costing GP)
-        let (thumb, posted) = {
-            let mut iter = second.children().skip(1);
-
-            let thumb = iter
-                .next().unwrap()
-                .find(Name("img"))
-                .nth(0).unwrap()
-                .attr("src").unwrap()
-                .to_string();
-
-            let posted = iter
-                .next().unwrap()
-                .first_child().unwrap()
-                .text();
-
-            // TODO: i don't know how to get rate...
-            (thumb, posted)
-        };
+    // the fourth contains uploader name and number of pages in the article
+    let (uploader, length) = {
+        let uploader = fourth
+            .first_child()
+            .and_then(|node| node.first_child())
+            .ok_or_else(|| missing("uploader", "td.gl4c a", &fourth))?
+            .text();
+
+        let length = fourth
+            .last_child()
+            .ok_or_else(|| missing("length", "td.gl4c > div", &fourth))?
+            .text()
+            .split_ascii_whitespace()
+            .nth(0)
+            .ok_or_else(|| missing("length", "td.gl4c > div", &fourth))?
+            .parse::<usize>()?;
+
+        (uploader, length)
+    };
+
+    let (torrents, favorite) = row_markers(node);
+
+    Ok(DraftMeta {
+        kind,
+        thumb,
+        thumb_crop,
+        posted,
+        path,
+        title,
+        tags,
+        uploader,
+        length,
+        rating,
+        torrents,
+        favorite,
+        // only the api tells these
+        file_size: None,
+        expunged: false,
+    })
+}
+
+// a row of a list in any display mode, found by the classes rather than
+// by the positions; every mode but compact puts things in its own places
+fn loose_row(row: &Node) -> Result<DraftMeta, ErrorBox> {
+    let title = row
+        .find(Class("glink"))
+        .nth(0)
+        .ok_or_else(|| missing("title", ".glink", row))?
+        .text();
+
+    let path = row
+        .find(Name("a"))
+        .filter_map(|node| node.attr("href"))
+        .find(|href| href.contains("/g/"))
+        .ok_or_else(|| missing("link", "a[href*=\"/g/\"]", row))?
+        .to_owned();
+
+    let kind = row
+        .find(Class("cn").or(Class("cs")))
+        .nth(0)
+        .ok_or_else(|| missing("category", ".cn, .cs", row))?
+        .text()
+        .parse::<ArticleKind>()?;
+
+    let (thumb, thumb_crop) = thumbnail(row).unwrap_or_default();
+
+    let posted = row
+        .find(Name("div"))
+        .find(|node| node.attr("id").map_or(false, |id| id.starts_with("posted_")))
+        .ok_or_else(|| missing("date of posting", "div[id^=posted_]", row))?
+        .text();
+
+    // minimal modes don't show the tags
+    let mut tags = TagMap::new();
+
+    for node in row.find(Class("gt").or(Class("gtl"))) {
+        let tag = node.attr("title").ok_or(ParseTagError())?.parse::<Tag>()?;
+
+        match node.attr("class") == Some("gtl") {
+            true => tags.add_weak(tag),
+            false => tags.add(tag),
+        }
+    }
+
+    let uploader = row
+        .find(Name("a"))
+        .find(|node| node.attr("href").map_or(false, |x| x.contains("/uploader/")))
+        .map_or_else(String::new, |node| node.text());
+
+    // outer divs have the text of all the inner ones, like the extended
+    // mode which puts the uploader and such beside the length
+    let length = row
+        .find(Name("div"))
+        .map(|node| node.text())
+        .find_map(|text| match text.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+            [num, "pages"] | [num, "page"] => num.parse::<usize>().ok(),
+            _ => None
+        })
+        .unwrap_or(0);
+
+    let (torrents, favorite) = row_markers(row);
+
+    Ok(DraftMeta {
+        kind,
+        thumb,
+        thumb_crop,
+        posted,
+        path,
+        title,
+        tags,
+        uploader,
+        length,
+        rating: star_rating(row),
+        torrents,
+        favorite,
+        // only the api tells these
+        file_size: None,
+        expunged: false,
+    })
+}
+
+// how the list of the document is laid out. the two minimal modes are
+// the same but for the tags, so they're both told as Minimal
+pub fn display_mode(doc: &Document) -> Option<DisplayMode> {
+    let list = doc.find(Class("itg")).nth(0)?;
+
+    let mode = match list.attr("class")? {
+        class if class.contains("gltm") => DisplayMode::Minimal,
+        class if class.contains("gltc") => DisplayMode::Compact,
+        class if class.contains("glte") => DisplayMode::Extended,
+        class if class.contains("gld") => DisplayMode::Thumbnail,
+        _ => return None
+    };
+
+    Some(mode)
+}
+
+// every row of a list page parsed, or None if nothing's found
+fn list_rows(doc: &Document) -> Option<Vec<Result<DraftMeta, ErrorBox>>> {
+    // no hits found
+    let mode = display_mode(doc)?;
 
-        // the third contains link, title, and tags
-        let (path, title, tags) = {
-            let node = third.first_child().unwrap();
-
-            let path = node
-                .attr("href").unwrap()
-                .to_string();
-
-            let mut iter = node.children();
-            let title = iter.next().unwrap().text();
-            
-            // although only some of the tags are visible in a browser,
-            // there are all the tags in html; the rest are just hidden
-            let tags = iter
-                .next().unwrap()
-                .children()
-                .map(|x| x.attr("title").ok_or(ParseTagError())?.parse::<Tag>())
-                .collect::<Result<TagMap, _>>()?;
-
-            (path, title, tags)
+    if mode != DisplayMode::Compact {
+        let list = doc.find(Class("itg")).nth(0)?;
+
+        let rows = match mode {
+            DisplayMode::Thumbnail => list.find(Class("gl1t")).collect::<Vec<_>>(),
+            _ => list.find(Name("tr")).collect()
         };
 
-        // the fourth contains uploader name and number of pages in the article
-        let (uploader, length) = {
-            let uploader = fourth
-                .first_child().unwrap()
-                .first_child().unwrap()
-                .text();
-
-            let length = fourth
-                .last_child().unwrap()
-                .text()
-                .split_ascii_whitespace()
-                .nth(0).unwrap()
-                .parse::<usize>()?;
-
-            (uploader, length)
+        // the header and adverts don't have a title
+        let rows = rows
+            .iter()
+            .filter(|row| row.find(Class("glink")).nth(0).is_some())
+            .map(|row| loose_row(row))
+            .collect::<Vec<_>>();
+
+        // requested invalid page
+        return match rows.is_empty() {
+            true => None,
+            false => Some(rows)
         };
+    }
 
-        list.push(DraftMeta {
-            kind,
-            thumb,
-            posted,
-            path,
-            title,
-            tags,
-            uploader,
-            length
-        });
+    let table = doc
+        .find(Name("table").and(Class("gltc")))
+        .nth(0)?
+        .first_child()?;
+
+    // requested invalid page
+    if table.children().nth(1)?.first_child()?.as_text().is_some() {
+        return None;
     }
 
-    Ok(Some(list))
+    let rows = table
+        .children()
+        // the first element is header row; skip
+        .skip(1)
+        // advert!
+        .filter(|node| node.first_child().map_or(true, |x| x.attr("class") != Some("itd")))
+        .map(|node| list_row(&node))
+        .collect();
+
+    Some(rows)
+}
+
+// take a document for a list page (e.g. search result),
+// return the list of the articles in the document
+pub fn article_list(doc: &Document)
+    -> Result<Option<Vec<DraftMeta>>, ErrorBox> {
+    list_rows(doc)
+        .map(|rows| rows.into_iter().collect())
+        .transpose()
+}
+
+// same as article_list(), but the rows which can't be parsed are skipped
+pub fn article_list_lenient(doc: &Document)
+    -> Option<(Vec<DraftMeta>, Vec<ParseWarning>)> {
+    list_rows(doc).map(|rows| lenient("gallery", rows))
 }
 
 // take a document of an article gallery, return information of the article
@@ -147,159 +470,189 @@ pub fn article_list(doc: &Document)
 // NOTE: this function DOES NOT parse the image list. call parse_image_list() 
 // and change the article data accordingly to get the list of images.
 pub fn article(doc: &Document, path: String)
-    -> Result<ArticleMeta, Box<dyn Error>> {
+    -> Result<ArticleMeta, ErrorBox> {
+    article_meta(doc, path.clone()).map_err(|err| at(err, &path))
+}
+
+fn article_meta(doc: &Document, path: String)
+    -> Result<ArticleMeta, ErrorBox> {
     let (title, original_title) = {
-        let mut iter = doc.find(Attr("id", "gd2")).nth(0).unwrap().children();
-        
-        let title = iter.next().unwrap().text();
-        let orig = iter.next().unwrap().text();
+        let root = by_id(doc, "title", "gd2")?;
+        let mut iter = root.children();
+
+        let title = iter.next().ok_or_else(|| missing("title", "#gd2 > h1", &root))?.text();
+        let orig = iter.next().ok_or_else(|| missing("original title", "#gd2 > h1", &root))?.text();
 
         (title, orig)
     };
 
     let thumb = {
-        let text = doc
-            .find(Attr("id", "gd1"))
-            .nth(0).unwrap()
-            .first_child().unwrap()
-            .attr("style").unwrap();
+        let root = by_id(doc, "thumbnail", "gd1")?;
+        let text = root
+            .first_child()
+            .and_then(|node| node.attr("style"))
+            .ok_or_else(|| missing("thumbnail", "#gd1 > div[style]", &root))?;
+
+        let begin = text.find("url(");
+        let end = text.find(')');
+
+        match (begin, end) {
+            (Some(begin), Some(end)) if begin < end =>
+                text[(begin + "url(".len())..end].to_owned(),
+            _ => return Err(missing("thumbnail", "#gd1 > div[style]", &root))
+        }
+    };
 
-        let begin = text.find("url(").unwrap();
-        let end = text.find(')').unwrap();
+    let kind = {
+        let root = by_id(doc, "category", "gdc")?;
 
-        text[(begin + "url(".len())..end].to_owned()
+        root.first_child()
+            .and_then(|node| node.first_child()) // this should be a text node
+            .and_then(|node| node.as_text())
+            .ok_or_else(|| missing("category", "#gdc > div", &root))?
+            .parse::<ArticleKind>()?
     };
 
-    let kind = doc
-        .find(Attr("id", "gdc"))
-        .nth(0).unwrap()
-        .first_child().unwrap()
-        .first_child().unwrap() // this should be a text node
-        .as_text().unwrap()
-        .parse::<ArticleKind>()?;
+    let uploader = {
+        let root = by_id(doc, "uploader", "gdn")?;
 
-    let uploader = doc
-        .find(Attr("id", "gdn"))
-        .nth(0).unwrap()
-        .first_child().unwrap()
-        .text();
+        root.first_child()
+            .ok_or_else(|| missing("uploader", "#gdn > a", &root))?
+            .text()
+    };
 
     // parse #gdd, which has most useful informations
-    let mut iter = doc
-        .find(Attr("id", "gdd"))
-        .nth(0).unwrap()
-        .first_child().unwrap()
-        .first_child().unwrap()
+    let gdd = by_id(doc, "details", "gdd")?;
+    let mut iter = gdd
+        .first_child()
+        .and_then(|node| node.first_child())
+        .ok_or_else(|| missing("details", "#gdd > table > tr", &gdd))?
         .children();
 
-    let posted = iter
-        .next().unwrap()
-        .last_child().unwrap()
-        .text();
+    // the value of the next row of #gdd
+    let mut row = |field: &str| iter
+        .next()
+        .and_then(|node| node.last_child())
+        .ok_or_else(|| missing(field, "#gdd tr > td.gdt2", &gdd));
+
+    let posted = row("date of posting")?.text();
 
     let parent = {
-        let node = iter
-            .next().unwrap()
-            .last_child().unwrap()
-            .first_child().unwrap();
+        let node = row("parent")?;
+        let node = node.first_child().ok_or_else(|| missing("parent", "#gdd tr > td.gdt2", &node))?;
 
         match node.as_text() {
             Some("None") => None,
-            None => Some(node.attr("href").unwrap().to_string()),
-            _ => unreachable!()
+            _ => Some(node
+                .attr("href")
+                .ok_or_else(|| missing("parent", "#gdd tr > td.gdt2 > a", &node))?
+                .to_string())
         }
     };
 
     // what is this for?
-    let visible = iter
-        .next().unwrap()
-        .last_child().unwrap()
-        .first_child().unwrap()
-        .as_text().unwrap() == "Yes";
+    let visible = row("visibility")?
+        .first_child()
+        .and_then(|node| node.as_text()) == Some("Yes");
 
     let (language, translated) = {
-        let node = iter
-            .next().unwrap()
-            .last_child().unwrap();
-
-        let language = node
-            .first_child().unwrap()
-            .as_text().unwrap()
-            .trim()
-            .to_owned();
+        let node = row("language")?;
+
+        let language = Language::from(node
+            .first_child()
+            .and_then(|node| node.as_text())
+            .ok_or_else(|| missing("language", "#gdd tr > td.gdt2", &node))?);
 
         let translated = node
-            .last_child().unwrap()
-            .name().is_some();
+            .last_child()
+            .map_or(false, |node| node.name().is_some());
 
         (language, translated)
     };
 
-    let file_size = iter
-        .next().unwrap()
-        .last_child().unwrap()
-        .text();
+    let file_size = row("file size")?.text();
 
-    let length = iter
-        .next().unwrap()
-        .last_child().unwrap()
-        .first_child().unwrap()
-        .as_text().unwrap()
-        .strip_suffix(" pages").unwrap() // it seems there is no article with 1 page
-        .parse::<usize>()?;
+    let length = {
+        let node = row("length")?;
+
+        node.first_child()
+            .and_then(|node| node.as_text())
+            .and_then(|text| text.strip_suffix(" pages")) // it seems there is no article with 1 page
+            .ok_or_else(|| missing("length", "#gdd tr > td.gdt2", &node))?
+            .parse::<usize>()?
+    };
 
     let favorited = {
-        let text = iter
-            .next().unwrap()
-            .last_child().unwrap()
-            .first_child().unwrap()
-            .as_text().unwrap();
+        let node = row("favorited")?;
+        let text = node
+            .first_child()
+            .and_then(|node| node.as_text())
+            .ok_or_else(|| missing("favorited", "#favcount", &node))?;
 
         match text {
             "Never" => 0,
             "Once" => 1,
             "Twice" => 2,
             more => more // the text would be like "(n) times"
-                .strip_suffix(" times").unwrap()
+                .strip_suffix(" times")
+                .ok_or_else(|| missing("favorited", "#favcount", &node))?
                 .parse::<usize>()?
         }
     };
 
-    let rating_count = doc
-        .find(Attr("id", "rating_count"))
-        .nth(0).unwrap()
-        .first_child().unwrap()
-        .as_text().unwrap()
-        .parse::<usize>()?;
-
-    let rating = doc
-        .find(Attr("id", "rating_label"))
-        .nth(0).unwrap()
-        .first_child().unwrap()
-        .as_text().unwrap()
-        .split_ascii_whitespace()
-        .nth(1).unwrap()
-        .parse::<f64>()?;
+    let rating_count = {
+        let root = by_id(doc, "rating count", "rating_count")?;
+
+        root.first_child()
+            .and_then(|node| node.as_text())
+            .ok_or_else(|| missing("rating count", "#rating_count", &root))?
+            .parse::<usize>()?
+    };
+
+    // the label is rounded, while the script has it as is
+    let rating = match script_var(doc, "average_rating").and_then(|x| x.parse().ok()) {
+        Some(rating) => rating,
+        None => {
+            let root = by_id(doc, "rating", "rating_label")?;
+
+            root.first_child()
+                .and_then(|node| node.as_text())
+                .and_then(|text| text.split_ascii_whitespace().nth(1))
+                .ok_or_else(|| missing("rating", "#rating_label", &root))?
+                .parse::<f64>()?
+        }
+    };
 
     let tags = {
-        let list = doc
-            .find(Attr("id", "taglist"))
-            .nth(0).unwrap()
-            .first_child().unwrap()
-            .first_child().unwrap();
+        let root = by_id(doc, "tags", "taglist")?;
+        let list = root
+            .first_child()
+            .and_then(|node| node.first_child())
+            .ok_or_else(|| missing("tags", "#taglist > table > tr", &root))?;
 
         let mut tags = TagMap::new();
 
         for row in list.children() {
             // remove last colon and parse
             let cat = row
-                .first_child().unwrap()
-                .first_child().unwrap()
-                .as_text().unwrap();
-            let cat = cat[..(cat.len() - 1)].parse::<TagKind>()?;
-
-            for elem in row.last_child().unwrap().children() {
-                tags[cat].push(elem.text());
+                .first_child()
+                .and_then(|node| node.first_child())
+                .and_then(|node| node.as_text())
+                .ok_or_else(|| missing("namespace", "#taglist td.tc", &row))?;
+            let cat = cat.trim_end_matches(':').parse::<TagKind>()?;
+
+            let values = row
+                .last_child()
+                .ok_or_else(|| missing("tags", "#taglist td > div", &row))?;
+
+            for elem in values.children() {
+                let tag = Tag::new(cat.clone(), &elem.text());
+
+                // tags with few votes are drawn with a dashed or dotted border
+                match elem.attr("class") {
+                    Some("gtl") | Some("gtw") => tags.add_weak(tag),
+                    _ => tags.add(tag),
+                }
             }
         }
 
@@ -327,18 +680,97 @@ pub fn article(doc: &Document, path: String)
     })
 }
 
-pub fn comments(doc: &Document) -> Result<Vec<Comment>, Box<dyn Error>> {
+pub fn comments(doc: &Document) -> Result<Vec<Comment>, ErrorBox> {
     // parse comments; .c1 is a class each comment node belongs to
     doc.find(Class("c1")).map(|node| comment(&node)).collect()
 }
 
-fn comment(node: &Node) -> Result<Comment, Box<dyn Error>> {
+// same as comments(), but the comments which can't be parsed are skipped
+pub fn comments_lenient(doc: &Document) -> (Vec<Comment>, Vec<ParseWarning>) {
+    let results = doc.find(Class("c1")).map(|node| comment(&node)).collect();
+    lenient("comment", results)
+}
+
+// the rendered bbcode of a comment, down to what Span has. the rest
+// of the markup is dropped, but what's in it is kept
+fn markup(node: &Node) -> Vec<Span> {
+    let mut spans = Vec::new();
+
+    for child in node.children() {
+        if let Some(text) = child.as_text() {
+            // newlines of the source don't break the lines; <br> does
+            let text = text.replace('\u{a0}', " ").replace('\n', " ");
+
+            if !text.is_empty() {
+                spans.push(Span::Text(text));
+            }
+
+            continue;
+        }
+
+        // html comments and such
+        let name = match child.name() {
+            Some(name) => name,
+            None => continue
+        };
+
+        let style = child.attr("style").unwrap_or("").replace(' ', "");
+        let class = child.attr("class").unwrap_or("");
+        let inner = || markup(&child);
+
+        let span = match name {
+            "script" | "style" => continue,
+            "br" => Span::Break,
+            "img" => match child.attr("src") {
+                Some(src) => Span::Image(src.to_owned()),
+                None => continue
+            },
+            "a" => match child.attr("href") {
+                Some(href) => Span::Link { url: href.to_owned(), content: inner() },
+                None => {
+                    spans.extend(inner());
+                    continue;
+                }
+            },
+            "b" | "strong" => Span::Bold(inner()),
+            "i" | "em" => Span::Italic(inner()),
+            "u" | "ins" => Span::Underline(inner()),
+            "s" | "strike" | "del" => Span::Strike(inner()),
+            // the site renders the rest of bbcode as spans with styles
+            _ if class.contains("spoiler") || style.contains("color:transparent") =>
+                Span::Spoiler(inner()),
+            _ if style.contains("font-weight:bold") => Span::Bold(inner()),
+            _ if style.contains("font-style:italic") => Span::Italic(inner()),
+            _ if style.contains("text-decoration:underline") => Span::Underline(inner()),
+            _ if style.contains("text-decoration:line-through") => Span::Strike(inner()),
+            // blocks like quotes are paragraphs of their own
+            "div" | "p" | "blockquote" => {
+                spans.push(Span::Break);
+                spans.push(Span::Break);
+                spans.extend(inner());
+                spans.push(Span::Break);
+                spans.push(Span::Break);
+                continue;
+            },
+            _ => {
+                spans.extend(inner());
+                continue;
+            }
+        };
+
+        spans.push(span);
+    }
+
+    spans
+}
+
+fn comment(node: &Node) -> Result<Comment, ErrorBox> {
     let (top, bottom, votes, edited) = {
         let mut iter = node.children();
 
-        let first = iter.next().unwrap();
-        let second = iter.next().unwrap();
-        let third = iter.next().unwrap();
+        let first = iter.next().ok_or_else(|| missing("comment header", "div.c1 > div.c2", node))?;
+        let second = iter.next().ok_or_else(|| missing("comment content", "div.c1 > div.c6", node))?;
+        let third = iter.next().ok_or_else(|| missing("comment votes", "div.c1 > div.c7", node))?;
         let fourth = iter.next();
 
         // if the comment is edited, "Last edited on (date)." 
@@ -353,7 +785,10 @@ fn comment(node: &Node) -> Result<Comment, Box<dyn Error>> {
     let (left, right) = {
         let mut iter = top.children();
 
-        (iter.next().unwrap(), iter.next().unwrap())
+        match (iter.next(), iter.next()) {
+            (Some(left), Some(right)) => (left, right),
+            _ => return Err(missing("comment header", "div.c2 > div.c3", &top))
+        }
     };
 
     // parse integer which is always prefixed with a '+' or '-' sign
@@ -363,50 +798,64 @@ fn comment(node: &Node) -> Result<Comment, Box<dyn Error>> {
         let sign = match text.chars().nth(0) {
             Some('+') => 1,
             Some('-') => -1,
-            _ => unreachable!() // logically impossible
+            // not prefixed after all
+            _ => return text.parse::<i64>()
         };
     
         text[1..].parse::<i64>().map(|x| x * sign)
     }
     
-    let (posted, writer) = {
+    let (posted, writer, writer_id) = {
         let mut iter = left.children();
 
         let posted = iter
-            .next().unwrap()
-            .as_text().unwrap()
-            .strip_prefix("Posted on ").unwrap()
-            .strip_suffix(" by:   ").unwrap() // " by: &nbsp; "
+            .next()
+            .and_then(|node| node.as_text())
+            .and_then(|text| text.strip_prefix("Posted on "))
+            .and_then(|text| text.strip_suffix(" by:   ")) // " by: &nbsp; "
+            .ok_or_else(|| missing("date of the comment", "div.c3", &left))?
             .to_owned();
 
-        let writer = iter
-            .next().unwrap()
-            .first_child().unwrap()
+        let node = iter.next().ok_or_else(|| missing("writer of the comment", "div.c3 > a", &left))?;
+
+        let writer = node
+            .first_child()
+            .ok_or_else(|| missing("writer of the comment", "div.c3 > a", &left))?
             .text();
 
-        (posted, writer)
+        // the link to the forum profile is like "...?showuser=(id)". the
+        // name links to the uploads of the writer, so it's one of the next
+        let writer_id = left
+            .find(Name("a"))
+            .filter_map(|a| a.attr("href"))
+            .find_map(|href| href.split("showuser=").nth(1))
+            .and_then(|id| id.split('&').nth(0).unwrap().parse::<u64>().ok());
+
+        (posted, writer, writer_id)
     };
 
     let vote = if right.is(Class("c4")) {
         None
     } else {
         let text = right
-            .last_child().unwrap()
-            .first_child().unwrap()
-            .as_text().unwrap();
+            .last_child()
+            .and_then(|node| node.first_child())
+            .and_then(|node| node.as_text())
+            .ok_or_else(|| missing("score of the comment", "div.c5 > span", &right))?;
 
         let score = parse_prefixed(text)?;
 
         // parse a string formatted like "(writer) (score)"
-        fn parse_vote(vote: &str) -> Result<(String, i64), ParseIntError> {
+        fn parse_vote(vote: &str, votes: &Node) -> Result<(String, i64), ErrorBox> {
             // position of the last whitespace
-            let pos = vote.rfind(' ').unwrap();
+            let pos = vote.rfind(' ').ok_or_else(|| missing("score of a vote", "div.c7", votes))?;
 
             Ok((vote[..pos].to_owned(), parse_prefixed(&vote[(pos + 1)..])?))
         }
 
         let omitted = votes
-            .last_child().unwrap()
+            .last_child()
+            .ok_or_else(|| missing("votes of the comment", "div.c7", &votes))?
             .as_text()
             .and_then(|text| text.strip_prefix(", and "))
             .and_then(|text| text.strip_suffix(" more..."))
@@ -416,21 +865,23 @@ fn comment(node: &Node) -> Result<Comment, Box<dyn Error>> {
             let mut list = Vec::new();
 
             let base = votes
-                .first_child().unwrap()
-                .as_text().unwrap();
+                .first_child()
+                .and_then(|node| node.as_text())
+                .ok_or_else(|| missing("votes of the comment", "div.c7", &votes))?;
 
             let base = base
                 .strip_suffix(", ")
                 .unwrap_or(base);
 
-            list.push(parse_vote(base)?);
+            list.push(parse_vote(base, &votes)?);
 
             for span in votes.find(Name("span")) {
                 let vote = span
-                    .first_child().unwrap()
-                    .as_text().unwrap();
+                    .first_child()
+                    .and_then(|node| node.as_text())
+                    .ok_or_else(|| missing("vote of the comment", "div.c7 > span", &votes))?;
 
-                list.push(parse_vote(vote)?);
+                list.push(parse_vote(vote, &votes)?);
             }
 
             list
@@ -446,62 +897,671 @@ fn comment(node: &Node) -> Result<Comment, Box<dyn Error>> {
     let edited = if let Some(node) = edited {
         Some(node
             .children()
-            .nth(1).unwrap()
+            .nth(1)
+            .ok_or_else(|| missing("date of the edit", "div.c8", &node))?
             .text())
     } else {
         None
     };
 
-    let content = bottom
-        .first_child().unwrap()
-        .text();
-    
+    let body = CommentBody::from_spans(markup(&bottom));
+    let content = body.plain_text();
+
     Ok(Comment {
         posted,
         edited,
         vote,
         writer,
-        content
+        writer_id,
+        content,
+        body,
     })
 }
 
 // take a document of an article gallery, return list of link to image of the page
+// along with its thumbnail
 //
 // NOTE: this function can only get 40 images in maximum at a time. get document
 // of another page and call this again to obtain all images.
-pub fn image_list(doc: &Document)
-    -> Result<Vec<String>, Box<dyn Error>> {
-    let mut images = Vec::new();
+pub fn previews(doc: &Document)
+    -> Result<Vec<Preview>, ErrorBox> {
+    let mut previews = Vec::new();
     
     // is finding from id faster? i can just find by class as well...
-    let list = doc.find(Attr("id", "gdt")).nth(0).unwrap();
+    let list = by_id(doc, "previews", "gdt")?;
 
     for node in list.children() {
-        // advert!
-        if node.attr("class") != Some("gdtm") {
-            continue;
-        }
-
-        let link = node
-            .first_child().unwrap()
-            .first_child().unwrap()
-            .attr("href").unwrap()
-            .to_string();
+        let preview = match node.attr("class") {
+            // <div class="gdtm"><div style="...url(sprite) -100px 0 ...">
+            // <a href="(image page)"><img ...></a></div></div>
+            Some("gdtm") => {
+                let sprite = node.first_child();
+
+                let href = sprite
+                    .and_then(|sprite| sprite.first_child())
+                    .and_then(|link| link.attr("href"))
+                    .ok_or_else(|| missing("preview link", "#gdt .gdtm a[href]", &node))?;
+
+                let style = sprite.and_then(|sprite| sprite.attr("style")).unwrap_or("");
+
+                let (url, crop) = match Crop::from_style(style) {
+                    Some((url, crop)) => (url, Some(crop)),
+                    None => (String::new(), None),
+                };
+
+                Preview {
+                    entry: href.parse::<ImageEntry>()?,
+                    url,
+                    large: false,
+                    crop,
+                }
+            },
+
+            // <div class="gdtl"><a href="(image page)"><img src="(thumb)"></a></div>
+            Some("gdtl") => {
+                let href = node
+                    .first_child()
+                    .and_then(|link| link.attr("href"))
+                    .ok_or_else(|| missing("preview link", "#gdt .gdtl a[href]", &node))?;
+
+                let url = node
+                    .find(Name("img"))
+                    .nth(0)
+                    .and_then(|img| img.attr("src"))
+                    .unwrap_or("")
+                    .to_owned();
+
+                Preview {
+                    entry: href.parse::<ImageEntry>()?,
+                    url,
+                    large: true,
+                    crop: None,
+                }
+            },
+
+            // advert!
+            _ => continue
+        };
 
-        images.push(link);
+        previews.push(preview);
     }
     
-    Ok(images)
+    Ok(previews)
+}
+
+// newer versions of a gallery, which its page links to at the bottom:
+// "There are newer versions of this gallery available:" and then links
+// like "<a href=...>title</a>, added 2021-03-04 12:34" one by one
+pub fn newer_versions(doc: &Document) -> Vec<NewerVersion> {
+    doc.find(Attr("id", "gnd").descendant(Name("a")))
+        .filter_map(|link| {
+            let posted = link.next()
+                .and_then(|node| node.as_text())
+                .map_or("", |text| text.trim().trim_start_matches(',').trim())
+                .trim_start_matches("added")
+                .trim();
+
+            Some(NewerVersion {
+                path: link.attr("href")?.to_owned(),
+                title: link.text(),
+                posted: posted.to_owned(),
+            })
+        })
+        .collect()
 }
 
 // get the actual path to image
 pub fn image(doc: &Document)
-    -> Result<String, Box<dyn Error>> {
-    Ok(
-        doc
-        .find(Attr("id", "img"))
-        .nth(0).unwrap()
-        .attr("src").unwrap()
-        .to_string()
-    )
+    -> Result<String, ErrorBox> {
+    let img = by_id(doc, "image", "img")?;
+
+    img.attr("src")
+        .map(|x| x.to_owned())
+        .ok_or_else(|| missing("image", "#img[src]", &img))
+}
+
+// the key of the "reload broken image" link on an image page, like
+// "12345-678". the page asked with "?nl=(key)" has another server's
+// image, since the one which gave it was bad
+pub fn image_reload(doc: &Document) -> Result<String, ErrorBox> {
+    let link = by_id(doc, "reload key", "loadfail")?;
+
+    // it's like "return nl('12345-678')"
+    link.attr("onclick")
+        .and_then(|x| x.split("nl('").nth(1))
+        .and_then(|x| x.split('\'').nth(0))
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_owned())
+        .ok_or_else(|| missing("reload key", "#loadfail[onclick]", &link))
+}
+
+// get the full sha-1 hash of the image from its image page
+pub fn image_hash(doc: &Document)
+    -> Result<Option<String>, ErrorBox> {
+    // "show all galleries with this file" link is like "?f_shash=(hash)&..."
+    let hash = doc
+        .find(Attr("id", "i6").descendant(Name("a")))
+        .filter_map(|node| node.attr("href"))
+        .find_map(|href| href.split("f_shash=").nth(1))
+        .map(|text| text.split('&').nth(0).unwrap().to_owned());
+
+    Ok(hash)
+}
+
+// take a document of a profile page in the forum, return the profile
+pub fn profile(doc: &Document, id: u64) -> Result<Profile, ErrorBox> {
+    // the title would be like "Viewing Profile -> (name) - E-Hentai Forums"
+    let name = doc
+        .find(Name("title"))
+        .nth(0)
+        .ok_or_else(|| ParseError {
+            field: "name".to_owned(),
+            selector: "title".to_owned(),
+            url: None,
+            snippet: String::new(),
+        })?
+        .text();
+
+    let name = name.strip_prefix("Viewing Profile -> ").unwrap_or(&name);
+    let name = name.rsplitn(2, " - ").last().unwrap_or(name).trim().to_owned();
+
+    // labels are in a cell and values are in the next one
+    let mut fields = Vec::new();
+
+    for cell in doc.find(Name("td")) {
+        let label = cell.text();
+
+        let label = match label.trim().strip_suffix(':') {
+            Some(label) if !label.is_empty() => label.trim().to_owned(),
+            _ => continue
+        };
+
+        let mut next = cell.next();
+
+        // skip whitespaces between the cells
+        while let Some(node) = next {
+            if node.name().is_some() {
+                break;
+            }
+
+            next = node.next();
+        }
+
+        if let Some(node) = next {
+            fields.push((label, node.text().trim().to_owned()));
+        }
+    }
+
+    fn find(fields: &[(String, String)], pred: impl Fn(&str) -> bool)
+        -> Option<String> {
+        fields
+            .iter()
+            .find(|(label, _)| pred(&label.to_ascii_lowercase()))
+            .map(|(_, value)| value.clone())
+    }
+
+    let joined = find(&fields, |label| label == "joined");
+    let group = find(&fields, |label| label.ends_with("group"));
+    let standing = find(&fields, |label| {
+        label.contains("standing") || label.contains("warn")
+    });
+
+    // it would be like "1,234 (0.5 per day)"
+    let posts = find(&fields, |label| label == "posts")
+        .and_then(|text| text
+            .split_ascii_whitespace()
+            .nth(0)
+            .and_then(|x| x.replace(',', "").parse::<usize>().ok()));
+
+    Ok(Profile {
+        id,
+        name,
+        joined,
+        posts,
+        group,
+        standing,
+        fields,
+    })
+}
+
+// take a document of the member list in the forum,
+// return the id of the member of the name
+pub fn member_id(doc: &Document, name: &str) -> Result<Option<u64>, ErrorBox> {
+    let id = doc
+        .find(Name("a"))
+        .filter(|node| node.text().trim().eq_ignore_ascii_case(name))
+        .filter_map(|node| node.attr("href"))
+        .filter_map(|href| href.split("showuser=").nth(1))
+        .find_map(|id| id.split('&').nth(0).unwrap().parse::<u64>().ok());
+
+    Ok(id)
+}
+
+// take a document of an ehwiki page, return what the page says about the tag
+pub fn tag_wiki(doc: &Document, tag: &Tag, path: String)
+    -> Result<Option<TagWiki>, ErrorBox> {
+    // the wiki shows this instead of the content when there is no page
+    if doc.find(Class("noarticletext")).next().is_some() {
+        return Ok(None);
+    }
+
+    let content = match doc.find(Class("mw-parser-output")).nth(0) {
+        Some(node) => node,
+        None => return Ok(None),
+    };
+
+    // text before the first heading doesn't have a heading, obviously
+    let mut sections = vec![(String::new(), String::new())];
+    let mut related = Vec::new();
+    let mut examples = Vec::new();
+
+    for node in content.children() {
+        match node.name() {
+            Some("h2") | Some("h3") | Some("h4") => {
+                let heading = node
+                    .find(Class("mw-headline"))
+                    .nth(0)
+                    .map_or_else(|| node.text(), |x| x.text());
+
+                sections.push((heading.trim().to_owned(), String::new()));
+                continue;
+            },
+            // table of contents, notices and such
+            Some("div") | Some("table") | Some("style") | None => continue,
+            Some(_) => (),
+        }
+
+        let (heading, text) = sections.last_mut().unwrap();
+        let line = node.text();
+
+        if !line.trim().is_empty() {
+            if !text.is_empty() {
+                text.push('\n');
+            }
+
+            text.push_str(line.trim());
+        }
+
+        for link in node.find(Name("a")) {
+            let href = link.attr("href").unwrap_or("");
+
+            if let Ok(id) = href.parse::<GalleryId>() {
+                examples.push(id);
+            } else if href.starts_with("/wiki/")
+                && heading.to_lowercase().contains("related") {
+                related.push(link.text().trim().to_owned());
+            }
+        }
+    }
+
+    let description = sections
+        .iter()
+        .find(|(heading, text)| {
+            !text.is_empty() && (heading.is_empty() || heading == "Description")
+        })
+        .map(|(_, text)| text.split('\n').nth(0).unwrap().to_owned())
+        .unwrap_or_default();
+
+    // drop the headless one if there was nothing before the first heading
+    sections.retain(|(heading, text)| !heading.is_empty() || !text.is_empty());
+
+    Ok(Some(TagWiki {
+        tag: tag.clone(),
+        path,
+        description,
+        related,
+        examples,
+        sections,
+    }))
+}
+
+// take a document with a form, return the fields the form would submit as is
+pub fn form_fields(doc: &Document) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+
+    for node in doc.find(Name("input").or(Name("select")).or(Name("textarea"))) {
+        let name = match node.attr("name") {
+            Some(name) => name.to_owned(),
+            None => continue
+        };
+
+        let value = match node.name() {
+            Some("select") => {
+                let options = node.find(Name("option")).collect::<Vec<_>>();
+
+                // the first one is selected if none is
+                options.iter()
+                    .find(|option| option.attr("selected").is_some())
+                    .or_else(|| options.first())
+                    .map(|option| match option.attr("value") {
+                        Some(value) => value.to_owned(),
+                        None => option.text(),
+                    })
+            },
+            Some("textarea") => Some(node.text()),
+            _ => match node.attr("type").unwrap_or("text") {
+                "radio" | "checkbox" => node
+                    .attr("checked")
+                    .map(|_| node.attr("value").unwrap_or("on").to_owned()),
+                "submit" | "button" | "image" | "reset" => None,
+                _ => Some(node.attr("value").unwrap_or("").to_owned()),
+            }
+        };
+
+        if let Some(value) = value {
+            fields.push((name, value));
+        }
+    }
+
+    fields
+}
+
+// take a document of home.php, return the image limit of the account
+pub fn image_limit(doc: &Document) -> Result<ImageLimit, ErrorBox> {
+    // numbers are in bold, like "You are currently at <strong>13</strong>
+    // towards a limit of <strong>5,000</strong>."
+    let numbers = |node: Node| {
+        node.find(Name("strong"))
+            .filter_map(|x| x.text().trim().replace(',', "").parse::<u64>().ok())
+            .collect::<Vec<_>>()
+    };
+
+    let find = |text: &str| {
+        doc.find(Name("p"))
+            .find(|node| node.text().contains(text))
+            .map(numbers)
+    };
+
+    let (used, limit) = match find("towards a limit of").as_deref() {
+        Some([used, limit, ..]) => (*used, *limit),
+        // not logged in, probably
+        _ => return Err(EhError::NotFound("image limits".to_owned()).into())
+    };
+
+    let first = |numbers: Option<Vec<u64>>| numbers.and_then(|x| x.first().copied());
+
+    Ok(ImageLimit {
+        used,
+        limit,
+        regen_per_minute: first(find("regenerates at a rate of")),
+        reset_cost: first(find("Reset Cost")),
+    })
+}
+
+// take a document of the exchange, return the gp and credits the account has.
+// they're written like "Available: 1,234 Credits" and "Available: 56 kGP"
+pub fn funds(doc: &Document) -> Result<Funds, ErrorBox> {
+    let text = doc
+        .find(Name("body"))
+        .nth(0)
+        .map_or_else(String::new, |body| body.text());
+
+    let mut gp = None;
+    let mut credits = None;
+
+    for part in text.split("Available:").skip(1) {
+        let mut words = part.split_whitespace();
+
+        let amount = match words.next() {
+            Some(word) => word.replace(',', ""),
+            None => continue
+        };
+
+        let amount = match amount.parse::<u64>() {
+            Ok(amount) => amount,
+            Err(_) => continue
+        };
+
+        match words.next() {
+            Some("Credits") => { credits.get_or_insert(amount); },
+            Some("kGP") => { gp.get_or_insert(amount * 1000); },
+            Some("GP") => { gp.get_or_insert(amount); },
+            _ => ()
+        }
+    }
+
+    match (gp, credits) {
+        (Some(gp), Some(credits)) => Ok(Funds { gp, credits }),
+        // not logged in, probably
+        _ => Err(EhError::NotFound("funds".to_owned()).into())
+    }
+}
+
+// take a document for a list page, return whether the pager links to
+// the next page. the last cell of the pager is "&gt;", linked or not
+pub fn has_next_page(doc: &Document) -> bool {
+    if doc.find(Attr("id", "dnext")).any(|node| node.attr("href").is_some()) {
+        return true;
+    }
+
+    doc.find(Name("table").and(Class("ptt")))
+        .nth(0)
+        .and_then(|pager| pager.find(Name("td")).last())
+        .map_or(false, |cell| cell.find(Name("a")).next().is_some())
+}
+
+// take a document of favorites.php, return the galleries in the list
+pub fn favorites(doc: &Document) -> Result<Option<Vec<Favorite>>, ErrorBox> {
+    let table = match doc.find(Name("table").and(Class("itg"))).nth(0) {
+        Some(table) => table,
+        // no favorites in the category
+        None => return Ok(None),
+    };
+
+    let mut list = Vec::new();
+
+    for row in table.find(Name("tr")) {
+        // the header and adverts don't have a title
+        if row.find(Class("glink")).nth(0).is_none() {
+            continue;
+        }
+
+        let meta = loose_row(&row)?;
+        let category = meta.favorite.unwrap_or(0);
+
+        // the date of posting is marked with the color of the category
+        let posted = row
+            .find(Name("div"))
+            .find(|node| node.attr("id").map_or(false, |id| id.starts_with("posted_")))
+            .ok_or_else(|| missing("date of posting", "div[id^=posted_]", &row))?;
+
+        let category_name = posted
+            .attr("title")
+            .map_or_else(|| format!("Favorites {}", category), |x| x.to_owned());
+
+        let note = row
+            .find(Class("glfnote"))
+            .nth(0)
+            .map(|node| node.text())
+            .map(|text| text.trim().trim_start_matches("Note:").trim().to_owned())
+            .filter(|text| !text.is_empty());
+
+        // the date and the time are in separate paragraphs
+        let favorited = row
+            .find(Class("glfav"))
+            .nth(0)
+            .map(|node| {
+                node.find(Name("p"))
+                    .map(|p| p.text().trim().to_owned())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default();
+
+        list.push(Favorite {
+            meta,
+            category,
+            category_name,
+            note,
+            favorited,
+        });
+    }
+
+    Ok(Some(list))
+}
+
+// take a document of mytags.php, return the tags in the list
+pub fn my_tags(doc: &Document) -> Result<Vec<WatchedTag>, ErrorBox> {
+    let input = |name: &str| doc.find(Name("input").and(Attr("name", name))).nth(0);
+
+    // every tag has a checkbox named like "tagwatch_1234"
+    let ids = doc
+        .find(Name("input"))
+        .filter_map(|node| node.attr("name"))
+        .filter_map(|name| name.strip_prefix("tagwatch_"))
+        .filter_map(|id| id.parse::<u64>().ok())
+        .collect::<Vec<_>>();
+
+    let mut list = Vec::new();
+
+    for id in ids {
+        let preview = format!("tagpreview_{}", id);
+
+        let tag = doc
+            .find(Attr("id", preview.as_str()))
+            .nth(0)
+            .and_then(|node| node.attr("title"))
+            .ok_or(ParseTagError())?
+            .parse::<Tag>()?;
+
+        let checked = |name: String| {
+            input(&name).map_or(false, |node| node.attr("checked").is_some())
+        };
+
+        let value = |name: String| {
+            input(&name)
+                .and_then(|node| node.attr("value"))
+                .map(|value| value.trim().to_owned())
+                .filter(|value| !value.is_empty())
+        };
+
+        list.push(WatchedTag {
+            id,
+            tag,
+            watch: checked(format!("tagwatch_{}", id)),
+            hide: checked(format!("taghide_{}", id)),
+            color: value(format!("tagcolor_{}", id)),
+            weight: value(format!("tagweight_{}", id))
+                .and_then(|weight| weight.parse().ok())
+                .unwrap_or(0),
+        });
+    }
+
+    Ok(list)
+}
+
+// a variable in the scripts of the site, like "var apiuid = 1234;"
+fn script_var(doc: &Document, name: &str) -> Option<String> {
+    let pattern = format!("var {} ", name);
+
+    doc.find(Name("script"))
+        .map(|node| node.text())
+        .find_map(|script| {
+            let rest = &script[script.find(&pattern)?..];
+            let value = rest[rest.find('=')? + 1..].split(';').nth(0)?;
+
+            Some(value.trim().trim_matches('"').to_owned())
+        })
+}
+
+// take a document with the scripts of the site, return the key to use
+// the api as the account
+pub fn api_key(doc: &Document) -> Option<ApiKey> {
+    Some(ApiKey {
+        uid: script_var(doc, "apiuid")?.parse().ok()?,
+        key: script_var(doc, "apikey")?,
+    })
+}
+
+// take a document of a gallery, return its rating. the stars are drawn
+// by the vote of the account if there is, in a color other than the usual
+pub fn rating(doc: &Document) -> Option<Rating> {
+    let average = script_var(doc, "average_rating")?.parse().ok()?;
+    let count = doc
+        .find(Attr("id", "rating_count"))
+        .nth(0)?
+        .text()
+        .parse()
+        .ok()?;
+
+    let voted = doc
+        .find(Attr("id", "rating_image"))
+        .nth(0)
+        .and_then(|node| node.attr("class"))
+        .map_or(false, |class| class.split_whitespace().any(|x| x != "ir"));
+
+    let own_vote = match voted {
+        true => script_var(doc, "display_rating").and_then(|x| x.parse().ok()),
+        false => None
+    };
+
+    Some(Rating {
+        average,
+        count,
+        own_vote,
+    })
+}
+
+// take a document of a gallery, return the link to its archiver. it's in
+// a script like "return popUp('https://e-hentai.org/archiver.php?...',480,320)"
+pub fn archiver_path(doc: &Document) -> Option<String> {
+    doc.find(Name("a"))
+        .filter_map(|node| node.attr("onclick"))
+        .find(|script| script.contains("archiver.php"))
+        .and_then(|script| script.split('\'').find(|part| part.contains("archiver.php")))
+        .map(|path| path.replace("&amp;", "&"))
+}
+
+// take a document the archiver gave after a request, return what it said
+pub fn archiver_message(doc: &Document) -> String {
+    let node = doc
+        .find(Attr("id", "db"))
+        .nth(0)
+        .or_else(|| doc.find(Name("body")).nth(0));
+
+    let text = node.map_or_else(String::new, |node| node.text());
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// take a document of hentaiathome.php, return the clients of the account
+pub fn hath_clients(doc: &Document) -> Vec<HathClient> {
+    const STATUS: [&str; 4] = ["Online", "Offline", "Suspended", "Hibernating"];
+
+    let mut list = Vec::new();
+
+    for row in doc.find(Name("tr")) {
+        // the name links to the settings, like "?cid=1234&act=settings"
+        let link = row
+            .find(Name("a"))
+            .find(|node| node.attr("href").map_or(false, |x| x.contains("cid=")));
+
+        let link = match link {
+            Some(link) => link,
+            None => continue
+        };
+
+        let id = link
+            .attr("href")
+            .and_then(|href| href.split("cid=").nth(1))
+            .and_then(|id| id.split('&').nth(0).unwrap().parse::<u64>().ok());
+
+        let id = match id {
+            Some(id) => id,
+            None => continue
+        };
+
+        let status = row
+            .find(Name("td"))
+            .map(|cell| cell.text().trim().to_owned())
+            .find(|text| STATUS.contains(&text.as_str()))
+            .unwrap_or_default();
+
+        list.push(HathClient {
+            id,
+            name: link.text().trim().to_owned(),
+            status,
+        });
+    }
+
+    list
 }
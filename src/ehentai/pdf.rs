@@ -0,0 +1,280 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::io;
+use std::io::Write;
+use std::error::Error;
+use futures::stream::{self, StreamExt};
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use hyper::body::Bytes;
+
+use super::article::Article;
+use super::export::ExportOptions;
+use super::image::{ImageFormat, ImageData};
+use super::queue::Priority;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+// a tiny pdf writer which knows nothing but putting objects in a row
+struct PdfWriter<W: Write> {
+    inner: W,
+    offset: usize,
+    // byte offset of each object; object n is at [n - 1]
+    xref: Vec<usize>,
+}
+
+impl<W: Write> PdfWriter<W> {
+    fn new(inner: W) -> io::Result<Self> {
+        let mut writer = Self {
+            inner,
+            offset: 0,
+            xref: Vec::new(),
+        };
+
+        // the binary comment tells it's not a text file
+        writer.write(b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n")?;
+        Ok(writer)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.inner.write_all(bytes)?;
+        self.offset += bytes.len();
+        Ok(())
+    }
+
+    fn object(&mut self, id: usize, dict: &str, stream: Option<&[u8]>)
+        -> io::Result<()> {
+        if self.xref.len() < id {
+            self.xref.resize(id, 0);
+        }
+
+        self.xref[id - 1] = self.offset;
+        self.write(format!("{} 0 obj\n", id).as_bytes())?;
+
+        if let Some(data) = stream {
+            self.write(format!("<< {} /Length {} >>\nstream\n", dict, data.len()).as_bytes())?;
+            self.write(data)?;
+            self.write(b"\nendstream\n")?;
+        } else {
+            self.write(format!("{}\n", dict).as_bytes())?;
+        }
+
+        self.write(b"endobj\n")
+    }
+
+    fn finish(mut self, root: usize, info: Option<usize>) -> io::Result<W> {
+        let start = self.offset;
+        let size = self.xref.len() + 1;
+
+        // every entry must be exactly 20 bytes long
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", size);
+
+        for offset in self.xref.iter() {
+            table.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+
+        let info = info
+            .map(|id| format!(" /Info {} 0 R", id))
+            .unwrap_or_default();
+
+        table.push_str(&format!(
+            "trailer\n<< /Size {} /Root {} 0 R{} >>\nstartxref\n{}\n%%EOF\n",
+            size, root, info, start
+        ));
+
+        self.write(table.as_bytes())?;
+        Ok(self.inner)
+    }
+}
+
+// text strings in utf-16 so that non-ascii titles survive
+fn text_string(text: &str) -> String {
+    let mut res = String::from("<FEFF");
+
+    for unit in text.encode_utf16() {
+        res.push_str(&format!("{:04X}", unit));
+    }
+
+    res.push('>');
+    res
+}
+
+// get width, height and number of color components from a jpeg header
+fn jpeg_info(data: &[u8]) -> Option<(u16, u16, u8)> {
+    let be16 = |pos: usize| -> Option<u16> {
+        Some(u16::from_be_bytes([*data.get(pos)?, *data.get(pos + 1)?]))
+    };
+
+    // skip the SOI marker
+    let mut pos = 2;
+
+    loop {
+        if *data.get(pos)? != 0xff {
+            return None;
+        }
+
+        let marker = *data.get(pos + 1)?;
+
+        match marker {
+            // padding
+            0xff => pos += 1,
+            // markers without any payload
+            0x01 | 0xd0..=0xd7 => pos += 2,
+            // start of frame; what we want (c4, c8 and cc are not frames)
+            0xc0..=0xcf if marker != 0xc4 && marker != 0xc8 && marker != 0xcc => {
+                let height = be16(pos + 5)?;
+                let width = be16(pos + 7)?;
+                let components = *data.get(pos + 9)?;
+
+                return Some((width, height, components));
+            },
+            _ => pos += 2 + be16(pos + 2)? as usize
+        }
+    }
+}
+
+// an image ready to be put in a pdf
+struct PdfImage {
+    width: u32,
+    height: u32,
+    dict: String,
+    data: Bytes,
+}
+
+impl PdfImage {
+    fn new(image: ImageData) -> Result<Self, ErrorBox> {
+        // jpeg can go in as it is; pdf understands it natively
+        if image.format == Some(ImageFormat::Jpeg) {
+            if let Some((width, height, components)) = jpeg_info(&image.data) {
+                let color = match components {
+                    1 => "/DeviceGray",
+                    // adobe writes cmyk jpegs inverted
+                    4 => "/DeviceCMYK /Decode [1 0 1 0 1 0 1 0]",
+                    _ => "/DeviceRGB"
+                };
+
+                return Ok(Self {
+                    width: width as u32,
+                    height: height as u32,
+                    dict: format!(
+                        "/Type /XObject /Subtype /Image /Width {} /Height {} \
+                         /ColorSpace {} /BitsPerComponent 8 /Filter /DCTDecode",
+                        width, height, color
+                    ),
+                    data: image.data,
+                });
+            }
+        }
+
+        // anything else is decoded and put as compressed raw pixels
+        let decoded = ::image::load_from_memory(&image.data)?.to_rgb8();
+        let (width, height) = decoded.dimensions();
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(decoded.as_raw())?;
+
+        Ok(Self {
+            width,
+            height,
+            dict: format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} \
+                 /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode",
+                width, height
+            ),
+            data: Bytes::from(encoder.finish()?),
+        })
+    }
+}
+
+impl Article {
+    // download the whole article into a pdf file, one image per page.
+    // the images are not resampled; a page is as large as its image
+    //
+    // NOTE: writing into `writer` blocks, so give it something fast
+    // (a file or a buffer) rather than a socket.
+    pub async fn export_pdf<W: Write>(&self, writer: W,
+        options: &ExportOptions) -> Result<W, ErrorBox> {
+        Priority::Download.or_current(self.write_pdf(writer, options)).await
+    }
+
+    async fn write_pdf<W: Write>(&self, writer: W,
+        options: &ExportOptions) -> Result<W, ErrorBox> {
+        const CATALOG: usize = 1;
+        const PAGES: usize = 2;
+        const INFO: usize = 3;
+
+        let len = self.meta().length;
+        let mut pdf = PdfWriter::new(writer)?;
+
+        self.load_image_list().await?;
+
+        let progress = &options.progress;
+        progress.set_total(len);
+
+        let control = &options.control;
+
+        let mut images = stream::iter(0..len)
+            .map(|index| async move {
+                control.proceed().await?;
+                self.load_image_data(index, progress.clone()).await
+            })
+            .buffered(options.concurrency.max(1));
+
+        let mut kids = Vec::new();
+
+        while let Some(image) = images.next().await {
+            let image = PdfImage::new(image?)?;
+
+            // each page takes 3 objects; the page, its content and the image
+            let page = INFO + 1 + kids.len() * 3;
+            let (content, xobject) = (page + 1, page + 2);
+
+            pdf.object(xobject, &image.dict, Some(&image.data[..]))?;
+
+            let draw = format!(
+                "q {} 0 0 {} 0 0 cm /Im0 Do Q",
+                image.width, image.height
+            );
+
+            pdf.object(content, "", Some(draw.as_bytes()))?;
+
+            pdf.object(page, &format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>",
+                PAGES, image.width, image.height, xobject, content
+            ), None)?;
+
+            kids.push(format!("{} 0 R", page));
+        }
+
+        pdf.object(PAGES, &format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "), kids.len()
+        ), None)?;
+
+        pdf.object(CATALOG, &format!(
+            "<< /Type /Catalog /Pages {} 0 R >>", PAGES
+        ), None)?;
+
+        let info = if options.metadata {
+            let meta = self.meta();
+
+            pdf.object(INFO, &format!(
+                "<< /Title {} /Author {} /Subject {} >>",
+                text_string(&meta.title),
+                text_string(&meta.uploader),
+                text_string(&meta.path)
+            ), None)?;
+
+            Some(INFO)
+        } else {
+            // leave an empty one; the object number is taken anyway
+            pdf.object(INFO, "<< >>", None)?;
+            None
+        };
+
+        Ok(pdf.finish(CATALOG, info)?)
+    }
+}
@@ -0,0 +1,31 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+// a member profile in the forum, which is where accounts live
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub id: u64,
+    pub name: String,
+    pub joined: Option<String>,
+    pub posts: Option<usize>,
+    pub group: Option<String>,
+    pub standing: Option<String>,
+
+    // every "label: value" pair of the profile, in case what you want
+    // isn't above; the forum shows different fields for each member
+    pub fields: Vec<(String, String)>,
+}
+
+impl Profile {
+    pub fn path(&self) -> String {
+        format!("https://forums.e-hentai.org/index.php?showuser={}", self.id)
+    }
+
+    pub fn field(&self, label: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(label))
+            .map(|(_, value)| value.as_str())
+    }
+}
@@ -0,0 +1,170 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use std::collections::VecDeque;
+
+// how far back the speed of now looks
+const SPEED_WINDOW: Duration = Duration::from_secs(10);
+
+// a plain copy of the counters at some moment
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProgressState {
+    // number of images to be downloaded in total
+    pub total: usize,
+    // image pages resolved into the actual path to the image
+    pub pages_fetched: usize,
+    pub images_downloaded: usize,
+    pub bytes: u64,
+    pub failures: usize,
+
+    // since the download started
+    pub elapsed: Duration,
+    // bytes per second in the last few seconds, and since it started
+    pub speed: f64,
+    pub average_speed: f64,
+    // how long the rest would take at the speed of now, if anything is
+    // downloaded yet to tell the size of an image
+    pub eta: Option<Duration>,
+}
+
+impl ProgressState {
+    pub fn remaining(&self) -> usize {
+        self.total.saturating_sub(self.images_downloaded + self.failures)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Timing {
+    started: Option<Instant>,
+    // images as they came in lately, with their sizes
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl Timing {
+    fn start(&mut self, now: Instant) {
+        self.started.get_or_insert(now);
+    }
+
+    fn forget(&mut self, now: Instant) {
+        while let Some(&(at, _)) = self.samples.front() {
+            if now.duration_since(at) <= SPEED_WINDOW {
+                break;
+            }
+
+            self.samples.pop_front();
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    total: AtomicUsize,
+    pages_fetched: AtomicUsize,
+    images_downloaded: AtomicUsize,
+    bytes: AtomicU64,
+    failures: AtomicUsize,
+    timing: Mutex<Timing>,
+}
+
+// a handle to watch how a download is going. clone it, give one to
+// a downloading function and read the other from wherever
+#[derive(Debug, Default, Clone)]
+pub struct Progress {
+    inner: Arc<Counters>,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> ProgressState {
+        let mut state = ProgressState {
+            total: self.inner.total.load(Ordering::Relaxed),
+            pages_fetched: self.inner.pages_fetched.load(Ordering::Relaxed),
+            images_downloaded: self.inner.images_downloaded.load(Ordering::Relaxed),
+            bytes: self.inner.bytes.load(Ordering::Relaxed),
+            failures: self.inner.failures.load(Ordering::Relaxed),
+            ..ProgressState::default()
+        };
+
+        let now = Instant::now();
+        let mut timing = self.inner.timing.lock().unwrap();
+        timing.forget(now);
+
+        let started = match timing.started {
+            Some(started) => started,
+            None => return state,
+        };
+
+        state.elapsed = now.duration_since(started);
+
+        // a download younger than the window is measured by its age
+        let window = state.elapsed.min(SPEED_WINDOW).as_secs_f64();
+        let recent = timing.samples.iter().map(|&(_, bytes)| bytes).sum::<u64>();
+
+        if window > 0.0 {
+            state.speed = recent as f64 / window;
+            state.average_speed = state.bytes as f64 / state.elapsed.as_secs_f64();
+        }
+
+        // nothing coming lately isn't the same as never coming
+        let speed = match state.speed > 0.0 {
+            true => state.speed,
+            false => state.average_speed,
+        };
+
+        state.eta = match (state.remaining(), state.images_downloaded) {
+            (0, _) => Some(Duration::from_secs(0)),
+            (_, 0) => None,
+            (remaining, done) if speed > 0.0 => {
+                let per_image = state.bytes as f64 / done as f64;
+                Some(Duration::from_secs_f64(remaining as f64 * per_image / speed))
+            },
+            _ => None,
+        };
+
+        state
+    }
+
+    // whether every image has been either downloaded or failed
+    pub fn is_done(&self) -> bool {
+        let state = self.state();
+        state.images_downloaded + state.failures >= state.total
+    }
+
+    // the clock starts with whichever comes first of these
+    fn start(&self) {
+        self.inner.timing.lock().unwrap().start(Instant::now());
+    }
+
+    pub(super) fn set_total(&self, total: usize) {
+        self.inner.total.store(total, Ordering::Relaxed);
+        self.start();
+    }
+
+    pub(super) fn page_fetched(&self) {
+        self.inner.pages_fetched.fetch_add(1, Ordering::Relaxed);
+        self.start();
+    }
+
+    pub(super) fn image_downloaded(&self, bytes: usize) {
+        self.inner.images_downloaded.fetch_add(1, Ordering::Relaxed);
+        self.inner.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+
+        let now = Instant::now();
+        let mut timing = self.inner.timing.lock().unwrap();
+
+        timing.start(now);
+        timing.samples.push_back((now, bytes as u64));
+        timing.forget(now);
+    }
+
+    pub(super) fn failed(&self) {
+        self.inner.failures.fetch_add(1, Ordering::Relaxed);
+    }
+}
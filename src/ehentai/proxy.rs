@@ -0,0 +1,108 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::str;
+use std::error::Error;
+use std::task::{Context, Poll};
+use futures::future::{BoxFuture, FutureExt};
+use hyper::Uri;
+use hyper::service::Service;
+use hyper::client::connect::HttpConnector;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+// connects through an http proxy if there is, by asking it to CONNECT to
+// the destination. what goes through the tunnel is up to the connector
+// above, so it works for https (and the detour) as well
+#[derive(Clone)]
+pub(super) struct ProxyConnector {
+    http: HttpConnector,
+    proxy: Option<Uri>,
+}
+
+impl ProxyConnector {
+    pub fn new(mut http: HttpConnector, proxy: Option<Uri>) -> Self {
+        // the tls connector above takes care of the scheme
+        http.enforce_http(false);
+
+        Self {
+            http,
+            proxy,
+        }
+    }
+}
+
+async fn tunnel(stream: &mut TcpStream, dest: &Uri) -> Result<(), ErrorBox> {
+    let host = dest.host().ok_or("destination without host")?;
+    let port = dest.port_u16().unwrap_or(match dest.scheme_str() {
+        Some("http") => 80,
+        _ => 443,
+    });
+
+    let request = format!(
+        "CONNECT {0}:{1} HTTP/1.1\r\nHost: {0}:{1}\r\n\r\n",
+        host, port
+    );
+
+    stream.write_all(request.as_bytes()).await?;
+
+    // read the response header, which is all there is before the tunnel.
+    // a byte at a time, to be sure not to read over the header
+    let mut header = Vec::new();
+    let mut byte = [0; 1];
+
+    while !header.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte).await? == 0 {
+            return Err("proxy closed the connection".into());
+        }
+
+        header.push(byte[0]);
+
+        if header.len() > 8192 {
+            return Err("proxy gave too long header".into());
+        }
+    }
+
+    // the status line would be like "HTTP/1.1 200 Connection established"
+    let status = str::from_utf8(&header)?
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or("");
+
+    match status {
+        "200" => Ok(()),
+        _ => Err(format!("proxy refused to connect: {}", status).into())
+    }
+}
+
+impl Service<Uri> for ProxyConnector {
+    type Response = TcpStream;
+    type Error = ErrorBox;
+    type Future = BoxFuture<'static, Result<TcpStream, ErrorBox>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), ErrorBox>> {
+        self.http.poll_ready(cx).map_err(|err| err.into())
+    }
+
+    fn call(&mut self, dest: Uri) -> Self::Future {
+        match self.proxy.clone() {
+            None => {
+                let connecting = self.http.call(dest);
+                async move { Ok(connecting.await?) }.boxed()
+            },
+            Some(proxy) => {
+                let connecting = self.http.call(proxy);
+
+                async move {
+                    let mut stream = connecting.await?;
+                    tunnel(&mut stream, &dest).await?;
+
+                    Ok(stream)
+                }.boxed()
+            }
+        }
+    }
+}
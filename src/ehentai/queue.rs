@@ -0,0 +1,148 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::cmp::Reverse;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use std::collections::BTreeSet;
+use hyper::{Body, Request};
+use tokio::sync::Notify;
+
+// which request goes first when several are waiting under the rate
+// limit. without a rate limit they all go right away
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    // prefetching, and whatever no one is waiting for yet
+    Background,
+    // images of a download, and their pages
+    Download,
+    // what someone is looking at
+    Interactive,
+}
+
+tokio::task_local! {
+    static PRIORITY: Priority;
+}
+
+impl Priority {
+    // send every request in `future` at this priority, like
+    // `Priority::Background.scope(article.download_to(..))`
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        PRIORITY.scope(self, future).await
+    }
+
+    // same as scope(), unless the caller has given one already
+    pub(super) async fn or_current<F: Future>(self, future: F) -> F::Output {
+        match PRIORITY.try_with(|_| ()) {
+            Ok(_) => future.await,
+            Err(_) => self.scope(future).await,
+        }
+    }
+
+    // what the task says, or by what's asked for if it doesn't
+    pub(super) fn of(req: &Request<Body>) -> Self {
+        PRIORITY.try_with(|priority| *priority).unwrap_or_else(|_| {
+            let image = req.headers()
+                .get("Accept")
+                .and_then(|value| value.to_str().ok())
+                .map_or(false, |mime| mime.starts_with("image/"));
+
+            match image {
+                true => Priority::Download,
+                false => Priority::Interactive,
+            }
+        })
+    }
+}
+
+enum Turn {
+    Now,
+    After(Duration),
+    // others are before us
+    Behind,
+}
+
+struct State {
+    // when the next request may be sent
+    next: Instant,
+    // the most urgent and then the oldest first
+    waiting: BTreeSet<(Reverse<Priority>, u64)>,
+    count: u64,
+}
+
+// requests waiting for their turn under the rate limit
+pub(super) struct Queue {
+    interval: Duration,
+    state: Mutex<State>,
+    // a turn was taken, or someone gave up waiting for one
+    changed: Notify,
+}
+
+// a place in the queue; it's left when the request goes, or when
+// the one who's waiting doesn't want to anymore
+struct Ticket<'a> {
+    queue: &'a Queue,
+    key: (Reverse<Priority>, u64),
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        self.queue.state.lock().unwrap().waiting.remove(&self.key);
+        self.queue.changed.notify_waiters();
+    }
+}
+
+impl Queue {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            state: Mutex::new(State {
+                next: Instant::now(),
+                waiting: BTreeSet::new(),
+                count: 0,
+            }),
+            changed: Notify::new(),
+        }
+    }
+
+    // wait until it's the turn of a request of the priority
+    pub async fn wait(&self, priority: Priority) {
+        let ticket = {
+            let mut state = self.state.lock().unwrap();
+            state.count += 1;
+
+            let key = (Reverse(priority), state.count);
+            state.waiting.insert(key);
+
+            Ticket { queue: self, key }
+        };
+
+        loop {
+            // made before looking, not to miss a change in between
+            let changed = self.changed.notified();
+
+            let turn = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+
+                if state.waiting.iter().next() != Some(&ticket.key) {
+                    Turn::Behind
+                } else if state.next <= now {
+                    state.next = now + self.interval;
+                    Turn::Now
+                } else {
+                    Turn::After(state.next - now)
+                }
+            };
+
+            match turn {
+                Turn::Now => break,
+                // it will be ours then, unless someone more urgent comes
+                Turn::After(wait) => tokio::time::sleep(wait).await,
+                Turn::Behind => changed.await,
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::error::Error;
+use serde_json::{json, Value};
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use super::client::Client;
+use super::id::GalleryId;
+use super::error::EhError;
+use super::mytags::ApiKey;
+use super::api;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+// the rating of a gallery, not rounded as the stars are
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Rating {
+    pub average: f64,
+    pub count: usize,
+    // what the account voted, from 0.5 to 5 by halves
+    pub own_vote: Option<f64>,
+}
+
+impl Rating {
+    // what "rategallery" answers, like
+    // {"rating_avg": 4.52, "rating_usr": 4.5, "rating_cnt": 123, ...}
+    pub(super) fn from_api(value: &Value) -> Self {
+        Self {
+            average: value["rating_avg"].as_f64().unwrap_or(0.0),
+            count: value["rating_cnt"].as_u64().unwrap_or(0) as usize,
+            own_vote: value["rating_usr"].as_f64().filter(|x| *x > 0.0),
+        }
+    }
+}
+
+// vote on the gallery by the account
+pub(super) async fn rate(client: &Client, id: &GalleryId, key: &ApiKey,
+    score: f64) -> Result<Rating, ErrorBox> {
+    // the api takes it in halves of a star
+    let halves = (score * 2.0).round();
+
+    if !(1.0..=10.0).contains(&halves) {
+        return Err(EhError::Api(format!("can't rate {} stars", score)).into());
+    }
+
+    let res = api::call_fresh(client, json!({
+        "method": "rategallery",
+        "apiuid": key.uid,
+        "apikey": key.key,
+        "gid": id.gid,
+        "token": id.token,
+        "rating": halves as u32,
+    })).await?;
+
+    Ok(Rating::from_api(&res))
+}
@@ -0,0 +1,98 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::error::Error;
+use std::sync::Arc;
+use std::collections::VecDeque;
+use tokio::task::JoinHandle;
+
+use super::article::Article;
+use super::image::ImageData;
+use super::progress::Progress;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+// reads images of an article in order like a person does, keeping the next
+// few images downloading in background while the current one is viewed
+pub struct Reader {
+    article: Arc<Article>,
+    // index of the next image to start downloading
+    next: usize,
+    prefetch: usize,
+    pending: VecDeque<(usize, JoinHandle<Result<ImageData, ErrorBox>>)>,
+}
+
+impl Reader {
+    pub fn new(article: Arc<Article>, start: usize, prefetch: usize) -> Self {
+        Self {
+            article,
+            next: start,
+            prefetch,
+            pending: VecDeque::new(),
+        }
+    }
+
+    // index of the image next() would give
+    pub fn position(&self) -> usize {
+        self.pending.front().map_or(self.next, |(index, _)| *index)
+    }
+
+    // jump to another image, dropping whatever is being prefetched
+    pub fn seek(&mut self, index: usize) {
+        for (_, handle) in self.pending.drain(..) {
+            handle.abort();
+        }
+
+        self.next = index;
+    }
+
+    // start downloading until the window is full
+    fn fill(&mut self) {
+        let len = self.article.meta().length;
+
+        // the one to be given now plus `prefetch` more
+        while self.pending.len() <= self.prefetch && self.next < len {
+            let article = self.article.clone();
+            let index = self.next;
+
+            let handle = tokio::spawn(async move {
+                article.load_image_data(index, Progress::new()).await
+            });
+
+            self.pending.push_back((index, handle));
+            self.next += 1;
+        }
+    }
+
+    // the next image in order, waiting for it if it hasn't come yet.
+    // none after the last image
+    pub async fn next(&mut self) -> Option<Result<ImageData, ErrorBox>> {
+        self.fill();
+        let (_, handle) = self.pending.pop_front()?;
+
+        // keep the window full while the caller is busy with this one
+        self.fill();
+
+        match handle.await {
+            Ok(res) => Some(res),
+            Err(err) => Some(Err(err.into())),
+        }
+    }
+}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        for (_, handle) in self.pending.iter() {
+            handle.abort();
+        }
+    }
+}
+
+impl Article {
+    // read the article from `start`-th image, prefetching `prefetch` images
+    // ahead. the article is shared with the background downloads
+    pub fn reader(self: Arc<Self>, start: usize, prefetch: usize) -> Reader {
+        Reader::new(self, start, prefetch)
+    }
+}
@@ -0,0 +1,225 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::fmt;
+use std::str::FromStr;
+use std::error::Error;
+
+use super::tag::{ArticleKind, TagKind};
+
+pub(super) const UCONFIG: &str = "https://e-hentai.org/uconfig.php";
+
+#[derive(Debug)]
+pub struct ParseDisplayModeError();
+
+impl fmt::Display for ParseDisplayModeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Failed to parse display mode")
+    }
+}
+
+impl Error for ParseDisplayModeError {}
+
+// how search results are laid out, which changes how they're parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DisplayMode {
+    Minimal,
+    MinimalPlus,
+    Compact,
+    Extended,
+    Thumbnail,
+}
+
+// the site calls them by a letter in the form
+impl FromStr for DisplayMode {
+    type Err = ParseDisplayModeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "m" => Ok(DisplayMode::Minimal),
+            "p" => Ok(DisplayMode::MinimalPlus),
+            "l" => Ok(DisplayMode::Compact),
+            "e" => Ok(DisplayMode::Extended),
+            "t" => Ok(DisplayMode::Thumbnail),
+            _ => Err(ParseDisplayModeError())
+        }
+    }
+}
+
+impl fmt::Display for DisplayMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DisplayMode::Minimal => write!(f, "m"),
+            DisplayMode::MinimalPlus => write!(f, "p"),
+            DisplayMode::Compact => write!(f, "l"),
+            DisplayMode::Extended => write!(f, "e"),
+            DisplayMode::Thumbnail => write!(f, "t"),
+        }
+    }
+}
+
+// categories in the form, named like "ct_doujinshi"
+const KINDS: [(&str, ArticleKind); 10] = [
+    ("doujinshi", ArticleKind::Doujinshi),
+    ("manga", ArticleKind::Manga),
+    ("artistcg", ArticleKind::ArtistCG),
+    ("gamecg", ArticleKind::GameCG),
+    ("western", ArticleKind::Western),
+    ("non-h", ArticleKind::NonH),
+    ("imageset", ArticleKind::ImageSet),
+    ("cosplay", ArticleKind::Cosplay),
+    ("asianporn", ArticleKind::AsianPorn),
+    ("misc", ArticleKind::Misc),
+];
+
+// namespaces in the form, named by their number like "xn_6" for artists
+fn namespace(num: usize) -> Option<TagKind> {
+    let kind = match num {
+        1 => TagKind::Reclass,
+        2 => TagKind::Language,
+        3 => TagKind::Parody,
+        4 => TagKind::Character,
+        5 => TagKind::Group,
+        6 => TagKind::Artist,
+        7 => TagKind::Male,
+        8 => TagKind::Female,
+        9 => TagKind::Mixed,
+        10 => TagKind::Other,
+        11 => TagKind::Cosplayer,
+        _ => return None
+    };
+
+    Some(kind)
+}
+
+fn namespace_num(kind: &TagKind) -> Option<usize> {
+    (1..=11).find(|&num| namespace(num).as_ref() == Some(kind))
+}
+
+// choices of the form which are given by their index
+const THUMB_ROWS: [usize; 4] = [4, 10, 20, 40];
+const RESULTS_PER_PAGE: [usize; 4] = [25, 50, 100, 200];
+
+// the settings of the account in uconfig.php. only what changes how
+// the site looks to us is here; the rest is kept as it was
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub display_mode: DisplayMode,
+    pub large_thumbs: bool,
+    // rows of thumbnails in a gallery page
+    pub thumb_rows: usize,
+    pub results_per_page: usize,
+    // always open the multi-page viewer instead of image pages
+    pub always_mpv: bool,
+    // categories hidden from the front page and searches
+    pub excluded_kinds: Vec<ArticleKind>,
+    // numbered as the site does; the code of the language,
+    // plus 1024 if translated and 2048 if rewritten
+    pub excluded_languages: Vec<u32>,
+    // namespaces which are not searched without being named
+    pub excluded_namespaces: Vec<TagKind>,
+
+    // every field of the form as it was, so submitting it doesn't
+    // reset something we don't know about
+    form: Vec<(String, String)>,
+}
+
+impl Settings {
+    // read the settings from the fields the form would submit
+    pub(super) fn from_form(form: Vec<(String, String)>) -> Self {
+        let value = |name: &str| {
+            form.iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+
+        let index = |name: &str| {
+            value(name).and_then(|value| value.parse::<usize>().ok())
+        };
+
+        let excluded_kinds = KINDS
+            .iter()
+            .filter(|(name, _)| value(&format!("ct_{}", name)).is_some())
+            .map(|(_, kind)| *kind)
+            .collect();
+
+        let excluded_languages = form
+            .iter()
+            .filter_map(|(key, _)| key.strip_prefix("xl_"))
+            .filter_map(|code| code.parse().ok())
+            .collect();
+
+        let excluded_namespaces = form
+            .iter()
+            .filter_map(|(key, _)| key.strip_prefix("xn_"))
+            .filter_map(|num| num.parse().ok())
+            .filter_map(namespace)
+            .collect();
+
+        Self {
+            display_mode: value("dm")
+                .and_then(|mode| mode.parse().ok())
+                .unwrap_or(DisplayMode::Compact),
+            large_thumbs: value("ts") == Some("1"),
+            thumb_rows: index("tr")
+                .and_then(|i| THUMB_ROWS.get(i).copied())
+                .unwrap_or(THUMB_ROWS[0]),
+            results_per_page: index("rc")
+                .and_then(|i| RESULTS_PER_PAGE.get(i).copied())
+                .unwrap_or(RESULTS_PER_PAGE[0]),
+            always_mpv: value("qb") == Some("1"),
+            excluded_kinds,
+            excluded_languages,
+            excluded_namespaces,
+            form,
+        }
+    }
+
+    // the fields to submit, with what was changed in
+    pub(super) fn to_form(&self) -> Vec<(String, String)> {
+        let ours = |key: &str| {
+            ["dm", "ts", "tr", "rc", "qb"].contains(&key)
+                || key.starts_with("ct_")
+                || key.starts_with("xl_")
+                || key.starts_with("xn_")
+                || key == "apply"
+        };
+
+        let mut form = self.form
+            .iter()
+            .filter(|(key, _)| !ours(key))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        // choices not in the list go to the nearest one below
+        let nearest = |choices: &[usize], value: usize| {
+            choices.iter().rposition(|&x| x <= value).unwrap_or(0)
+        };
+
+        let mut push = |key: String, value: String| form.push((key, value));
+
+        push("dm".into(), self.display_mode.to_string());
+        push("ts".into(), (self.large_thumbs as u8).to_string());
+        push("tr".into(), nearest(&THUMB_ROWS, self.thumb_rows).to_string());
+        push("rc".into(), nearest(&RESULTS_PER_PAGE, self.results_per_page).to_string());
+        push("qb".into(), (self.always_mpv as u8).to_string());
+
+        for (name, kind) in KINDS.iter() {
+            if self.excluded_kinds.contains(kind) {
+                push(format!("ct_{}", name), "1".into());
+            }
+        }
+
+        for code in self.excluded_languages.iter() {
+            push(format!("xl_{}", code), "on".into());
+        }
+
+        for num in self.excluded_namespaces.iter().filter_map(namespace_num) {
+            push(format!("xn_{}", num), "1".into());
+        }
+
+        push("apply".into(), "Apply".into());
+        form
+    }
+}
@@ -0,0 +1,187 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::error::Error;
+use std::collections::{BTreeMap, HashMap};
+use serde_json::{json, Value};
+
+use super::article::{DraftMeta, ArticleMeta};
+use super::page::Page;
+use super::tag::{ArticleKind, Tag, TagKind, TagMap};
+use super::language::NOT_LANGUAGES;
+use super::date;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+// how often things appear in a bunch of galleries, e.g. a search
+#[derive(Debug, Clone, Default)]
+pub struct SearchStats {
+    pub galleries: usize,
+    // sum of the lengths of the galleries
+    pub pages: usize,
+    // number of galleries posted in each month, like "2021-03"
+    pub monthly: BTreeMap<String, usize>,
+    pub tags: HashMap<Tag, usize>,
+    pub languages: HashMap<String, usize>,
+    pub kinds: HashMap<ArticleKind, usize>,
+
+    // ratings rounded to halves of a star; `ratings[7]` is the number of
+    // galleries rated around 3.5. drafts of layouts which don't show
+    // stars aren't counted here
+    pub ratings: [usize; 11],
+}
+
+impl SearchStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add_tags(&mut self, tags: &TagMap) {
+        for tag in tags.tags() {
+            if tag.kind() == &TagKind::Language
+                && !NOT_LANGUAGES.contains(&tag.name()) {
+                *self.languages.entry(tag.name().to_owned()).or_insert(0) += 1;
+            }
+
+            *self.tags.entry(tag).or_insert(0) += 1;
+        }
+    }
+
+    fn add_gallery(&mut self, kind: ArticleKind, posted: &str, length: usize) {
+        self.galleries += 1;
+        self.pages += length;
+        *self.kinds.entry(kind).or_insert(0) += 1;
+
+        if let Some((year, month, ..)) = date::parse(posted) {
+            let key = format!("{:04}-{:02}", year, month);
+            *self.monthly.entry(key).or_insert(0) += 1;
+        }
+    }
+
+    fn add_rating(&mut self, rating: f64) {
+        let bucket = (rating * 2.0).round().max(0.0) as usize;
+        self.ratings[bucket.min(10)] += 1;
+    }
+
+    pub fn add_draft(&mut self, meta: &DraftMeta) {
+        self.add_gallery(meta.kind, &meta.posted, meta.length);
+        self.add_tags(&meta.tags);
+
+        if let Some(rating) = meta.rating {
+            self.add_rating(rating.into());
+        }
+    }
+
+    pub fn add_article(&mut self, meta: &ArticleMeta) {
+        self.add_gallery(meta.kind, &meta.posted, meta.length);
+        self.add_tags(&meta.tags);
+        self.add_rating(meta.rating);
+    }
+
+    // the most common `n` tags, from the most common one
+    pub fn top_tags(&self, n: usize) -> Vec<(&Tag, usize)> {
+        let mut tags = self.tags
+            .iter()
+            .map(|(tag, &count)| (tag, count))
+            .collect::<Vec<_>>();
+
+        // ties are broken by the tag itself to give them in a stable order
+        tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        tags.truncate(n);
+        tags
+    }
+
+    fn top_of(&self, kind: TagKind, n: usize) -> Vec<(&str, usize)> {
+        let mut tags = self.tags
+            .iter()
+            .filter(|(tag, _)| tag.kind() == &kind)
+            .map(|(tag, &count)| (tag.name(), count))
+            .collect::<Vec<_>>();
+
+        tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        tags.truncate(n);
+        tags
+    }
+
+    pub fn top_artists(&self, n: usize) -> Vec<(&str, usize)> {
+        self.top_of(TagKind::Artist, n)
+    }
+
+    pub fn top_groups(&self, n: usize) -> Vec<(&str, usize)> {
+        self.top_of(TagKind::Group, n)
+    }
+
+    pub fn top_languages(&self, n: usize) -> Vec<(&str, usize)> {
+        let mut languages = self.languages
+            .iter()
+            .map(|(name, &count)| (name.as_str(), count))
+            .collect::<Vec<_>>();
+
+        languages.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        languages.truncate(n);
+        languages
+    }
+
+    // everything in a json object, with the top `n` of the rankings
+    pub fn report(&self, n: usize) -> Value {
+        let ranking = |list: Vec<(&str, usize)>| list
+            .into_iter()
+            .map(|(name, count)| json!({ "name": name, "count": count }))
+            .collect::<Vec<_>>();
+
+        let kinds = self.kinds
+            .iter()
+            .map(|(kind, count)| (kind.to_string(), json!(count)))
+            .collect::<serde_json::Map<_, _>>();
+
+        json!({
+            "galleries": self.galleries,
+            "pages": self.pages,
+            "monthly": self.monthly,
+            "kinds": kinds,
+            "ratings": self.ratings,
+            "top_artists": ranking(self.top_artists(n)),
+            "top_groups": ranking(self.top_groups(n)),
+            "top_languages": ranking(self.top_languages(n)),
+            "top_tags": self.top_tags(n)
+                .into_iter()
+                .map(|(tag, count)| json!({ "name": tag.to_string(), "count": count }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl Page {
+    // go through the rest of the search and count what the drafts have.
+    // use take() beforehand unless you want to crawl thousands of pages
+    pub async fn stats(mut self) -> Result<SearchStats, ErrorBox> {
+        let mut stats = SearchStats::new();
+
+        while let Some(list) = self.next().await? {
+            for draft in list.iter() {
+                stats.add_draft(draft.meta());
+            }
+        }
+
+        Ok(stats)
+    }
+
+    // the same as stats(), but stops after `max` galleries
+    pub async fn stats_limited(mut self, max: usize) -> Result<SearchStats, ErrorBox> {
+        let mut stats = SearchStats::new();
+
+        while stats.galleries < max {
+            let list = match self.next().await? {
+                Some(list) => list,
+                None => break
+            };
+
+            for draft in list.iter().take(max - stats.galleries) {
+                stats.add_draft(draft.meta());
+            }
+        }
+
+        Ok(stats)
+    }
+}
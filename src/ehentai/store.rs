@@ -0,0 +1,291 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::error::Error;
+use std::path::Path;
+use std::sync::Mutex;
+use rusqlite::{params, Connection, OptionalExtension, Row, ToSql};
+
+use super::article::ArticleMeta;
+use super::download::DownloadSummary;
+use super::tag::{ArticleKind, Tag, TagMap};
+use super::language::Language;
+use super::id::GalleryId;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS articles (
+        gid INTEGER PRIMARY KEY,
+        token TEXT NOT NULL,
+        path TEXT NOT NULL,
+        title TEXT NOT NULL,
+        original_title TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        thumb TEXT NOT NULL,
+        uploader TEXT NOT NULL,
+        posted TEXT NOT NULL,
+        parent TEXT,
+        visible INTEGER NOT NULL,
+        language TEXT NOT NULL,
+        translated INTEGER NOT NULL,
+        file_size TEXT NOT NULL,
+        length INTEGER NOT NULL,
+        favorited INTEGER NOT NULL,
+        rating_count INTEGER NOT NULL,
+        rating REAL NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS tags (
+        gid INTEGER NOT NULL REFERENCES articles(gid) ON DELETE CASCADE,
+        namespace TEXT NOT NULL,
+        name TEXT NOT NULL,
+        weak INTEGER NOT NULL,
+        PRIMARY KEY (gid, namespace, name)
+    );
+
+    CREATE INDEX IF NOT EXISTS tags_by_name ON tags (namespace, name);
+
+    CREATE TABLE IF NOT EXISTS downloads (
+        gid INTEGER PRIMARY KEY,
+        dir TEXT NOT NULL,
+        downloaded INTEGER NOT NULL,
+        failed INTEGER NOT NULL,
+        bytes INTEGER NOT NULL
+    );
+";
+
+// how far an article has been downloaded, as Article::download_to() said
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadState {
+    pub dir: String,
+    // images in the directory, including the ones skipped
+    pub downloaded: usize,
+    pub failed: usize,
+    pub bytes: u64,
+}
+
+impl DownloadState {
+    pub fn is_complete(&self) -> bool {
+        self.failed == 0
+    }
+}
+
+// metadata of articles kept in a sqlite database. articles are
+// identified by their gid, so saving one again replaces the old one
+pub struct Store {
+    conn: Mutex<Connection>,
+}
+
+impl Store {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ErrorBox> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, ErrorBox> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: Connection) -> Result<Self, ErrorBox> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn save(&self, meta: &ArticleMeta) -> Result<(), ErrorBox> {
+        let id = meta.path.parse::<GalleryId>()?;
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        tx.execute(
+            "INSERT OR REPLACE INTO articles VALUES
+                (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+            params![
+                id.gid as i64,
+                id.token,
+                meta.path,
+                meta.title,
+                meta.original_title,
+                meta.kind.to_string(),
+                meta.thumb,
+                meta.uploader,
+                meta.posted,
+                meta.parent,
+                meta.visible,
+                meta.language.to_string(),
+                meta.translated,
+                meta.file_size,
+                meta.length as i64,
+                meta.favorited as i64,
+                meta.rating_count as i64,
+                meta.rating,
+            ],
+        )?;
+
+        tx.execute("DELETE FROM tags WHERE gid = ?1", params![id.gid as i64])?;
+
+        for tag in meta.tags.tags() {
+            tx.execute(
+                "INSERT OR IGNORE INTO tags VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    id.gid as i64,
+                    tag.kind().to_string(),
+                    tag.name(),
+                    meta.tags.is_weak(&tag),
+                ],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn remove(&self, gid: u64) -> Result<(), ErrorBox> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute("DELETE FROM articles WHERE gid = ?1", params![gid as i64])?;
+        conn.execute("DELETE FROM downloads WHERE gid = ?1", params![gid as i64])?;
+
+        Ok(())
+    }
+
+    pub fn load(&self, gid: u64) -> Result<Option<ArticleMeta>, ErrorBox> {
+        let mut list = self.select("WHERE gid = ?1", &[&(gid as i64)])?;
+        Ok(list.pop())
+    }
+
+    pub fn by_tag(&self, tag: &Tag) -> Result<Vec<ArticleMeta>, ErrorBox> {
+        self.select(
+            "WHERE gid IN (SELECT gid FROM tags WHERE namespace = ?1 AND name = ?2)",
+            &[&tag.kind().to_string(), &tag.name()],
+        )
+    }
+
+    pub fn by_uploader(&self, uploader: &str) -> Result<Vec<ArticleMeta>, ErrorBox> {
+        self.select("WHERE uploader = ?1", &[&uploader])
+    }
+
+    pub fn by_language(&self, language: &Language) -> Result<Vec<ArticleMeta>, ErrorBox> {
+        self.select("WHERE language = ?1 COLLATE NOCASE", &[&language.to_string()])
+    }
+
+    pub fn by_rating(&self, min: f64) -> Result<Vec<ArticleMeta>, ErrorBox> {
+        self.select("WHERE rating >= ?1", &[&min])
+    }
+
+    pub fn all(&self) -> Result<Vec<ArticleMeta>, ErrorBox> {
+        self.select("", &[])
+    }
+
+    pub fn set_download(&self, gid: u64, summary: &DownloadSummary)
+        -> Result<(), ErrorBox> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO downloads VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                gid as i64,
+                summary.dir.to_string_lossy().into_owned(),
+                (summary.downloaded + summary.skipped) as i64,
+                summary.failed.len() as i64,
+                summary.bytes as i64,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn download_state(&self, gid: u64) -> Result<Option<DownloadState>, ErrorBox> {
+        let conn = self.conn.lock().unwrap();
+
+        let state = conn.query_row(
+            "SELECT dir, downloaded, failed, bytes FROM downloads WHERE gid = ?1",
+            params![gid as i64],
+            |row| Ok(DownloadState {
+                dir: row.get(0)?,
+                downloaded: row.get::<_, i64>(1)? as usize,
+                failed: row.get::<_, i64>(2)? as usize,
+                bytes: row.get::<_, i64>(3)? as u64,
+            }),
+        ).optional()?;
+
+        Ok(state)
+    }
+
+    fn select(&self, filter: &str, args: &[&dyn ToSql])
+        -> Result<Vec<ArticleMeta>, ErrorBox> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT * FROM articles {} ORDER BY gid DESC",
+            filter
+        ))?;
+
+        // rows have to be collected before tags are queried, since
+        // we can't parse the kind or tags inside the closure
+        let rows = stmt
+            .query_map(args, read_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut tags_stmt = conn.prepare(
+            "SELECT namespace, name, weak FROM tags WHERE gid = ?1 ORDER BY rowid"
+        )?;
+
+        let mut list = Vec::with_capacity(rows.len());
+
+        for (gid, kind, mut meta) in rows {
+            meta.kind = kind.parse()?;
+
+            let tags = tags_stmt
+                .query_map(params![gid], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, bool>(2)?))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            for (namespace, name, weak) in tags {
+                let tag = Tag::new(namespace.parse()?, &name);
+
+                if weak {
+                    meta.tags.add_weak(tag);
+                } else {
+                    meta.tags.add(tag);
+                }
+            }
+
+            list.push(meta);
+        }
+
+        Ok(list)
+    }
+}
+
+// the kind is given separately since it can't be parsed here
+fn read_row(row: &Row) -> rusqlite::Result<(i64, String, ArticleMeta)> {
+    let meta = ArticleMeta {
+        path: row.get("path")?,
+        title: row.get("title")?,
+        original_title: row.get("original_title")?,
+        // replaced by the caller
+        kind: ArticleKind::Misc,
+        thumb: row.get("thumb")?,
+        uploader: row.get("uploader")?,
+        posted: row.get("posted")?,
+        parent: row.get("parent")?,
+        visible: row.get("visible")?,
+        language: Language::from(row.get::<_, String>("language")?.as_str()),
+        translated: row.get("translated")?,
+        file_size: row.get("file_size")?,
+        length: row.get::<_, i64>("length")? as usize,
+        favorited: row.get::<_, i64>("favorited")? as usize,
+        rating_count: row.get::<_, i64>("rating_count")? as usize,
+        rating: row.get("rating")?,
+        tags: TagMap::new(),
+    };
+
+    Ok((row.get("gid")?, row.get("kind")?, meta))
+}
@@ -3,12 +3,22 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use std::fmt;
+use std::sync::Arc;
 use std::str::FromStr;
 use std::iter::{FromIterator, IntoIterator};
 use std::error::Error;
 use std::ops::{Index, IndexMut};
+use std::collections::{BTreeMap, BTreeSet};
 
-#[derive(Debug, Copy, Clone)]
+use super::intern::intern;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+#[cfg(feature = "serde")]
+use serde::de;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ArticleKind {
     Doujinshi,
     Manga,
@@ -84,34 +94,47 @@ impl fmt::Display for ParseTagError {
 
 impl Error for ParseTagError {}
 
-#[derive(Clone, Copy)]
+// namespaces of tags, in the order the site shows them
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum TagKind {
     Reclass,
     Language,
-    Group,
     Parody,
     Character,
+    Group,
     Artist,
+    Cosplayer,
     Male,
     Female,
-    Misc,
+    Mixed,
+    Other,
+    Temp,
+    // whatever the site will add in the future
+    Unknown(String),
 }
 
 impl FromStr for TagKind {
     type Err = ParseTagError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
+        match s.to_ascii_lowercase().as_str() {
             // alternates according to [https://ehwiki.org/wiki/Namespace]
             "reclass" | "r" => Ok(TagKind::Reclass),
-            "language" | "lang" => Ok(TagKind::Language),
-            "group" | "creator" | "circle" | "g" => Ok(TagKind::Group),
+            "language" | "lang" | "l" => Ok(TagKind::Language),
             "parody" | "series" | "p" => Ok(TagKind::Parody),
             "character" | "char" | "c" => Ok(TagKind::Character),
+            "group" | "creator" | "circle" | "g" => Ok(TagKind::Group),
             "artist" | "a" => Ok(TagKind::Artist),
+            "cosplayer" | "cos" => Ok(TagKind::Cosplayer),
             "male" | "m" => Ok(TagKind::Male),
             "female" | "f" => Ok(TagKind::Female),
-            "misc" | "" => Ok(TagKind::Misc),
+            "mixed" | "x" => Ok(TagKind::Mixed),
+            // the site used to call it misc
+            "other" | "misc" | "o" | "" => Ok(TagKind::Other),
+            "temp" => Ok(TagKind::Temp),
+            other if other.bytes().all(|x| x.is_ascii_alphanumeric()) => {
+                Ok(TagKind::Unknown(other.to_owned()))
+            },
             _ => Err(ParseTagError())
         }
     }
@@ -121,47 +144,109 @@ impl fmt::Display for TagKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if f.alternate() {
             // give shorter names
-            match *self {
+            match self {
                 TagKind::Reclass => write!(f, "r"),
                 TagKind::Language => write!(f, "lang"),
-                TagKind::Group => write!(f, "g"),
                 TagKind::Parody => write!(f, "p"),
                 TagKind::Character => write!(f, "c"),
+                TagKind::Group => write!(f, "g"),
                 TagKind::Artist => write!(f, "a"),
+                TagKind::Cosplayer => write!(f, "cos"),
                 TagKind::Male => write!(f, "m"),
                 TagKind::Female => write!(f, "f"),
-                TagKind::Misc => write!(f, "misc")
+                TagKind::Mixed => write!(f, "x"),
+                TagKind::Other => write!(f, "o"),
+                TagKind::Temp => write!(f, "temp"),
+                TagKind::Unknown(name) => write!(f, "{}", name),
             }
         } else {
-            match *self {
+            match self {
                 TagKind::Reclass => write!(f, "reclass"),
                 TagKind::Language => write!(f, "language"),
-                TagKind::Group => write!(f, "group"),
                 TagKind::Parody => write!(f, "parody"),
                 TagKind::Character => write!(f, "character"),
+                TagKind::Group => write!(f, "group"),
                 TagKind::Artist => write!(f, "artist"),
+                TagKind::Cosplayer => write!(f, "cosplayer"),
                 TagKind::Male => write!(f, "male"),
                 TagKind::Female => write!(f, "female"),
-                TagKind::Misc => write!(f, "misc")
+                TagKind::Mixed => write!(f, "mixed"),
+                TagKind::Other => write!(f, "other"),
+                TagKind::Temp => write!(f, "temp"),
+                TagKind::Unknown(name) => write!(f, "{}", name),
             }
         }
     }
 }
 
-pub struct Tag(TagKind, String);
+// serialized by name, so it can be a key of json objects
+#[cfg(feature = "serde")]
+impl Serialize for TagKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for TagKind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(de::Error::custom)
+    }
+}
+
+// the name is shared with every other tag of the same name; see intern
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Tag(TagKind, Arc<str>);
+
+impl Tag {
+    pub fn new(kind: TagKind, name: &str) -> Self {
+        Tag(kind, intern(name))
+    }
+
+    pub fn kind(&self) -> &TagKind {
+        &self.0
+    }
+
+    pub fn name(&self) -> &str {
+        &self.1
+    }
+
+    // a search term which only matches this tag, like `artist:"foo bar$"`
+    pub fn to_query(&self) -> String {
+        // there is no way to escape quotes in the search syntax
+        let name = self.1.replace('"', "");
+
+        if name.contains(char::is_whitespace) {
+            format!("{}:\"{}$\"", self.0, name)
+        } else {
+            format!("{}:{}$", self.0, name)
+        }
+    }
+}
 
 impl FromStr for Tag {
     type Err = ParseTagError;
 
+    // also takes a search term like `f:"big breasts$"`
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let colon = s.as_bytes().iter().position(|&x| x == b':');
 
         match colon {
             Some(pos) => {
-                let category = s[..pos].parse()?;
-                let tag = String::from(&s[(pos + 1)..]);
+                let category = s[..pos].trim().parse()?;
+
+                let tag = s[(pos + 1)..].trim();
+                let tag = tag.strip_prefix('"')
+                    .and_then(|x| x.strip_suffix('"'))
+                    .unwrap_or(tag);
+                let tag = tag.strip_suffix('$').unwrap_or(tag).trim();
+
+                if tag.is_empty() {
+                    return Err(ParseTagError());
+                }
 
-                Ok(Tag(category, tag))
+                Ok(Tag(category, intern(tag)))
             },
             None => Err(ParseTagError())
         }
@@ -170,70 +255,262 @@ impl FromStr for Tag {
 
 impl fmt::Display for Tag {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}:{}", self.0, self.1)
+        if f.alternate() {
+            write!(f, "{:#}:{}", self.0, self.1)
+        } else {
+            write!(f, "{}:{}", self.0, self.1)
+        }
+    }
+}
+
+// serialized as "namespace:tag" like the site and other tools write
+#[cfg(feature = "serde")]
+impl Serialize for Tag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Tag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(de::Error::custom)
     }
 }
 
+// tags grouped by namespace, which are kept in the order the site shows
 #[derive(Debug, Default, Clone)]
 pub struct TagMap {
     // all is (probably) sorted alphabetically
     // (just because the webpage gives tags so)
-    language: Vec<String>,
-    group: Vec<String>,
-    parody: Vec<String>,
-    character: Vec<String>,
-    artist: Vec<String>,
-    male: Vec<String>,
-    female: Vec<String>,
-    reclass: Vec<String>,
-    misc: Vec<String>,
+    tags: BTreeMap<TagKind, Vec<Arc<str>>>,
+    // tags which not enough people voted for yet; they're in `tags` as well
+    weak: BTreeSet<Tag>,
 }
 
+// what `map[kind]` gives for a namespace without any tag
+static EMPTY: Vec<Arc<str>> = Vec::new();
+
 impl TagMap {
     pub fn new() -> Self {
         Self::default()
     }
 
+    // iterate over namespaces which have any tag
+    pub fn iter(&self) -> impl Iterator<Item = (&TagKind, &[Arc<str>])> + '_ {
+        self.tags
+            .iter()
+            .map(|(kind, list)| (kind, list.as_slice()))
+            .filter(|(_, list)| !list.is_empty())
+    }
+
+    // tags in a namespace, like names of the artists
+    pub fn namespace(&self, kind: TagKind) -> impl Iterator<Item = &str> + '_ {
+        self[kind].iter().map(|name| &name[..])
+    }
+
+    // the first tag in a namespace, which is usually the only one
+    // for namespaces like language
+    pub fn first(&self, kind: TagKind) -> Option<&str> {
+        self.namespace(kind).next()
+    }
+
     pub fn add(&mut self, tag: Tag) {
         self[tag.0].push(tag.1);
     }
 
-    pub fn has(&mut self, tag: &Tag) -> bool {
-        self[tag.0].iter().any(|x| x == &tag.1)
+    // add a tag which is not confirmed yet
+    pub fn add_weak(&mut self, tag: Tag) {
+        self.weak.insert(tag.clone());
+        self.add(tag);
+    }
+
+    pub fn is_weak(&self, tag: &Tag) -> bool {
+        self.weak.contains(tag)
+    }
+
+    pub fn weak_tags(&self) -> impl Iterator<Item = &Tag> + '_ {
+        self.weak.iter()
+    }
+
+    pub fn has(&self, tag: &Tag) -> bool {
+        self.tags
+            .get(&tag.0)
+            .map_or(false, |list| list.iter().any(|x| x == &tag.1))
+    }
+
+    // every tag one by one, namespace by namespace
+    pub fn tags(&self) -> impl Iterator<Item = Tag> + '_ {
+        self.iter().flat_map(|(kind, list)| {
+            list.iter().map(move |name| Tag(kind.clone(), name.clone()))
+        })
+    }
+
+    // add a tag of another map unless we have it, keeping it weak if it was
+    fn merge(&mut self, other: &TagMap, tag: Tag) {
+        if self.has(&tag) {
+            return;
+        }
+
+        if other.is_weak(&tag) {
+            self.add_weak(tag);
+        } else {
+            self.add(tag);
+        }
+    }
+
+    // tags in either of the two. a tag is weak only if it's weak in both
+    pub fn union(&self, other: &TagMap) -> TagMap {
+        let mut tags = self.clone();
+
+        for tag in other.tags() {
+            if tags.is_weak(&tag) && !other.is_weak(&tag) {
+                tags.weak.remove(&tag);
+            }
+
+            tags.merge(other, tag);
+        }
+
+        tags
+    }
+
+    // tags in both of the two. a tag is weak if it's weak in any of them
+    pub fn intersection(&self, other: &TagMap) -> TagMap {
+        let mut tags = TagMap::new();
+
+        for tag in self.tags().filter(|tag| other.has(tag)) {
+            if other.is_weak(&tag) {
+                tags.add_weak(tag);
+            } else {
+                tags.merge(self, tag);
+            }
+        }
+
+        tags
+    }
+
+    // tags in this but not in the other
+    pub fn difference(&self, other: &TagMap) -> TagMap {
+        let mut tags = TagMap::new();
+
+        for tag in self.tags().filter(|tag| !other.has(tag)) {
+            tags.merge(self, tag);
+        }
+
+        tags
+    }
+
+    // a search query for galleries which have all of the tags
+    pub fn to_query(&self) -> String {
+        self.tags()
+            .map(|tag| tag.to_query())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn contains_all(&self, other: &TagMap) -> bool {
+        other.tags().all(|tag| self.has(&tag))
+    }
+
+    // whether the tags satisfy a query in the search syntax of the site,
+    // like `female:glasses -male:"big penis$" ~english ~korean`.
+    // terms without namespace match any namespace, and names without
+    // trailing `$` match any tag which starts with it, as the site does
+    pub fn matches(&self, query: &str) -> bool {
+        let mut any = None;
+
+        for term in query_terms(query) {
+            let (prefix, term) = match term.chars().next() {
+                Some(c @ '-') | Some(c @ '~') => (Some(c), &term[1..]),
+                _ => (None, &term[..]),
+            };
+
+            let found = self.matches_term(term);
+
+            match prefix {
+                Some('-') if found => return false,
+                Some('~') => *any.get_or_insert(false) |= found,
+                None if !found => return false,
+                _ => (),
+            }
+        }
+
+        any.unwrap_or(true)
+    }
+
+    fn matches_term(&self, term: &str) -> bool {
+        let (kind, name) = match term.find(':') {
+            Some(pos) => match term[..pos].parse::<TagKind>() {
+                Ok(kind) => (Some(kind), &term[(pos + 1)..]),
+                Err(_) => (None, term),
+            },
+            None => (None, term),
+        };
+
+        let name = name.trim_matches('"').to_lowercase();
+
+        let (name, exact) = match name.strip_suffix('$') {
+            Some(name) => (name.to_owned(), true),
+            None => (name, false),
+        };
+
+        self.iter()
+            .filter(|(key, _)| kind.as_ref().map_or(true, |kind| kind == *key))
+            .flat_map(|(_, list)| list.iter())
+            .any(|tag| {
+                let tag = tag.to_lowercase();
+                if exact { tag == name } else { tag.starts_with(&name) }
+            })
+    }
+}
+
+// split a query into terms by whitespaces, but not inside quotes
+fn query_terms(query: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut term = String::new();
+    let mut quoted = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                term.push(c);
+            },
+            c if c.is_whitespace() && !quoted => {
+                if !term.is_empty() {
+                    terms.push(std::mem::take(&mut term));
+                }
+            },
+            c => term.push(c),
+        }
+    }
+
+    if !term.is_empty() {
+        terms.push(term);
+    }
+
+    terms
+}
+
+// namespaces without any tag are the same as not having them
+impl PartialEq for TagMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter()) && self.weak == other.weak
     }
 }
 
 impl Index<TagKind> for TagMap {
-    type Output = Vec<String>;
+    type Output = Vec<Arc<str>>;
 
     fn index(&self, category: TagKind) -> &Self::Output {
-        match category {
-            TagKind::Reclass => &self.reclass,
-            TagKind::Language => &self.language,
-            TagKind::Group => &self.group,
-            TagKind::Parody => &self.parody,
-            TagKind::Character => &self.character,
-            TagKind::Artist => &self.artist,
-            TagKind::Male => &self.male,
-            TagKind::Female => &self.female,
-            TagKind::Misc => &self.misc,
-        }
+        self.tags.get(&category).unwrap_or(&EMPTY)
     }
 }
 
 impl IndexMut<TagKind> for TagMap {
     fn index_mut(&mut self, category: TagKind) -> &mut Self::Output {
-        match category {
-            TagKind::Reclass => &mut self.reclass,
-            TagKind::Language => &mut self.language,
-            TagKind::Group => &mut self.group,
-            TagKind::Parody => &mut self.parody,
-            TagKind::Character => &mut self.character,
-            TagKind::Artist => &mut self.artist,
-            TagKind::Male => &mut self.male,
-            TagKind::Female => &mut self.female,
-            TagKind::Misc => &mut self.misc,
-        }
+        self.tags.entry(category).or_insert_with(Vec::new)
     }
 }
 
@@ -248,3 +525,42 @@ impl FromIterator<Tag> for TagMap {
         tags
     }
 }
+
+// serialized as a map from namespace to tags, omitting empty ones.
+// weak tags are listed again under "_weak", which can't be a namespace
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct TagMapRepr {
+    #[serde(flatten)]
+    tags: BTreeMap<TagKind, Vec<String>>,
+    #[serde(rename = "_weak", default, skip_serializing_if = "BTreeSet::is_empty")]
+    weak: BTreeSet<Tag>,
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for TagMap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let tags = self.iter()
+            .map(|(kind, list)| (kind.clone(), list.iter().map(|name| name.to_string()).collect()))
+            .collect();
+
+        TagMapRepr { tags, weak: self.weak.clone() }.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for TagMap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = TagMapRepr::deserialize(deserializer)?;
+
+        let tags = repr.tags
+            .into_iter()
+            .map(|(kind, list)| (kind, list.iter().map(|name| intern(name)).collect()))
+            .collect();
+
+        Ok(Self {
+            tags,
+            weak: repr.weak,
+        })
+    }
+}
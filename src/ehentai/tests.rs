@@ -37,7 +37,7 @@ async fn sequential() {
     use std::path::PathBuf;
 
     let explorer = Explorer::new();
-    let mut article = explorer.article_from_path(URL.into()).await.unwrap();
+    let article = explorer.article_from_path(URL.into()).await.unwrap();
     article.load_image_list().await.unwrap();
 
     let mut path = PathBuf::from("./tests/sequential/");
@@ -78,7 +78,7 @@ async fn parallel() {
     // load article infos 
     let explorer = Explorer::new();
 
-    let mut article = explorer.article_from_path(URL.into()).await.unwrap();
+    let article = explorer.article_from_path(URL.into()).await.unwrap();
     article.load_image_list().await.unwrap();
     let len = article.meta().length;
 
@@ -123,3 +123,1905 @@ async fn parallel() {
         file.write_all(&image).unwrap();
     }
 }
+
+#[test]
+fn image_entry() {
+    let entry = "https://e-hentai.org/s/0123456789/1088955-12"
+        .parse::<ImageEntry>()
+        .unwrap();
+
+    assert_eq!(entry.hash, "0123456789");
+    assert_eq!(entry.gid, 1088955);
+    assert_eq!(entry.page, 12);
+
+    assert!("https://e-hentai.org/g/1088955/4464b39d07/".parse::<ImageEntry>().is_err());
+}
+
+#[test]
+fn image_format() {
+    assert_eq!(ImageFormat::sniff(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR"), Some(ImageFormat::Png));
+    assert_eq!(ImageFormat::sniff(b"RIFF\0\0\0\0WEBPVP8 "), Some(ImageFormat::Webp));
+    assert_eq!(ImageFormat::sniff(b"<html>"), None);
+
+    assert_eq!(ImageFormat::from_mime("image/jpeg; charset=binary"), Some(ImageFormat::Jpeg));
+    assert_eq!(ImageFormat::Gif.extension(), "gif");
+}
+
+#[test]
+fn gallery_id() {
+    let id = URL.parse::<GalleryId>().unwrap();
+
+    assert_eq!(id, GalleryId::new(1088955, "4464b39d07"));
+    assert_eq!(id.path(), URL);
+
+    assert!("https://e-hentai.org/s/0123456789/1088955-12".parse::<GalleryId>().is_err());
+}
+
+#[test]
+fn tag_interned() {
+    let a = "female:glasses".parse::<Tag>().unwrap();
+    let b = Tag::new(TagKind::Female, "glasses");
+
+    // the same name is kept only once, however it's made
+    assert_eq!(a, b);
+    assert!(std::ptr::eq(a.name().as_ptr(), b.name().as_ptr()));
+
+    let tags = vec![a].into_iter().collect::<TagMap>();
+    assert!(std::ptr::eq(tags.first(TagKind::Female).unwrap().as_ptr(), b.name().as_ptr()));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn tag_serde() {
+    let tags = vec!["artist:foo bar", "female:glasses", "misc:full color"]
+        .into_iter()
+        .map(|x| x.parse::<Tag>().unwrap())
+        .collect::<TagMap>();
+
+    let text = serde_json::to_string(&tags).unwrap();
+    assert_eq!(text, r#"{"artist":["foo bar"],"female":["glasses"],"other":["full color"]}"#);
+    assert_eq!(serde_json::from_str::<TagMap>(&text).unwrap(), tags);
+
+    let tag = Tag::new(TagKind::Artist, "foo bar");
+    let text = serde_json::to_string(&tag).unwrap();
+    assert_eq!(text, r#""artist:foo bar""#);
+    assert_eq!(serde_json::from_str::<Tag>(&text).unwrap(), tag);
+}
+
+#[test]
+fn tag_set() {
+    let tags = vec!["artist:foo bar", "female:glasses", "language:english"]
+        .into_iter()
+        .map(|x| x.parse::<Tag>().unwrap())
+        .collect::<TagMap>();
+
+    let mut other = TagMap::new();
+    other.add_weak(Tag::new(TagKind::Female, "glasses"));
+    other.add(Tag::new(TagKind::Male, "glasses"));
+
+    assert!(tags.union(&other).contains_all(&other));
+    assert!(tags.intersection(&other).is_weak(&Tag::new(TagKind::Female, "glasses")));
+    assert!(!tags.difference(&other).has(&Tag::new(TagKind::Female, "glasses")));
+
+    assert!(tags.matches(r#"f:glasses artist:"foo bar$" ~korean ~english"#));
+    assert!(!tags.matches("glasses -artist:foo"));
+    assert!(!tags.matches(r#"artist:"foo$""#));
+}
+
+#[test]
+fn tag_query() {
+    let tags = vec!["artist:foo bar", "f:glasses"]
+        .into_iter()
+        .map(|x| x.parse::<Tag>().unwrap())
+        .collect::<TagMap>();
+
+    let query = tags.to_query();
+
+    assert_eq!(query, r#"artist:"foo bar$" female:glasses$"#);
+    assert!(tags.matches(&query));
+}
+
+#[test]
+fn tag_parse() {
+    let tag = r#"F:"big breasts$""#.parse::<Tag>().unwrap();
+
+    assert_eq!(tag, Tag::new(TagKind::Female, "big breasts"));
+    assert_eq!(tag.to_string(), "female:big breasts");
+    assert_eq!(format!("{:#}", tag), "f:big breasts");
+
+    assert_eq!("cos".parse::<TagKind>().unwrap(), TagKind::Cosplayer);
+    assert_eq!("misc".parse::<TagKind>().unwrap(), TagKind::Other);
+    assert_eq!("newspace".parse::<TagKind>().unwrap(), TagKind::Unknown("newspace".to_owned()));
+
+    assert!("glasses".parse::<Tag>().is_err());
+    assert!("female:".parse::<Tag>().is_err());
+}
+
+#[test]
+fn response_cache() {
+    use std::time::Duration;
+    use super::cache::{ResponseCache, Validators};
+
+    let cache = ResponseCache::new();
+    cache.insert("a".to_owned(), "nothing".into(), Validators::default());
+    assert!(cache.get("a").is_none());
+
+    cache.configure(2, Duration::from_secs(60));
+    cache.insert("a".to_owned(), "1".into(), Validators::default());
+    cache.insert("b".to_owned(), "2".into(), Validators::default());
+
+    // "b" is the least recently used after this
+    assert!(cache.get("a").is_some());
+    cache.insert("c".to_owned(), "3".into(), Validators::default());
+
+    assert!(cache.get("b").is_none());
+    assert_eq!(cache.get("c").unwrap(), "3");
+
+    // expired, but can be revalidated
+    cache.configure(2, Duration::from_secs(0));
+
+    let validators = Validators {
+        etag: Some("\"abc\"".to_owned()),
+        last_modified: None,
+    };
+
+    cache.insert("d".to_owned(), "4".into(), validators);
+
+    assert!(cache.get("d").is_none());
+    assert!(cache.validators("d").is_some());
+    assert_eq!(cache.revalidate("d").unwrap(), "4");
+}
+
+#[test]
+fn cache_purge() {
+    use std::time::Duration;
+    use super::cache::{ResponseCache, Validators};
+
+    let cache = ResponseCache::new();
+    cache.configure(4, Duration::from_secs(60));
+
+    cache.insert("https://e-hentai.org/g/1/abc/".to_owned(), "12".into(), Validators::default());
+    cache.insert("https://e-hentai.org/g/2/def/".to_owned(), "345".into(), Validators::default());
+    assert_eq!(cache.size(), (2, 5));
+
+    cache.remove_where(|key, _| key.contains("/g/1/"));
+    assert_eq!(cache.size(), (1, 3));
+
+    cache.remove_where(|_, age| age < Duration::from_secs(60));
+    assert_eq!(cache.size(), (0, 0));
+}
+
+#[test]
+fn settings_form() {
+    use select::document::Document;
+    use super::parser;
+
+    let doc = Document::from(r#"<form>
+        <input type="radio" name="dm" value="l">
+        <input type="radio" name="dm" value="t" checked>
+        <input type="radio" name="tr" value="2" checked>
+        <input type="checkbox" name="ct_manga" value="1" checked>
+        <input type="checkbox" name="ct_western" value="1">
+        <input type="checkbox" name="xl_1025" checked>
+        <input type="checkbox" name="xn_6" value="1" checked>
+        <input type="text" name="xu" value="someone">
+        <select name="fs"><option value="p">p</option><option value="f" selected>f</option></select>
+        <input type="submit" name="apply" value="Apply">
+    </form>"#);
+
+    let mut settings = Settings::from_form(parser::form_fields(&doc));
+
+    assert_eq!(settings.display_mode, DisplayMode::Thumbnail);
+    assert_eq!(settings.thumb_rows, 20);
+    assert_eq!(settings.excluded_kinds, vec![ArticleKind::Manga]);
+    assert_eq!(settings.excluded_languages, vec![1025]);
+    assert_eq!(settings.excluded_namespaces, vec![TagKind::Artist]);
+
+    settings.display_mode = DisplayMode::Extended;
+    settings.thumb_rows = 15;
+
+    let form = settings.to_form();
+    let value = |name: &str| form.iter().find(|(key, _)| key == name).map(|(_, x)| x.as_str());
+
+    assert_eq!(value("dm"), Some("e"));
+    assert_eq!(value("tr"), Some("1"));
+    assert_eq!(value("xu"), Some("someone"));
+    assert_eq!(value("fs"), Some("f"));
+    assert_eq!(value("xn_6"), Some("1"));
+}
+
+#[test]
+fn image_limit() {
+    use select::document::Document;
+    use super::parser;
+
+    let doc = Document::from(r#"<div class="homebox">
+        <p>You are currently at <strong>1,234</strong> towards a limit of <strong>5,000</strong>.</p>
+        <p>This regenerates at a rate of <strong>3</strong> per minute.</p>
+        <p>Reset Cost: <strong>1,234</strong> GP</p>
+    </div>"#);
+
+    let limit = parser::image_limit(&doc).unwrap();
+
+    assert_eq!(limit.used, 1234);
+    assert_eq!(limit.remaining(), 3766);
+    assert_eq!(limit.regen_per_minute, Some(3));
+    assert_eq!(limit.reset_cost, Some(1234));
+
+    assert!(parser::image_limit(&Document::from("<p>Please log in</p>")).is_err());
+}
+
+#[test]
+fn funds() {
+    use select::document::Document;
+    use super::parser;
+
+    let doc = Document::from(r#"<body>
+        <div>Available: 12,345 Credits</div>
+        <div>Available: 67 kGP</div>
+    </body>"#);
+
+    let funds = parser::funds(&doc).unwrap();
+
+    assert_eq!(funds, Funds { gp: 67000, credits: 12345 });
+    assert!(funds.can_afford(5000));
+}
+
+#[test]
+fn favorites_list() {
+    use select::document::Document;
+    use super::parser;
+
+    let doc = Document::from(r#"<table class="itg gltc"><tbody>
+        <tr><th>Category</th><th>Published</th><th>Title</th><th>Favorited</th></tr>
+        <tr>
+            <td class="gl1c glcat"><div class="cn ct2">Manga</div></td>
+            <td class="gl2c">
+                <div class="glthumb"><img src="https://ehgt.org/t/00/00/thumb.jpg"></div>
+                <div id="posted_1234" style="border-color:#f00;background-color:rgba(255,0,0,.1)" title="Later">2021-01-02 03:04</div>
+            </td>
+            <td class="gl3c glname">
+                <a href="https://e-hentai.org/g/1234/abcdef0123/">
+                    <div class="glink">some manga</div>
+                    <div><div class="gt" title="artist:someone">someone</div><div class="gtl" title="female:glasses">glasses</div></div>
+                </a>
+                <div class="glfnote">Note: read it again</div>
+            </td>
+            <td class="glfc glfav"><p>2021-05-06</p><p>07:08</p></td>
+        </tr>
+    </tbody></table>
+    <table class="ptt"><tr><td><a>1</a></td><td>&gt;</td></tr></table>"#);
+
+    let list = parser::favorites(&doc).unwrap().unwrap();
+    assert_eq!(list.len(), 1);
+
+    let favorite = &list[0];
+    assert_eq!(favorite.meta.title, "some manga");
+    assert_eq!(favorite.meta.kind, ArticleKind::Manga);
+    assert_eq!(favorite.category, 1);
+    assert_eq!(favorite.category_name, "Later");
+    assert_eq!(favorite.note.as_deref(), Some("read it again"));
+    assert_eq!(favorite.favorited, "2021-05-06 07:08");
+    assert!(favorite.meta.tags.is_weak(&"female:glasses".parse().unwrap()));
+
+    assert!(!parser::has_next_page(&doc));
+}
+
+#[test]
+fn favorites_diff() {
+    use std::collections::HashSet;
+
+    let a = GalleryId::new(1, "aa");
+    let b = GalleryId::new(2, "bb");
+    let c = GalleryId::new(3, "cc");
+
+    let mut local = vec![a.clone(), b.clone()].into_iter().collect::<HashSet<_>>();
+    let diff = FavoritesDiff::new(vec![b.clone(), c.clone()], &local);
+
+    assert_eq!(diff.only_local, vec![a]);
+    assert_eq!(diff.only_remote, vec![c.clone()]);
+
+    diff.apply_local(&mut local);
+    assert_eq!(local, vec![b, c].into_iter().collect());
+}
+
+#[test]
+fn my_tags() {
+    use select::document::Document;
+    use super::parser;
+
+    let doc = Document::from(r#"
+        <script type="text/javascript">var apiuid = 1234;
+        var apikey = "0123abcd";</script>
+        <div id="usertag_55">
+            <div id="tagpreview_55" title="female:glasses">glasses</div>
+            <input type="checkbox" name="tagwatch_55" checked>
+            <input type="checkbox" name="taghide_55">
+            <input type="text" name="tagcolor_55" value="#ff0000">
+            <input type="text" name="tagweight_55" value="-5">
+        </div>
+    "#);
+
+    let tags = parser::my_tags(&doc).unwrap();
+
+    assert_eq!(tags, vec![WatchedTag {
+        id: 55,
+        tag: "female:glasses".parse().unwrap(),
+        watch: true,
+        hide: false,
+        color: Some("#ff0000".to_owned()),
+        weight: -5,
+    }]);
+
+    let key = parser::api_key(&doc).unwrap();
+    assert_eq!(key.uid, 1234);
+    assert_eq!(key.key, "0123abcd");
+}
+
+#[test]
+fn hath() {
+    use select::document::Document;
+    use super::parser;
+
+    let gallery = Document::from(r#"<a href="" onclick="return popUp('https://e-hentai.org/archiver.php?gid=1&amp;token=abc&amp;or=123--456',480,320)">Archive Download</a>"#);
+
+    assert_eq!(
+        parser::archiver_path(&gallery).as_deref(),
+        Some("https://e-hentai.org/archiver.php?gid=1&token=abc&or=123--456")
+    );
+
+    let clients = Document::from(r#"<table id="hct">
+        <tr><th>Client</th><th>Status</th></tr>
+        <tr><td><a href="hentaiathome.php?cid=42&amp;act=settings">home</a></td><td>Online</td></tr>
+    </table>"#);
+
+    assert_eq!(parser::hath_clients(&clients), vec![HathClient {
+        id: 42,
+        name: "home".to_owned(),
+        status: "Online".to_owned(),
+    }]);
+}
+
+#[test]
+fn cookies() {
+    use super::cookies::CookieJar;
+
+    let text = "# Netscape HTTP Cookie File\n\
+        .e-hentai.org\tTRUE\t/\tFALSE\t0\tipb_member_id\t1234\n\
+        #HttpOnly_.exhentai.org\tTRUE\t/\tFALSE\t0\tigneous\tabcd\n\
+        forums.e-hentai.org\tFALSE\t/\tFALSE\t0\tsession\tx\n";
+
+    let jar = CookieJar::from_netscape(text).unwrap();
+
+    assert_eq!(jar.header("e-hentai.org").as_deref(), Some("ipb_member_id=1234"));
+    assert_eq!(jar.header("api.e-hentai.org").as_deref(), Some("ipb_member_id=1234"));
+    assert_eq!(jar.header("exhentai.org").as_deref(), Some("igneous=abcd"));
+    assert_eq!(jar.header("forums.e-hentai.org").as_deref(), Some("ipb_member_id=1234; session=x"));
+    assert!(jar.header("evil-e-hentai.org").is_none());
+
+    assert!(CookieJar::from_netscape("e-hentai.org\tTRUE\n").is_err());
+
+    let jar = CookieJar::from_header("Cookie: a=1; b=2").unwrap();
+    assert_eq!(jar.header("exhentai.org").as_deref(), Some("a=1; b=2"));
+}
+
+#[test]
+fn search_results() {
+    use select::document::Document;
+    use super::parser;
+
+    let info = |html: &str| parser::page_info(&Document::from(html)).unwrap();
+    let count = |html: &str| info(html).map(|x| (x.total, !x.approximate));
+
+    assert_eq!(count(r#"<p class="ip">Showing 608,394 results</p>"#), Some((608394, true)));
+    assert_eq!(count(r#"<div class="searchtext"><p class="ip">Found about 10,000 results.</p></div>"#), Some((10000, false)));
+    assert_eq!(count("<p>No hits found</p>"), None);
+
+    let shown = info("<p class=\"ip\">Showing 26 \u{2013} 50 of 1,632 results</p>\
+        <table class=\"ptt\"><tr><td><a>&lt;</a></td><td><a>1</a></td><td><a>2</a></td>\
+        <td>3</td><td><a>66</a></td><td><a>&gt;</a></td></tr></table>").unwrap();
+
+    assert_eq!(shown, PageInfo {
+        first_shown: Some(26),
+        last_shown: Some(50),
+        total: 1632,
+        approximate: false,
+        pages: Some(66),
+    });
+
+    let last = Document::from(r#"<table class="ptt"><tr><td><a>1</a></td><td><a>2</a></td><td>&gt;</td></tr></table>"#);
+    let more = Document::from(r#"<table class="ptt"><tr><td><a>1</a></td><td><a href="?page=1">&gt;</a></td></tr></table>"#);
+
+    assert!(!parser::has_next_page(&last));
+    assert!(parser::has_next_page(&more));
+}
+
+#[test]
+fn dates() {
+    use super::date;
+
+    assert_eq!(date::parse("2021-03-04 12:34"), Some((2021, 3, 4, 12, 34)));
+    assert_eq!(date::parse("04 March 2021, 12:34"), Some((2021, 3, 4, 12, 34)));
+    assert_eq!(date::parse("2021-13-04 12:34"), None);
+    assert_eq!(date::parse("yesterday"), None);
+}
+
+#[test]
+fn file_sizes() {
+    use super::article::parse_size;
+
+    assert_eq!(parse_size("24.15 MiB"), Some(25323110));
+    assert_eq!(parse_size("512 KiB"), Some(524288));
+    assert_eq!(parse_size("1.00 GiB"), Some(1 << 30));
+    assert_eq!(parse_size("300 B"), Some(300));
+    assert_eq!(parse_size("24.15"), None);
+    assert_eq!(parse_size("many MiB"), None);
+}
+
+#[test]
+fn languages() {
+    assert_eq!(Language::from("Korean "), Language::Korean);
+    assert_eq!(Language::from("ko"), Language::Korean);
+    assert_eq!(Language::from("Esperanto"), Language::Other("Esperanto".to_owned()));
+    assert_eq!(Language::English.to_string(), "English");
+    assert_eq!(Language::Korean.code(), Some("ko"));
+    assert_eq!(Language::Korean.tag(), "language:korean".parse::<Tag>().unwrap());
+
+    let tags = vec!["language:translated", "language:english"]
+        .into_iter()
+        .map(|x| x.parse::<Tag>().unwrap())
+        .collect::<TagMap>();
+
+    assert_eq!(Language::from_tags(&tags), Language::English);
+    assert_eq!(Language::from_tags(&TagMap::new()), Language::Japanese);
+    assert_eq!(Language::from_tag(&"language:rewrite".parse().unwrap()), None);
+}
+
+// a gallery of the list, for tests to change what they look at
+pub(crate) fn draft_meta() -> DraftMeta {
+    DraftMeta {
+        kind: ArticleKind::Manga,
+        thumb: String::new(),
+        thumb_crop: None,
+        posted: "2021-03-04 12:34".to_owned(),
+        path: "https://e-hentai.org/g/1234/abcdef1234/".to_owned(),
+        title: "some manga".to_owned(),
+        tags: TagMap::new(),
+        uploader: "someone".to_owned(),
+        length: 20,
+        rating: None,
+        torrents: false,
+        favorite: None,
+        file_size: None,
+        expunged: false,
+    }
+}
+
+// the same for a gallery page
+pub(crate) fn article_meta() -> ArticleMeta {
+    ArticleMeta {
+        path: "https://e-hentai.org/g/1234/abcdef1234/".to_owned(),
+        title: "title".to_owned(),
+        original_title: String::new(),
+        kind: ArticleKind::Doujinshi,
+        thumb: String::new(),
+        uploader: "someone".to_owned(),
+        posted: "2021-03-04 12:34".to_owned(),
+        parent: None,
+        visible: true,
+        language: Language::Japanese,
+        translated: false,
+        file_size: String::new(),
+        length: 20,
+        favorited: 0,
+        rating_count: 0,
+        rating: 0.0,
+        tags: TagMap::new(),
+    }
+}
+
+[test]
+fn meta_identity() {
+    use std::collections::HashSet;
+
+    let meta = |path: &str, title: &str| DraftMeta {
+        path: path.to_owned(),
+        title: title.to_owned(),
+        ..draft_meta()
+    };
+
+    let set = vec![
+        meta("https://e-hentai.org/g/1234/abcdef1234/", "old title"),
+        meta("https://exhentai.org/g/1234/abcdef1234/", "new title"),
+        meta("https://e-hentai.org/g/5678/abcdef1234/", "old title"),
+    ].into_iter().collect::<HashSet<_>>();
+
+    assert_eq!(set.len(), 2);
+}
+
+#[test]
+fn meta_summary() {
+    let mut meta = DraftMeta {
+        tags: vec!["language:korean".parse::<Tag>().unwrap()].into_iter().collect(),
+        rating: Some(4.5),
+        ..draft_meta()
+    };
+
+    assert_eq!(meta.to_string(), "some manga [Korean] (20 pages)");
+    assert_eq!(meta.summary(), "some manga [Korean] (20 pages)");
+
+    meta.title = "a".repeat(100);
+    assert_eq!(meta.summary(), format!("{}... [Korean] (20 pages)", "a".repeat(60)));
+}
+
+#[cfg(feature = "feed")]
+#[test]
+fn feed_render() {
+    let meta = DraftMeta {
+        thumb: "https://ehgt.org/t/ab/cd/abcd-1280x1811-jpg_250.webp".to_owned(),
+        title: "tom & jerry".to_owned(),
+        tags: vec!["language:korean".parse::<Tag>().unwrap()].into_iter().collect(),
+        rating: Some(4.5),
+        ..draft_meta()
+    };
+
+    let mut feed = Feed::new(FeedFormat::Rss, "korean", "https://e-hentai.org/?f_search=korean");
+    let rss = feed.render(vec![&meta]);
+
+    assert!(rss.contains("<title>tom &amp; jerry</title>"));
+    assert!(rss.contains("<pubDate>04 Mar 2021 12:34:00 +0000</pubDate>"));
+    assert!(rss.contains("<category>language:korean</category>"));
+    assert!(rss.contains("type=\"image/webp\" length=\"0\""));
+
+    feed.format = FeedFormat::Atom;
+    let atom = feed.render(vec![&meta]);
+
+    assert!(atom.contains("<id>https://e-hentai.org/g/1234/abcdef1234/</id>"));
+    assert!(atom.contains("<updated>2021-03-04T12:34:00Z</updated>"));
+    assert!(atom.contains("<category term=\"language:korean\"/>"));
+    assert!(atom.contains("<author><name>someone</name></author>"));
+
+    // a feed with nothing is still a valid one
+    assert!(feed.render(Vec::<&DraftMeta>::new()).contains("<updated>1970-01-01T00:00:00Z</updated>"));
+}
+
+#[tokio::test]
+async fn crawl_watermark() {
+    let explorer = Explorer::new();
+
+    // nothing crawled yet, so it's where it started
+    let crawler = explorer.crawl_since("language:korean", 1234);
+    assert_eq!(crawler.watermark(), Some(1234));
+    assert_eq!(explorer.crawl("language:korean").watermark(), None);
+
+    let path = std::env::temp_dir().join("lude-crawl-since.json");
+    tokio::fs::write(&path, r#"{
+        "source": { "search": "language:korean" },
+        "page": 2,
+        "done": false,
+        "lowest": 5678,
+        "count": 2,
+        "since": 1234,
+        "newest": 6789
+    }"#).await.unwrap();
+
+    let crawler = explorer.resume_crawl("language:korean", &path).await.unwrap();
+    assert_eq!(crawler.watermark(), Some(6789));
+
+    tokio::fs::remove_file(&path).await.unwrap();
+}
+
+#[tokio::test]
+async fn crawl_checkpoint() {
+    let path = std::env::temp_dir().join("lude-crawl.json");
+    tokio::fs::write(&path, r#"{
+        "source": { "category": "Artist CG" },
+        "page": 12,
+        "done": true,
+        "lowest": 1234,
+        "count": 2
+    }"#).await.unwrap();
+
+    let explorer = Explorer::new();
+    let mut crawler = explorer.resume_crawl(ArticleKind::ArtistCG, &path).await.unwrap();
+
+    assert_eq!(crawler.source(), &CrawlSource::Category(ArticleKind::ArtistCG));
+    assert_eq!(crawler.page(), 12);
+    assert_eq!(crawler.seen(), 2);
+
+    // a finished one doesn't go to the site again
+    assert!(crawler.next().await.unwrap().is_none());
+
+    // nor is it taken for another one
+    assert!(explorer.resume_crawl("language:korean", &path).await.is_err());
+
+    tokio::fs::remove_file(&path).await.unwrap();
+    let crawler = explorer.resume_crawl("language:korean", &path).await.unwrap();
+    assert_eq!(crawler.page(), 0);
+    assert!(!crawler.is_done());
+}
+
+#[tokio::test]
+async fn dump_rows() {
+    let meta = DraftMeta {
+        title: "one, \"two\"".to_owned(),
+        tags: vec!["language:korean".parse::<Tag>().unwrap()].into_iter().collect(),
+        rating: Some(4.5),
+        ..draft_meta()
+    };
+
+    let fields = "gid,title,pages,file_size"
+        .split(',')
+        .map(|x| x.parse::<DumpField>().unwrap())
+        .collect::<Vec<_>>();
+
+    let mut csv = Dumper::new(Vec::new(), DumpFormat::Csv).fields(&fields);
+    csv.write_stream(futures::stream::iter(vec![&meta, &meta])).await.unwrap();
+    assert_eq!(csv.rows(), 2);
+
+    let csv = String::from_utf8(csv.finish().await.unwrap()).unwrap();
+    assert_eq!(csv, "gid,title,pages,file_size\n\
+        1234,\"one, \"\"two\"\"\",20,\n\
+        1234,\"one, \"\"two\"\"\",20,\n");
+
+    let mut ndjson = Dumper::new(Vec::new(), DumpFormat::Ndjson)
+        .fields(&[DumpField::Gid, DumpField::Tags, DumpField::Posted]);
+    ndjson.write(&meta).await.unwrap();
+
+    let ndjson = String::from_utf8(ndjson.finish().await.unwrap()).unwrap();
+    assert_eq!(ndjson, "{\"gid\":1234,\"posted\":\"2021-03-04 12:34\",\"tags\":[\"language:korean\"]}\n");
+
+    assert!("nothing".parse::<DumpField>().is_err());
+}
+
+#[cfg(feature = "lanraragi")]
+#[test]
+fn lanraragi_tags() {
+    use super::lanraragi;
+
+    let meta = ArticleMeta {
+        tags: vec!["artist:foo", "female:glasses"]
+            .into_iter()
+            .map(|x| x.parse::<Tag>().unwrap())
+            .collect(),
+        ..article_meta()
+    };
+
+    assert_eq!(
+        lanraragi::tags(&meta),
+        "artist:foo, female:glasses, category:doujinshi, source:e-hentai.org/g/1234/abcdef1234"
+    );
+
+    assert_eq!(lanraragi::base64(b"foob"), "Zm9vYg==");
+    assert_eq!(lanraragi::base64(b"foobar"), "Zm9vYmFy");
+}
+
+#[cfg(feature = "lanraragi")]
+#[tokio::test]
+async fn lanraragi_upload() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use super::lanraragi::Lanraragi;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // a server which takes one request and tells what it got
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut req = Vec::new();
+        let mut buf = [0; 4096];
+
+        let head = loop {
+            let read = socket.read(&mut buf).await.unwrap();
+            req.extend_from_slice(&buf[..read]);
+
+            if let Some(pos) = req.windows(4).position(|x| x == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+
+        let len = String::from_utf8_lossy(&req[..head])
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length: ").map(str::to_owned))
+            .unwrap()
+            .parse::<usize>()
+            .unwrap();
+
+        while req.len() < head + len {
+            let read = socket.read(&mut buf).await.unwrap();
+            req.extend_from_slice(&buf[..read]);
+        }
+
+        let body = r#"{"success":1,"id":"0123abcd"}"#;
+        socket.write_all(format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(), body
+        ).as_bytes()).await.unwrap();
+
+        String::from_utf8(req).unwrap()
+    });
+
+    let path = std::env::temp_dir().join(format!("lude-upload-{}.cbz", std::process::id()));
+    tokio::fs::write(&path, b"not really a zip").await.unwrap();
+
+    let meta = ArticleMeta {
+        title: "some doujinshi".to_owned(),
+        ..article_meta()
+    };
+
+    let lanraragi = Lanraragi::new(&format!("http://{}", addr), Some("key"));
+    let id = lanraragi.upload(&path, &meta, Some("SET_1234")).await.unwrap();
+    tokio::fs::remove_file(&path).await.unwrap();
+
+    assert_eq!(id, "0123abcd");
+
+    // hyper writes the names of headers in lowercase
+    let req = server.await.unwrap();
+
+    assert!(req.starts_with("PUT /api/archives/upload HTTP/1.1\r\n"));
+    assert!(req.contains("authorization: Bearer a2V5\r\n"));
+    assert!(req.contains("name=\"title\"\r\n\r\nsome doujinshi\r\n"));
+    assert!(req.contains("name=\"category_id\"\r\n\r\nSET_1234\r\n"));
+    assert!(req.contains("name=\"file_checksum\"\r\n\r\n240db3986ff0c20e0de52ae69943b98496cb779b\r\n"));
+    assert!(req.contains("Content-Type: application/zip\r\n\r\nnot really a zip\r\n--"));
+}
+
+#[test]
+fn meta_enrich() {
+    let mut meta = DraftMeta {
+        tags: vec!["language:korean".parse::<Tag>().unwrap()].into_iter().collect(),
+        rating: Some(4.5),
+        ..draft_meta()
+    };
+
+    let tags = vec!["language:korean", "female:glasses", "artist:someone"]
+        .into_iter()
+        .map(|x| x.parse::<Tag>().unwrap())
+        .collect::<TagMap>();
+
+    meta.enrich(GalleryData {
+        id: GalleryId::new(1234, "abcdef1234"),
+        title: "some manga".to_owned(),
+        title_jpn: String::new(),
+        kind: ArticleKind::Manga,
+        thumb: String::new(),
+        uploader: "someone".to_owned(),
+        posted: 1614861240,
+        file_count: 20,
+        file_size: 12345678,
+        expunged: true,
+        rating: 4.52,
+        torrent_count: 1,
+        tags: tags.clone(),
+    });
+
+    assert_eq!(meta.tags, tags);
+    assert_eq!(meta.file_size, Some(12345678));
+    assert!(meta.expunged);
+    assert!(meta.torrents);
+    // the rest is as the list showed
+    assert_eq!(meta.rating, Some(4.5));
+}
+
+#[test]
+fn parsed_title() {
+    let title = ParsedTitle::parse(
+        "(C97) [Bad Mushrooms (Chicke III, 4why)] Nibun no Yuudou (Gotoubun no Hanayome) [Korean]"
+    );
+
+    assert_eq!(title.event.as_deref(), Some("C97"));
+    assert_eq!(title.circle.as_deref(), Some("Bad Mushrooms"));
+    assert_eq!(title.artists, vec!["Chicke III", "4why"]);
+    assert_eq!(title.title, "Nibun no Yuudou");
+    assert_eq!(title.parody.as_deref(), Some("Gotoubun no Hanayome"));
+    assert_eq!(title.language, Some(Language::Korean));
+
+    let title = ParsedTitle::parse("[Someone] Some Title [English] [Digital]");
+    assert_eq!(title.circle, None);
+    assert_eq!(title.artists, vec!["Someone"]);
+    assert_eq!(title.title, "Some Title");
+    assert_eq!(title.language, Some(Language::English));
+    assert_eq!(title.extra, vec!["Digital"]);
+
+    let title = ParsedTitle::parse("(Only Parentheses)");
+    assert_eq!(title.event, None);
+    assert_eq!(title.title, "(Only Parentheses)");
+}
+
+#[test]
+fn rating() {
+    use select::document::Document;
+    use super::parser;
+
+    let doc = Document::from(r#"
+        <script>var apiuid = 1234; var average_rating = 4.63; var display_rating = 4.5;</script>
+        <div id="rating_image" class="ir irb"></div>
+        <table><tr><td id="rating_count">123</td></tr></table>
+    "#);
+
+    let rating = parser::rating(&doc).unwrap();
+    assert_eq!(rating.average, 4.63);
+    assert_eq!(rating.count, 123);
+    assert_eq!(rating.own_vote, Some(4.5));
+
+    let doc = Document::from(r#"
+        <script>var average_rating = 4.63; var display_rating = 4.63;</script>
+        <div id="rating_image" class="ir"></div>
+        <table><tr><td id="rating_count">123</td></tr></table>
+    "#);
+
+    assert_eq!(parser::rating(&doc).unwrap().own_vote, None);
+}
+
+#[test]
+fn draft_filter() {
+    use super::filter::{self, DraftFilter};
+
+    let meta = |length: usize, rating: Option<f32>, tags: &[&str]| DraftMeta {
+        path: format!("https://e-hentai.org/g/{}/abcdef1234/", length),
+        tags: tags.iter().map(|x| x.parse::<Tag>().unwrap()).collect(),
+        length,
+        rating,
+        ..draft_meta()
+    };
+
+    let include = vec!["female:glasses".parse::<Tag>().unwrap()].into_iter().collect();
+    let exclude = vec!["language:english".parse::<Tag>().unwrap()].into_iter().collect();
+
+    let mut drafts = DraftFilter::default();
+    drafts.add(filter::min_pages(10));
+    drafts.add(filter::min_rating(4.0));
+    drafts.add(filter::tags(include, exclude));
+    drafts.add(filter::language(Language::Korean));
+
+    let list = vec![
+        meta(20, Some(4.5), &["female:glasses", "language:korean"]),
+        meta(5, Some(4.5), &["female:glasses", "language:korean"]),
+        meta(30, None, &["female:glasses", "language:korean"]),
+        meta(40, Some(5.0), &["female:glasses", "language:english"]),
+        meta(50, Some(5.0), &["language:korean"]),
+    ];
+
+    let (list, stop) = drafts.apply(list);
+    assert_eq!(list.iter().map(|x| x.length).collect::<Vec<_>>(), vec![20]);
+    assert!(!stop);
+
+    drafts.set_until(|meta| meta.length < 25);
+
+    let (list, stop) = drafts.apply(vec![
+        meta(20, Some(4.5), &["female:glasses", "language:korean"]),
+        meta(30, Some(4.5), &["female:glasses", "language:korean"]),
+        meta(21, Some(4.5), &["female:glasses", "language:korean"]),
+    ]);
+
+    assert_eq!(list.len(), 1);
+    assert!(stop);
+}
+
+#[test]
+fn star_rating() {
+    use select::document::Document;
+    use super::parser;
+
+    let doc = Document::from(r#"<table class="itg gltc"><tbody>
+        <tr><th>Category</th><th>Published</th><th>Title</th><th>Favorited</th></tr>
+        <tr>
+            <td class="gl1c glcat"><div class="cn ct2">Manga</div></td>
+            <td class="gl2c">
+                <div class="glthumb"><img src="https://ehgt.org/t/00/00/thumb.jpg"></div>
+                <div><div id="posted_1234" style="border-color:#f00">2021-01-02 03:04</div>
+                <div class="ir" style="background-position:-16px -21px;opacity:1"></div></div>
+            </td>
+            <td class="gl3c glname">
+                <a href="https://e-hentai.org/g/1234/abcdef0123/"><div class="glink">some manga</div></a>
+            </td>
+            <td class="glfc glfav"><p>2021-05-06</p><p>07:08</p></td>
+        </tr>
+    </tbody></table>"#);
+
+    let list = parser::favorites(&doc).unwrap().unwrap();
+    assert_eq!(list[0].meta.rating, Some(3.5));
+}
+
+#[test]
+fn stats_report() {
+    let meta = |posted: &str, tags: &[&str]| DraftMeta {
+        kind: ArticleKind::Doujinshi,
+        posted: posted.to_owned(),
+        title: "some doujinshi".to_owned(),
+        tags: tags.iter().map(|x| x.parse::<Tag>().unwrap()).collect(),
+        length: 24,
+        ..draft_meta()
+    };
+
+    let mut stats = SearchStats::new();
+    stats.add_draft(&meta("2021-03-04 12:34", &["artist:foo", "group:bar"]));
+    stats.add_draft(&meta("2021-03-20 00:00", &["artist:foo"]));
+    stats.add_draft(&DraftMeta {
+        rating: Some(3.5),
+        ..meta("2021-05-01 09:00", &["artist:baz"])
+    });
+
+    let report = stats.report(1);
+    assert_eq!(report["galleries"], 3);
+    assert_eq!(report["pages"], 72);
+    assert_eq!(report["monthly"]["2021-03"], 2);
+    assert_eq!(report["monthly"]["2021-05"], 1);
+    assert_eq!(report["top_artists"][0]["name"], "foo");
+    assert_eq!(report["top_artists"][0]["count"], 2);
+    assert_eq!(report["top_groups"][0]["name"], "bar");
+    // only the one with stars is counted
+    assert_eq!(report["ratings"][7], 1);
+    assert_eq!(stats.ratings.iter().sum::<usize>(), 1);
+}
+
+#[test]
+fn lenient_list() {
+    use select::document::Document;
+    use super::parser;
+
+    // the site gives it without any whitespace between the tags
+    let doc = Document::from("<table class=\"itg gltc\"><tbody>\
+        <tr><th>Category</th><th>Published</th><th>Title</th><th>Uploader</th></tr>\
+        <tr>\
+            <td class=\"gl1c glcat\"><div class=\"cn ct2\">Manga</div></td>\
+            <td class=\"gl2c\">\
+                <div></div>\
+                <div class=\"glthumb\"><div><img src=\"https://ehgt.org/t/00/00/thumb.jpg\"></div></div>\
+                <div><div id=\"posted_1234\">2021-01-02 03:04</div></div>\
+            </td>\
+            <td class=\"gl3c glname\"><a href=\"https://e-hentai.org/g/1234/abcdef0123/\">\
+                <div class=\"glink\">some manga</div>\
+                <div><div class=\"gt\" title=\"artist:someone\">someone</div></div>\
+            </a></td>\
+            <td class=\"gl4c glhide\"><div><a href=\"https://e-hentai.org/uploader/someone\">someone</a></div><div>20 pages</div></td>\
+        </tr>\
+        <tr>\
+            <td class=\"gl1c glcat\"><div class=\"cn ct2\">Manga</div></td>\
+            <td class=\"gl2c\"></td>\
+        </tr>\
+    </tbody></table>");
+
+    assert!(parser::article_list(&doc).is_err());
+
+    let (list, warnings) = parser::article_list_lenient(&doc).unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].length, 20);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].index, 1);
+}
+
+#[test]
+fn parse_error() {
+    use select::document::Document;
+    use super::parser;
+
+    let path = "https://e-hentai.org/g/1234/abcdef1234/";
+
+    let doc = Document::from(r#"<div id="gd2"></div>"#);
+    let err = parser::article(&doc, path.to_owned()).unwrap_err();
+    let err = err.downcast::<ParseError>().unwrap();
+
+    assert_eq!(err.field, "title");
+    assert_eq!(err.url.as_deref(), Some(path));
+    assert!(err.snippet.contains("gd2"));
+
+    let doc = Document::from("<p>nothing here</p>");
+    let err = parser::article(&doc, path.to_owned()).unwrap_err();
+    assert_eq!(err.downcast::<ParseError>().unwrap().selector, "#gd2");
+
+    // what the site gives for an image page which isn't there
+    let doc = Document::from("<html><body>Invalid page.</body></html>");
+    let err = parser::image(&doc).unwrap_err();
+    assert_eq!(err.downcast::<ParseError>().unwrap().selector, "#img");
+
+    let doc = Document::from(r#"<img id="img">"#);
+    let err = parser::image(&doc).unwrap_err();
+    assert_eq!(err.downcast::<ParseError>().unwrap().selector, "#img[src]");
+
+    assert!(parser::profile(&Document::from("<p></p>"), 1234).is_err());
+
+    let doc = Document::from(r#"<div id="gdt"><div class="gdtm"><div></div></div></div>"#);
+    let err = parser::previews(&doc).unwrap_err();
+    assert_eq!(err.downcast::<ParseError>().unwrap().selector, "#gdt .gdtm a[href]");
+    assert!(parser::previews(&Document::from("<p></p>")).is_err());
+}
+
+#[test]
+fn display_modes() {
+    use select::document::Document;
+    use super::parser;
+
+    let doc = Document::from("<div class=\"itg gld\">\
+        <div class=\"gl1t\">\
+            <a href=\"https://e-hentai.org/g/1234/abcdef0123/\"><span class=\"glink\">some manga</span></a>\
+            <div class=\"gl3t\"><a href=\"https://e-hentai.org/g/1234/abcdef0123/\"><img src=\"https://ehgt.org/t/00/00/thumb.jpg\"></a></div>\
+            <div class=\"gl5t\">\
+                <div><div class=\"cs ct2\">Manga</div><div id=\"posted_1234\">2021-01-02 03:04</div></div>\
+                <div><div class=\"ir\" style=\"background-position:-16px -21px;opacity:1\"></div><div>20 pages</div></div>\
+            </div>\
+        </div>\
+    </div>");
+
+    assert_eq!(parser::display_mode(&doc), Some(DisplayMode::Thumbnail));
+
+    let list = parser::article_list(&doc).unwrap().unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].title, "some manga");
+    assert_eq!(list[0].kind, ArticleKind::Manga);
+    assert_eq!(list[0].posted, "2021-01-02 03:04");
+    assert_eq!(list[0].length, 20);
+    assert_eq!(list[0].rating, Some(3.5));
+
+    let doc = Document::from("<table class=\"itg glte\"><tbody><tr>\
+        <td class=\"gl1e\"><div><a href=\"https://e-hentai.org/g/1234/abcdef0123/\"><img src=\"thumb.jpg\"></a></div></td>\
+        <td class=\"gl2e\"><div>\
+            <div class=\"gl3e\">\
+                <div class=\"cn ct3\">Artist CG</div>\
+                <div id=\"posted_1234\">2021-01-02 03:04</div>\
+                <div><a href=\"https://e-hentai.org/uploader/someone\">someone</a></div>\
+                <div>3 pages</div>\
+            </div>\
+            <a href=\"https://e-hentai.org/g/1234/abcdef0123/\"><div>\
+                <div class=\"glink\">some cg</div>\
+                <div><table><tr><td><div class=\"gt\" title=\"artist:someone\">someone</div></td></tr></table></div>\
+            </div></a>\
+        </div></td>\
+    </tr></tbody></table>");
+
+    assert_eq!(parser::display_mode(&doc), Some(DisplayMode::Extended));
+
+    let list = parser::article_list(&doc).unwrap().unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].uploader, "someone");
+    assert_eq!(list[0].length, 3);
+    assert_eq!(list[0].tags.first(TagKind::Artist), Some("someone"));
+}
+
+// pages saved from the site, to test the parser without it
+macro_rules! fixture {
+    ($name:literal) => {
+        include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/", $name))
+    };
+}
+
+#[test]
+fn fixture_gallery() {
+    use select::document::Document;
+    use super::parser;
+
+    let path = "https://e-hentai.org/g/1234/abcdef0123/";
+    let doc = Document::from(fixture!("gallery.html"));
+
+    let meta = parser::article(&doc, path.to_owned()).unwrap();
+    assert_eq!(meta.title, "[Some Circle (Someone)] Some Manga [English]");
+    assert_eq!(meta.original_title, "[サークル (誰か)] ある漫画");
+    assert_eq!(meta.kind, ArticleKind::Manga);
+    assert_eq!(meta.thumb, "https://ehgt.org/00/00/thumb_l.jpg");
+    assert_eq!(meta.uploader, "someone");
+    assert_eq!(meta.posted, "2021-03-04 12:34");
+    assert_eq!(meta.parent.as_deref(), Some("https://e-hentai.org/g/1200/0123abcdef/"));
+    assert!(meta.visible);
+    assert_eq!(meta.language, Language::English);
+    assert!(meta.translated);
+    assert_eq!(meta.file_size, "52.38 MiB");
+    assert_eq!(meta.length, 24);
+    assert_eq!(meta.favorited, 123);
+    assert_eq!(meta.rating_count, 45);
+    assert_eq!(meta.rating, 4.52);
+    assert_eq!(meta.tags.first(TagKind::Artist), Some("someone"));
+    assert_eq!(meta.tags.namespace(TagKind::Female).count(), 2);
+
+    let previews = parser::previews(&doc).unwrap();
+    assert_eq!(previews.len(), 2);
+    assert_eq!(previews[1].entry.page, 2);
+    assert_eq!(previews[1].url, "https://ehgt.org/m/001234/1234-00.jpg");
+    assert_eq!(previews[1].crop, Some(Crop { x: 100, y: 0, width: 100, height: 144 }));
+
+    let comments = parser::comments(&doc).unwrap();
+    assert_eq!(comments.len(), 2);
+    assert_eq!(comments[0].writer, "someone");
+    assert_eq!(comments[0].posted, "04 March 2021, 12:40");
+    assert!(comments[0].vote.is_none());
+
+    let comment = &comments[1];
+    assert_eq!(comment.writer_id, Some(42));
+    assert_eq!(comment.content, "lovely art");
+    assert_eq!(comment.edited.as_deref(), Some("05 March 2021, 01:10"));
+
+    let vote = comment.vote.as_ref().unwrap();
+    assert_eq!(vote.score, 12);
+    assert_eq!(vote.voters.len(), 3);
+    assert_eq!(vote.voters[1], ("alice".to_owned(), 5));
+    assert_eq!(vote.omitted, 2);
+}
+
+#[test]
+fn fixture_search() {
+    use select::document::Document;
+    use super::parser;
+
+    let doc = Document::from(fixture!("search.html"));
+    assert_eq!(parser::display_mode(&doc), Some(DisplayMode::Compact));
+
+    let list = parser::article_list(&doc).unwrap().unwrap();
+    assert_eq!(list.len(), 2);
+
+    assert_eq!(list[0].path, "https://e-hentai.org/g/1234/abcdef0123/");
+    assert_eq!(list[0].kind, ArticleKind::Manga);
+    assert_eq!(list[0].posted, "2021-03-04 12:34");
+    assert_eq!(list[0].uploader, "someone");
+    assert_eq!(list[0].length, 24);
+    assert_eq!(list[0].rating, Some(4.5));
+    assert_eq!(list[0].language(), Language::English);
+    assert!(list[0].torrents);
+    assert_eq!(list[0].favorite, Some(1));
+
+    assert_eq!(list[1].kind, ArticleKind::Doujinshi);
+    assert_eq!(list[1].length, 1);
+    assert_eq!(list[1].rating, Some(3.0));
+    assert!(!list[1].torrents);
+    assert_eq!(list[1].favorite, None);
+    assert_eq!(list[1].tags.first(TagKind::Parody), Some("some parody"));
+}
+
+#[tokio::test]
+async fn parse_off_runtime() {
+    use hyper::body::Bytes;
+    use super::{client, parser};
+
+    // small pages are parsed right there, and large ones on another thread
+    let small = Bytes::from_static(fixture!("search.html").as_bytes());
+    let large = Bytes::from(format!("{}<!--{}-->", fixture!("search.html"), "x".repeat(64 * 1024)));
+
+    for bytes in vec![small, large] {
+        let list = client::parse_with(bytes, |doc| parser::article_list(doc))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].path, "https://e-hentai.org/g/1234/abcdef0123/");
+    }
+}
+
+#[cfg(feature = "vcr")]
+#[tokio::test]
+async fn replay() {
+    use super::vcr::{self, Recorded};
+
+    let dir = std::env::temp_dir().join("lude-replay");
+    let id = "https://e-hentai.org/g/1234/abcdef0123/".parse::<GalleryId>().unwrap();
+
+    vcr::store(&dir, &Recorded {
+        key: format!("GET {}", id.path()),
+        status: 200,
+        headers: vec![("content-type".to_owned(), "text/html".to_owned())],
+        body: fixture!("gallery.html").into(),
+    }).await.unwrap();
+
+    let explorer = ExplorerBuilder::new().replay(&dir).build().await.unwrap();
+
+    let meta = explorer.article_meta(&id).await.unwrap();
+    assert_eq!(meta.length, 24);
+
+    // what's not recorded doesn't go to the site
+    let other = "https://e-hentai.org/g/5678/0123abcdef/".parse::<GalleryId>().unwrap();
+    let err = explorer.article_meta(&other).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<EhError>(), Some(EhError::NotFound(_))));
+}
+
+#[cfg(feature = "vcr")]
+#[tokio::test]
+async fn watch_new() {
+    use std::time::Duration;
+    use futures::stream::StreamExt;
+    use super::vcr::{self, Recorded};
+
+    let dir = std::env::temp_dir().join("lude-watch");
+
+    vcr::store(&dir, &Recorded {
+        key: "GET https://e-hentai.org/?page=0&f_search=test".to_owned(),
+        status: 200,
+        headers: vec![("content-type".to_owned(), "text/html".to_owned())],
+        body: fixture!("search.html").into(),
+    }).await.unwrap();
+
+    let explorer = ExplorerBuilder::new().replay(&dir).build().await.unwrap();
+
+    // the oldest first, and only those after the gid
+    let drafts = explorer
+        .watch_since("test", Duration::from_millis(1), 1233)
+        .take(2)
+        .collect::<Vec<_>>()
+        .await;
+
+    let gids = drafts.iter().map(|x| x.meta().id().unwrap().gid).collect::<Vec<_>>();
+    assert_eq!(gids, vec![1234, 5678]);
+}
+
+#[cfg(feature = "vcr")]
+#[tokio::test]
+async fn crawl_since_replay() {
+    use super::vcr::{self, Recorded};
+
+    let dir = std::env::temp_dir().join(format!("lude-crawl-replay-{}", std::process::id()));
+
+    // the newest first, as the site sorts them
+    let page = fixture!("search.html");
+    let (rows, tail) = page.split_at(page.find("</table>").unwrap());
+    let rows = rows.split("<tr><td class=\"gl1c").collect::<Vec<_>>();
+    let page = format!("{1}<tr><td class=\"gl1c{3}<tr><td class=\"gl1c{2}{0}", tail, rows[0], rows[1], rows[2]);
+
+    vcr::store(&dir, &Recorded {
+        key: "GET https://e-hentai.org/?page=0&f_search=test".to_owned(),
+        status: 200,
+        headers: vec![("content-type".to_owned(), "text/html".to_owned())],
+        body: page.into(),
+    }).await.unwrap();
+
+    let explorer = ExplorerBuilder::new().replay(&dir).build().await.unwrap();
+    let mut crawler = explorer.crawl_since("test", 1234);
+
+    // the api isn't on the tape yet, so it fails as if the site did
+    assert!(crawler.next().await.is_err());
+    assert_eq!(crawler.watermark(), Some(1234));
+    assert_eq!(crawler.page(), 0);
+    assert!(!crawler.is_done());
+
+    vcr::store(&dir, &Recorded {
+        key: r#"POST https://api.e-hentai.org/api.php {"gidlist":[[5678,"0123abcdef"]],"method":"gdata","namespace":1}"#.to_owned(),
+        status: 200,
+        headers: vec![("content-type".to_owned(), "application/json".to_owned())],
+        body: r#"{"gmetadata":[{"gid":5678,"token":"0123abcdef","title":"Another","category":"Doujinshi","posted":"1614585600","filecount":"1","filesize":1234,"expunged":false,"rating":"4.5","torrentcount":"0","tags":["parody:some parody"]}]}"#.into(),
+    }).await.unwrap();
+
+    // the same page again, up to the one it started since
+    let list = crawler.next().await.unwrap().unwrap();
+    assert_eq!(list.iter().map(|x| x.id.gid).collect::<Vec<_>>(), vec![5678]);
+    assert_eq!(crawler.watermark(), Some(5678));
+    assert_eq!(crawler.seen(), 1);
+    assert!(crawler.is_done());
+    assert!(crawler.next().await.unwrap().is_none());
+
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+}
+
+#[test]
+fn error_pages() {
+    use hyper::StatusCode;
+    use super::client::inspect;
+
+    let challenge = b"<html><head><title>Just a moment...</title></head>\
+        <body><script src=\"/cdn-cgi/challenge-platform/h/b/orchestrate/jsch/v1\"></script></body></html>";
+
+    match inspect(StatusCode::FORBIDDEN, false, true, challenge) {
+        Err(EhError::ChallengeRequired { body }) => assert!(body.contains("Just a moment")),
+        other => panic!("not a challenge: {:?}", other),
+    }
+
+    let down = b"<html><body>The site is down for maintenance. Please try again later.</body></html>";
+
+    match inspect(StatusCode::OK, false, true, down) {
+        Err(EhError::SiteUnavailable { status, .. }) => assert_eq!(status, 200),
+        other => panic!("not a downtime: {:?}", other),
+    }
+
+    assert!(matches!(
+        inspect(StatusCode::BAD_GATEWAY, false, false, b""),
+        Err(EhError::SiteUnavailable { status: 502, .. })
+    ));
+
+    // real pages go through
+    let gallery = fixture!("gallery.html");
+    assert!(inspect(StatusCode::OK, false, true, gallery.as_bytes()).is_ok());
+    assert!(inspect(StatusCode::OK, true, false, b"").is_err());
+
+    // even if they have the words, like in a title
+    let gallery = gallery.replace("Some Manga [English]", "Temporarily Unavailable [English]");
+    assert!(inspect(StatusCode::OK, false, true, gallery.as_bytes()).is_ok());
+
+    let api = br#"{"gmetadata":[{"gid":1234,"title":"Temporarily Unavailable"}]}"#;
+    assert!(inspect(StatusCode::OK, false, false, api).is_ok());
+}
+
+#[test]
+fn comment_markup() {
+    use select::document::Document;
+    use super::parser;
+
+    let doc = Document::from("<div class=\"c1\"><div class=\"c2\">\
+        <div class=\"c3\">Posted on 05 March 2021, 01:02 by: &nbsp; <a href=\"https://e-hentai.org/uploader/reader\">reader</a></div>\
+        <div class=\"c4 nosel\">Uploader Comment</div></div>\
+        <div class=\"c6\" id=\"comment_0\">read the <span style=\"font-weight:bold\">first</span> one, \
+        at <a href=\"https://e-hentai.org/g/1200/0123abcdef/\">here</a><br>\
+        it's <span class=\"spoiler\">great</span><br><br>\
+        <img src=\"https://ehgt.org/g/smile.gif\"> and <i>more</i></div>\
+        <div class=\"c7\"></div></div>");
+
+    let comments = parser::comments(&doc).unwrap();
+    let body = comments[0].body();
+
+    assert_eq!(body.paragraphs.len(), 2);
+    assert_eq!(body.paragraphs[0][1], Span::Bold(vec![Span::Text("first".to_owned())]));
+    assert_eq!(body.paragraphs[0][4], Span::Break);
+    assert_eq!(body.paragraphs[0][6], Span::Spoiler(vec![Span::Text("great".to_owned())]));
+    assert_eq!(body.paragraphs[1][0], Span::Image("https://ehgt.org/g/smile.gif".to_owned()));
+    assert_eq!(body.links(), vec!["https://e-hentai.org/g/1200/0123abcdef/"]);
+
+    assert_eq!(comments[0].content(), "read the first one, at here\nit's great\n\nand more");
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn posted_filter() {
+    use chrono::{TimeZone, Utc};
+    use select::document::Document;
+    use super::filter::{self, DraftFilter};
+    use super::parser;
+
+    let doc = Document::from(fixture!("search.html"));
+    let list = parser::article_list(&doc).unwrap().unwrap();
+
+    assert_eq!(list[0].posted_at(), Some(Utc.with_ymd_and_hms(2021, 3, 4, 12, 34, 0).unwrap()));
+
+    let mut drafts = DraftFilter::default();
+    drafts.add(filter::posted_before(Utc.with_ymd_and_hms(2021, 3, 4, 0, 0, 0).unwrap()));
+
+    let (passed, _) = drafts.apply(list.clone());
+    assert_eq!(passed.len(), 1);
+    assert_eq!(passed[0].length, 1);
+
+    let mut drafts = DraftFilter::default();
+    drafts.set_until(filter::posted_since(Utc.with_ymd_and_hms(2021, 3, 2, 0, 0, 0).unwrap()));
+
+    let (passed, stop) = drafts.apply(list);
+    assert_eq!(passed.len(), 1);
+    assert!(stop);
+}
+
+#[test]
+fn thumb_sprite() {
+    use select::document::Document;
+    use super::parser;
+
+    let doc = Document::from("<div class=\"itg gld\"><div class=\"gl1t\">\
+        <a href=\"https://e-hentai.org/g/1234/abcdef0123/\"><span class=\"glink\">some manga</span></a>\
+        <div class=\"gl3t\"><a href=\"https://e-hentai.org/g/1234/abcdef0123/\">\
+            <div style=\"width:200px; height:283px; background:transparent url(https://ehgt.org/t/sprite.jpg) -400px 0 no-repeat\">\
+            <img src=\"https://ehgt.org/g/blank.gif\"></div>\
+        </a></div>\
+        <div class=\"gl5t\"><div><div class=\"cs ct2\">Manga</div><div id=\"posted_1234\">2021-01-02 03:04</div></div></div>\
+    </div></div>");
+
+    let list = parser::article_list(&doc).unwrap().unwrap();
+    assert_eq!(list[0].thumb, "https://ehgt.org/t/sprite.jpg");
+    assert_eq!(list[0].thumb_crop, Some(Crop { x: 400, y: 0, width: 200, height: 283 }));
+
+    // a thumbnail by itself isn't cropped
+    let doc = Document::from(fixture!("search.html"));
+    let list = parser::article_list(&doc).unwrap().unwrap();
+    assert_eq!(list[0].thumb, "https://ehgt.org/t/00/00/1234-thumb.jpg");
+    assert_eq!(list[0].thumb_crop, None);
+}
+
+#[tokio::test]
+async fn request_priority() {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use super::queue::Queue;
+
+    let queue = Arc::new(Queue::new(Duration::from_millis(20)));
+    let order = Arc::new(Mutex::new(Vec::new()));
+
+    // take the turn, so that the others have to wait for the next
+    queue.wait(Priority::Interactive).await;
+
+    let handles = vec![Priority::Background, Priority::Download, Priority::Interactive]
+        .into_iter()
+        .map(|priority| {
+            let queue = queue.clone();
+            let order = order.clone();
+
+            tokio::spawn(async move {
+                queue.wait(priority).await;
+                order.lock().unwrap().push(priority);
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    assert_eq!(
+        *order.lock().unwrap(),
+        vec![Priority::Interactive, Priority::Download, Priority::Background]
+    );
+}
+
+#[test]
+fn download_file_name() {
+    use super::naming::file_name;
+
+    assert_eq!(file_name("1234 [Circle] Title: Part 1/2?"), "1234 [Circle] Title_ Part 1_2_");
+    assert_eq!(file_name("ends with dots..."), "ends with dots");
+    assert_eq!(file_name("  "), "_");
+    assert_eq!(file_name(&"a".repeat(200)).len(), 150);
+    assert_eq!(file_name("con.txt"), "_con.txt");
+}
+
+#[test]
+fn page_check() {
+    use super::verify::{check, image_hash};
+    use super::disk_cache::fnv1a;
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png.extend_from_slice(&[0; 16]);
+    png.extend_from_slice(b"IEND\xaeB`\x82");
+
+    assert_eq!(image_hash(&png), "a1665eea23");
+    assert_eq!(check(&png, None, Some("a1665eea23")), PageCheck::Hash);
+    assert_eq!(check(&png, Some(fnv1a(&png)), None), PageCheck::Manifest);
+
+    // resampled ones don't have the hash, but they're whole. this
+    // one has nothing in it, so it isn't if it's decoded
+    #[cfg(not(feature = "image"))]
+    assert_eq!(check(&png, None, Some("0123456789")), PageCheck::Whole);
+    assert_eq!(check(&png[..20], None, Some("a1665eea23")), PageCheck::Broken);
+    assert_eq!(check(b"<html>", None, None), PageCheck::Broken);
+    assert_eq!(check(b"", None, None), PageCheck::Broken);
+}
+
+#[test]
+fn image_reload_key() {
+    use select::document::Document;
+    use super::parser;
+
+    let doc = Document::from(r#"
+        <a href="#" id="loadfail" onclick="return nl('12345-678')">Reload broken image</a>
+    "#);
+
+    assert_eq!(parser::image_reload(&doc).unwrap(), "12345-678");
+    assert!(parser::image_reload(&Document::from("<div></div>")).is_err());
+}
+
+#[test]
+fn newer_version_links() {
+    use select::document::Document;
+    use super::parser;
+
+    let doc = Document::from(r#"
+        <div id="gnd">There are newer versions of this gallery available:<br>
+        <a href="https://e-hentai.org/g/5678/0123abcdef/">New Title</a>, added 2021-03-04 12:34<br>
+        <a href="https://e-hentai.org/g/6789/abcdef0123/">Newer Title</a>, added 2021-05-06 07:08</div>
+    "#);
+
+    let newer = parser::newer_versions(&doc);
+    assert_eq!(newer.len(), 2);
+    assert_eq!(newer[0], NewerVersion {
+        path: "https://e-hentai.org/g/5678/0123abcdef/".to_owned(),
+        title: "New Title".to_owned(),
+        posted: "2021-03-04 12:34".to_owned(),
+    });
+    assert_eq!(newer[1].posted, "2021-05-06 07:08");
+
+    assert!(parser::newer_versions(&Document::from("<div></div>")).is_empty());
+}
+
+#[test]
+fn gallery_dl_layout() {
+    use std::path::Path;
+    use super::gallerydl;
+
+    let meta = ArticleMeta {
+        title: "[Circle] Title 1/2".to_owned(),
+        language: Language::English,
+        translated: true,
+        file_size: "24.15 MiB".to_owned(),
+        length: 24,
+        favorited: 12,
+        rating: 4.5,
+        tags: vec!["female:glasses".parse::<Tag>().unwrap()].into_iter().collect(),
+        ..article_meta()
+    };
+
+    assert_eq!(gallerydl::directory(&meta), Path::new("exhentai").join("1234 [Circle] Title 1_2"));
+
+    let original = gallerydl::url_name("https://abc.hath.network/h/0123-456/keystamp=1;fileindex=2;xres=1280/001%20a.JPG");
+    assert_eq!(original, "001 a.JPG");
+
+    let name = gallerydl::image_name(&meta, 6, "0123456789", &original);
+    assert_eq!(name, "1234_0007_0123456789_001 a.jpg");
+    assert_eq!(gallerydl::original_name(&name), Some("001 a.jpg"));
+    assert_eq!(gallerydl::image_index(&name, 1234, 24), Some(6));
+    assert_eq!(gallerydl::image_index(&name, 5678, 24), None);
+
+    let json = gallerydl::metadata(&meta, 6, "0123456789", &original);
+    let value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+
+    assert_eq!(value["category"], "exhentai");
+    assert_eq!(value["gid"], 1234);
+    assert_eq!(value["date"], "2021-03-04 12:34:00");
+    assert_eq!(value["num"], 7);
+    assert_eq!(value["filename"], "001 a");
+    assert_eq!(value["extension"], "jpg");
+    assert_eq!(value["tags"][0], "female:glasses");
+    assert_eq!(value["expunged"], false);
+    assert!(json.contains("\n    \"category\": \"exhentai\""));
+}
+
+#[test]
+fn naming_template() {
+    let meta = ArticleMeta {
+        title: "(C97) [Circle (Artist)] Some Title: Part 1 (Some Parody) [English]".to_owned(),
+        language: Language::English,
+        translated: true,
+        file_size: "24.15 MiB".to_owned(),
+        length: 120,
+        tags: vec!["artist:someone else".parse::<Tag>().unwrap()].into_iter().collect(),
+        ..article_meta()
+    };
+
+    let template = "{artist}/{title} ({language})/{page:03}.{ext}".parse::<NameTemplate>().unwrap();
+    assert_eq!(template.render(&meta, Some((6, "jpg"))), "someone else/Some Title_ Part 1 (English)/007.jpg");
+
+    let (gallery, images) = template.split_images();
+    assert_eq!(gallery.unwrap().to_string(), "{artist}/{title} ({language})");
+    assert_eq!(images.unwrap().render(&meta, Some((6, "png"))), "007.png");
+
+    // as wide as the last page, without a width
+    let template = "{gid} {{{circle}}} {page}".parse::<NameTemplate>().unwrap();
+    assert_eq!(template.render(&meta, Some((6, "jpg"))), "1234 {Circle} 007");
+
+    assert!("{nothing}".parse::<NameTemplate>().is_err());
+    assert!("{page".parse::<NameTemplate>().is_err());
+    assert!("{page:x}".parse::<NameTemplate>().is_err());
+    assert!("{title}.{ext}".parse::<NameTemplate>().is_err());
+    assert!("{title}/{page}/{ext}".parse::<NameTemplate>().is_ok());
+}
+
+#[tokio::test]
+async fn image_index() {
+    let dir = std::env::temp_dir().join(format!("lude-images-{}", std::process::id()));
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+
+    tokio::fs::write(dir.join("1.jpg"), b"same image").await.unwrap();
+
+    let index = ImageIndex::open(dir.join("images.json")).await.unwrap();
+    assert!(index.is_empty());
+
+    index.insert("0123456789", dir.join("1.jpg"));
+    index.insert("abcdef0123", dir.join("gone.jpg"));
+    index.save().await.unwrap();
+
+    let index = ImageIndex::open(dir.join("images.json")).await.unwrap();
+    assert_eq!(index.len(), 2);
+    assert_eq!(index.find("0123456789").await, Some(dir.join("1.jpg")));
+
+    // the file has gone, so it's forgotten
+    assert_eq!(index.find("abcdef0123").await, None);
+    assert_eq!(index.len(), 1);
+
+    super::dedupe::link(&dir.join("1.jpg"), &dir.join("2.jpg")).await.unwrap();
+    assert_eq!(tokio::fs::read(dir.join("2.jpg")).await.unwrap(), b"same image");
+
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+}
+
+#[tokio::test]
+async fn manifest_roundtrip() {
+    let dir = std::env::temp_dir().join(format!("lude-manifest-{}", std::process::id()));
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+
+    let mut manifest = Manifest::new("https://e-hentai.org/g/1234/abcdef1234/".to_owned(), 3);
+    manifest.images = vec![
+        "https://e-hentai.org/s/0123456789/1234-1".to_owned(),
+        "https://e-hentai.org/s/abcdef0123/1234-2".to_owned(),
+    ];
+    manifest.pages.insert(1, PageRecord {
+        file: "2.png".to_owned(),
+        size: 1234,
+        checksum: u64::MAX - 1,
+    });
+
+    manifest.save(&dir).await.unwrap();
+    assert_eq!(Manifest::load(&dir).await, Some(manifest));
+
+    // a broken one is none at all
+    tokio::fs::write(dir.join(".manifest.json"), "{").await.unwrap();
+    assert_eq!(Manifest::load(&dir).await, None);
+
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+}
+
+#[test]
+fn progress_speed() {
+    let progress = Progress::new();
+    assert_eq!(progress.state().eta, None);
+
+    progress.set_total(4);
+    std::thread::sleep(std::time::Duration::from_millis(20));
+
+    progress.image_downloaded(1000);
+    progress.image_downloaded(3000);
+
+    let state = progress.state();
+    assert_eq!(state.remaining(), 2);
+    assert!(state.speed > 0.0 && state.average_speed > 0.0);
+
+    // two more images of 2000 bytes at the speed of now
+    let eta = state.eta.unwrap().as_secs_f64();
+    assert!((eta - 4000.0 / state.speed).abs() < 0.1);
+
+    progress.failed();
+    progress.image_downloaded(0);
+    assert_eq!(progress.state().eta, Some(std::time::Duration::from_secs(0)));
+}
+
+#[tokio::test]
+async fn control_pause() {
+    use std::time::Duration;
+
+    let parent = Control::new();
+    let child = parent.child();
+
+    parent.pause();
+    assert_eq!(child.state(), ControlState::Paused);
+
+    let waiting = {
+        let child = child.clone();
+        tokio::spawn(async move { child.proceed().await })
+    };
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(!waiting.is_finished());
+
+    parent.resume();
+    assert!(waiting.await.unwrap().is_ok());
+
+    // cancelled for good, even if resumed after
+    child.cancel();
+    child.resume();
+    assert_eq!(child.state(), ControlState::Cancelled);
+    assert!(matches!(child.proceed().await, Err(EhError::Cancelled)));
+    assert_eq!(parent.state(), ControlState::Running);
+}
+
+#[cfg(feature = "store")]
+#[test]
+fn store_roundtrip() {
+    let store = Store::open_in_memory().unwrap();
+
+    let mut tags = vec!["artist:foo".parse::<Tag>().unwrap()].into_iter().collect::<TagMap>();
+    tags.add_weak("female:glasses".parse().unwrap());
+
+    let meta = ArticleMeta {
+        title: "some doujinshi".to_owned(),
+        parent: Some("https://e-hentai.org/g/1200/0123abcdef/".to_owned()),
+        language: Language::Korean,
+        translated: true,
+        rating: 4.5,
+        tags,
+        ..article_meta()
+    };
+
+    store.save(&meta).unwrap();
+    assert!(store.load(5678).unwrap().is_none());
+
+    let loaded = store.load(1234).unwrap().unwrap();
+    assert_eq!(loaded.title, "some doujinshi");
+    assert_eq!(loaded.kind, ArticleKind::Doujinshi);
+    assert_eq!(loaded.parent, meta.parent);
+    assert_eq!(loaded.language, Language::Korean);
+    assert!(loaded.translated);
+    assert_eq!(loaded.rating, 4.5);
+    assert_eq!(loaded.tags, meta.tags);
+    assert!(loaded.tags.is_weak(&"female:glasses".parse().unwrap()));
+
+    assert_eq!(store.by_tag(&"artist:foo".parse().unwrap()).unwrap().len(), 1);
+    assert_eq!(store.by_language(&Language::Korean).unwrap().len(), 1);
+    assert!(store.by_rating(4.8).unwrap().is_empty());
+
+    // saved again, it replaces the old one along with its tags
+    store.save(&ArticleMeta {
+        title: "new title".to_owned(),
+        tags: vec!["artist:bar".parse::<Tag>().unwrap()].into_iter().collect(),
+        ..meta
+    }).unwrap();
+
+    let all = store.all().unwrap();
+    assert_eq!(all.len(), 1);
+    assert_eq!(all[0].title, "new title");
+    assert!(store.by_tag(&"artist:foo".parse().unwrap()).unwrap().is_empty());
+
+    store.remove(1234).unwrap();
+    assert!(store.load(1234).unwrap().is_none());
+}
+
+#[cfg(feature = "store")]
+#[test]
+fn store_download_state() {
+    let store = Store::open_in_memory().unwrap();
+    store.save(&article_meta()).unwrap();
+    assert_eq!(store.download_state(1234).unwrap(), None);
+
+    let mut summary = DownloadSummary {
+        dir: "galleries/1234".into(),
+        downloaded: 15,
+        skipped: 3,
+        existing: Vec::new(),
+        broken: Vec::new(),
+        linked: 0,
+        failed: vec![(7, "timed out".to_owned()), (12, "timed out".to_owned())],
+        bytes: 12345678,
+    };
+
+    store.set_download(1234, &summary).unwrap();
+
+    let state = store.download_state(1234).unwrap().unwrap();
+    assert_eq!(state, DownloadState {
+        dir: "galleries/1234".to_owned(),
+        downloaded: 18,
+        failed: 2,
+        bytes: 12345678,
+    });
+    assert!(!state.is_complete());
+
+    // the failed ones came with another try
+    summary.downloaded = 2;
+    summary.skipped = 18;
+    summary.failed.clear();
+    store.set_download(1234, &summary).unwrap();
+
+    let state = store.download_state(1234).unwrap().unwrap();
+    assert_eq!(state.downloaded, 20);
+    assert!(state.is_complete());
+
+    store.remove(1234).unwrap();
+    assert_eq!(store.download_state(1234).unwrap(), None);
+}
+
+#[cfg(feature = "store")]
+#[test]
+fn store_reopen() {
+    let path = std::env::temp_dir().join(format!("lude-store-{}.sqlite", std::process::id()));
+    std::fs::remove_file(&path).ok();
+
+    {
+        let store = Store::open(&path).unwrap();
+        store.save(&article_meta()).unwrap();
+    }
+
+    // the schema is made only if it isn't there, keeping what's saved
+    let store = Store::open(&path).unwrap();
+    assert_eq!(store.load(1234).unwrap().unwrap().title, "title");
+    store.save(&ArticleMeta {
+        path: "https://e-hentai.org/g/5678/abcdef1234/".to_owned(),
+        ..article_meta()
+    }).unwrap();
+    assert_eq!(store.all().unwrap().len(), 2);
+
+    drop(store);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(all(feature = "store", feature = "vcr"))]
+#[tokio::test]
+async fn store_images_per_page() {
+    use super::vcr::{self, Recorded};
+
+    let dir = std::env::temp_dir().join(format!("lude-store-pages-{}", std::process::id()));
+    let meta = ArticleMeta { length: 150, ..article_meta() };
+
+    // an account showing 100 thumbnails a page
+    let page = |range: std::ops::Range<usize>| {
+        let cells = range
+            .map(|page| format!(
+                "<div class=\"gdtm\"><div style=\"width:100px; height:144px; background:transparent \
+                url(https://ehgt.org/m/001234/1234-00.jpg) -0px 0 no-repeat\">\
+                <a href=\"https://e-hentai.org/s/{:010x}/1234-{}\"><img></a></div></div>",
+                page, page
+            ))
+            .collect::<String>();
+
+        format!("<div id=\"gdt\">{}</div>", cells)
+    };
+
+    for (p, range) in vec![(0, 1..101), (1, 101..151)] {
+        vcr::store(&dir, &Recorded {
+            key: format!("GET {}?p={}", meta.path, p),
+            status: 200,
+            headers: vec![("content-type".to_owned(), "text/html".to_owned())],
+            body: page(range).into(),
+        }).await.unwrap();
+    }
+
+    let store = Store::open_in_memory().unwrap();
+    store.save(&meta).unwrap();
+
+    let explorer = ExplorerBuilder::new().replay(&dir).build().await.unwrap();
+    let article = explorer.article_from_store(&store, 1234).unwrap();
+
+    // nothing's fetched, so it doesn't know how the pages are split yet
+    assert_eq!(article.to_data().images_per_page, 0);
+
+    article.load_image_list().await.unwrap();
+    let list = article.image_list();
+
+    assert_eq!(list.len(), 150);
+    assert_eq!(list[45].page, 46);
+    assert_eq!(list[120].page, 121);
+    assert_eq!(article.to_data().images_per_page, 100);
+
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+}
+
+#[cfg(feature = "vcr")]
+#[tokio::test]
+async fn api_errors_uncached() {
+    use super::vcr::{self, Recorded};
+
+    let dir = std::env::temp_dir().join(format!("lude-api-errors-{}", std::process::id()));
+    let key = r#"POST https://api.e-hentai.org/api.php {"gidlist":[[1234,"abcdef1234"]],"method":"gdata","namespace":1}"#;
+
+    vcr::store(&dir, &Recorded {
+        key: key.to_owned(),
+        status: 200,
+        headers: vec![("content-type".to_owned(), "application/json".to_owned())],
+        body: r#"{"error":"Rate limited"}"#.into(),
+    }).await.unwrap();
+
+    let explorer = ExplorerBuilder::new().replay(&dir).build().await.unwrap();
+    let article = explorer.article_from_data(ArticleData {
+        meta: article_meta(),
+        images_per_page: 0,
+        images: Vec::new(),
+        previews: Vec::new(),
+        comments: Vec::new(),
+    });
+
+    assert!(article.estimated_size().await.is_err());
+
+    // it's asked again rather than the error being kept
+    vcr::store(&dir, &Recorded {
+        key: key.to_owned(),
+        status: 200,
+        headers: vec![("content-type".to_owned(), "application/json".to_owned())],
+        body: r#"{"gmetadata":[{"gid":1234,"token":"abcdef1234","title":"title","category":"Doujinshi","filecount":"20","filesize":12345678}]}"#.into(),
+    }).await.unwrap();
+
+    assert_eq!(article.estimated_size().await.unwrap(), 12345678);
+
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+}
+
+#[tokio::test]
+async fn disk_cache_parts() {
+    use super::disk_cache::DiskCache;
+
+    let dir = std::env::temp_dir().join(format!("lude-disk-cache-{}", std::process::id()));
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+
+    // what a crash left halfway isn't taken as an entry
+    tokio::fs::write(dir.join("page-1234.part"), b"<html><bo").await.unwrap();
+
+    let cache = DiskCache::open(dir.clone(), 1024).await.unwrap();
+    assert_eq!(cache.size(), (0, 0));
+    assert!(cache.get("page-1234").await.is_none());
+
+    cache.put("page-1234", b"<html><body></body></html>").await;
+    assert_eq!(cache.get("page-1234").await.unwrap(), b"<html><body></body></html>");
+    assert_eq!(cache.size(), (1, 26));
+
+    let mut names = Vec::new();
+    let mut entries = tokio::fs::read_dir(&dir).await.unwrap();
+
+    while let Some(entry) = entries.next_entry().await.unwrap() {
+        names.push(entry.file_name().to_string_lossy().into_owned());
+    }
+
+    assert_eq!(names, vec!["page-1234"]);
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+}
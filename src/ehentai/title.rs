@@ -0,0 +1,153 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::article::{DraftMeta, ArticleMeta};
+use super::language::Language;
+
+// a title in the usual convention, which is like
+// "(event) [circle (artists)] title (parody) [language] [others]".
+// every part but the title is optional
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedTitle {
+    // like "C97", the convention it was sold at
+    pub event: Option<String>,
+    pub circle: Option<String>,
+    pub artists: Vec<String>,
+    pub title: String,
+    pub parody: Option<String>,
+    pub language: Option<Language>,
+    // whatever else is in brackets at the end, like "Digital"
+    pub extra: Vec<String>,
+}
+
+// split "(a) b" into ("a", "b") if it starts with a group
+fn group_start(text: &str, open: char, close: char) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+
+    if !text.starts_with(open) {
+        return None;
+    }
+
+    let mut depth = 0;
+
+    for (pos, c) in text.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+
+            if depth == 0 {
+                let inner = &text[open.len_utf8()..pos];
+                return Some((inner.trim(), &text[pos + close.len_utf8()..]));
+            }
+        }
+    }
+
+    None
+}
+
+// split "b (a)" into ("a", "b") if it ends with a group
+fn group_end(text: &str, open: char, close: char) -> Option<(&str, &str)> {
+    let text = text.trim_end();
+
+    if !text.ends_with(close) {
+        return None;
+    }
+
+    let mut depth = 0;
+
+    for (pos, c) in text.char_indices().rev() {
+        if c == close {
+            depth += 1;
+        } else if c == open {
+            depth -= 1;
+
+            if depth == 0 {
+                let inner = &text[pos + open.len_utf8()..text.len() - close.len_utf8()];
+                return Some((inner.trim(), &text[..pos]));
+            }
+        }
+    }
+
+    None
+}
+
+impl ParsedTitle {
+    pub fn parse(title: &str) -> Self {
+        let mut res = Self::default();
+        let mut rest = title.trim();
+
+        if let Some((event, next)) = group_start(rest, '(', ')') {
+            res.event = Some(event.to_owned());
+            rest = next;
+        }
+
+        // "[circle (artist, artist)]", or "[artist]" of those without circles
+        if let Some((group, next)) = group_start(rest, '[', ']') {
+            match group_end(group, '(', ')') {
+                Some((artists, circle)) if !circle.trim().is_empty() => {
+                    res.circle = Some(circle.trim().to_owned());
+                    res.artists = artists
+                        .split(',')
+                        .map(|x| x.trim().to_owned())
+                        .filter(|x| !x.is_empty())
+                        .collect();
+                },
+                _ => res.artists.push(group.to_owned())
+            }
+
+            rest = next;
+        }
+
+        // brackets at the end, from the last; one of them may be the language
+        loop {
+            let found = group_end(rest, '[', ']')
+                .or_else(|| group_end(rest, '{', '}'));
+
+            let (group, next) = match found {
+                Some(found) => found,
+                None => break
+            };
+
+            match Language::from(group) {
+                Language::Other(_) => res.extra.insert(0, group.to_owned()),
+                language => res.language = Some(language),
+            }
+
+            rest = next;
+        }
+
+        if let Some((parody, next)) = group_end(rest, '(', ')') {
+            // a title wholly in parentheses is still a title
+            if !next.trim().is_empty() {
+                res.parody = Some(parody.to_owned());
+                rest = next;
+            }
+        }
+
+        res.title = rest.trim().to_owned();
+
+        // nothing was there but brackets, so it's not in the convention
+        if res.title.is_empty() {
+            return Self {
+                title: title.trim().to_owned(),
+                ..Self::default()
+            };
+        }
+
+        res
+    }
+}
+
+impl DraftMeta {
+    pub fn parsed_title(&self) -> ParsedTitle {
+        ParsedTitle::parse(&self.title)
+    }
+}
+
+impl ArticleMeta {
+    pub fn parsed_title(&self) -> ParsedTitle {
+        ParsedTitle::parse(&self.title)
+    }
+}
@@ -0,0 +1,165 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::io;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use hyper::{Body, Request, Response, StatusCode};
+use hyper::body::Bytes;
+use serde_json::{json, Value};
+use tokio::fs;
+
+use super::disk_cache::fnv1a;
+use super::error::EhError;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+// what a response is recorded with but the body; cookies the site
+// sets are dropped so that recordings can be shared
+const SKIPPED_HEADERS: [&str; 1] = ["set-cookie"];
+
+// a directory of responses, which are written as they come or read
+// instead of going to the site. it's meant for tests: record once, and
+// replay the same responses as many times as you like
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Tape {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+// a response as it's kept on the tape
+pub(crate) struct Recorded {
+    pub key: String,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Bytes,
+}
+
+// how a request is told from the others, e.g. "GET https://...". the
+// body is read out for that, so the request is made again
+pub(crate) async fn key(req: Request<Body>)
+    -> Result<(Request<Body>, String), ErrorBox> {
+    let (parts, body) = req.into_parts();
+    let body = hyper::body::to_bytes(body).await?;
+
+    let mut key = format!("{} {}", parts.method, parts.uri);
+
+    if !body.is_empty() {
+        key.push(' ');
+        key.push_str(&String::from_utf8_lossy(&body));
+    }
+
+    Ok((Request::from_parts(parts, Body::from(body)), key))
+}
+
+// files of a response are named after the hash of its key,
+// like "0123456789abcdef.json" and "0123456789abcdef.body"
+fn paths(dir: &Path, key: &str) -> (PathBuf, PathBuf) {
+    let name = format!("{:016x}", fnv1a(key));
+    (dir.join(format!("{}.json", name)), dir.join(format!("{}.body", name)))
+}
+
+pub(crate) async fn store(dir: &Path, recorded: &Recorded) -> Result<(), ErrorBox> {
+    fs::create_dir_all(dir).await?;
+
+    let (meta, body) = paths(dir, &recorded.key);
+
+    // the key is kept to know what it was, though it's found by the name
+    let headers = recorded.headers
+        .iter()
+        .map(|(name, value)| json!([name, value]))
+        .collect::<Vec<_>>();
+
+    let value = json!({
+        "key": recorded.key,
+        "status": recorded.status,
+        "headers": headers,
+    });
+
+    fs::write(meta, serde_json::to_vec_pretty(&value)?).await?;
+    fs::write(body, &recorded.body).await?;
+    Ok(())
+}
+
+pub(crate) async fn load(dir: &Path, key: &str) -> Result<Recorded, ErrorBox> {
+    let (meta, body) = paths(dir, key);
+
+    let meta = match fs::read(meta).await {
+        Ok(meta) => meta,
+        Err(err) if err.kind() == io::ErrorKind::NotFound =>
+            return Err(EhError::NotFound(format!("recorded response of {}", key)).into()),
+        Err(err) => return Err(err.into())
+    };
+
+    let value = serde_json::from_slice::<Value>(&meta)?;
+
+    let status = value["status"]
+        .as_u64()
+        .ok_or("recorded response without status")? as u16;
+
+    let headers = value["headers"]
+        .as_array()
+        .map_or(&[][..], |x| x.as_slice())
+        .iter()
+        .filter_map(|pair| Some((
+            pair[0].as_str()?.to_owned(),
+            pair[1].as_str()?.to_owned()
+        )))
+        .collect();
+
+    Ok(Recorded {
+        key: key.to_owned(),
+        status,
+        headers,
+        body: Bytes::from(fs::read(body).await?),
+    })
+}
+
+fn response(recorded: Recorded) -> Result<Response<Body>, ErrorBox> {
+    let mut res = Response::builder().status(StatusCode::from_u16(recorded.status)?);
+
+    for (name, value) in recorded.headers.iter() {
+        res = res.header(name.as_str(), value.as_str());
+    }
+
+    Ok(res.body(Body::from(recorded.body))?)
+}
+
+impl Tape {
+    // the recorded response, if we're replaying. what's not on the tape is
+    // an error then, rather than going to the site
+    pub async fn replay(&self, key: &str) -> Result<Option<Response<Body>>, ErrorBox> {
+        match self {
+            Tape::Record(_) => Ok(None),
+            Tape::Replay(dir) => Ok(Some(response(load(dir, key).await?)?)),
+        }
+    }
+
+    // write the response down if we're recording, and give it back
+    pub async fn record(&self, key: String, res: Response<Body>)
+        -> Result<Response<Body>, ErrorBox> {
+        let dir = match self {
+            Tape::Record(dir) => dir,
+            Tape::Replay(_) => return Ok(res),
+        };
+
+        let (parts, body) = res.into_parts();
+
+        let headers = parts.headers
+            .iter()
+            .filter(|(name, _)| !SKIPPED_HEADERS.contains(&name.as_str()))
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+            .collect();
+
+        let recorded = Recorded {
+            key,
+            status: parts.status.as_u16(),
+            headers,
+            body: hyper::body::to_bytes(body).await?,
+        };
+
+        store(dir, &recorded).await?;
+        response(recorded)
+    }
+}
@@ -0,0 +1,101 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use sha1::{Sha1, Digest};
+
+use super::image::ImageFormat;
+use super::disk_cache::fnv1a;
+use super::error::EhError;
+
+// how a file of a page looked, when it was checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageCheck {
+    // it's there, and that's all we know; verifying was turned off
+    Unverified,
+    // it's what the download manifest says was written
+    Manifest,
+    // the sha-1 of it is what the site names the image page after, so
+    // it's the very file which was uploaded
+    Hash,
+    // it's not the uploaded file, which is what the site gives when it
+    // resamples large images, but it ends where its format should
+    Whole,
+    // it's cut off or not an image at all, so it's downloaded again
+    Broken,
+}
+
+impl PageCheck {
+    pub fn is_ok(&self) -> bool {
+        *self != PageCheck::Broken
+    }
+}
+
+// whether an image ends where it should for its format. it's a cheap
+// check for those which were cut off, not a decoder
+pub(super) fn is_whole(data: &[u8]) -> bool {
+    match ImageFormat::sniff(data) {
+        // some encoders put padding after the end marker
+        Some(ImageFormat::Jpeg) => data[data.len().saturating_sub(64)..]
+            .windows(2)
+            .any(|x| x == [0xff, 0xd9]),
+        Some(ImageFormat::Png) => data.ends_with(b"IEND\xaeB`\x82"),
+        Some(ImageFormat::Gif) => data.ends_with(b"\x3b"),
+        // the riff header says how long the rest is
+        Some(ImageFormat::Webp) => {
+            let len = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+            data.len() >= len + 8
+        },
+        None => false
+    }
+}
+
+// with the image crate around, make sure it decodes as well. it takes
+// much longer, but finds broken data in the middle
+#[cfg(feature = "image")]
+fn decodes(data: &[u8]) -> bool {
+    image::load_from_memory(data).is_ok()
+}
+
+#[cfg(not(feature = "image"))]
+fn decodes(_data: &[u8]) -> bool {
+    true
+}
+
+// whether the error is from an image which came broken
+pub(super) fn is_broken(err: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+    matches!(err.downcast_ref::<EhError>(), Some(EhError::BrokenImage { .. }))
+}
+
+// the first 10 hex digits of the sha-1, as image pages are named
+pub(super) fn image_hash(data: &[u8]) -> String {
+    let digest = Sha1::digest(data);
+
+    digest[..5]
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect()
+}
+
+// check a file of a page against the checksum the manifest recorded and
+// the hash of its image page, whichever is known
+pub(super) fn check(data: &[u8], checksum: Option<u64>, hash: Option<&str>) -> PageCheck {
+    if data.is_empty() {
+        return PageCheck::Broken;
+    }
+
+    if checksum == Some(fnv1a(data)) {
+        return PageCheck::Manifest;
+    }
+
+    if let Some(hash) = hash {
+        if image_hash(data).eq_ignore_ascii_case(hash) {
+            return PageCheck::Hash;
+        }
+    }
+
+    match is_whole(data) && decodes(data) {
+        true => PageCheck::Whole,
+        false => PageCheck::Broken,
+    }
+}
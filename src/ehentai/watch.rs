@@ -0,0 +1,265 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::sync::Arc;
+use std::error::Error;
+use std::time::Duration;
+use std::collections::VecDeque;
+use futures::stream::{self, Stream};
+
+use super::client::Client;
+use super::id::GalleryId;
+use super::tag::Tag;
+use super::article::{Draft, Article, Comment, NewerVersion};
+use super::page::Page;
+
+type ErrorBox = Box<dyn Error + Send + Sync>;
+
+// pages looked at in a poll at most. if more galleries than those were
+// posted in between, the older ones are missed
+const MAX_PAGES: usize = 10;
+
+// what's watched for new galleries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchSource {
+    // results of a search, like "language:english"
+    Search(String),
+    // the watched page of the account, of the tags watched in "my tags"
+    Watched,
+}
+
+impl From<&str> for WatchSource {
+    fn from(keyword: &str) -> Self {
+        WatchSource::Search(keyword.to_owned())
+    }
+}
+
+impl From<String> for WatchSource {
+    fn from(keyword: String) -> Self {
+        WatchSource::Search(keyword)
+    }
+}
+
+struct State {
+    client: Arc<Client>,
+    source: WatchSource,
+    interval: Duration,
+    // the newest gallery seen so far; none before the first poll
+    last: Option<u64>,
+    // new ones not given yet, the oldest first
+    queue: VecDeque<Draft>,
+    polled: bool,
+}
+
+// galleries newer than `last`, the oldest first, and the newest gid now.
+// without `last` it only looks at the first page to tell where it is
+async fn poll(state: &State) -> Result<(Vec<Draft>, Option<u64>), ErrorBox> {
+    let page = match &state.source {
+        WatchSource::Search(keyword) => Page::new(state.client.clone(), 0, keyword),
+        WatchSource::Watched => Page::watched(state.client.clone(), ""),
+    };
+
+    let mut page = page.fresh(true);
+    let mut newest = state.last;
+    let mut found = Vec::new();
+
+    for _ in 0..MAX_PAGES {
+        let drafts = match page.next().await? {
+            Some(drafts) => drafts,
+            None => break
+        };
+
+        // the results are sorted by the time they're posted, and so by gid
+        let mut caught_up = state.last.is_none();
+
+        for draft in drafts {
+            let gid = match draft.meta().id() {
+                Some(id) => id.gid,
+                None => continue
+            };
+
+            newest = Some(newest.map_or(gid, |x| x.max(gid)));
+
+            match state.last {
+                Some(last) if gid > last => found.push((gid, draft)),
+                _ => caught_up = true,
+            }
+        }
+
+        if caught_up {
+            break;
+        }
+    }
+
+    found.sort_by_key(|(gid, _)| *gid);
+    Ok((found.into_iter().map(|(_, draft)| draft).collect(), newest))
+}
+
+pub(super) fn watch(client: Arc<Client>, source: WatchSource, interval: Duration,
+    last: Option<u64>) -> impl Stream<Item = Draft> + Send + 'static {
+    let state = State {
+        client,
+        source,
+        interval,
+        last,
+        queue: VecDeque::new(),
+        polled: false,
+    };
+
+    stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(draft) = state.queue.pop_front() {
+                return Some((draft, state));
+            }
+
+            if state.polled {
+                tokio::time::sleep(state.interval).await;
+            }
+
+            state.polled = true;
+
+            // a poll which failed is tried again the next time
+            if let Ok((found, newest)) = poll(&state).await {
+                state.queue.extend(found);
+                state.last = newest;
+            }
+        }
+    })
+}
+
+// what's changed in a watched gallery since it was looked at
+#[derive(Debug, Clone)]
+pub enum GalleryEvent {
+    // comments posted since, in the order of the page. edited ones
+    // don't count
+    Comments { path: String, comments: Vec<Comment> },
+    Rating { path: String, rating: f64, count: usize },
+    // tags voted on and off
+    Tags { path: String, added: Vec<Tag>, removed: Vec<Tag> },
+    // what archivers wait for: the gallery has a new version, which may
+    // replace the copy of the old one
+    NewerVersion { path: String, version: NewerVersion },
+}
+
+impl GalleryEvent {
+    // the gallery it's of
+    pub fn path(&self) -> &str {
+        match self {
+            GalleryEvent::Comments { path, .. }
+            | GalleryEvent::Rating { path, .. }
+            | GalleryEvent::Tags { path, .. }
+            | GalleryEvent::NewerVersion { path, .. } => path,
+        }
+    }
+}
+
+struct Watched {
+    path: String,
+    // none until it's fetched the first time
+    article: Option<Article>,
+}
+
+// fetch the gallery again and tell what's changed. the first time is to
+// know how it is, so nothing is
+async fn look(client: &Arc<Client>, watched: &mut Watched, events: &mut VecDeque<GalleryEvent>)
+    -> Result<(), ErrorBox> {
+    if watched.article.is_none() {
+        watched.article = Some(Article::new(client.clone(), watched.path.clone()).await?);
+        return Ok(());
+    }
+
+    let article = watched.article.as_mut().unwrap();
+
+    let tags = article.meta().tags.clone();
+    let comments = article.comments().cloned().collect::<Vec<_>>();
+    let newer = article.newer_versions().to_vec();
+
+    let changes = article.refresh().await?;
+    let meta = article.meta();
+    let path = meta.path.clone();
+
+    if changes.comments {
+        let posted = article
+            .comments()
+            .filter(|x| !comments.iter().any(|old| old.writer == x.writer && old.posted == x.posted))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if !posted.is_empty() {
+            events.push_back(GalleryEvent::Comments { path: path.clone(), comments: posted });
+        }
+    }
+
+    if changes.rating {
+        events.push_back(GalleryEvent::Rating {
+            path: path.clone(),
+            rating: meta.rating,
+            count: meta.rating_count,
+        });
+    }
+
+    if changes.tags {
+        let added = meta.tags.tags().filter(|x| !tags.has(x)).collect::<Vec<_>>();
+        let removed = tags.tags().filter(|x| !meta.tags.has(x)).collect::<Vec<_>>();
+
+        if !added.is_empty() || !removed.is_empty() {
+            events.push_back(GalleryEvent::Tags { path: path.clone(), added, removed });
+        }
+    }
+
+    if changes.newer {
+        for version in article.newer_versions().iter().filter(|x| !newer.contains(x)) {
+            events.push_back(GalleryEvent::NewerVersion {
+                path: path.clone(),
+                version: version.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+struct GalleryState {
+    client: Arc<Client>,
+    galleries: Vec<Watched>,
+    interval: Duration,
+    queue: VecDeque<GalleryEvent>,
+    polled: bool,
+}
+
+pub(super) fn watch_galleries(client: Arc<Client>, ids: Vec<GalleryId>,
+    interval: Duration) -> impl Stream<Item = GalleryEvent> + Send + 'static {
+    let galleries = ids
+        .into_iter()
+        .map(|id| Watched { path: id.path(), article: None })
+        .collect();
+
+    let state = GalleryState {
+        client,
+        galleries,
+        interval,
+        queue: VecDeque::new(),
+        polled: false,
+    };
+
+    stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(event) = state.queue.pop_front() {
+                return Some((event, state));
+            }
+
+            if state.polled {
+                tokio::time::sleep(state.interval).await;
+            }
+
+            state.polled = true;
+
+            // one which failed is looked at again the next time, along
+            // with what's changed in between
+            for watched in state.galleries.iter_mut() {
+                look(&state.client, watched, &mut state.queue).await.ok();
+            }
+        }
+    })
+}
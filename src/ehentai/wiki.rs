@@ -0,0 +1,28 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::id::GalleryId;
+use super::page;
+use super::tag::Tag;
+
+// what ehwiki says about a tag
+#[derive(Debug, Clone)]
+pub struct TagWiki {
+    pub tag: Tag,
+    pub path: String,
+    // the first paragraph of the page, without markups
+    pub description: String,
+    // names of the pages in the related sections, which are mostly tags
+    pub related: Vec<String>,
+    // galleries linked as examples of the tag
+    pub examples: Vec<GalleryId>,
+    // every section of the page by its heading, in case the above isn't enough
+    pub sections: Vec<(String, String)>,
+}
+
+// the wiki names pages after tags, with spaces replaced by underscores
+pub(super) fn wiki_path(tag: &Tag) -> String {
+    let name = tag.name().replace(' ', "_");
+    format!("https://ehwiki.org/wiki/{}", page::percent_encode(&name))
+}
@@ -6,5 +6,20 @@ extern crate tokio;
 extern crate hyper;
 extern crate select;
 extern crate detour;
+extern crate futures;
+extern crate serde_json;
+extern crate zip;
+
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "image")]
+extern crate image;
+#[cfg(feature = "flate2")]
+extern crate flate2;
+#[cfg(feature = "rusqlite")]
+extern crate rusqlite;
+#[cfg(feature = "chrono")]
+extern crate chrono;
 
 pub mod ehentai;
+pub mod backend;